@@ -0,0 +1,33 @@
+fn main() {
+    // the C header is only generated when building the ffi layer, from src/ffi.rs alone so the
+    // rest of the crate's public constants don't end up as C macros; builds write it to OUT_DIR
+    // and only TSP_WRITE_HEADER=1 updates the checked in copy under include/
+    #[cfg(feature = "ffi")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out_dir = std::env::var("OUT_DIR").unwrap();
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        println!("cargo:rerun-if-env-changed=TSP_WRITE_HEADER");
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+            .expect("unable to read cbindgen.toml");
+        let header = cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(format!("{}/src/ffi.rs", crate_dir))
+            .generate()
+            .expect("unable to generate the C header");
+        header.write_to_file(format!("{}/simulated_annealing_tsp.h", out_dir));
+        if std::env::var_os("TSP_WRITE_HEADER").is_some() {
+            header.write_to_file(format!("{}/include/simulated_annealing_tsp.h", crate_dir));
+        }
+    }
+
+    // a vendored protoc is used so building the gRPC service needs no system install
+    #[cfg(feature = "grpc")]
+    {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+        println!("cargo:rerun-if-changed=proto/tsp.proto");
+        tonic_build::compile_protos("proto/tsp.proto")
+            .expect("unable to compile the gRPC protocol");
+    }
+}
@@ -0,0 +1,76 @@
+#ifndef SIMULATED_ANNEALING_TSP_H
+#define SIMULATED_ANNEALING_TSP_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The most cities `tsp_solve` solves with `"brute_force"`, larger instances fail.
+ */
+#define TSP_BRUTE_FORCE_MAX_CITIES 12
+
+typedef struct tsp_instance tsp_instance;
+
+typedef struct tsp_solution tsp_solution;
+
+/**
+ * Builds an instance from a row-major `num_cities * num_cities` weight array.
+ * Returns null if the array is null or empty. Free with `tsp_instance_free`.
+ *
+ * # Safety
+ * `weights` must point to at least `num_cities * num_cities` readable values.
+ */
+struct tsp_instance *tsp_instance_new(const uint16_t *weights, uintptr_t num_cities);
+
+/**
+ * # Safety
+ * `instance` must be null or come from `tsp_instance_new`, and not be used afterwards.
+ */
+void tsp_instance_free(struct tsp_instance *instance);
+
+/**
+ * Runs the solver with the given name, e.g. `"simulated_annealing"`, `"brute_force"`,
+ * `"held_karp"` or `"lin_kernighan"`, with its default settings. The temperature is only
+ * used by simulated annealing and `max_iterations` by solvers counting iterations or
+ * generations, 0 keeping the solver's default for either. Returns null if the solver
+ * is unknown or fails. Free with `tsp_solution_free`.
+ *
+ * # Safety
+ * `instance` must come from `tsp_instance_new` and `solver` must be a null terminated string.
+ */
+struct tsp_solution *tsp_solve(const struct tsp_instance *instance,
+                               const char *solver,
+                               uint64_t temperature,
+                               uint64_t max_iterations);
+
+/**
+ * # Safety
+ * `solution` must come from `tsp_solve`.
+ */
+uint64_t tsp_solution_cost(const struct tsp_solution *solution);
+
+/**
+ * # Safety
+ * `solution` must come from `tsp_solve`.
+ */
+uintptr_t tsp_solution_len(const struct tsp_solution *solution);
+
+/**
+ * Copies at most `capacity` cities of the tour into `out`, returns how many were copied.
+ *
+ * # Safety
+ * `solution` must come from `tsp_solve` and `out` must have room for `capacity` values.
+ */
+uintptr_t tsp_solution_tour(const struct tsp_solution *solution, uint32_t *out, uintptr_t capacity);
+
+/**
+ * # Safety
+ * `solution` must be null or come from `tsp_solve`, and not be used afterwards.
+ */
+void tsp_solution_free(struct tsp_solution *solution);
+
+#endif  /* SIMULATED_ANNEALING_TSP_H */
@@ -0,0 +1,479 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::common::{check_city_map, TSPError};
+use super::cost::CostFunction;
+use super::weight::{lighter, Weight};
+
+const NEIGHBOURHOOD_SIZE: usize = 5; // nearest neighbours used for the clustering coefficient
+const TRIANGLE_SAMPLES: usize = 1_000_000; // triples checked for the triangle inequality
+
+pub struct InstanceHardness {
+    pub weight_mean: f64,
+    pub weight_variance: f64,
+    pub coefficient_of_variation: f64,
+    pub clustering_coefficient: f64, // average local clustering of the nearest neighbour graph
+    pub nearest_neighbour_gap_mean: f64, // gap between the 1st and 2nd nearest neighbour, relative to the mean weight
+    pub nearest_neighbour_gap_std: f64,
+    pub score: f64, // higher is harder
+}
+
+pub struct InstanceSummary<W = u16> {
+    pub num_cities: usize,
+    pub weight_min: W,
+    pub weight_max: W,
+    pub weight_mean: f64,
+    pub symmetry: f64, // fraction of city pairs weighted the same in both directions
+    pub coefficient_of_variation: f64,
+}
+
+impl InstanceHardness {
+    // iteration budget for the heuristic solvers, grows with the score
+    pub fn suggested_max_iterations(&self) -> u64 {
+        ((self.score * 1000.) as u64).max(1000)
+    }
+}
+
+pub(crate) fn mean_and_variance(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0., 0.);
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    (mean, variance)
+}
+
+// the k closest other cities of every city, closest first
+fn nearest_neighbours<W: Weight>(intercity_map: &[Vec<W>], k: usize) -> Vec<Vec<usize>> {
+    intercity_map
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut others: Vec<usize> = (0..row.len()).filter(|&j| j != i).collect();
+            others.sort_by(|&a, &b| row[a].total_cmp(&row[b]));
+            others.truncate(k);
+            others
+        })
+        .collect()
+}
+
+fn clustering_coefficient(neighbours: &[Vec<usize>]) -> f64 {
+    let num_cities = neighbours.len();
+    let mut adjacent = vec![vec![false; num_cities]; num_cities];
+    for (i, list) in neighbours.iter().enumerate() {
+        for &j in list {
+            adjacent[i][j] = true;
+            adjacent[j][i] = true;
+        }
+    }
+
+    let local_coefficients: Vec<f64> = adjacent
+        .iter()
+        .map(|row| {
+            let linked: Vec<usize> = (0..num_cities).filter(|&j| row[j]).collect();
+            let degree = linked.len();
+            if degree < 2 {
+                return 0.;
+            }
+            let triangles = linked
+                .iter()
+                .enumerate()
+                .flat_map(|(idx, &a)| linked[idx + 1..].iter().map(move |&b| (a, b)))
+                .filter(|&(a, b)| adjacent[a][b])
+                .count();
+            2. * triangles as f64 / (degree * (degree - 1)) as f64
+        })
+        .collect();
+
+    mean_and_variance(&local_coefficients).0
+}
+
+// off diagonal weights, row by row
+fn edge_weights<W: Weight>(intercity_map: &[Vec<W>]) -> impl Iterator<Item = W> + '_ {
+    intercity_map.iter().enumerate().flat_map(|(i, row)| {
+        row.iter()
+            .enumerate()
+            .filter(move |&(j, _)| i != j)
+            .map(|(_, &w)| w)
+    })
+}
+
+pub fn summarize<W: Weight>(intercity_map: &[Vec<W>]) -> Result<InstanceSummary<W>, TSPError> {
+    check_city_map(intercity_map)?;
+    Ok(summarize_costs(&intercity_map, intercity_map.len()))
+}
+
+// one pass over the pairs of cities without collecting their weights, so that instances too large
+// for their matrix to fit in memory can be summarized too, the variance kept by Welford's method
+pub(crate) fn summarize_costs<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+) -> InstanceSummary<W> {
+    let mut bounds: Option<(W, W)> = None;
+    let (mut num_weights, mut weight_mean, mut squared_deviations) = (0, 0., 0.);
+    let mut num_symmetric = 0;
+    for i in 0..num_cities as u32 {
+        for j in i + 1..num_cities as u32 {
+            let (there, back) = (cost_function.cost(i, j), cost_function.cost(j, i));
+            if there == back {
+                num_symmetric += 1;
+            }
+            for weight in [there, back] {
+                bounds = Some(match bounds {
+                    Some((min, max)) => (
+                        lighter(min, weight),
+                        if weight > max { weight } else { max },
+                    ),
+                    None => (weight, weight),
+                });
+                num_weights += 1;
+                let deviation = weight.to_f64() - weight_mean;
+                weight_mean += deviation / num_weights as f64;
+                squared_deviations += deviation * (weight.to_f64() - weight_mean);
+            }
+        }
+    }
+
+    let weight_variance = if num_weights > 0 {
+        squared_deviations / num_weights as f64
+    } else {
+        0.
+    };
+    let coefficient_of_variation = if weight_mean > 0. {
+        weight_variance.sqrt() / weight_mean
+    } else {
+        0.
+    };
+    let num_pairs = num_cities * num_cities.saturating_sub(1) / 2;
+    let symmetry = if num_pairs > 0 {
+        num_symmetric as f64 / num_pairs as f64
+    } else {
+        1.
+    };
+
+    let (weight_min, weight_max) = bounds.unwrap_or_default();
+    InstanceSummary {
+        num_cities,
+        weight_min,
+        weight_max,
+        weight_mean,
+        symmetry,
+        coefficient_of_variation,
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct HistogramBucket {
+    pub low: u16,
+    pub high: u16, // inclusive
+    pub count: usize,
+}
+
+// off diagonal weights in up to num_buckets equally wide buckets from the lightest to the
+// heaviest weight, a single bucket means every edge weighs the same
+pub fn weight_histogram(
+    intercity_map: &[Vec<u16>],
+    num_buckets: usize,
+) -> Result<Vec<HistogramBucket>, TSPError> {
+    check_city_map(intercity_map)?;
+
+    let weights: Vec<u16> = edge_weights(intercity_map).collect();
+    let (Some(&min), Some(&max)) = (weights.iter().min(), weights.iter().max()) else {
+        return Ok(Vec::new());
+    };
+    let span = (max - min) as usize + 1;
+    let width = span.div_ceil(num_buckets.max(1));
+
+    let mut buckets: Vec<HistogramBucket> = (0..span.div_ceil(width))
+        .map(|index| HistogramBucket {
+            low: (min as usize + index * width) as u16,
+            high: (min as usize + (index + 1) * width - 1).min(max as usize) as u16,
+            count: 0,
+        })
+        .collect();
+    for weight in weights {
+        buckets[(weight - min) as usize / width].count += 1;
+    }
+
+    Ok(buckets)
+}
+
+// one line per bucket, `low - high | ##### count`, the longest bar being bar_width wide
+pub fn format_histogram(buckets: &[HistogramBucket], bar_width: usize) -> String {
+    let max_count = buckets
+        .iter()
+        .map(|bucket| bucket.count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    buckets
+        .iter()
+        .map(|bucket| {
+            format!(
+                "{:>5} - {:>5} | {} {}",
+                bucket.low,
+                bucket.high,
+                "#".repeat((bucket.count * bar_width).div_ceil(max_count)),
+                bucket.count
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// pairs of cities that are probably the same place entered twice, which is usually a data
+// error and only gives the solvers more cities to order
+pub struct DuplicateReport {
+    pub close_pairs: Vec<(usize, usize, u16)>, // (city, city, weight) at most the threshold apart
+    pub identical_rows: Vec<(usize, usize)>,   // same weight to every other city
+}
+
+impl DuplicateReport {
+    pub fn is_empty(&self) -> bool {
+        self.close_pairs.is_empty() && self.identical_rows.is_empty()
+    }
+}
+
+// pairs weighted differently in each direction count the cheaper weight
+pub fn find_near_duplicates(
+    intercity_map: &[Vec<u16>],
+    threshold: u16,
+) -> Result<DuplicateReport, TSPError> {
+    check_city_map(intercity_map)?;
+
+    let num_cities = intercity_map.len();
+    let pairs = || (0..num_cities).flat_map(|i| (i + 1..num_cities).map(move |j| (i, j)));
+
+    let close_pairs = pairs()
+        .map(|(i, j)| (i, j, intercity_map[i][j].min(intercity_map[j][i])))
+        .filter(|&(_, _, weight)| weight <= threshold)
+        .collect();
+    let identical_rows = pairs()
+        .filter(|&(i, j)| {
+            (0..num_cities)
+                .filter(|&k| k != i && k != j)
+                .all(|k| intercity_map[i][k] == intercity_map[j][k])
+        })
+        .collect();
+
+    Ok(DuplicateReport {
+        close_pairs,
+        identical_rows,
+    })
+}
+
+pub fn estimate_hardness<W: Weight>(
+    intercity_map: &[Vec<W>],
+) -> Result<InstanceHardness, TSPError> {
+    check_city_map(intercity_map)?;
+
+    let num_cities = intercity_map.len();
+    let weights: Vec<f64> = edge_weights(intercity_map).map(W::to_f64).collect();
+    let (weight_mean, weight_variance) = mean_and_variance(&weights);
+    let coefficient_of_variation = if weight_mean > 0. {
+        weight_variance.sqrt() / weight_mean
+    } else {
+        0.
+    };
+
+    let neighbours = nearest_neighbours(intercity_map, NEIGHBOURHOOD_SIZE.min(num_cities - 1));
+    let clustering_coefficient = clustering_coefficient(&neighbours);
+
+    let gaps: Vec<f64> = neighbours
+        .iter()
+        .enumerate()
+        .filter(|(_, list)| list.len() >= 2)
+        .map(|(i, list)| {
+            let gap = intercity_map[i][list[1]].to_f64() - intercity_map[i][list[0]].to_f64();
+            if weight_mean > 0. {
+                gap / weight_mean
+            } else {
+                0.
+            }
+        })
+        .collect();
+    let (nearest_neighbour_gap_mean, gap_variance) = mean_and_variance(&gaps);
+
+    // large instances with varied weights and no clear structure are the hardest,
+    // clusters and clear nearest neighbour choices make greedy decisions more reliable
+    let score = num_cities as f64 * (1. + coefficient_of_variation) * (2. - clustering_coefficient)
+        / (1. + nearest_neighbour_gap_mean);
+
+    Ok(InstanceHardness {
+        weight_mean,
+        weight_variance,
+        coefficient_of_variation,
+        clustering_coefficient,
+        nearest_neighbour_gap_mean,
+        nearest_neighbour_gap_std: gap_variance.sqrt(),
+        score,
+    })
+}
+
+// fraction of the distinct (i, j, k) triples where going through j is shorter than going from
+// i to k directly, every triple is checked on small instances and a fixed sample on larger ones
+pub fn triangle_violations<W: Weight>(intercity_map: &[Vec<W>]) -> Result<f64, TSPError> {
+    check_city_map(intercity_map)?;
+
+    let num_cities = intercity_map.len();
+    if num_cities < 3 {
+        return Ok(0.);
+    }
+    let violated = |(i, j, k): (usize, usize, usize)| {
+        intercity_map[i][k].to_cost()
+            > intercity_map[i][j].to_cost() + intercity_map[j][k].to_cost()
+    };
+
+    let num_triples = num_cities * (num_cities - 1) * (num_cities - 2);
+    let (num_violated, num_checked) = if num_triples <= TRIANGLE_SAMPLES {
+        let triples = (0..num_cities)
+            .flat_map(|i| {
+                (0..num_cities).flat_map(move |j| (0..num_cities).map(move |k| (i, j, k)))
+            })
+            .filter(|&(i, j, k)| i != j && j != k && i != k);
+        (
+            triples.filter(|&triple| violated(triple)).count(),
+            num_triples,
+        )
+    } else {
+        let mut rng = StdRng::seed_from_u64(0);
+        let num_violated = (0..TRIANGLE_SAMPLES)
+            .map(|_| loop {
+                let triple = (
+                    rng.gen_range(0..num_cities),
+                    rng.gen_range(0..num_cities),
+                    rng.gen_range(0..num_cities),
+                );
+                if triple.0 != triple.1 && triple.1 != triple.2 && triple.0 != triple.2 {
+                    break triple;
+                }
+            })
+            .filter(|&triple| violated(triple))
+            .count();
+        (num_violated, TRIANGLE_SAMPLES)
+    };
+    Ok(num_violated as f64 / num_checked as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        estimate_hardness, find_near_duplicates, format_histogram, summarize, triangle_violations,
+        weight_histogram, HistogramBucket,
+    };
+    use crate::common::generate_map;
+
+    #[test]
+    fn test_uniform_weights() {
+        let map: Vec<Vec<u16>> = (0..6)
+            .map(|i| (0..6).map(|j| if i == j { 0 } else { 7 }).collect())
+            .collect();
+        let hardness = estimate_hardness(&map).ok().unwrap();
+
+        assert_eq!(hardness.weight_mean, 7.);
+        assert_eq!(hardness.weight_variance, 0.);
+        assert_eq!(hardness.coefficient_of_variation, 0.);
+        assert_eq!(hardness.nearest_neighbour_gap_mean, 0.);
+        assert!(hardness.suggested_max_iterations() >= 1000);
+    }
+
+    #[test]
+    fn test_hardness_grows_with_size() {
+        let small = estimate_hardness(&generate_map(5, (1, 100)).ok().unwrap())
+            .ok()
+            .unwrap();
+        let large = estimate_hardness(&generate_map(60, (1, 100)).ok().unwrap())
+            .ok()
+            .unwrap();
+
+        assert!((0.0..=1.0).contains(&small.clustering_coefficient));
+        assert!(large.score > small.score);
+        assert!(estimate_hardness::<u16>(&[]).is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let map: Vec<Vec<u16>> = vec![vec![0, 2, 4], vec![2, 0, 9], vec![4, 6, 0]];
+        let summary = summarize(&map).ok().unwrap();
+
+        assert_eq!(summary.num_cities, 3);
+        assert_eq!(summary.weight_min, 2);
+        assert_eq!(summary.weight_max, 9);
+        assert!((summary.weight_mean - 27. / 6.).abs() < 1e-9);
+        assert!((summary.symmetry - 2. / 3.).abs() < 1e-9);
+        assert!(summary.coefficient_of_variation > 0.);
+
+        let symmetric = generate_map(8, (1, 100)).ok().unwrap();
+        assert_eq!(summarize(&symmetric).ok().unwrap().symmetry, 1.);
+        assert!(summarize::<u16>(&[]).is_err());
+
+        // weights past u16 and floats summarized the same way
+        let wide: Vec<Vec<u32>> = vec![vec![0, 70000, 4], vec![70000, 0, 9], vec![4, 9, 0]];
+        assert_eq!(summarize(&wide).ok().unwrap().weight_max, 70000);
+        let real = vec![vec![0., 1.5, 2.5], vec![1.5, 0., 0.5], vec![2.5, 0.5, 0.]];
+        let summary = summarize(&real).ok().unwrap();
+        assert_eq!((summary.weight_min, summary.weight_max), (0.5, 2.5));
+        assert!((summary.weight_mean - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_near_duplicates() {
+        // cities 1 and 3 are the same place, 0 and 2 are merely close
+        let map = vec![
+            vec![0, 5, 2, 5],
+            vec![5, 0, 7, 0],
+            vec![2, 7, 0, 7],
+            vec![5, 0, 7, 0],
+        ];
+        let report = find_near_duplicates(&map, 2).ok().unwrap();
+        assert_eq!(report.close_pairs, vec![(0, 2, 2), (1, 3, 0)]);
+        assert_eq!(report.identical_rows, vec![(1, 3)]);
+        assert!(!report.is_empty());
+
+        let report = find_near_duplicates(&map, 0).ok().unwrap();
+        assert_eq!(report.close_pairs, vec![(1, 3, 0)]);
+
+        let distinct = vec![vec![0, 3, 4], vec![3, 0, 5], vec![4, 5, 0]];
+        assert!(find_near_duplicates(&distinct, 2).ok().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_weight_histogram() {
+        let map = vec![vec![0, 1, 10], vec![1, 0, 4], vec![10, 4, 0]];
+        let buckets = weight_histogram(&map, 2).ok().unwrap();
+        assert_eq!(
+            buckets,
+            vec![
+                HistogramBucket {
+                    low: 1,
+                    high: 5,
+                    count: 4
+                },
+                HistogramBucket {
+                    low: 6,
+                    high: 10,
+                    count: 2
+                }
+            ]
+        );
+        assert_eq!(
+            format_histogram(&buckets, 4),
+            "    1 -     5 | #### 4\n    6 -    10 | ## 2"
+        );
+
+        // all equal weights collapse into one bucket
+        let uniform = vec![vec![0, 3], vec![3, 0]];
+        assert_eq!(weight_histogram(&uniform, 10).ok().unwrap().len(), 1);
+        assert!(weight_histogram(&[vec![0]], 10).ok().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_triangle_violations() {
+        // 0 to 2 directly costs 9 but only 2 through 1, both directions
+        let map: Vec<Vec<u16>> = vec![vec![0, 1, 9], vec![1, 0, 1], vec![9, 1, 0]];
+        assert!((triangle_violations(&map).ok().unwrap() - 2. / 6.).abs() < 1e-9);
+
+        let uniform: Vec<Vec<u16>> = vec![vec![0, 3, 3], vec![3, 0, 3], vec![3, 3, 0]];
+        assert_eq!(triangle_violations(&uniform).ok().unwrap(), 0.);
+        assert_eq!(triangle_violations(&[vec![0u16]]).ok().unwrap(), 0.);
+    }
+}
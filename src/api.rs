@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{summarize, InstanceSummary};
+use crate::brute_force::brute_force_tsp;
+use crate::common::TSPError;
+use crate::sim_annealing::simulated_annealing_tsp;
+use crate::tour::normalize_path;
+
+// request and response bodies shared by the wasm bindings and the network services
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Instance {
+    pub intercity_map: Vec<Vec<u16>>,
+}
+
+impl Instance {
+    // weight statistics, e.g. for picking solver parameters before a solve
+    pub fn summary(&self) -> Result<InstanceSummary, TSPError> {
+        summarize(&self.intercity_map)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Algorithm {
+    BruteForce,
+    SimulatedAnnealing,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct Config {
+    pub algorithm: Algorithm,
+    pub temperature: u64,
+    pub max_iterations: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            algorithm: Algorithm::SimulatedAnnealing,
+            temperature: 200,
+            max_iterations: 200000,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Solution {
+    pub path: Vec<u32>,
+    pub cost: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SolveRequest {
+    pub instance: Instance,
+    #[serde(default)]
+    pub config: Config,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Done(Solution),
+    Failed { error: String },
+}
+
+impl From<Result<Solution, TSPError>> for JobStatus {
+    fn from(result: Result<Solution, TSPError>) -> Self {
+        match result {
+            Ok(solution) => JobStatus::Done(solution),
+            Err(err) => JobStatus::Failed {
+                error: err.to_string(),
+            },
+        }
+    }
+}
+
+// serializes through serde_json::Value, whose maps are sorted by key (serde_json's
+// preserve_order feature must stay off), so output is byte for byte stable even for
+// types holding hash maps and can be diffed or snapshotted across runs
+pub fn to_canonical_json<T: Serialize>(value: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&serde_json::to_value(value)?)
+}
+
+pub fn solve(instance: &Instance, config: &Config) -> Result<Solution, TSPError> {
+    let (path, cost) = match config.algorithm {
+        Algorithm::BruteForce => brute_force_tsp(&instance.intercity_map),
+        Algorithm::SimulatedAnnealing => simulated_annealing_tsp(
+            &instance.intercity_map,
+            config.temperature,
+            config.max_iterations,
+        ),
+    }?;
+
+    Ok(Solution {
+        path: normalize_path(&instance.intercity_map, &path),
+        cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{solve, to_canonical_json, Algorithm, Config, Instance, JobStatus, Solution};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_config_defaults() {
+        let config: Config = serde_json::from_str(r#"{"algorithm": "brute_force"}"#).unwrap();
+        assert_eq!(config.algorithm, Algorithm::BruteForce);
+        assert_eq!(config.max_iterations, Config::default().max_iterations);
+
+        let instance: Instance =
+            serde_json::from_str(r#"{"intercity_map": [[0, 1, 9], [1, 0, 1], [9, 1, 0]]}"#)
+                .unwrap();
+        assert_eq!(solve(&instance, &config).ok().unwrap().cost, 2);
+        assert_eq!(instance.summary().ok().unwrap().weight_max, 9);
+    }
+
+    #[test]
+    fn test_canonical_json() {
+        let done = JobStatus::Done(Solution {
+            path: vec![2, 0, 1],
+            cost: 7,
+        });
+        assert_eq!(
+            to_canonical_json(&done).unwrap(),
+            r#"{"cost":7,"path":[2,0,1],"status":"done"}"#
+        );
+
+        // insertion order doesn't leak into the output
+        let forwards: HashMap<u32, u32> = (0..100).map(|i| (i, i)).collect();
+        let backwards: HashMap<u32, u32> = (0..100).rev().map(|i| (i, i)).collect();
+        assert_eq!(
+            to_canonical_json(&forwards).unwrap(),
+            to_canonical_json(&backwards).unwrap()
+        );
+    }
+}
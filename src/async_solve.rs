@@ -0,0 +1,83 @@
+use std::ops::ControlFlow;
+use tokio_util::sync::CancellationToken;
+
+use crate::common::TSPError;
+use crate::config::SolverConfig;
+use crate::sim_annealing::Annealer;
+
+// iterations between cancellation checks
+const CANCELLATION_CHECK_INTERVAL: u64 = 1000;
+
+// runs simulated annealing on tokio's blocking pool, the solve stops early when the token
+// is cancelled or when the returned future is dropped, and resolves to TSPError::Cancelled, the
+// caller's token itself is only ever cancelled by the caller
+pub async fn solve_async(
+    intercity_map: Vec<Vec<u16>>,
+    temperature: u64,
+    max_iterations: u64,
+    token: CancellationToken,
+) -> Result<(Vec<u32>, u64), TSPError> {
+    let token = token.child_token();
+    let _drop_guard = token.clone().drop_guard();
+    let worker_token = token.clone();
+
+    let solve = tokio::task::spawn_blocking(move || {
+        let config = SolverConfig {
+            temperature,
+            max_iterations,
+            ..SolverConfig::default()
+        };
+        let (path, cost, _) = Annealer::new(config)
+            .on_progress(CANCELLATION_CHECK_INTERVAL, |_| {
+                if worker_token.is_cancelled() {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .solve(&intercity_map)?;
+        Ok((path, cost))
+    });
+
+    let result = solve.await.map_err(|_| TSPError::Cancelled)?;
+    if token.is_cancelled() {
+        return Err(TSPError::Cancelled);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::async_solve::solve_async;
+    use crate::common::{generate_map, TSPError};
+    use crate::testing::is_cost_consistent;
+    use tokio_util::sync::CancellationToken;
+
+    #[tokio::test]
+    async fn test_solve_async() {
+        let map = generate_map(6, (1, 100)).ok().unwrap();
+        let (path, cost) = solve_async(map.clone(), 200, 2000, CancellationToken::new())
+            .await
+            .ok()
+            .unwrap();
+        assert!(is_cost_consistent(&map, &path, cost));
+
+        // finishing leaves the token as it was, for the caller to use again
+        let token = CancellationToken::new();
+        assert!(solve_async(map.clone(), 200, 2000, token.clone())
+            .await
+            .is_ok());
+        assert!(!token.is_cancelled());
+        assert!(solve_async(map, 200, 2000, token.clone()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cancellation() {
+        let map = generate_map(6, (1, 100)).ok().unwrap();
+        let token = CancellationToken::new();
+        let solve = tokio::spawn(solve_async(map, 200, u64::MAX, token.clone()));
+
+        token.cancel();
+        assert!(matches!(solve.await.unwrap(), Err(TSPError::Cancelled)));
+    }
+}
@@ -0,0 +1,220 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::analysis::{estimate_hardness, triangle_violations};
+use crate::bench::BenchSolver;
+use crate::brute_force::{estimate_brute_force_seconds, DEFAULT_MAX_CITIES};
+use crate::common::{format_seconds, TSPError};
+use crate::weight::Weight;
+
+// auto mode picks the solver and its settings from the instance's size, how far its weights are
+// from satisfying the triangle inequality and the time budget, keeping the reasoning so that
+// `--explain` can show it
+
+// brute force is picked without a budget when it is expected to finish within this
+const DEFAULT_EXACT_SECONDS: f64 = 10.;
+const TEMPERATURE: u64 = 200;
+// above this fraction of shortcut triples the iteration budget is doubled, rounding makes a few
+// triples of TSPLIB's Euclidean instances shortcuts by a unit
+const METRIC_TOLERANCE: f64 = 0.05;
+
+pub struct AutoChoice {
+    pub solver: BenchSolver,
+    pub reasons: Vec<String>,
+    pub trade_offs: Vec<String>,
+}
+
+impl fmt::Display for AutoChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.solver {
+            BenchSolver::BruteForce { .. } => write!(f, "chose brute_force")?,
+            BenchSolver::HeldKarp => write!(f, "chose held_karp")?,
+            BenchSolver::BranchAndBound { .. } => write!(f, "chose branch_and_bound")?,
+            BenchSolver::Christofides => write!(f, "chose christofides")?,
+            BenchSolver::DoubleTree => write!(f, "chose double_tree")?,
+            BenchSolver::LinKernighan => write!(f, "chose lin_kernighan")?,
+            BenchSolver::Named { name, .. } => write!(f, "chose {}", name)?,
+            BenchSolver::SimulatedAnnealing {
+                temperature,
+                max_iterations,
+                time_limit,
+            } => {
+                write!(
+                    f,
+                    "chose simulated_annealing, temperature {}, {} iterations",
+                    temperature, max_iterations
+                )?;
+                if let Some(time_limit) = time_limit {
+                    write!(f, ", stopping after {:.3}s", time_limit.as_secs_f64())?;
+                }
+            }
+        }
+        for reason in &self.reasons {
+            write!(f, "\n  because {}", reason)?;
+        }
+        for trade_off in &self.trade_offs {
+            write!(f, "\n  instead, {}", trade_off)?;
+        }
+        Ok(())
+    }
+}
+
+// the most cities brute force is expected to finish within the given seconds
+fn exact_city_limit(seconds: f64) -> usize {
+    (1..)
+        .take_while(|&num_cities| estimate_brute_force_seconds(num_cities) <= seconds)
+        .last()
+        .unwrap_or(0)
+}
+
+pub fn choose_solver<W: Weight>(
+    intercity_map: &[Vec<W>],
+    budget: Option<Duration>,
+) -> Result<AutoChoice, TSPError> {
+    let num_cities = intercity_map.len();
+    let hardness = estimate_hardness(intercity_map)?;
+    let violations = triangle_violations(intercity_map)?;
+
+    let exact_seconds = estimate_brute_force_seconds(num_cities);
+    let allowed_seconds = budget.map_or(DEFAULT_EXACT_SECONDS, |budget| budget.as_secs_f64());
+    let budget_name = match budget {
+        Some(budget) => format!("the {:.3}s budget", budget.as_secs_f64()),
+        None => format!("the default {}s for exact solving", DEFAULT_EXACT_SECONDS),
+    };
+
+    let mut reasons = Vec::new();
+    let mut trade_offs = Vec::new();
+    if num_cities <= DEFAULT_MAX_CITIES && exact_seconds <= allowed_seconds {
+        reasons.push(format!(
+            "{} cities take brute force about {}, within {}, and its tour is optimal",
+            num_cities,
+            format_seconds(exact_seconds),
+            budget_name
+        ));
+        trade_offs.push(format!(
+            "a budget under {} switches to simulated annealing, which is faster but not \
+             guaranteed to find the optimum",
+            format_seconds(exact_seconds)
+        ));
+        return Ok(AutoChoice {
+            solver: BenchSolver::BruteForce { time_limit: None },
+            reasons,
+            trade_offs,
+        });
+    }
+
+    reasons.push(format!(
+        "{} cities would take brute force about {}, more than {}",
+        num_cities,
+        format_seconds(exact_seconds),
+        budget_name
+    ));
+
+    let mut max_iterations = hardness.suggested_max_iterations();
+    reasons.push(format!(
+        "a hardness score of {:.1} suggests {} iterations",
+        hardness.score, max_iterations
+    ));
+    if violations > METRIC_TOLERANCE {
+        max_iterations *= 2;
+        reasons.push(format!(
+            "{:.1}% of city triples break the triangle inequality, so local moves say less about \
+             the whole tour and the iterations are doubled",
+            100. * violations
+        ));
+    } else {
+        reasons.push(
+            "the weights all but satisfy the triangle inequality, so the reported spanning tree bound \
+             is within a factor of 2 of the optimum"
+                .to_string(),
+        );
+    }
+    if let Some(budget) = budget {
+        reasons.push(format!(
+            "the run stops at the {:.3}s budget with the best tour so far",
+            budget.as_secs_f64()
+        ));
+    }
+
+    trade_offs.push(format!(
+        "`--iterations <n>` trades time for quality, {} is a quicker but rougher start",
+        (max_iterations / 4).max(1000)
+    ));
+    if num_cities <= DEFAULT_MAX_CITIES {
+        trade_offs.push(format!(
+            "a budget of {} makes brute force find the optimum",
+            format_seconds(exact_seconds)
+        ));
+    } else {
+        trade_offs.push(format!(
+            "brute force is only an option up to {} cities, about {} cities fit within {}",
+            DEFAULT_MAX_CITIES,
+            exact_city_limit(allowed_seconds),
+            budget_name
+        ));
+    }
+
+    Ok(AutoChoice {
+        solver: BenchSolver::SimulatedAnnealing {
+            temperature: TEMPERATURE,
+            max_iterations,
+            time_limit: budget,
+        },
+        reasons,
+        trade_offs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auto::choose_solver;
+    use crate::bench::BenchSolver;
+    use crate::common::generate_map;
+    use std::time::Duration;
+
+    #[test]
+    fn test_choose_solver() {
+        let small = generate_map(6, (1, 100)).ok().unwrap();
+        let choice = choose_solver(&small, None).ok().unwrap();
+        assert_eq!(choice.solver, BenchSolver::BruteForce { time_limit: None });
+        assert!(choice
+            .to_string()
+            .starts_with("chose brute_force\n  because 6 cities"));
+
+        // too little time for the exact solver
+        let choice = choose_solver(&small, Some(Duration::from_nanos(1)))
+            .ok()
+            .unwrap();
+        assert!(matches!(
+            choice.solver,
+            BenchSolver::SimulatedAnnealing {
+                time_limit: Some(_),
+                ..
+            }
+        ));
+        assert!(!choice.trade_offs.is_empty());
+
+        // consecutive cities are far apart but close to everything else
+        let map: Vec<Vec<u16>> = (0..20)
+            .map(|i: usize| {
+                (0..20)
+                    .map(|j: usize| match i.abs_diff(j) {
+                        0 => 0,
+                        1 => 50,
+                        _ => 1,
+                    })
+                    .collect()
+            })
+            .collect();
+        let doubled = |map: &[Vec<u16>]| {
+            let choice = choose_solver(map, None).ok().unwrap();
+            choice
+                .reasons
+                .iter()
+                .any(|reason| reason.contains("doubled"))
+        };
+        assert!(doubled(&map));
+        assert!(!doubled(&generate_map(20, (10, 11)).ok().unwrap()));
+        assert!(choose_solver::<u16>(&[], None).is_err());
+    }
+}
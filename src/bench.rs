@@ -0,0 +1,1009 @@
+use log::{error, info};
+use rand::{rngs::StdRng, SeedableRng};
+use std::fs::File;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::common::{generate_map_with_rng, TSPError};
+use crate::config::SolverConfig;
+use crate::cost::{CostFunction, MatrixCost, TourMode};
+use crate::instance::TspInstance;
+use crate::io::tsplib::{read_tsplib, TsplibInstance};
+use crate::memory::{
+    check_memory_limit, estimate_annealing_memory, estimate_brute_force_memory,
+    estimate_christofides_memory, estimate_held_karp_memory, estimate_lin_kernighan_memory,
+    process_peak_memory,
+};
+use crate::significance::{wilcoxon_signed_rank, SignedRankTest};
+use crate::sim_annealing::{Annealer, SolverEvent};
+use crate::solver::{solver_by_name, Budget, Solver};
+use crate::solvers::held_karp::{held_karp_tsp_with, HELD_KARP_MAX_CITIES};
+use crate::stats::{cpu_time_since, process_cpu_time, SolveStats};
+use crate::weight::Weight;
+
+// published optimal round trip lengths of the symmetric TSPLIB instances
+pub const KNOWN_OPTIMA: &[(&str, u64)] = &[
+    ("burma14", 3323),
+    ("ulysses16", 6859),
+    ("gr17", 2085),
+    ("gr21", 2707),
+    ("ulysses22", 7013),
+    ("gr24", 1272),
+    ("fri26", 937),
+    ("bayg29", 1610),
+    ("bays29", 2020),
+    ("dantzig42", 699),
+    ("swiss42", 1273),
+    ("att48", 10628),
+    ("gr48", 5046),
+    ("hk48", 11461),
+    ("eil51", 426),
+    ("berlin52", 7542),
+    ("brazil58", 25395),
+    ("st70", 675),
+    ("eil76", 538),
+    ("pr76", 108159),
+    ("gr96", 55209),
+    ("rat99", 1211),
+    ("kroA100", 21282),
+    ("kroB100", 22141),
+    ("kroC100", 20749),
+    ("kroD100", 21294),
+    ("kroE100", 22068),
+    ("rd100", 7910),
+    ("eil101", 629),
+    ("lin105", 14379),
+    ("pr107", 44303),
+    ("gr120", 6942),
+    ("pr124", 59030),
+    ("bier127", 118282),
+    ("ch130", 6110),
+    ("pr136", 96772),
+    ("gr137", 69853),
+    ("pr144", 58537),
+    ("ch150", 6528),
+    ("kroA150", 26524),
+    ("kroB150", 26130),
+    ("pr152", 73682),
+    ("u159", 42080),
+    ("a280", 2579),
+];
+
+// brute force is skipped on anything bigger, it would never finish
+const BRUTE_FORCE_MAX_CITIES: usize = 10;
+// branch and bound only finishes past this when its bound happens to be tight
+const BRANCH_AND_BOUND_MAX_CITIES: usize = 30;
+// generated instances up to this size have their optimum worked out by Held-Karp
+const GENERATED_OPTIMUM_MAX_CITIES: usize = 16;
+
+pub fn known_optimum(name: &str) -> Option<u64> {
+    KNOWN_OPTIMA
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|&(_, optimum)| optimum)
+}
+
+// each solver has its own budget, a time limit stops a run early with the best it has so far,
+// so exact solvers can be given minutes while the heuristics get seconds
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BenchSolver {
+    SimulatedAnnealing {
+        temperature: u64,
+        max_iterations: u64,
+        time_limit: Option<Duration>,
+    },
+    BruteForce {
+        time_limit: Option<Duration>,
+    },
+    HeldKarp,
+    BranchAndBound {
+        time_limit: Option<Duration>,
+    },
+    Christofides,
+    DoubleTree,
+    LinKernighan,
+    // any other solver of solver::solver_by_name, with its default settings and the budget
+    Named {
+        name: &'static str,
+        budget: Budget,
+    },
+}
+
+impl BenchSolver {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BenchSolver::SimulatedAnnealing { .. } => "simulated_annealing",
+            BenchSolver::BruteForce { .. } => "brute_force",
+            BenchSolver::HeldKarp => "held_karp",
+            BenchSolver::BranchAndBound { .. } => "branch_and_bound",
+            BenchSolver::Christofides => "christofides",
+            BenchSolver::DoubleTree => "double_tree",
+            BenchSolver::LinKernighan => "lin_kernighan",
+            BenchSolver::Named { name, .. } => name,
+        }
+    }
+
+    pub fn estimated_memory(&self, num_cities: usize) -> usize {
+        match self {
+            BenchSolver::SimulatedAnnealing { .. } => estimate_annealing_memory(num_cities),
+            BenchSolver::BruteForce { .. } => estimate_brute_force_memory(num_cities),
+            BenchSolver::HeldKarp => estimate_held_karp_memory(num_cities),
+            BenchSolver::BranchAndBound { .. } => estimate_brute_force_memory(num_cities),
+            BenchSolver::Christofides => estimate_christofides_memory(num_cities),
+            BenchSolver::DoubleTree => estimate_annealing_memory(num_cities),
+            BenchSolver::LinKernighan => estimate_lin_kernighan_memory(num_cities),
+            BenchSolver::Named { .. } => estimate_annealing_memory(num_cities),
+        }
+    }
+
+    // under the mode, round trips being what bench scores against the published optima
+    pub fn solve<W: Weight<Cost = u64>>(
+        &self,
+        intercity_map: &[Vec<W>],
+        mode: TourMode,
+    ) -> Result<(Vec<u32>, u64), TSPError> {
+        let instance = TspInstance::new(intercity_map.to_vec())?.with_mode(mode);
+        let (solver, budget): (Box<dyn Solver<W>>, Budget) = match *self {
+            BenchSolver::SimulatedAnnealing {
+                temperature,
+                max_iterations,
+                time_limit,
+            } => {
+                let config = SolverConfig {
+                    temperature,
+                    max_iterations,
+                    ..SolverConfig::default()
+                };
+                let budget = Budget {
+                    time_limit,
+                    ..Budget::default()
+                };
+                (Box::new(config), budget)
+            }
+            BenchSolver::BruteForce { time_limit } | BenchSolver::BranchAndBound { time_limit } => {
+                let solver = solver_by_name(self.name()).expect("a registered solver");
+                let budget = Budget {
+                    time_limit,
+                    ..Budget::default()
+                };
+                (solver, budget)
+            }
+            BenchSolver::HeldKarp
+            | BenchSolver::Christofides
+            | BenchSolver::DoubleTree
+            | BenchSolver::LinKernighan => (
+                solver_by_name(self.name()).expect("a registered solver"),
+                Budget::default(),
+            ),
+            BenchSolver::Named { name, budget } => (
+                solver_by_name(name).expect("parse_solver only names known solvers"),
+                budget,
+            ),
+        };
+        let solution = solver.solve(&instance, &budget)?;
+        Ok((solution.tour.into_cities(), solution.cost))
+    }
+
+    // solve, calling on_event at the end of every epoch of epoch_length iterations when annealing,
+    // and returning the annealing run's statistics with the tour
+    pub fn solve_observed<W: Weight<Cost = u64>>(
+        &self,
+        intercity_map: &[Vec<W>],
+        mode: TourMode,
+        epoch_length: u64,
+        on_event: impl FnMut(&SolverEvent),
+    ) -> ObservedResult<W> {
+        match *self {
+            BenchSolver::SimulatedAnnealing {
+                temperature,
+                max_iterations,
+                time_limit,
+            } => {
+                let config = SolverConfig {
+                    temperature,
+                    max_iterations,
+                    time_limit,
+                    mode,
+                    ..SolverConfig::default()
+                };
+                let (path, cost, stats) = Annealer::new(config)
+                    .observer(epoch_length, on_event)
+                    .solve(intercity_map)?;
+                Ok((path, cost, Some(stats)))
+            }
+            BenchSolver::BruteForce { .. }
+            | BenchSolver::HeldKarp
+            | BenchSolver::BranchAndBound { .. }
+            | BenchSolver::Christofides
+            | BenchSolver::DoubleTree
+            | BenchSolver::LinKernighan
+            | BenchSolver::Named { .. } => {
+                let (path, cost) = self.solve(intercity_map, mode)?;
+                Ok((path, cost, None))
+            }
+        }
+    }
+}
+
+// a tour, its cost and, for annealing, how the run went
+pub type ObservedResult<W = u16> =
+    Result<(Vec<u32>, u64, Option<SolveStats<<W as Weight>::Delta>>), TSPError>;
+
+// `name` or `name=budget`, the budget being a time limit like `5s` or `0.5s`, or for simulated
+// annealing an iteration count like `1e6` which replaces max_iterations
+pub fn parse_solver(spec: &str, temperature: u64, max_iterations: u64) -> Option<BenchSolver> {
+    let (name, budget) = match spec.split_once('=') {
+        Some((name, budget)) => (name, Some(budget)),
+        None => (spec, None),
+    };
+
+    let time_limit = match budget.map(|budget| budget.strip_suffix('s')) {
+        Some(Some(seconds)) => match seconds.parse::<f64>() {
+            Ok(seconds) if seconds >= 0. => Some(Duration::from_secs_f64(seconds)),
+            _ => {
+                error!("Invalid time limit '{}s' for {}", seconds, name);
+                return None;
+            }
+        },
+        _ => None,
+    };
+    let iterations = match budget.filter(|_| time_limit.is_none()) {
+        Some(iterations) => match iterations.parse::<f64>() {
+            Ok(iterations) if iterations >= 0. && iterations.fract() == 0. => {
+                Some(iterations as u64)
+            }
+            _ => {
+                error!("Invalid budget '{}' for {}", iterations, name);
+                return None;
+            }
+        },
+        None => None,
+    };
+
+    match name {
+        "simulated_annealing" => Some(BenchSolver::SimulatedAnnealing {
+            temperature,
+            max_iterations: iterations.unwrap_or(max_iterations),
+            time_limit,
+        }),
+        "brute_force" if iterations.is_none() => Some(BenchSolver::BruteForce { time_limit }),
+        "brute_force" => {
+            error!("Brute force only takes a time limit");
+            None
+        }
+        "held_karp" if budget.is_none() => Some(BenchSolver::HeldKarp),
+        "held_karp" => {
+            error!("Held-Karp takes no budget");
+            None
+        }
+        "branch_and_bound" if iterations.is_none() => {
+            Some(BenchSolver::BranchAndBound { time_limit })
+        }
+        "branch_and_bound" => {
+            error!("Branch and bound only takes a time limit");
+            None
+        }
+        "christofides" if budget.is_none() => Some(BenchSolver::Christofides),
+        "christofides" => {
+            error!("Christofides takes no budget");
+            None
+        }
+        "double_tree" if budget.is_none() => Some(BenchSolver::DoubleTree),
+        "double_tree" => {
+            error!("The double tree takes no budget");
+            None
+        }
+        "lin_kernighan" if budget.is_none() => Some(BenchSolver::LinKernighan),
+        "lin_kernighan" => {
+            error!("Lin-Kernighan takes no budget");
+            None
+        }
+        _ => match solver_by_name::<u16>(name) {
+            Some(solver) => Some(BenchSolver::Named {
+                name: solver.name(),
+                budget: Budget {
+                    time_limit,
+                    max_iterations: iterations,
+                },
+            }),
+            None => {
+                error!("Unknown solver {}", name);
+                None
+            }
+        },
+    }
+}
+
+// an instance to run the solvers on, with its optimal round trip when that is known
+pub struct BenchInstance {
+    pub name: String,
+    pub intercity_map: Vec<Vec<u16>>,
+    pub optimum: Option<u64>,
+}
+
+pub struct BenchResult {
+    pub instance: String,
+    pub num_cities: usize,
+    pub solver: &'static str,
+    pub tour_cost: u64, // of a round trip, as the optima are
+    pub optimum: Option<u64>,
+    pub elapsed: Duration,
+    pub cpu_time: Option<Duration>,
+    pub estimated_memory: usize,
+    pub peak_memory: Option<usize>, // of the whole process, so far
+}
+
+impl BenchResult {
+    // percentage above the known optimum
+    pub fn gap(&self) -> Option<f64> {
+        self.optimum
+            .map(|optimum| 100. * (self.tour_cost as f64 - optimum as f64) / optimum as f64)
+    }
+}
+
+pub struct SolverScore {
+    pub solver: &'static str,
+    pub num_instances: usize,
+    pub num_optimal: usize,
+    pub mean_gap: f64,
+    pub max_gap: f64,
+}
+
+// every .tsp file in the directory in file name order, unnamed instances take the file's name
+pub(crate) fn load_suite(suite: &Path) -> Result<Vec<TsplibInstance>, TSPError> {
+    let mut files: Vec<_> = std::fs::read_dir(suite)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "tsp"))
+        .collect();
+    files.sort();
+
+    if files.is_empty() {
+        error!("No .tsp files in {}", suite.display());
+        return Err(TSPError::InvalidFileFormat);
+    }
+
+    files
+        .iter()
+        .map(|file| {
+            let mut instance = read_tsplib(&mut File::open(file)?)?;
+            if instance.name.is_empty() {
+                instance.name = file.file_stem().unwrap().to_string_lossy().into_owned();
+            }
+            Ok(instance)
+        })
+        .collect()
+}
+
+// runs every solver on every .tsp file in the directory, warning about runs projected to
+// need more than memory_limit bytes
+pub fn run_suite(
+    suite: &Path,
+    solvers: &[BenchSolver],
+    memory_limit: Option<usize>,
+) -> Result<Vec<BenchResult>, TSPError> {
+    run_instances(&suite_instances(suite)?, solvers, memory_limit, 1)
+}
+
+// the .tsp files of the directory, with the published optima of those that have one
+pub fn suite_instances(suite: &Path) -> Result<Vec<BenchInstance>, TSPError> {
+    Ok(load_suite(suite)?
+        .into_iter()
+        .map(|instance| BenchInstance {
+            optimum: known_optimum(&instance.name),
+            name: instance.name,
+            intercity_map: instance.intercity_map,
+        })
+        .collect())
+}
+
+// count random instances of each size with weights from 1 to 100, named like `random50_2`,
+// the same seed giving the same instances, the optimum worked out for the small ones
+pub fn generate_instances(
+    sizes: &[u32],
+    count: usize,
+    seed: u64,
+) -> Result<Vec<BenchInstance>, TSPError> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut instances = Vec::new();
+    for &num_cities in sizes {
+        for index in 0..count {
+            let intercity_map = generate_map_with_rng(num_cities, (1, 100), &mut rng)?;
+            let optimum = if num_cities as usize <= GENERATED_OPTIMUM_MAX_CITIES {
+                Some(held_karp_tsp_with(&intercity_map, intercity_map.len(), TourMode::Closed)?.1)
+            } else {
+                None
+            };
+            instances.push(BenchInstance {
+                name: format!("random{}_{}", num_cities, index),
+                intercity_map,
+                optimum,
+            });
+        }
+    }
+    Ok(instances)
+}
+
+// runs every solver repetitions times on every instance, one result per run
+pub fn run_instances(
+    instances: &[BenchInstance],
+    solvers: &[BenchSolver],
+    memory_limit: Option<usize>,
+    repetitions: usize,
+) -> Result<Vec<BenchResult>, TSPError> {
+    let mut results = Vec::new();
+    for BenchInstance {
+        name,
+        intercity_map,
+        optimum,
+    } in instances
+    {
+        for solver in solvers {
+            if matches!(solver, BenchSolver::BruteForce { time_limit: None })
+                && intercity_map.len() > BRUTE_FORCE_MAX_CITIES
+            {
+                info!("Skipping brute force on {}, too many cities", name);
+                continue;
+            }
+            if *solver == BenchSolver::HeldKarp && intercity_map.len() > HELD_KARP_MAX_CITIES {
+                info!("Skipping Held-Karp on {}, too many cities", name);
+                continue;
+            }
+            if matches!(solver, BenchSolver::BranchAndBound { time_limit: None })
+                && intercity_map.len() > BRANCH_AND_BOUND_MAX_CITIES
+            {
+                info!("Skipping branch and bound on {}, too many cities", name);
+                continue;
+            }
+            if matches!(
+                solver,
+                BenchSolver::Christofides | BenchSolver::LinKernighan
+            ) && !MatrixCost::new(intercity_map).is_symmetric()
+            {
+                info!(
+                    "Skipping {} on {}, it needs symmetric weights",
+                    solver.name(),
+                    name
+                );
+                continue;
+            }
+
+            let estimated_memory = solver.estimated_memory(intercity_map.len());
+            check_memory_limit(solver.name(), estimated_memory, memory_limit);
+
+            for _ in 0..repetitions {
+                let start = Instant::now();
+                let cpu_start = process_cpu_time();
+                let (_, tour_cost) = solver.solve(intercity_map, TourMode::Closed)?;
+                let elapsed = start.elapsed();
+                let cpu_time = cpu_time_since(cpu_start);
+
+                results.push(BenchResult {
+                    instance: name.clone(),
+                    num_cities: intercity_map.len(),
+                    solver: solver.name(),
+                    tour_cost,
+                    optimum: *optimum,
+                    elapsed,
+                    cpu_time,
+                    estimated_memory,
+                    peak_memory: process_peak_memory(),
+                });
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+// aggregates the gaps per solver, instances without a known optimum are left out
+pub fn score(results: &[BenchResult]) -> Vec<SolverScore> {
+    let mut scores: Vec<SolverScore> = Vec::new();
+    for result in results {
+        let Some(gap) = result.gap() else {
+            continue;
+        };
+
+        let index = match scores
+            .iter()
+            .position(|score| score.solver == result.solver)
+        {
+            Some(index) => index,
+            None => {
+                scores.push(SolverScore {
+                    solver: result.solver,
+                    num_instances: 0,
+                    num_optimal: 0,
+                    mean_gap: 0.,
+                    max_gap: 0.,
+                });
+                scores.len() - 1
+            }
+        };
+
+        let score = &mut scores[index];
+        score.num_instances += 1;
+        score.num_optimal += (gap <= 0.) as usize;
+        score.mean_gap += (gap - score.mean_gap) / score.num_instances as f64;
+        score.max_gap = score.max_gap.max(gap);
+    }
+
+    scores
+}
+
+// how one solver did over its runs on one instance
+pub struct Comparison {
+    pub instance: String,
+    pub solver: &'static str,
+    pub num_runs: usize,
+    pub best_cost: u64,
+    pub mean_cost: f64,
+    pub optimum: Option<u64>,
+    pub mean_elapsed: Duration,
+}
+
+impl Comparison {
+    // percentage of the best run above the known optimum
+    pub fn gap(&self) -> Option<f64> {
+        self.optimum
+            .map(|optimum| 100. * (self.best_cost as f64 - optimum as f64) / optimum as f64)
+    }
+}
+
+// the runs of each solver on each instance summed up, in the order they were run
+pub fn compare(results: &[BenchResult]) -> Vec<Comparison> {
+    let mut comparisons: Vec<Comparison> = Vec::new();
+    for result in results {
+        let index = match comparisons.iter().position(|comparison| {
+            comparison.instance == result.instance && comparison.solver == result.solver
+        }) {
+            Some(index) => index,
+            None => {
+                comparisons.push(Comparison {
+                    instance: result.instance.clone(),
+                    solver: result.solver,
+                    num_runs: 0,
+                    best_cost: u64::MAX,
+                    mean_cost: 0.,
+                    optimum: result.optimum,
+                    mean_elapsed: Duration::ZERO,
+                });
+                comparisons.len() - 1
+            }
+        };
+
+        let comparison = &mut comparisons[index];
+        comparison.num_runs += 1;
+        comparison.best_cost = comparison.best_cost.min(result.tour_cost);
+        comparison.mean_cost +=
+            (result.tour_cost as f64 - comparison.mean_cost) / comparison.num_runs as f64;
+        comparison.mean_elapsed = (comparison.mean_elapsed * (comparison.num_runs as u32 - 1)
+            + result.elapsed)
+            / comparison.num_runs as u32;
+    }
+    comparisons
+}
+
+// a row per solver and instance, with the gap left blank where no optimum is known
+pub fn comparison_table(comparisons: &[Comparison]) -> String {
+    let instance_width = comparisons
+        .iter()
+        .map(|comparison| comparison.instance.len())
+        .chain(["instance".len()])
+        .max()
+        .unwrap();
+    let solver_width = comparisons
+        .iter()
+        .map(|comparison| comparison.solver.len())
+        .chain(["solver".len()])
+        .max()
+        .unwrap();
+
+    let mut table = format!(
+        "{:<instance_width$}  {:<solver_width$}  {:>4}  {:>10}  {:>12}  {:>8}  {:>10}\n",
+        "instance", "solver", "runs", "best", "mean", "gap %", "seconds"
+    );
+    for comparison in comparisons {
+        table += &format!(
+            "{:<instance_width$}  {:<solver_width$}  {:>4}  {:>10}  {:>12.1}  {:>8}  {:>10.3}\n",
+            comparison.instance,
+            comparison.solver,
+            comparison.num_runs,
+            comparison.best_cost,
+            comparison.mean_cost,
+            comparison
+                .gap()
+                .map_or(String::new(), |gap| format!("{:.2}", gap)),
+            comparison.mean_elapsed.as_secs_f64()
+        );
+    }
+    table
+}
+
+// two solvers' costs run for run on the same instances, to tell whether one really beats the other
+pub struct PairedComparison {
+    pub pairs: Vec<(u64, u64)>, // round trip costs of the first and second solver
+    pub test: SignedRankTest,
+}
+
+impl PairedComparison {
+    pub fn mean_costs(&self) -> (f64, f64) {
+        let num_pairs = self.pairs.len().max(1) as f64;
+        let (first, second) = self.pairs.iter().fold((0., 0.), |(first, second), pair| {
+            (first + pair.0 as f64, second + pair.1 as f64)
+        });
+        (first / num_pairs, second / num_pairs)
+    }
+}
+
+// runs both solvers repetitions times on every instance, pairing the runs in order, instances
+// either solver would be skipped on being left out, with a Wilcoxon signed-rank test of the
+// pairs
+pub fn run_paired(
+    instances: &[BenchInstance],
+    first: BenchSolver,
+    second: BenchSolver,
+    memory_limit: Option<usize>,
+    repetitions: usize,
+) -> Result<PairedComparison, TSPError> {
+    let mut pairs = Vec::new();
+    for instance in instances {
+        let instance = std::slice::from_ref(instance);
+        let first_runs = run_instances(instance, &[first], memory_limit, repetitions)?;
+        let second_runs = run_instances(instance, &[second], memory_limit, repetitions)?;
+        if first_runs.len() != second_runs.len() {
+            info!(
+                "Leaving {} out, only one solver ran on it",
+                instance[0].name
+            );
+            continue;
+        }
+        pairs.extend(
+            first_runs
+                .iter()
+                .zip(&second_runs)
+                .map(|(first, second)| (first.tour_cost, second.tour_cost)),
+        );
+    }
+
+    let (first_costs, second_costs): (Vec<f64>, Vec<f64>) = pairs
+        .iter()
+        .map(|&(first, second)| (first as f64, second as f64))
+        .unzip();
+    let test = wilcoxon_signed_rank(&first_costs, &second_costs);
+    Ok(PairedComparison { pairs, test })
+}
+
+// how often a run's cost is compared against the target or its time limit checked
+const TARGET_CHECK_INTERVAL: u64 = 100;
+
+pub struct TimeToTarget {
+    pub instance: String,
+    pub target: u64,
+    pub num_runs: usize,
+    pub times: Vec<Duration>, // sorted, only the runs that reached the target
+}
+
+impl TimeToTarget {
+    // the empirical run length distribution as (seconds, probability) points of a TTT plot,
+    // the i-th fastest of n runs is plotted at probability (i - 0.5) / n
+    pub fn distribution(&self) -> Vec<(f64, f64)> {
+        self.times
+            .iter()
+            .enumerate()
+            .map(|(i, time)| (time.as_secs_f64(), (i as f64 + 0.5) / self.num_runs as f64))
+            .collect()
+    }
+}
+
+// independent annealing runs that each stop as soon as their round trip cost reaches the target
+pub fn time_to_target(
+    instance: &str,
+    intercity_map: &[Vec<u16>],
+    target: u64,
+    temperature: u64,
+    max_iterations: u64,
+    num_runs: usize,
+) -> Result<TimeToTarget, TSPError> {
+    let mut times = Vec::with_capacity(num_runs);
+    for _ in 0..num_runs {
+        let start = Instant::now();
+        let mut reached = None;
+        let config = SolverConfig {
+            temperature,
+            max_iterations,
+            mode: TourMode::Closed,
+            ..SolverConfig::default()
+        };
+        Annealer::new(config)
+            .on_progress(TARGET_CHECK_INTERVAL, |progress| {
+                if progress.current_cost <= target {
+                    reached = Some(start.elapsed());
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .solve(intercity_map)?;
+        times.extend(reached);
+    }
+    times.sort();
+
+    Ok(TimeToTarget {
+        instance: instance.to_string(),
+        target,
+        num_runs,
+        times,
+    })
+}
+
+// time to target for every instance in the suite with a known optimum,
+// the target being target_gap percent above it
+pub fn run_time_to_target_suite(
+    suite: &Path,
+    target_gap: f64,
+    temperature: u64,
+    max_iterations: u64,
+    num_runs: usize,
+) -> Result<Vec<TimeToTarget>, TSPError> {
+    let mut results = Vec::new();
+    for TsplibInstance {
+        name,
+        intercity_map,
+        ..
+    } in load_suite(suite)?
+    {
+        let Some(optimum) = known_optimum(&name) else {
+            info!("Skipping {}, its optimum is unknown", name);
+            continue;
+        };
+
+        let target = (optimum as f64 * (1. + target_gap / 100.)) as u64;
+        results.push(time_to_target(
+            &name,
+            &intercity_map,
+            target,
+            temperature,
+            max_iterations,
+            num_runs,
+        )?);
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::{
+        compare, comparison_table, generate_instances, known_optimum, parse_solver, run_instances,
+        run_paired, run_suite, score, time_to_target, BenchSolver,
+    };
+    use crate::common::generate_map;
+    use crate::cost::TourMode;
+    use crate::solver::Budget;
+    use std::time::Duration;
+
+    #[test]
+    fn test_run_suite() {
+        let suite = std::env::temp_dir().join(format!("tsp_bench_{}", std::process::id()));
+        std::fs::create_dir_all(&suite).unwrap();
+        // a square of side 3 and 4, the optimal round trip is its perimeter
+        std::fs::write(
+            suite.join("square.tsp"),
+            "NAME : square\nDIMENSION : 4\nEDGE_WEIGHT_TYPE : EUC_2D\n\
+             NODE_COORD_SECTION\n1 0 0\n2 3 0\n3 3 4\n4 0 4\nEOF\n",
+        )
+        .unwrap();
+
+        let solvers = [
+            BenchSolver::BruteForce { time_limit: None },
+            BenchSolver::SimulatedAnnealing {
+                temperature: 200,
+                max_iterations: 1000,
+                time_limit: None,
+            },
+        ];
+        let results = run_suite(&suite, &solvers, None).ok().unwrap();
+        std::fs::remove_dir_all(&suite).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].tour_cost, 14);
+        assert_eq!(results[0].optimum, None);
+        assert!(score(&results).is_empty());
+        assert_eq!(known_optimum("berlin52"), Some(7542));
+    }
+
+    #[test]
+    fn test_compare() {
+        let instances = generate_instances(&[6, 30], 2, 3).ok().unwrap();
+        assert_eq!(instances.len(), 4);
+        assert_eq!(instances[1].name, "random6_1");
+        assert!(instances[1].optimum.is_some());
+        assert_eq!(instances[2].optimum, None);
+        let again = generate_instances(&[6, 30], 2, 3).ok().unwrap();
+        assert_eq!(again[3].intercity_map, instances[3].intercity_map);
+
+        let solvers = [
+            BenchSolver::HeldKarp,
+            BenchSolver::SimulatedAnnealing {
+                temperature: 200,
+                max_iterations: 1000,
+                time_limit: None,
+            },
+        ];
+        // held karp is skipped on the bigger instances
+        let results = run_instances(&instances, &solvers, None, 3).ok().unwrap();
+        assert_eq!(results.len(), 6 * 3);
+
+        let comparisons = compare(&results);
+        assert_eq!(comparisons.len(), 6);
+        let exact = &comparisons[0];
+        assert_eq!(
+            (exact.instance.as_str(), exact.solver),
+            ("random6_0", "held_karp")
+        );
+        assert_eq!(exact.num_runs, 3);
+        assert_eq!(exact.gap(), Some(0.));
+        assert_eq!(exact.mean_cost, exact.best_cost as f64);
+        let annealed = &comparisons[1];
+        assert!(annealed.mean_cost >= annealed.best_cost as f64);
+        assert!(annealed.gap().unwrap() >= 0.);
+
+        let table = comparison_table(&comparisons);
+        assert_eq!(table.lines().count(), 1 + 6);
+        assert!(table.starts_with("instance    solver"));
+    }
+
+    #[test]
+    fn test_exact_solvers_have_no_gap() {
+        // round trips against round trip optima
+        let instances = generate_instances(&[9], 5, 21).ok().unwrap();
+        let solvers = [
+            BenchSolver::BruteForce { time_limit: None },
+            BenchSolver::BruteForce {
+                time_limit: Some(Duration::from_secs(60)),
+            },
+            BenchSolver::HeldKarp,
+            BenchSolver::BranchAndBound { time_limit: None },
+        ];
+        let results = run_instances(&instances, &solvers, None, 1).ok().unwrap();
+        assert_eq!(results.len(), 5 * 4);
+        assert!(results.iter().all(|result| result.gap() == Some(0.)));
+    }
+
+    #[test]
+    fn test_run_paired() {
+        let instances = generate_instances(&[4, 12], 5, 8).ok().unwrap();
+        let unannealed = BenchSolver::SimulatedAnnealing {
+            temperature: 200,
+            max_iterations: 0,
+            time_limit: None,
+        };
+        let annealed = BenchSolver::SimulatedAnnealing {
+            temperature: 200,
+            max_iterations: 20000,
+            time_limit: None,
+        };
+        let paired = run_paired(&instances[5..], unannealed, annealed, None, 2)
+            .ok()
+            .unwrap();
+        assert_eq!(paired.pairs.len(), 10);
+        assert!(paired.pairs.iter().all(|(first, second)| first >= second));
+        assert!(paired.test.p_value < 0.01);
+        let (first, second) = paired.mean_costs();
+        assert!(first > second);
+
+        // a solver against itself on instances small enough for every run to be optimal
+        let paired = run_paired(
+            &instances[..5],
+            BenchSolver::HeldKarp,
+            BenchSolver::HeldKarp,
+            None,
+            1,
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(paired.test.num_ties, 5);
+        assert_eq!(paired.test.p_value, 1.);
+
+        // held karp is skipped on all of these
+        let big = generate_instances(&[30], 1, 8).ok().unwrap();
+        let paired = run_paired(&big, BenchSolver::HeldKarp, annealed, None, 1)
+            .ok()
+            .unwrap();
+        assert!(paired.pairs.is_empty());
+    }
+
+    #[test]
+    fn test_parse_solver() {
+        assert_eq!(
+            parse_solver("brute_force=60s", 200, 1000),
+            Some(BenchSolver::BruteForce {
+                time_limit: Some(Duration::from_secs(60))
+            })
+        );
+        assert_eq!(
+            parse_solver("simulated_annealing=0.5s", 200, 1000),
+            Some(BenchSolver::SimulatedAnnealing {
+                temperature: 200,
+                max_iterations: 1000,
+                time_limit: Some(Duration::from_millis(500))
+            })
+        );
+        assert_eq!(
+            parse_solver("simulated_annealing=1e6", 200, 1000),
+            Some(BenchSolver::SimulatedAnnealing {
+                temperature: 200,
+                max_iterations: 1000000,
+                time_limit: None
+            })
+        );
+        assert_eq!(
+            parse_solver("brute_force", 200, 1000),
+            Some(BenchSolver::BruteForce { time_limit: None })
+        );
+        assert_eq!(parse_solver("brute_force=1e6", 200, 1000), None);
+        assert_eq!(parse_solver("simulated_annealing=fast", 200, 1000), None);
+        assert_eq!(parse_solver("bogo_sort", 200, 1000), None);
+        // the rest by solver::solver_by_name
+        let genetic = parse_solver("genetic=500", 200, 1000);
+        assert_eq!(
+            genetic,
+            Some(BenchSolver::Named {
+                name: "genetic",
+                budget: Budget {
+                    time_limit: None,
+                    max_iterations: Some(500)
+                }
+            })
+        );
+        let map = generate_map(6, (1, 100)).ok().unwrap();
+        let (path, cost) = genetic.unwrap().solve(&map, TourMode::Open).ok().unwrap();
+        assert_eq!(cost, TourMode::Open.cost(&map, &path));
+        let (path, cost) = genetic.unwrap().solve(&map, TourMode::Closed).ok().unwrap();
+        assert_eq!(cost, TourMode::Closed.cost(&map, &path));
+        assert_eq!(
+            parse_solver("held_karp", 200, 1000),
+            Some(BenchSolver::HeldKarp)
+        );
+        assert_eq!(parse_solver("held_karp=5s", 200, 1000), None);
+        assert_eq!(
+            parse_solver("christofides", 200, 1000),
+            Some(BenchSolver::Christofides)
+        );
+        assert_eq!(parse_solver("double_tree=1s", 200, 1000), None);
+        assert_eq!(
+            parse_solver("lin_kernighan", 200, 1000),
+            Some(BenchSolver::LinKernighan)
+        );
+        assert_eq!(
+            parse_solver("branch_and_bound=5s", 200, 1000),
+            Some(BenchSolver::BranchAndBound {
+                time_limit: Some(Duration::from_secs(5))
+            })
+        );
+    }
+
+    #[test]
+    fn test_time_to_target() {
+        let map = vec![
+            vec![0, 3, 5, 4],
+            vec![3, 0, 4, 5],
+            vec![5, 4, 0, 3],
+            vec![4, 5, 3, 0],
+        ];
+        let unreachable = time_to_target("square", &map, 13, 200, 1000, 3)
+            .ok()
+            .unwrap();
+        assert_eq!(unreachable.num_runs, 3);
+        assert!(unreachable.distribution().is_empty());
+
+        let reachable = time_to_target("square", &map, 14, 200, 100000, 4)
+            .ok()
+            .unwrap();
+        let distribution = reachable.distribution();
+        assert_eq!(distribution.len(), 4);
+        assert_eq!(distribution[0].1, 0.125);
+        assert!(distribution.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+}
@@ -0,0 +1,82 @@
+use super::common::{check_city_map, TSPError};
+use super::cost::CostFunction;
+use super::weight::{lighter, Cost, Weight};
+
+// lower bounds on the cost of any tour, to tell how far a heuristic solve could still be from optimal
+
+// weight of the minimum spanning tree, every path through all the cities is a spanning tree so
+// none can be cheaper, pairs weighted differently in each direction count the cheaper weight
+pub fn spanning_tree_bound<W: Weight>(intercity_map: &[Vec<W>]) -> Result<W::Cost, TSPError> {
+    check_city_map(intercity_map)?;
+
+    let cities: Vec<u32> = (0..intercity_map.len() as u32).collect();
+    Ok(spanning_tree_weight(&intercity_map, &cities))
+}
+
+// weight of the minimum spanning tree over just the given cities, a path through them being one
+// of its spanning trees
+pub(crate) fn spanning_tree_weight<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    cities: &[u32],
+) -> W::Cost {
+    let Some(&first) = cities.first() else {
+        return W::Cost::ZERO;
+    };
+    let weight = |i: u32, j: u32| lighter(cost_function.cost(i, j), cost_function.cost(j, i));
+
+    // prim's algorithm, dense matrices make the quadratic version the fastest
+    let mut in_tree = vec![false; cities.len()];
+    let mut closest: Vec<W> = cities.iter().map(|&city| weight(first, city)).collect();
+    in_tree[0] = true;
+
+    let mut bound = W::Cost::ZERO;
+    for _ in 1..cities.len() {
+        let next = (0..cities.len())
+            .filter(|&j| !in_tree[j])
+            .min_by(|&a, &b| closest[a].total_cmp(&closest[b]))
+            .unwrap();
+        in_tree[next] = true;
+        bound += closest[next].to_cost();
+        for j in 0..cities.len() {
+            if !in_tree[j] {
+                closest[j] = lighter(closest[j], weight(cities[next], cities[j]));
+            }
+        }
+    }
+
+    bound
+}
+
+// how far above the bound a cost is, as a percentage of the bound
+pub fn gap_percent(cost: u64, bound: u64) -> f64 {
+    100. * (cost as f64 - bound as f64) / (bound as f64).max(1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bounds::{gap_percent, spanning_tree_bound};
+    use crate::brute_force::brute_force_tsp;
+    use crate::common::generate_map;
+
+    #[test]
+    fn test_spanning_tree_bound() {
+        // a line of cities, the tree is the optimal path
+        let map: Vec<Vec<u16>> = vec![
+            vec![0, 1, 2, 3],
+            vec![1, 0, 1, 2],
+            vec![2, 1, 0, 1],
+            vec![3, 2, 1, 0],
+        ];
+        assert_eq!(spanning_tree_bound(&map).ok().unwrap(), 3);
+
+        for _ in 0..10 {
+            let map = generate_map(7, (1, 100)).ok().unwrap();
+            let (_, optimal_cost) = brute_force_tsp(&map).ok().unwrap();
+            assert!(spanning_tree_bound(&map).ok().unwrap() <= optimal_cost);
+        }
+
+        assert_eq!(spanning_tree_bound(&[vec![0u16]]).ok().unwrap(), 0);
+        assert!(spanning_tree_bound::<u16>(&[]).is_err());
+        assert_eq!(gap_percent(110, 100), 10.);
+    }
+}
@@ -1,31 +1,213 @@
 use itertools::Itertools;
-use log::error;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Instant;
 
-use super::common::{valid_city_map, path_cost, TSPError};
+use super::common::{check_city_map, TSPError};
+use super::cost::{CostFunction, TourMode};
+use crate::weight::{Cost, Weight};
 
-pub fn brute_force_tsp(intercity_map: &Vec<Vec<u16>>) -> Result<(Vec<u16>, u32), TSPError> {
-    if !valid_city_map(&intercity_map) {
-        error!("The provided map must be square");
-        return Err(TSPError::InvalidMapShape);
+// 12 cities take seconds on a single thread, every city past that multiplies the time by the city
+// count
+pub const DEFAULT_MAX_CITIES: usize = 12;
+
+// rough time for one thread to cost one path per city in it
+const SECONDS_PER_PATH_CITY: f64 = 1.5e-9;
+
+// every one of the n! paths is costed over its n cities, on a single thread
+pub fn estimate_brute_force_seconds(num_cities: usize) -> f64 {
+    let num_paths: f64 = (1..=num_cities).map(|n| n as f64).product();
+    num_paths * num_cities as f64 * SECONDS_PER_PATH_CITY
+}
+
+pub fn brute_force_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    brute_force_tsp_with_limit(intercity_map, DEFAULT_MAX_CITIES)
+}
+
+// refuses maps of more than max_cities up front rather than running for days
+pub fn brute_force_tsp_with_limit<W: Weight>(
+    intercity_map: &[Vec<W>],
+    max_cities: usize,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    brute_force_tsp_with_max(&intercity_map, intercity_map.len(), max_cities)
+}
+
+fn brute_force_tsp_with_max<W: Weight>(
+    cost_function: &(impl CostFunction<W> + Sync),
+    num_cities: usize,
+    max_cities: usize,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    if num_cities > max_cities {
+        return Err(TSPError::TooManyCities {
+            num_cities,
+            max_cities,
+            estimated_seconds: estimate_brute_force_seconds(num_cities),
+        });
     }
 
-    let num_cities = intercity_map.len();
-    let initial_path = intercity_map.iter().enumerate().map(|(idx, _)| idx as u16);
-    let cost = |p: &Vec<u16>| path_cost(&intercity_map, p);
+    search(cost_function, num_cities, TourMode::Open)
+}
 
-    let optimal_path = initial_path
-        .permutations(num_cities)
-        .min_by(|p1, p2| cost(p1).cmp(&cost(p2)))
-        .unwrap();
+// any cost function over num_cities cities in the mode of the instance being solved, round trips
+// are the same tour from whichever city they start, so with TourMode::Closed city 0 is kept first
+// and only the other (n - 1)! orders are tried
+pub(crate) fn brute_force_tsp_with<W: Weight>(
+    cost_function: &(impl CostFunction<W> + Sync),
+    num_cities: usize,
+    mode: TourMode,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    if mode == TourMode::Open {
+        return brute_force_tsp_with_max(cost_function, num_cities, DEFAULT_MAX_CITIES);
+    }
 
-    let optimal_cost = path_cost(&intercity_map, &optimal_path);
+    // the same limit as open paths, closed ones running num_cities times faster
+    if num_cities > DEFAULT_MAX_CITIES + 1 {
+        return Err(TSPError::TooManyCities {
+            num_cities,
+            max_cities: DEFAULT_MAX_CITIES + 1,
+            estimated_seconds: estimate_brute_force_seconds(num_cities - 1),
+        });
+    }
 
-    Ok((optimal_path, optimal_cost))
+    search(cost_function, num_cities, mode)
 }
 
+// the search is split into one part per path prefix, the first city of open paths or the second
+// of round trips, city 0 being kept first for those, and the parts are shared out between as many
+// threads as the machine runs at once, each enumerating its part in place
+fn search<W: Weight>(
+    cost_function: &(impl CostFunction<W> + Sync),
+    num_cities: usize,
+    mode: TourMode,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    let prefixes: Vec<Vec<u32>> = match mode {
+        TourMode::Open => (0..num_cities as u32).map(|first| vec![first]).collect(),
+        TourMode::Closed if num_cities < 2 => vec![vec![0]],
+        TourMode::Closed => (1..num_cities as u32)
+            .map(|second| vec![0, second])
+            .collect(),
+    };
+    let num_threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(prefixes.len());
+
+    let next_part = AtomicUsize::new(0);
+    let searched: Result<Vec<_>, _> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..num_threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut searched = Vec::new();
+                    loop {
+                        let part = next_part.fetch_add(1, Ordering::Relaxed);
+                        let Some(prefix) = prefixes.get(part) else {
+                            break;
+                        };
+                        let (path, cost) = search_part(cost_function, num_cities, mode, prefix);
+                        searched.push((part, path, cost));
+                    }
+                    searched
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().map_err(|_| TSPError::SolverPanicked))
+            .collect()
+    });
+    let mut parts: Vec<(usize, Vec<u32>, W::Cost)> = searched?.into_iter().flatten().collect();
 
+    // the first cheapest path in lexicographic order, as a single thread would find it
+    parts.sort_by(|(part, _, cost), (other_part, _, other_cost)| {
+        cost.total_cmp(other_cost).then(part.cmp(other_part))
+    });
+    let (_, path, cost) = parts.swap_remove(0);
+    Ok((path, cost))
+}
+
+// the cheapest path starting with prefix, the first of them in lexicographic order
+fn search_part<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    mode: TourMode,
+    prefix: &[u32],
+) -> (Vec<u32>, W::Cost) {
+    let mut path = prefix.to_vec();
+    path.extend((0..num_cities as u32).filter(|city| !prefix.contains(city)));
+
+    let mut best_path = path.clone();
+    let mut best_cost = mode.cost(cost_function, &path);
+    while next_permutation(&mut path[prefix.len()..]) {
+        let cost = mode.cost(cost_function, &path);
+        if cost < best_cost {
+            best_cost = cost;
+            best_path.clone_from(&path);
+        }
+    }
+    (best_path, best_cost)
+}
+
+// how many paths are costed between deadline checks
+const DEADLINE_CHECK_INTERVAL: usize = 4096;
+
+// gives up at the deadline with the cheapest path seen so far, which is then likely not optimal,
+// round trips keeping city 0 first as without a deadline
+pub(crate) fn brute_force_tsp_until_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    mode: TourMode,
+    deadline: Instant,
+) -> (Vec<u32>, W::Cost) {
+    let first = match mode {
+        TourMode::Closed if num_cities > 0 => vec![0],
+        _ => vec![],
+    };
+    let rest = first.len() as u32..num_cities as u32;
+    let paths = rest.permutations(num_cities - first.len()).map(|rest| {
+        let mut path = first.clone();
+        path.extend(rest);
+        path
+    });
+
+    let mut best: Option<(Vec<u32>, W::Cost)> = None;
+    for (checked, path) in paths.enumerate() {
+        if checked % DEADLINE_CHECK_INTERVAL == 0 && best.is_some() && Instant::now() >= deadline {
+            break;
+        }
+        let cost = mode.cost(cost_function, &path);
+        if best.as_ref().is_none_or(|&(_, best_cost)| cost < best_cost) {
+            best = Some((path, cost));
+        }
+    }
+
+    best.unwrap()
+}
+
+// rearranges the path into the next permutation in lexicographic order, false after the last
+fn next_permutation(path: &mut [u32]) -> bool {
+    let Some(pivot) = path.windows(2).rposition(|pair| pair[0] < pair[1]) else {
+        return false;
+    };
+    let successor = path.iter().rposition(|&city| city > path[pivot]).unwrap();
+    path.swap(pivot, successor);
+    path[pivot + 1..].reverse();
+    true
+}
+
+#[cfg(test)]
 mod tests {
-    use crate::{brute_force_tsp, path_cost};
+    use crate::brute_force::{
+        brute_force_tsp, brute_force_tsp_until_with, brute_force_tsp_with,
+        brute_force_tsp_with_limit, estimate_brute_force_seconds,
+    };
+    use crate::common::{generate_map, TSPError};
+    use crate::cost::{path_cost_with, TourMode};
+    use crate::testing::is_cost_consistent;
+    use itertools::Itertools;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_brute_force_tsp() {
@@ -35,7 +217,7 @@ mod tests {
             vec![2, 1, 2, 2],
             vec![2, 2, 1, 2],
         ];
-        let path: Vec<u16> = vec![0, 3, 2, 1];
+        let path: Vec<u32> = vec![0, 3, 2, 1];
 
         let res = brute_force_tsp(&map);
         assert!(res.is_ok());
@@ -43,6 +225,105 @@ mod tests {
         let (optimal_path, optimal_cost) = res.ok().unwrap();
 
         assert_eq!(optimal_path, path);
-        assert_eq!(optimal_cost, path_cost(&map, &path));
+        assert_eq!(optimal_cost, path_cost_with(&map, &path));
+    }
+
+    #[test]
+    fn test_parallel_search() {
+        // the same path as costing every permutation in order, ties going to the first
+        let map = generate_map(7, (1, 3)).ok().unwrap();
+        let first_cheapest = (0..7u32)
+            .permutations(7)
+            .min_by_key(|path| path_cost_with(&map, path))
+            .unwrap();
+        assert_eq!(brute_force_tsp(&map).ok().unwrap().0, first_cheapest);
+
+        let (path, cost) = brute_force_tsp_with(&map, 7, TourMode::Closed)
+            .ok()
+            .unwrap();
+        let first_cheapest = (1..7u32)
+            .permutations(6)
+            .map(|rest| std::iter::once(0).chain(rest).collect::<Vec<u32>>())
+            .min_by_key(|path| TourMode::Closed.cost(&map, path))
+            .unwrap();
+        assert_eq!(path, first_cheapest);
+        assert_eq!(cost, TourMode::Closed.cost(&map, &path));
+
+        let one_city = vec![vec![0u16]];
+        assert_eq!(brute_force_tsp(&one_city).ok(), Some((vec![0], 0)));
+        assert_eq!(
+            brute_force_tsp_with(&one_city, 1, TourMode::Closed).ok(),
+            Some((vec![0], 0))
+        );
+
+        // a panic on a search thread comes back as an error rather than another panic
+        let panicking = |_: u32, _: u32| -> u16 { panic!("cost function bug") };
+        assert!(matches!(
+            brute_force_tsp_with(&panicking, 5, TourMode::Open),
+            Err(TSPError::SolverPanicked)
+        ));
+    }
+
+    #[test]
+    fn test_brute_force_deadline() {
+        let map = generate_map(7, (1, 100)).ok().unwrap();
+        let far_away = Instant::now() + Duration::from_secs(3600);
+        for mode in [TourMode::Open, TourMode::Closed] {
+            let (_, optimal_cost) = brute_force_tsp_with(&map, 7, mode).ok().unwrap();
+            assert_eq!(
+                brute_force_tsp_until_with(&map, 7, mode, far_away).1,
+                optimal_cost
+            );
+
+            // a passed deadline still gives a valid path
+            let (path, cost) = brute_force_tsp_until_with(&map, 7, mode, Instant::now());
+            assert_eq!(cost, mode.cost(&map, &path));
+        }
+        let (path, cost) = brute_force_tsp_until_with(&map, 7, TourMode::Open, Instant::now());
+        assert!(is_cost_consistent(&map, &path, cost));
+    }
+
+    #[test]
+    fn test_city_limit() {
+        let map = generate_map(6, (1, 100)).ok().unwrap();
+        assert!(brute_force_tsp_with_limit(&map, 6).is_ok());
+        assert!(matches!(
+            brute_force_tsp_with_limit(&map, 5),
+            Err(TSPError::TooManyCities {
+                num_cities: 6,
+                max_cities: 5,
+                ..
+            })
+        ));
+
+        // fails straight away instead of enumerating 20! paths
+        let map = generate_map(20, (1, 100)).ok().unwrap();
+        let err = brute_force_tsp(&map).err().unwrap();
+        assert!(err.to_string().contains("years"));
+        assert!(estimate_brute_force_seconds(13) > 10. * estimate_brute_force_seconds(12));
+    }
+
+    #[test]
+    fn test_closed_tours() {
+        let map = generate_map(6, (1, 100)).ok().unwrap();
+        let (path, cost) = brute_force_tsp_with(&map, 6, TourMode::Closed)
+            .ok()
+            .unwrap();
+        assert_eq!(path[0], 0);
+        assert_eq!(cost, TourMode::Closed.cost(&map, &path));
+
+        // no order of the cities makes a cheaper round trip
+        let cheapest = (0..6u32)
+            .permutations(6)
+            .map(|path| TourMode::Closed.cost(&map, &path))
+            .min();
+        assert_eq!(Some(cost), cheapest);
+
+        assert_eq!(
+            brute_force_tsp_with(&map, 6, TourMode::Open).ok(),
+            brute_force_tsp(&map).ok()
+        );
+        let too_many = generate_map(14, (1, 100)).ok().unwrap();
+        assert!(brute_force_tsp_with(&too_many, 14, TourMode::Closed).is_err());
     }
 }
@@ -0,0 +1,174 @@
+use crate::common::TSPError;
+use crate::cost::CostFunction;
+use crate::weight::Weight;
+
+// each city's k nearest others, closest first, the only cities a local search considers joining
+// it to, which takes a pass of 2-opt from O(n^2) moves to O(nk) and misses few improving ones,
+// a good tour's edges almost all joining near neighbours
+
+// lists as many as the repo's local searches use by default
+pub const DEFAULT_CANDIDATES: usize = 10;
+
+// from the weights, O(n^2) for any cost function, ties to the lower index
+pub fn nearest_candidates<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    k: usize,
+) -> Vec<Vec<u32>> {
+    (0..num_cities as u32)
+        .map(|city| nearest_cities(cost_function, num_cities, city, k))
+        .collect()
+}
+
+// one city's list, O(n) to pick out the k nearest and O(k log k) to order them
+pub fn nearest_cities<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    city: u32,
+    k: usize,
+) -> Vec<u32> {
+    let closer = |a: &u32, b: &u32| {
+        cost_function
+            .cost(city, *a)
+            .total_cmp(&cost_function.cost(city, *b))
+            .then(a.cmp(b))
+    };
+    let mut others: Vec<u32> = (0..num_cities as u32)
+        .filter(|&other| other != city)
+        .collect();
+    if k < others.len() {
+        others.select_nth_unstable_by(k, closer);
+        others.truncate(k);
+    }
+    others.sort_by(closer);
+    others
+}
+
+// by straight line distance between (x, y) points, bucketed into a grid of about two points a
+// cell so that each point's neighbours are found in the rings of cells around its own, O(nk)
+// for points spread over the plane rather than clustered in a few cells, ties to the lower index
+pub fn grid_candidates(points: &[(f64, f64)], k: usize) -> Vec<Vec<u32>> {
+    let num_points = points.len();
+    let k = k.min(num_points.saturating_sub(1));
+    if k == 0 {
+        return vec![Vec::new(); num_points];
+    }
+
+    let (min, max) = points.iter().fold(
+        ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+        |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+    );
+    let side = ((num_points as f64 / 2.).sqrt().ceil() as usize).max(1);
+    let extent = (max.0 - min.0).max(max.1 - min.1);
+    let width = if extent > 0. {
+        extent / side as f64
+    } else {
+        1.
+    };
+    let cell_of = |(x, y): (f64, f64)| {
+        let column = (((x - min.0) / width) as usize).min(side - 1);
+        let row = (((y - min.1) / width) as usize).min(side - 1);
+        (column, row)
+    };
+    let mut cells = vec![Vec::new(); side * side];
+    for (index, &point) in points.iter().enumerate() {
+        let (column, row) = cell_of(point);
+        cells[row * side + column].push(index as u32);
+    }
+
+    let distance = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).hypot(a.1 - b.1);
+    let closer = |a: &(f64, u32), b: &(f64, u32)| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1));
+    points
+        .iter()
+        .enumerate()
+        .map(|(index, &point)| {
+            let (column, row) = cell_of(point);
+            let mut found: Vec<(f64, u32)> = Vec::new();
+            for ring in 0..=side {
+                // the cells ring away are at least ring - 1 cells' width from the point
+                if ring > 0 && found.len() >= k {
+                    found.sort_by(closer);
+                    found.truncate(k);
+                    if found[k - 1].0 < (ring - 1) as f64 * width {
+                        break;
+                    }
+                }
+                let rows = row.saturating_sub(ring)..=(row + ring).min(side - 1);
+                for other_row in rows {
+                    let columns = column.saturating_sub(ring)..=(column + ring).min(side - 1);
+                    for other_column in columns {
+                        if other_row.abs_diff(row).max(other_column.abs_diff(column)) != ring {
+                            continue;
+                        }
+                        for &other in &cells[other_row * side + other_column] {
+                            if other as usize != index {
+                                found.push((distance(point, points[other as usize]), other));
+                            }
+                        }
+                    }
+                }
+            }
+            found.sort_by(closer);
+            found.truncate(k);
+            found.into_iter().map(|(_, other)| other).collect()
+        })
+        .collect()
+}
+
+// one list per city, naming only other cities
+pub fn check_candidates(num_cities: usize, candidates: &[Vec<u32>]) -> Result<(), TSPError> {
+    let invalid = (0..num_cities.max(candidates.len())).find(|&city| {
+        candidates.get(city).is_none_or(|list| {
+            list.iter()
+                .any(|&other| other as usize >= num_cities || other as usize == city)
+        })
+    });
+    if let Some(city) = invalid {
+        return Err(TSPError::InvalidCandidates { city });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::candidates::{check_candidates, grid_candidates, nearest_candidates};
+    use crate::common::TSPError;
+    use crate::metric::{metric_map, Euclidean};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_candidates() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let points: Vec<(f64, f64)> = (0..300)
+            .map(|_| (rng.gen_range(0.0..1000.), rng.gen_range(0.0..1000.)))
+            .collect();
+        // unrounded weights, so the grid and the weights agree on every neighbour
+        let map: Vec<Vec<f64>> = metric_map(&points, &Euclidean).ok().unwrap();
+        let grid = grid_candidates(&points, 8);
+        assert_eq!(grid, nearest_candidates(&map, 300, 8));
+        assert!(check_candidates(300, &grid).is_ok());
+
+        // clustered, coincident and too few points
+        let mut clustered = points.clone();
+        clustered.extend([(5000., 5000.); 5]);
+        let map: Vec<Vec<f64>> = metric_map(&clustered, &Euclidean).ok().unwrap();
+        assert_eq!(
+            grid_candidates(&clustered, 6),
+            nearest_candidates(&map, 305, 6)
+        );
+        assert_eq!(
+            grid_candidates(&[(1., 1.), (1., 1.)], 5),
+            vec![vec![1], vec![0]]
+        );
+        assert_eq!(grid_candidates(&[(1., 1.)], 5), vec![Vec::<u32>::new()]);
+
+        assert!(matches!(
+            check_candidates(3, &[vec![1], vec![2], vec![2]]),
+            Err(TSPError::InvalidCandidates { city: 2 })
+        ));
+        assert!(matches!(
+            check_candidates(3, &[vec![1], vec![2]]),
+            Err(TSPError::InvalidCandidates { city: 2 })
+        ));
+    }
+}
@@ -0,0 +1,136 @@
+use rand::{seq::SliceRandom, thread_rng};
+
+use super::common::TSPError;
+use super::geo::{geographic_map, GeoDistance};
+
+// (name, latitude, longitude) in degrees
+pub const WORLD_CAPITALS: &[(&str, f64, f64)] = &[
+    ("Abu Dhabi", 24.4539, 54.3773),
+    ("Abuja", 9.0765, 7.3986),
+    ("Accra", 5.6037, -0.1870),
+    ("Addis Ababa", 9.0320, 38.7469),
+    ("Algiers", 36.7538, 3.0588),
+    ("Amsterdam", 52.3676, 4.9041),
+    ("Ankara", 39.9334, 32.8597),
+    ("Athens", 37.9838, 23.7275),
+    ("Baghdad", 33.3152, 44.3661),
+    ("Bangkok", 13.7563, 100.5018),
+    ("Beijing", 39.9042, 116.4074),
+    ("Berlin", 52.5200, 13.4050),
+    ("Bern", 46.9480, 7.4474),
+    ("Bogota", 4.7110, -74.0721),
+    ("Brasilia", -15.7975, -47.8919),
+    ("Brussels", 50.8503, 4.3517),
+    ("Bucharest", 44.4268, 26.1025),
+    ("Budapest", 47.4979, 19.0402),
+    ("Buenos Aires", -34.6037, -58.3816),
+    ("Cairo", 30.0444, 31.2357),
+    ("Canberra", -35.2809, 149.1300),
+    ("Caracas", 10.4806, -66.9036),
+    ("Copenhagen", 55.6761, 12.5683),
+    ("Dakar", 14.7167, -17.4677),
+    ("Dhaka", 23.8103, 90.4125),
+    ("Dublin", 53.3498, -6.2603),
+    ("Hanoi", 21.0278, 105.8342),
+    ("Havana", 23.1136, -82.3666),
+    ("Helsinki", 60.1699, 24.9384),
+    ("Islamabad", 33.6844, 73.0479),
+    ("Jakarta", -6.2088, 106.8456),
+    ("Kabul", 34.5553, 69.2075),
+    ("Kampala", 0.3476, 32.5825),
+    ("Kathmandu", 27.7172, 85.3240),
+    ("Kinshasa", -4.4419, 15.2663),
+    ("Kyiv", 50.4501, 30.5234),
+    ("Lima", -12.0464, -77.0428),
+    ("Lisbon", 38.7223, -9.1393),
+    ("London", 51.5074, -0.1278),
+    ("Madrid", 40.4168, -3.7038),
+    ("Manila", 14.5995, 120.9842),
+    ("Mexico City", 19.4326, -99.1332),
+    ("Moscow", 55.7558, 37.6173),
+    ("Nairobi", -1.2921, 36.8219),
+    ("New Delhi", 28.6139, 77.2090),
+    ("Oslo", 59.9139, 10.7522),
+    ("Ottawa", 45.4215, -75.6972),
+    ("Paris", 48.8566, 2.3522),
+    ("Prague", 50.0755, 14.4378),
+    ("Pretoria", -25.7479, 28.2293),
+    ("Quito", -0.1807, -78.4678),
+    ("Rabat", 34.0209, -6.8416),
+    ("Reykjavik", 64.1466, -21.9426),
+    ("Riyadh", 24.7136, 46.6753),
+    ("Rome", 41.9028, 12.4964),
+    ("Santiago", -33.4489, -70.6693),
+    ("Seoul", 37.5665, 126.9780),
+    ("Singapore", 1.3521, 103.8198),
+    ("Stockholm", 59.3293, 18.0686),
+    ("Tehran", 35.6892, 51.3890),
+    ("Tokyo", 35.6762, 139.6503),
+    ("Tunis", 36.8065, 10.1815),
+    ("Vienna", 48.2082, 16.3738),
+    ("Warsaw", 52.2297, 21.0122),
+    ("Washington", 38.9072, -77.0369),
+    ("Wellington", -41.2865, 174.7762),
+];
+
+pub struct GeographicInstance {
+    pub names: Vec<&'static str>,
+    pub coordinates: Vec<(f64, f64)>, // (latitude, longitude) in degrees
+    pub intercity_map: Vec<Vec<u16>>, // great circle distances rounded to whole kilometres
+}
+
+// samples num_cities distinct capitals in random order
+pub fn generate_capitals_map(num_cities: u16) -> Result<GeographicInstance, TSPError> {
+    if num_cities as usize > WORLD_CAPITALS.len() {
+        return Err(TSPError::InvalidCityCount);
+    }
+
+    let sample: Vec<&(&str, f64, f64)> = WORLD_CAPITALS
+        .choose_multiple(&mut thread_rng(), num_cities as usize)
+        .collect();
+
+    let names = sample.iter().map(|&&(name, _, _)| name).collect();
+    let coordinates: Vec<(f64, f64)> = sample.iter().map(|&&(_, lat, lon)| (lat, lon)).collect();
+    let intercity_map = geographic_map(&coordinates, GeoDistance::Haversine)?;
+
+    Ok(GeographicInstance {
+        names,
+        coordinates,
+        intercity_map,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::capitals::{generate_capitals_map, WORLD_CAPITALS};
+    use itertools::Itertools;
+
+    #[test]
+    fn test_capitals_map() {
+        let instance = generate_capitals_map(10).ok().unwrap();
+
+        assert_eq!(instance.names.len(), 10);
+        assert_eq!(instance.names.iter().unique().count(), 10);
+        for (i, row) in instance.intercity_map.iter().enumerate() {
+            assert_eq!(row[i], 0);
+            for (j, &distance) in row.iter().enumerate() {
+                assert_eq!(distance, instance.intercity_map[j][i]);
+                assert!(distance <= 20016); // half the earth's circumference
+            }
+        }
+
+        assert!(generate_capitals_map(WORLD_CAPITALS.len() as u16 + 1).is_err());
+    }
+
+    #[test]
+    fn test_known_distance() {
+        let instance = generate_capitals_map(WORLD_CAPITALS.len() as u16)
+            .ok()
+            .unwrap();
+        let london = instance.names.iter().position(|&n| n == "London").unwrap();
+        let paris = instance.names.iter().position(|&n| n == "Paris").unwrap();
+
+        let distance = instance.intercity_map[london][paris];
+        assert!((340..=350).contains(&distance)); // roughly 344km
+    }
+}
@@ -1,38 +1,346 @@
 use itertools::Itertools;
-use log::error;
 use rand::{seq::SliceRandom, thread_rng, Rng};
 
+use crate::cost::{path_cost_with, CostFunction, TourMode};
+use crate::weight::Weight;
+
+// new variants keep coming with new solvers and formats, so matches outside the crate need a
+// wildcard arm
+#[derive(Debug)]
+#[non_exhaustive]
 pub enum TSPError {
-    InvalidMapShape,
-    InvalidWeightRange, // weight range cannot be reversed or empty
+    EmptyMap,
+    // row has len weights instead of expected
+    InvalidMapShape {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    AsymmetricWeight {
+        row: usize,
+        column: usize,
+        weight: f64,
+        mirrored: f64,
+    },
+    // negative, infinite or NaN
+    InvalidWeight {
+        row: usize,
+        column: usize,
+    },
+    InvalidWeightRange,     // weight range cannot be reversed or empty
+    PerturbationOutOfRange, // perturbation parameters out of range for the instance
+    InvalidFileFormat,
+    InvalidCityCount,
+    Cancelled,
+    // a solver's thread panicked, losing whatever it had found
+    SolverPanicked,
+    InvalidPathLength {
+        len: usize,
+        expected: usize,
+    },
+    CityOutOfRange {
+        position: usize,
+        city: u32,
+        num_cities: usize,
+    },
+    DuplicateCity {
+        position: usize,
+        city: u32,
+    },
+    CheckpointOutOfRange {
+        iteration: u64,
+        max_iterations: u64,
+    },
+    // an exact solver's saved state that wasn't taken on a map of this size or is inconsistent
+    InvalidCheckpoint,
+    NoMoveOperators,
+    UnknownMoveOperator {
+        name: String,
+    },
+    InvalidRunCount,
+    InvalidParameterGrid,
+    InvalidCoolingSchedule,
+    InvalidStoppingCriteria,
+    InvalidGeneticParameters,
+    InvalidGeneratorParameters,
+    InvalidCoordinate {
+        index: usize,
+    },
+    // city's candidate list is missing or names itself or a city out of range
+    InvalidCandidates {
+        city: usize,
+    },
+    TooManyCities {
+        num_cities: usize,
+        max_cities: usize,
+        estimated_seconds: f64,
+    },
+    InvalidBatchSize,
+    // no adapter or device to run on, or it failed mid-run
+    GpuUnavailable,
+    GpuBufferTooLarge {
+        bytes: u64,
+        max_bytes: u64,
+    },
+    Io(std::io::Error),
 }
 
 impl std::fmt::Display for TSPError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match *self {
-            TSPError::InvalidMapShape => write!(f, "invalid map shape"),
+        match self {
+            TSPError::EmptyMap => write!(f, "the map has no cities"),
+            TSPError::InvalidMapShape { row, len, expected } => write!(
+                f,
+                "invalid map shape, row {} has {} weights but the map has {} rows",
+                row, len, expected
+            ),
+            TSPError::AsymmetricWeight {
+                row,
+                column,
+                weight,
+                mirrored,
+            } => write!(
+                f,
+                "asymmetric weights, [{}][{}] is {} but [{}][{}] is {}",
+                row, column, weight, column, row, mirrored
+            ),
+            TSPError::InvalidWeight { row, column } => write!(
+                f,
+                "invalid weight at [{}][{}], weights must be finite and not negative",
+                row, column
+            ),
             TSPError::InvalidWeightRange => write!(f, "invalid weight range"),
+            TSPError::PerturbationOutOfRange => write!(f, "invalid perturbation"),
+            TSPError::InvalidFileFormat => write!(f, "invalid file format"),
+            TSPError::InvalidCityCount => write!(f, "invalid number of cities"),
+            TSPError::Cancelled => write!(f, "solve was cancelled"),
+            TSPError::SolverPanicked => write!(f, "a solver thread panicked"),
+            TSPError::InvalidPathLength { len, expected } => write!(
+                f,
+                "invalid path, it visits {} cities but the map has {}",
+                len, expected
+            ),
+            TSPError::CityOutOfRange {
+                position,
+                city,
+                num_cities,
+            } => write!(
+                f,
+                "invalid path, city {} at position {} is out of range for {} cities",
+                city, position, num_cities
+            ),
+            TSPError::DuplicateCity { position, city } => write!(
+                f,
+                "invalid path, city {} is visited again at position {}",
+                city, position
+            ),
+            TSPError::CheckpointOutOfRange {
+                iteration,
+                max_iterations,
+            } => write!(
+                f,
+                "checkpoint is at iteration {} but the run stops at {}",
+                iteration, max_iterations
+            ),
+            TSPError::InvalidCheckpoint => write!(f, "the checkpoint doesn't fit the map"),
+            TSPError::NoMoveOperators => write!(f, "no move operators registered"),
+            TSPError::UnknownMoveOperator { name } => write!(f, "unknown move operator {}", name),
+            TSPError::InvalidRunCount => write!(f, "at least one run is needed"),
+            TSPError::InvalidParameterGrid => write!(f, "invalid parameter grid"),
+            TSPError::InvalidCoolingSchedule => write!(f, "invalid cooling schedule"),
+            TSPError::InvalidStoppingCriteria => write!(f, "invalid stopping criteria"),
+            TSPError::InvalidGeneticParameters => {
+                write!(f, "invalid genetic algorithm parameters")
+            }
+            TSPError::InvalidGeneratorParameters => write!(f, "invalid generator parameters"),
+            TSPError::InvalidCoordinate { index } => {
+                write!(f, "point {} has invalid coordinates", index)
+            }
+            TSPError::InvalidCandidates { city } => {
+                write!(f, "city {} has an invalid candidate list", city)
+            }
+            TSPError::TooManyCities {
+                num_cities,
+                max_cities,
+                estimated_seconds,
+            } => write!(
+                f,
+                "brute force over {} cities would take about {}, over the {} city limit, \
+                 use simulated annealing instead",
+                num_cities,
+                format_seconds(*estimated_seconds),
+                max_cities
+            ),
+            TSPError::InvalidBatchSize => write!(f, "batches need at least one move"),
+            TSPError::GpuUnavailable => write!(f, "no GPU available"),
+            TSPError::GpuBufferTooLarge { bytes, max_bytes } => write!(
+                f,
+                "{} bytes of weights are over the GPU's {} byte buffer limit",
+                bytes, max_bytes
+            ),
+            TSPError::Io(err) => write!(f, "io error: {}", err),
         }
     }
 }
 
-fn generate_random_path(intercity_map: &Vec<Vec<u16>>) -> Vec<u16> {
+// the largest unit that keeps the number above 1, past a million years the exponent is what matters
+pub(crate) fn format_seconds(seconds: f64) -> String {
+    const UNITS: [(&str, f64); 5] = [
+        ("years", 365. * 24. * 3600.),
+        ("days", 24. * 3600.),
+        ("hours", 3600.),
+        ("minutes", 60.),
+        ("seconds", 1.),
+    ];
+    let (unit, length) = UNITS
+        .iter()
+        .find(|&&(_, length)| seconds >= length)
+        .unwrap_or(&UNITS[UNITS.len() - 1]);
+    let value = seconds / length;
+    if value >= 1e6 {
+        format!("{:.1e} {}", value, unit)
+    } else {
+        format!("{:.0} {}", value, unit)
+    }
+}
+
+impl std::error::Error for TSPError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TSPError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for TSPError {
+    fn from(err: std::io::Error) -> Self {
+        TSPError::Io(err)
+    }
+}
+
+pub fn generate_random_path<W>(intercity_map: &[Vec<W>]) -> Vec<u32> {
+    generate_random_path_with_rng(intercity_map, &mut thread_rng())
+}
+
+// the generators' `_with_rng` versions draw from the given rng, a seeded one such as
+// `StdRng::seed_from_u64` giving the same map or path for the same seed
+pub fn generate_random_path_with_rng<W, R: Rng>(intercity_map: &[Vec<W>], rng: &mut R) -> Vec<u32> {
     let num_cities = intercity_map.len();
-    let mut path: Vec<u16> = (0..(num_cities as u16)).collect();
-    path.shuffle(&mut thread_rng());
+    let mut path: Vec<u32> = (0..(num_cities as u32)).collect();
+    path.shuffle(rng);
     path
 }
 
-pub fn valid_city_map(intercity_map: &Vec<Vec<u16>>) -> bool {
-    intercity_map.len() != 0 && intercity_map[0].len() == intercity_map.len()
+// every row must have a valid weight for every city
+pub fn check_city_map<W: Weight>(intercity_map: &[Vec<W>]) -> Result<(), TSPError> {
+    if intercity_map.is_empty() {
+        return Err(TSPError::EmptyMap);
+    }
+
+    let expected = intercity_map.len();
+    if let Some(row) = intercity_map.iter().position(|row| row.len() != expected) {
+        let len = intercity_map[row].len();
+        return Err(TSPError::InvalidMapShape { row, len, expected });
+    }
+
+    // only floats can be invalid
+    if !W::INTEGRAL {
+        for (row, weights) in intercity_map.iter().enumerate() {
+            if let Some(column) = weights.iter().position(|weight| !weight.is_valid()) {
+                return Err(TSPError::InvalidWeight { row, column });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn check_symmetric<W: Weight>(intercity_map: &[Vec<W>]) -> Result<(), TSPError> {
+    check_city_map(intercity_map)?;
+    check_symmetric_with(&intercity_map, intercity_map.len())
 }
 
-pub fn generate_map(num_cities: u16, weight_range: (u16, u16)) -> Result<Vec<Vec<u16>>, TSPError> {
-    let mut gen = thread_rng();
+// same as check_symmetric but for any cost function over num_cities cities, taking its word for
+// it when it says it is symmetric
+pub(crate) fn check_symmetric_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+) -> Result<(), TSPError> {
+    if cost_function.is_symmetric() {
+        return Ok(());
+    }
+
+    for row in 0..num_cities {
+        for column in row + 1..num_cities {
+            let weight = cost_function.cost(row as u32, column as u32);
+            let mirrored = cost_function.cost(column as u32, row as u32);
+            if weight != mirrored {
+                return Err(TSPError::AsymmetricWeight {
+                    row,
+                    column,
+                    weight: weight.to_f64(),
+                    mirrored: mirrored.to_f64(),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the path must visit each of the num_cities cities exactly once
+pub fn check_path(num_cities: usize, path: &[u32]) -> Result<(), TSPError> {
+    if path.len() != num_cities {
+        return Err(TSPError::InvalidPathLength {
+            len: path.len(),
+            expected: num_cities,
+        });
+    }
+
+    let mut visited = vec![false; num_cities];
+    for (position, &city) in path.iter().enumerate() {
+        if city as usize >= num_cities {
+            return Err(TSPError::CityOutOfRange {
+                position,
+                city,
+                num_cities,
+            });
+        }
+        if std::mem::replace(&mut visited[city as usize], true) {
+            return Err(TSPError::DuplicateCity { position, city });
+        }
+    }
+
+    Ok(())
+}
+
+// independent check of a tour from any solver, returns its cost under the mode recomputed from
+// the map, so a round trip includes its closing edge
+pub fn verify_tour<W: Weight>(
+    intercity_map: &[Vec<W>],
+    tour: &[u32],
+    mode: TourMode,
+) -> Result<W::Cost, TSPError> {
+    check_city_map(intercity_map)?;
+    check_path(intercity_map.len(), tour)?;
+
+    Ok(mode.cost(&intercity_map, tour))
+}
+
+pub fn generate_map(num_cities: u32, weight_range: (u16, u16)) -> Result<Vec<Vec<u16>>, TSPError> {
+    generate_map_with_rng(num_cities, weight_range, &mut thread_rng())
+}
+
+#[allow(clippy::needless_range_loop)]
+pub fn generate_map_with_rng<R: Rng>(
+    num_cities: u32,
+    weight_range: (u16, u16),
+    gen: &mut R,
+) -> Result<Vec<Vec<u16>>, TSPError> {
     let (low, high) = weight_range;
 
     if high <= low {
-        error!("Weight range cannot be reversed or empty");
         return Err(TSPError::InvalidWeightRange);
     }
 
@@ -53,25 +361,80 @@ pub fn generate_map(num_cities: u16, weight_range: (u16, u16)) -> Result<Vec<Vec
     Ok(intercity_map)
 }
 
-pub fn path_cost(intercity_map: &Vec<Vec<u16>>, path: &Vec<u16>) -> u32 {
-    path.windows(2)
-        .map(|endpoints| intercity_map[endpoints[0] as usize][endpoints[1] as usize] as u32)
-        .sum()
+// every direction weighed on its own, like one way streets or uphill and downhill legs, the
+// solvers never assume [i][j] == [j][i]
+pub fn generate_asymmetric_map(
+    num_cities: u32,
+    weight_range: (u16, u16),
+) -> Result<Vec<Vec<u16>>, TSPError> {
+    generate_asymmetric_map_with_rng(num_cities, weight_range, &mut thread_rng())
 }
 
-pub fn generate_default_path(intercity_map: &Vec<Vec<u16>>) -> Vec<u16> {
+pub fn generate_asymmetric_map_with_rng<R: Rng>(
+    num_cities: u32,
+    weight_range: (u16, u16),
+    gen: &mut R,
+) -> Result<Vec<Vec<u16>>, TSPError> {
+    let (low, high) = weight_range;
+
+    if high <= low {
+        return Err(TSPError::InvalidWeightRange);
+    }
+
+    let num_cities = num_cities as usize;
+    Ok((0..num_cities)
+        .map(|i| {
+            (0..num_cities)
+                .map(|j| if i == j { 0 } else { gen.gen_range(low..high) })
+                .collect()
+        })
+        .collect())
+}
+
+// summed in the weights' cost type, u64 for whole weights, which no path overflows, any walk
+// through the map is costed but a city out of range is an error instead of a panic
+pub fn path_cost<W: Weight>(intercity_map: &[Vec<W>], path: &[u32]) -> Result<W::Cost, TSPError> {
+    check_city_map(intercity_map)?;
+
+    let num_cities = intercity_map.len();
+    if let Some((position, &city)) = path
+        .iter()
+        .enumerate()
+        .find(|&(_, &city)| city as usize >= num_cities)
+    {
+        return Err(TSPError::CityOutOfRange {
+            position,
+            city,
+            num_cities,
+        });
+    }
+
+    Ok(path_cost_with(&intercity_map, path))
+}
+
+pub fn generate_default_path<W>(intercity_map: &[Vec<W>]) -> Vec<u32> {
     intercity_map
         .iter()
         .enumerate()
-        .map(|(idx, _)| idx as u16)
+        .map(|(idx, _)| idx as u32)
         .collect_vec()
 }
 
+#[cfg(test)]
 mod tests {
+    use crate::brute_force::brute_force_tsp;
     use crate::common::generate_random_path;
-    use crate::{generate_map, path_cost};
+    use crate::common::{
+        check_city_map, check_path, check_symmetric, generate_asymmetric_map,
+        generate_asymmetric_map_with_rng, generate_map, generate_map_with_rng,
+        generate_random_path_with_rng, path_cost, verify_tour, TSPError,
+    };
+    use crate::config::SolverConfig;
+    use crate::cost::TourMode;
+    use crate::sim_annealing::Annealer;
     use itertools::zip_eq;
     use itertools::Itertools;
+    use rand::{rngs::StdRng, SeedableRng};
 
     #[test]
     fn test_path_cost() {
@@ -81,14 +444,15 @@ mod tests {
             vec![2, 2, 2, 2],
             vec![2, 2, 2, 2],
         ];
-        let path: Vec<u16> = vec![0, 1, 2, 3];
+        let path: Vec<u32> = vec![0, 1, 2, 3];
 
         // all paths costs 2 so 3 movements needed, so 2*3 is the cost
-        let cost = path_cost(&map, &path);
+        let cost = path_cost(&map, &path).ok().unwrap();
         assert_eq!(cost, 2 * 3);
     }
 
     #[test]
+    #[allow(clippy::needless_range_loop)]
     fn test_map_gen() {
         let map = generate_map(10, (25, 40));
         assert!(map.is_ok());
@@ -101,17 +465,175 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_asymmetric_map_gen() {
+        let map = generate_asymmetric_map(10, (25, 1000)).ok().unwrap();
+        assert!(check_city_map(&map).is_ok());
+        assert!((0..10).all(|i| map[i][i] == 0));
+        assert!(map
+            .iter()
+            .flatten()
+            .all(|&weight| weight == 0 || (25..1000).contains(&weight)));
+        assert!(check_symmetric(&map).is_err());
+        assert!(generate_asymmetric_map(3, (5, 5)).is_err());
+
+        // the solvers cost tours in the direction they are travelled
+        let map = generate_asymmetric_map(7, (1, 1000)).ok().unwrap();
+        let (path, cost) = brute_force_tsp(&map).ok().unwrap();
+        let reversed: Vec<u32> = path.iter().rev().copied().collect();
+        assert_eq!(path_cost(&map, &path).ok(), Some(cost));
+        assert!(path_cost(&map, &reversed).ok().unwrap() >= cost);
+    }
+
     #[test]
     fn test_random_path_gen() {
         let map = generate_map(10, (60, 90)).ok().unwrap();
         let path = generate_random_path(&map);
-        let path = path;
 
-        let dedupd = path.iter().unique().collect::<Vec<&u16>>();
+        let dedupd = path.iter().unique().collect::<Vec<&u32>>();
         for (&e1, &e2) in zip_eq(&dedupd, &path) {
             assert_eq!(*e1, e2);
         }
 
         assert_eq!(dedupd.len(), path.len());
     }
+
+    #[test]
+    fn test_seeded_generation() {
+        let seeded = |seed| StdRng::seed_from_u64(seed);
+        let map = generate_map_with_rng(10, (1, 100), &mut seeded(5))
+            .ok()
+            .unwrap();
+        assert_eq!(
+            map,
+            generate_map_with_rng(10, (1, 100), &mut seeded(5))
+                .ok()
+                .unwrap()
+        );
+        assert_ne!(
+            map,
+            generate_map_with_rng(10, (1, 100), &mut seeded(6))
+                .ok()
+                .unwrap()
+        );
+        assert_eq!(
+            generate_asymmetric_map_with_rng(6, (1, 100), &mut seeded(5))
+                .ok()
+                .unwrap(),
+            generate_asymmetric_map_with_rng(6, (1, 100), &mut seeded(5))
+                .ok()
+                .unwrap()
+        );
+        assert_eq!(
+            generate_random_path_with_rng(&map, &mut seeded(5)),
+            generate_random_path_with_rng(&map, &mut seeded(5))
+        );
+
+        // the same seed gives the same map and then the same tour
+        let tour = |seed| {
+            let map = generate_map_with_rng(12, (1, 100), &mut seeded(seed))
+                .ok()
+                .unwrap();
+            let config = SolverConfig {
+                temperature: 200,
+                max_iterations: 5000,
+                seed: Some(seed),
+                ..SolverConfig::default()
+            };
+            let (path, cost, _) = Annealer::new(config).solve(&map).ok().unwrap();
+            (path, cost)
+        };
+        assert_eq!(tour(9), tour(9));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let ragged: Vec<Vec<u16>> = vec![vec![0, 1, 2], vec![1, 0, 2], vec![2, 2]];
+        assert!(matches!(
+            check_city_map(&ragged),
+            Err(TSPError::InvalidMapShape {
+                row: 2,
+                len: 2,
+                expected: 3
+            })
+        ));
+        assert!(matches!(
+            check_city_map::<u16>(&[]),
+            Err(TSPError::EmptyMap)
+        ));
+
+        let map: Vec<Vec<u16>> = vec![vec![0, 1, 2], vec![1, 0, 4], vec![2, 4, 0]];
+        assert_eq!(path_cost(&map, &[0, 1, 0]).ok(), Some(2));
+        assert!(matches!(
+            path_cost(&map, &[0, 3]),
+            Err(TSPError::CityOutOfRange {
+                position: 1,
+                city: 3,
+                num_cities: 3
+            })
+        ));
+        assert!(matches!(
+            path_cost(&ragged, &[0]),
+            Err(TSPError::InvalidMapShape { .. })
+        ));
+
+        assert!(check_path(4, &[3, 1, 0, 2]).is_ok());
+        assert!(matches!(
+            check_path(4, &[3, 1, 7, 2]),
+            Err(TSPError::CityOutOfRange {
+                position: 2,
+                city: 7,
+                num_cities: 4
+            })
+        ));
+        assert!(matches!(
+            check_path(4, &[3, 1, 3, 2]),
+            Err(TSPError::DuplicateCity {
+                position: 2,
+                city: 3
+            })
+        ));
+        assert!(matches!(
+            check_path(4, &[0, 1]),
+            Err(TSPError::InvalidPathLength {
+                len: 2,
+                expected: 4
+            })
+        ));
+
+        // a std error, boxing with ? and keeping the io error as its source
+        let boxed = || -> Result<(), Box<dyn std::error::Error>> {
+            Err(TSPError::from(std::io::Error::other("disk full")))?
+        };
+        let err = boxed().unwrap_err();
+        assert_eq!(err.to_string(), "io error: disk full");
+        assert_eq!(err.source().unwrap().to_string(), "disk full");
+        assert!(std::error::Error::source(&TSPError::EmptyMap).is_none());
+        assert_eq!(format!("{:?}", TSPError::EmptyMap), "EmptyMap");
+    }
+
+    #[test]
+    fn test_verify_tour() {
+        let map = generate_map(6, (1, 50)).ok().unwrap();
+        let tour = generate_random_path(&map);
+        assert_eq!(
+            verify_tour(&map, &tour, TourMode::Open).ok(),
+            path_cost(&map, &tour).ok()
+        );
+        let closing = map[tour[5] as usize][tour[0] as usize] as u64;
+        assert_eq!(
+            verify_tour(&map, &tour, TourMode::Closed).ok().unwrap(),
+            path_cost(&map, &tour).ok().unwrap() + closing
+        );
+
+        assert!(matches!(
+            verify_tour(&map, &[0, 1, 2, 3, 4, 4], TourMode::Open),
+            Err(TSPError::DuplicateCity {
+                position: 5,
+                city: 4
+            })
+        ));
+        assert!(verify_tour(&map, &[0, 1, 2], TourMode::Open).is_err());
+        assert!(verify_tour::<u16>(&[], &[], TourMode::Closed).is_err());
+    }
 }
@@ -0,0 +1,329 @@
+#[cfg(feature = "config")]
+use log::error;
+use std::time::Duration;
+
+use crate::common::{check_city_map, check_path, TSPError};
+use crate::cooling::CoolingSchedule;
+use crate::cost::{CostFunction, TourMode};
+use crate::moves::{named_move, MoveOperator};
+use crate::solvers::double_tree::double_tree_tour_with;
+use crate::solvers::greedy_edge::greedy_edge_tour_with;
+use crate::solvers::nearest_neighbour::nearest_neighbour_tour_with;
+use crate::weight::Weight;
+
+// every knob of an annealing run in one place, so an experiment can be kept in a file and run
+// again as it was, with the config feature read from TOML such as
+//
+//     temperature = 500
+//     max_iterations = 1000000
+//     cooling = { kind = "geometric", alpha = 0.95, epoch_length = 100 }
+//     moves = ["two_opt", "insertion"]
+//     reheat_patience = 50000
+//     runs = 4
+//     parallel = true
+//     seed = 7
+//     mode = "closed"
+//     time_limit = 30
+//     initial = { kind = "nearest_neighbour", start = 0 }
+//
+// where every field is optional and defaults to what the plain annealer does, and
+// `initial = { kind = "tour", cities = [3, 0, 2, 1] }` carries on from a known tour instead
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+#[derive(Clone, PartialEq, Debug)]
+pub struct SolverConfig {
+    pub temperature: u64,
+    pub max_iterations: u64,
+    pub cooling: CoolingSchedule,
+    pub moves: Vec<String>, // by the names the operators trace under
+    pub reheat_patience: Option<u64>,
+    pub runs: u64,         // independent restarts, the best tour of them is kept
+    pub parallel: bool,    // whether the runs each get a thread rather than run in turn
+    pub seed: Option<u64>, // run i is seeded with seed + i, unseeded runs differ every time
+    pub mode: TourMode,
+    // in seconds, each run stops at its share of it with the best tour it found
+    #[cfg_attr(feature = "serde", serde(with = "seconds"))]
+    pub time_limit: Option<Duration>,
+    pub initial: InitialTour,
+}
+
+// the tour every run starts annealing from
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub enum InitialTour {
+    // the cities in order, 0 to n - 1
+    #[default]
+    Identity,
+    // greedy from start, a good tour lets the run start cooler and spend its iterations refining
+    NearestNeighbour {
+        start: u32,
+    },
+    // cheapest edges first, usually a better start than nearest neighbour
+    GreedyEdge,
+    // the spanning tree's cities depth first, within twice the optimum on metric maps
+    DoubleTree,
+    // a warm start from a given tour, e.g. yesterday's route, which the run never ends worse than
+    Tour {
+        cities: Vec<u32>,
+    },
+}
+
+impl InitialTour {
+    pub fn tour<W: Weight>(&self, intercity_map: &[Vec<W>]) -> Result<Vec<u32>, TSPError> {
+        check_city_map(intercity_map)?;
+        self.tour_with(&intercity_map, intercity_map.len())
+    }
+
+    // same as tour but for any cost function over num_cities cities
+    pub(crate) fn tour_with<W: Weight>(
+        &self,
+        cost_function: &impl CostFunction<W>,
+        num_cities: usize,
+    ) -> Result<Vec<u32>, TSPError> {
+        match *self {
+            InitialTour::Identity => Ok((0..num_cities as u32).collect()),
+            InitialTour::NearestNeighbour { start } => {
+                nearest_neighbour_tour_with(cost_function, num_cities, start)
+            }
+            InitialTour::GreedyEdge => Ok(greedy_edge_tour_with(cost_function, num_cities)),
+            InitialTour::DoubleTree => Ok(double_tree_tour_with(cost_function, num_cities)),
+            InitialTour::Tour { ref cities } => {
+                check_path(num_cities, cities)?;
+                Ok(cities.clone())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod seconds {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        time_limit: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time_limit {
+            Some(time_limit) => serializer.serialize_some(&time_limit.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(|seconds| Duration::try_from_secs_f64(seconds).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            temperature: 200,
+            max_iterations: 200000,
+            cooling: CoolingSchedule::default(),
+            moves: vec!["two_opt".to_string()],
+            reheat_patience: None,
+            runs: 1,
+            parallel: false,
+            seed: None,
+            mode: TourMode::Open,
+            time_limit: None,
+            initial: InitialTour::Identity,
+        }
+    }
+}
+
+impl SolverConfig {
+    // n runs at once on as many threads, the best tour of them kept
+    pub fn parallel_restarts(self, n: u64) -> SolverConfig {
+        SolverConfig {
+            runs: n,
+            parallel: true,
+            ..self
+        }
+    }
+
+    pub fn check(&self) -> Result<(), TSPError> {
+        self.cooling.check()?;
+        self.move_operators::<u16>()?; // the names are the same whatever the weights
+        if self.reheat_patience == Some(0) {
+            return Err(TSPError::InvalidCoolingSchedule);
+        }
+        if self.runs == 0 {
+            return Err(TSPError::InvalidRunCount);
+        }
+        Ok(())
+    }
+
+    // fresh operators for a run, each keeps the state of its proposed move
+    pub fn move_operators<W: Weight>(&self) -> Result<Vec<Box<dyn MoveOperator<W>>>, TSPError> {
+        if self.moves.is_empty() {
+            return Err(TSPError::NoMoveOperators);
+        }
+        self.moves
+            .iter()
+            .map(|name| {
+                named_move(name).ok_or_else(|| TSPError::UnknownMoveOperator { name: name.clone() })
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "config")]
+    pub fn from_toml(contents: &str) -> Result<SolverConfig, TSPError> {
+        let config: SolverConfig = toml::from_str(contents).map_err(|err| {
+            error!("Malformed solver config: {}", err);
+            TSPError::InvalidFileFormat
+        })?;
+        config.check()?;
+        Ok(config)
+    }
+
+    #[cfg(feature = "config")]
+    pub fn read(path: &std::path::Path) -> Result<SolverConfig, TSPError> {
+        SolverConfig::from_toml(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{generate_map, TSPError};
+    use crate::config::{InitialTour, SolverConfig};
+    use crate::cost::{path_cost_with, TourMode};
+    use crate::sim_annealing::Annealer;
+    use crate::solvers::nearest_neighbour::nearest_neighbour_tsp;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_solver_config() {
+        let map = generate_map(8, (1, 100)).ok().unwrap();
+        let solve = |config: &SolverConfig| {
+            Annealer::new(config.clone())
+                .solve(&map)
+                .map(|(path, cost, _)| (path, cost))
+        };
+        let config = SolverConfig {
+            max_iterations: 5000,
+            moves: vec!["two_opt".to_string(), "swap".to_string()],
+            reheat_patience: Some(500),
+            runs: 3,
+            seed: Some(7),
+            mode: TourMode::Closed,
+            ..SolverConfig::default()
+        };
+        let first = solve(&config).ok().unwrap();
+        let second = solve(&config).ok().unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.1, TourMode::Closed.cost(&map, &first.0));
+        // seeded, so threads don't change the outcome
+        let parallel = config.clone().parallel_restarts(3);
+        assert_eq!(solve(&parallel).ok(), Some(first));
+
+        // starting from a greedy tour, a run too short to move keeps it
+        let greedy = SolverConfig {
+            max_iterations: 0,
+            initial: InitialTour::NearestNeighbour { start: 2 },
+            ..SolverConfig::default()
+        };
+        assert_eq!(solve(&greedy).ok(), nearest_neighbour_tsp(&map, 2).ok());
+        // and a warm start never ends worse than the tour it was given
+        let (path, cost) = solve(&config).ok().unwrap();
+        let warm = SolverConfig {
+            temperature: 1000,
+            initial: InitialTour::Tour { cities: path },
+            ..config.clone()
+        };
+        let (_, warm_cost) = solve(&warm).ok().unwrap();
+        assert!(warm_cost <= cost);
+        let too_short = SolverConfig {
+            initial: InitialTour::Tour {
+                cities: vec![0, 1, 2],
+            },
+            ..SolverConfig::default()
+        };
+        assert!(solve(&too_short).is_err());
+
+        let unknown = SolverConfig {
+            moves: vec!["three_opt".to_string()],
+            ..SolverConfig::default()
+        };
+        assert!(matches!(
+            unknown.check(),
+            Err(TSPError::UnknownMoveOperator { .. })
+        ));
+        let no_runs = SolverConfig {
+            runs: 0,
+            ..SolverConfig::default()
+        };
+        assert!(solve(&no_runs).is_err());
+
+        // anytime, stopped by the clock long before the iterations run out
+        let timed = SolverConfig {
+            max_iterations: u64::MAX,
+            runs: 2,
+            time_limit: Some(Duration::from_millis(50)),
+            ..SolverConfig::default()
+        };
+        let start = Instant::now();
+        let (path, cost) = solve(&timed).ok().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(cost, path_cost_with(&map, &path));
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_from_toml() {
+        use crate::cooling::CoolingSchedule;
+
+        let config = SolverConfig::from_toml(
+            "temperature = 500\n\
+             cooling = { kind = \"geometric\", alpha = 0.95, epoch_length = 100 }\n\
+             moves = [\"two_opt\", \"insertion\"]\n\
+             mode = \"closed\"\n\
+             time_limit = 1.5\n\
+             initial = { kind = \"nearest_neighbour\", start = 3 }\n",
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(config.temperature, 500);
+        assert_eq!(
+            config.cooling,
+            CoolingSchedule::Geometric {
+                alpha: 0.95,
+                epoch_length: 100
+            }
+        );
+        assert_eq!(config.mode, TourMode::Closed);
+        assert_eq!(config.time_limit, Some(Duration::from_millis(1500)));
+        assert_eq!(config.initial, InitialTour::NearestNeighbour { start: 3 });
+        assert_eq!(
+            config.max_iterations,
+            SolverConfig::default().max_iterations
+        );
+
+        assert_eq!(
+            SolverConfig::from_toml("").ok(),
+            Some(SolverConfig::default())
+        );
+        assert!(SolverConfig::from_toml("temprature = 500").is_err());
+        assert!(SolverConfig::from_toml(
+            "cooling = { kind = \"geometric\", alpha = 2.0, epoch_length = 1 }"
+        )
+        .is_err());
+        assert!(SolverConfig::from_toml("moves = [\"three_opt\"]").is_err());
+        assert!(SolverConfig::from_toml("time_limit = -1").is_err());
+        let warm = SolverConfig::from_toml("initial = { kind = \"tour\", cities = [2, 0, 1] }\n")
+            .ok()
+            .unwrap();
+        assert_eq!(
+            warm.initial,
+            InitialTour::Tour {
+                cities: vec![2, 0, 1]
+            }
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use std::io::Write;
+
+use crate::common::TSPError;
+use crate::weight::Delta;
+
+const HEADER: &str = "iteration,temperature,current_cost,best_cost,accepted";
+
+// the state of a run at one iteration, solvers without a temperature record 0
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ConvergencePoint<D = i64> {
+    pub iteration: u64,
+    pub temperature: f64,
+    pub current_cost: D,
+    pub best_cost: D,
+    pub accepted: bool, // whether the iteration's candidate was moved to
+}
+
+// how a run converged, for plotting cooling curves, every sample_interval-th iteration is kept so
+// that long runs stay small
+#[derive(Clone, PartialEq, Debug)]
+pub struct ConvergenceTrace<D = i64> {
+    sample_interval: u64,
+    points: Vec<ConvergencePoint<D>>,
+}
+
+impl<D: Delta> Default for ConvergenceTrace<D> {
+    fn default() -> Self {
+        ConvergenceTrace::new(1)
+    }
+}
+
+impl<D: Delta> ConvergenceTrace<D> {
+    // a sample_interval of 0 is taken as 1, keeping every iteration
+    pub fn new(sample_interval: u64) -> ConvergenceTrace<D> {
+        ConvergenceTrace {
+            sample_interval: sample_interval.max(1),
+            points: Vec::new(),
+        }
+    }
+
+    pub fn is_sampled(&self, iteration: u64) -> bool {
+        iteration.is_multiple_of(self.sample_interval)
+    }
+
+    // keeps the point if its iteration is sampled
+    pub fn record(&mut self, point: ConvergencePoint<D>) {
+        if self.is_sampled(point.iteration) {
+            self.points.push(point);
+        }
+    }
+
+    pub fn points(&self) -> &[ConvergencePoint<D>] {
+        &self.points
+    }
+
+    // one line per point under a header, `accepted` written as 0 or 1
+    pub fn write_csv<W: Write>(&self, writer: &mut W) -> Result<(), TSPError> {
+        writeln!(writer, "{}", HEADER)?;
+        for point in &self.points {
+            writeln!(
+                writer,
+                "{},{},{},{},{}",
+                point.iteration,
+                point.temperature,
+                point.current_cost,
+                point.best_cost,
+                point.accepted as u8
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::generate_map;
+    use crate::config::SolverConfig;
+    use crate::convergence::ConvergenceTrace;
+    use crate::sim_annealing::Annealer;
+
+    #[test]
+    fn test_convergence_trace() {
+        let map = generate_map(6, (1, 100)).ok().unwrap();
+        let mut trace = ConvergenceTrace::new(10);
+        let config = SolverConfig {
+            max_iterations: 1000,
+            ..SolverConfig::default()
+        };
+        assert!(Annealer::new(config)
+            .convergence(&mut trace)
+            .solve(&map)
+            .is_ok());
+
+        let points = trace.points();
+        assert_eq!(points.len(), 100);
+        assert!(points.iter().all(|point| point.iteration % 10 == 0));
+        assert!(points.windows(2).all(|pair| {
+            pair[1].best_cost <= pair[0].best_cost && pair[1].temperature < pair[0].temperature
+        }));
+        assert!(points
+            .iter()
+            .all(|point| point.best_cost <= point.current_cost));
+
+        let mut csv = Vec::new();
+        trace.write_csv(&mut csv).ok().unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("iteration,temperature,current_cost,best_cost,accepted")
+        );
+        assert_eq!(lines.count(), 100);
+    }
+}
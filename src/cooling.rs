@@ -0,0 +1,180 @@
+use log::error;
+use std::fmt;
+
+use crate::common::TSPError;
+
+// how the temperature falls from the initial temperature T0 over a run, the geometric, linear
+// and logarithmic schedules hold each temperature for epoch_length iterations
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum CoolingSchedule {
+    // T0 / (1 + k), the annealer's original schedule, fast to cool early on
+    #[default]
+    Cauchy,
+    // T0 * alpha^epoch with 0 < alpha < 1
+    Geometric {
+        alpha: f64,
+        epoch_length: u64,
+    },
+    // from T0 down to 0 at the last iteration in equal steps
+    Linear {
+        epoch_length: u64,
+    },
+    // T0 / ln(e + epoch), slow enough to settle on the optimum given unlimited iterations
+    Logarithmic {
+        epoch_length: u64,
+    },
+}
+
+impl CoolingSchedule {
+    pub fn check(&self) -> Result<(), TSPError> {
+        let valid = match *self {
+            CoolingSchedule::Cauchy => true,
+            CoolingSchedule::Geometric {
+                alpha,
+                epoch_length,
+            } => alpha > 0. && alpha < 1. && epoch_length > 0,
+            CoolingSchedule::Linear { epoch_length }
+            | CoolingSchedule::Logarithmic { epoch_length } => epoch_length > 0,
+        };
+        if !valid {
+            return Err(TSPError::InvalidCoolingSchedule);
+        }
+        Ok(())
+    }
+
+    // temperature at the given iteration of a run of max_iterations
+    pub fn temperature(&self, initial: f64, iteration: u64, max_iterations: u64) -> f64 {
+        match *self {
+            CoolingSchedule::Cauchy => initial / (1. + iteration as f64),
+            CoolingSchedule::Geometric {
+                alpha,
+                epoch_length,
+            } => initial * alpha.powf((iteration / epoch_length) as f64),
+            CoolingSchedule::Linear { epoch_length } => {
+                let epoch_start = iteration - iteration % epoch_length;
+                initial * (1. - epoch_start as f64 / max_iterations.max(1) as f64).max(0.)
+            }
+            CoolingSchedule::Logarithmic { epoch_length } => {
+                initial / (std::f64::consts::E + (iteration / epoch_length) as f64).ln()
+            }
+        }
+    }
+
+    // `cauchy`, `geometric:<alpha>[:<epoch length>]`, `linear[:<epoch length>]` or
+    // `logarithmic[:<epoch length>]`, epochs being a single iteration by default
+    pub fn parse(spec: &str) -> Result<CoolingSchedule, TSPError> {
+        let mut fields = spec.split(':');
+        let name = fields.next().unwrap_or_default();
+        let parameters: Vec<f64> = match fields.map(str::parse).collect() {
+            Ok(parameters) => parameters,
+            Err(_) => {
+                error!("Invalid cooling schedule parameters in '{}'", spec);
+                return Err(TSPError::InvalidCoolingSchedule);
+            }
+        };
+        let epoch_length = |index: usize| match parameters.get(index) {
+            Some(&length) if length.fract() == 0. && length >= 1. => Some(length as u64),
+            Some(_) => None,
+            None => Some(1),
+        };
+
+        let schedule = match (name, parameters.len()) {
+            ("cauchy", 0) => Some(CoolingSchedule::Cauchy),
+            ("geometric", 1 | 2) => {
+                epoch_length(1).map(|epoch_length| CoolingSchedule::Geometric {
+                    alpha: parameters[0],
+                    epoch_length,
+                })
+            }
+            ("linear", 0 | 1) => {
+                epoch_length(0).map(|epoch_length| CoolingSchedule::Linear { epoch_length })
+            }
+            ("logarithmic", 0 | 1) => {
+                epoch_length(0).map(|epoch_length| CoolingSchedule::Logarithmic { epoch_length })
+            }
+            _ => None,
+        };
+        match schedule {
+            Some(schedule) => schedule.check().map(|_| schedule),
+            None => {
+                error!("Unknown cooling schedule '{}'", spec);
+                Err(TSPError::InvalidCoolingSchedule)
+            }
+        }
+    }
+}
+
+// the spec parse reads back
+impl fmt::Display for CoolingSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CoolingSchedule::Cauchy => write!(f, "cauchy"),
+            CoolingSchedule::Geometric {
+                alpha,
+                epoch_length,
+            } => write!(f, "geometric:{}:{}", alpha, epoch_length),
+            CoolingSchedule::Linear { epoch_length } => write!(f, "linear:{}", epoch_length),
+            CoolingSchedule::Logarithmic { epoch_length } => {
+                write!(f, "logarithmic:{}", epoch_length)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cooling::CoolingSchedule;
+
+    #[test]
+    fn test_schedules() {
+        let cauchy = CoolingSchedule::default();
+        assert_eq!(cauchy.temperature(100., 0, 10), 100.);
+        assert_eq!(cauchy.temperature(100., 3, 10), 25.);
+
+        let geometric = CoolingSchedule::Geometric {
+            alpha: 0.5,
+            epoch_length: 10,
+        };
+        assert_eq!(geometric.temperature(100., 9, 100), 100.);
+        assert_eq!(geometric.temperature(100., 25, 100), 25.);
+
+        let linear = CoolingSchedule::Linear { epoch_length: 1 };
+        assert_eq!(linear.temperature(100., 50, 100), 50.);
+        assert_eq!(linear.temperature(100., 100, 100), 0.);
+
+        let logarithmic = CoolingSchedule::Logarithmic { epoch_length: 1 };
+        assert!((logarithmic.temperature(100., 0, 100) - 100.).abs() < 1e-9);
+        assert!(logarithmic.temperature(100., 50, 100) > cauchy.temperature(100., 50, 100));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            CoolingSchedule::parse("geometric:0.95:100").ok(),
+            Some(CoolingSchedule::Geometric {
+                alpha: 0.95,
+                epoch_length: 100
+            })
+        );
+        assert_eq!(
+            CoolingSchedule::parse("linear").ok(),
+            Some(CoolingSchedule::Linear { epoch_length: 1 })
+        );
+        assert_eq!(
+            CoolingSchedule::parse("cauchy").ok(),
+            Some(CoolingSchedule::Cauchy)
+        );
+        assert!(CoolingSchedule::parse("geometric:1.5").is_err());
+        assert!(CoolingSchedule::parse("geometric").is_err());
+        assert!(CoolingSchedule::parse("linear:0").is_err());
+        assert!(CoolingSchedule::parse("logarithmic:x").is_err());
+        assert!(CoolingSchedule::parse("quadratic").is_err());
+
+        for spec in ["cauchy", "geometric:0.95:100", "linear:10", "logarithmic:1"] {
+            let schedule = CoolingSchedule::parse(spec).ok().unwrap();
+            assert_eq!(schedule.to_string(), spec);
+        }
+    }
+}
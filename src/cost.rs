@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::weight::{Cost, Weight};
+
+// where the annealer gets the weight of an edge from, a precomputed matrix or any closure
+// taking (from, to), e.g. one querying a database or a routing service
+pub trait CostFunction<W: Weight = u16> {
+    fn cost(&self, from: u32, to: u32) -> W;
+
+    // whether every edge costs the same both ways, moves can then skip recosting edges that only
+    // change direction, e.g. the inside of a reversed segment
+    fn is_symmetric(&self) -> bool {
+        false
+    }
+
+    // what the edges along path weigh, the return edge of a round trip aside, cost functions
+    // able to sum them faster than an edge at a time overriding it, e.g. matrix::DistanceMatrix
+    fn path_weight(&self, path: &[u32]) -> W::Cost {
+        path.windows(2)
+            .map(|edge| self.cost(edge[0], edge[1]).to_cost())
+            .sum()
+    }
+
+    // path_weight of every path, e.g. of a population
+    fn path_weights(&self, paths: &[Vec<u32>]) -> Vec<W::Cost> {
+        paths.iter().map(|path| self.path_weight(path)).collect()
+    }
+}
+
+impl<W: Weight> CostFunction<W> for Vec<Vec<W>> {
+    fn cost(&self, from: u32, to: u32) -> W {
+        self[from as usize][to as usize]
+    }
+}
+
+impl<W: Weight> CostFunction<W> for &[Vec<W>] {
+    fn cost(&self, from: u32, to: u32) -> W {
+        self[from as usize][to as usize]
+    }
+}
+
+impl<W: Weight, F: Fn(u32, u32) -> W> CostFunction<W> for F {
+    fn cost(&self, from: u32, to: u32) -> W {
+        self(from, to)
+    }
+}
+
+// a matrix whose symmetry is checked once up front, which the annealer wraps its maps in
+pub struct MatrixCost<'a, W = u16> {
+    weights: &'a [Vec<W>],
+    symmetric: bool,
+}
+
+impl<'a, W: Weight> MatrixCost<'a, W> {
+    pub fn new(weights: &'a [Vec<W>]) -> MatrixCost<'a, W> {
+        let symmetric = weights.iter().enumerate().all(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .skip(i + 1)
+                .all(|(j, &weight)| weights[j][i] == weight)
+        });
+        MatrixCost { weights, symmetric }
+    }
+}
+
+impl<W: Weight> CostFunction<W> for MatrixCost<'_, W> {
+    fn cost(&self, from: u32, to: u32) -> W {
+        self.weights[from as usize][to as usize]
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.symmetric
+    }
+}
+
+// remembers every edge it has been asked for so an expensive cost function is called once per edge,
+// behind a lock so parallel runs can share it
+pub struct CachedCost<C, W = u16> {
+    inner: C,
+    cache: Mutex<HashMap<(u32, u32), W>>,
+}
+
+impl<W: Weight, C: CostFunction<W>> CachedCost<C, W> {
+    pub fn new(inner: C) -> CachedCost<C, W> {
+        CachedCost {
+            inner,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn num_cached(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+}
+
+impl<W: Weight, C: CostFunction<W>> CostFunction<W> for CachedCost<C, W> {
+    fn cost(&self, from: u32, to: u32) -> W {
+        *self
+            .cache
+            .lock()
+            .unwrap()
+            .entry((from, to))
+            .or_insert_with(|| self.inner.cost(from, to))
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.inner.is_symmetric()
+    }
+}
+
+// whether a tour ends where it is (an open path, what the solvers find by default) or returns
+// to its first city (a closed round trip, the classic TSP)
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TourMode {
+    #[default]
+    Open,
+    Closed,
+}
+
+impl TourMode {
+    pub fn cost<W: Weight>(self, cost_function: &dyn CostFunction<W>, path: &[u32]) -> W::Cost {
+        path_cost_with(cost_function, path) + self.return_edge(cost_function, path)
+    }
+
+    // every path costed at once, see CostFunction::path_weights
+    pub fn costs<W: Weight>(
+        self,
+        cost_function: &dyn CostFunction<W>,
+        paths: &[Vec<u32>],
+    ) -> Vec<W::Cost> {
+        let weights = cost_function.path_weights(paths);
+        weights
+            .into_iter()
+            .zip(paths)
+            .map(|(weight, path)| weight + self.return_edge(cost_function, path))
+            .collect()
+    }
+
+    fn return_edge<W: Weight>(self, cost_function: &dyn CostFunction<W>, path: &[u32]) -> W::Cost {
+        match (self, path.first(), path.last()) {
+            (TourMode::Closed, Some(&first), Some(&last)) => {
+                cost_function.cost(last, first).to_cost()
+            }
+            _ => W::Cost::ZERO,
+        }
+    }
+}
+
+// same as common::path_cost but for any cost function
+pub fn path_cost_with<W: Weight>(cost_function: &dyn CostFunction<W>, path: &[u32]) -> W::Cost {
+    cost_function.path_weight(path)
+}
+
+// sums the path in any type the weights convert to, a weight's own cost type always fits a
+// path through as many cities as u32 can number, but callers scaling costs up, e.g.
+// cost * num_tours + tie_breaker to rank equal cost tours, can sum whole weights in u128 or
+// i128 instead so nothing overflows or has to go through a float
+pub fn path_cost_in<T: From<W> + std::iter::Sum<T>, W: Weight>(
+    cost_function: &dyn CostFunction<W>,
+    path: &[u32],
+) -> T {
+    path.windows(2)
+        .map(|edge| T::from(cost_function.cost(edge[0], edge[1])))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{generate_default_path, generate_map, path_cost};
+    use crate::cost::{
+        path_cost_in, path_cost_with, CachedCost, CostFunction, MatrixCost, TourMode,
+    };
+    use std::cell::Cell;
+
+    #[test]
+    fn test_cost_functions() {
+        let map = generate_map(6, (1, 100)).ok().unwrap();
+        let path = generate_default_path(&map);
+        let cost = path_cost(&map, &path).ok().unwrap();
+        assert_eq!(path_cost_with(&map, &path), cost);
+
+        assert!(MatrixCost::new(&map).is_symmetric());
+        assert_eq!(path_cost_with(&MatrixCost::new(&map), &path), cost);
+        assert_eq!(TourMode::Open.cost(&map, &path), cost);
+        assert_eq!(TourMode::Closed.cost(&map, &path), cost + map[5][0] as u64);
+        assert_eq!(TourMode::Closed.cost(&map, &[]), 0);
+        let paths = vec![path.clone(), vec![3, 1], Vec::new()];
+        assert_eq!(
+            TourMode::Closed.costs(&map, &paths),
+            vec![TourMode::Closed.cost(&map, &path), 2 * map[3][1] as u64, 0]
+        );
+        let asymmetric: Vec<Vec<u16>> = vec![vec![0, 1], vec![2, 0]];
+        assert!(!MatrixCost::new(&asymmetric).is_symmetric());
+        assert!(!map.is_symmetric());
+
+        let num_calls = Cell::new(0);
+        let cached = CachedCost::new(|from: u32, to: u32| {
+            num_calls.set(num_calls.get() + 1);
+            from.abs_diff(to) as u16
+        });
+        assert_eq!(path_cost_with(&cached, &path), 5);
+        assert_eq!(path_cost_with(&cached, &path), 5);
+        assert_eq!(num_calls.get(), 5);
+        assert_eq!(cached.num_cached(), 5);
+    }
+
+    #[test]
+    fn test_wide_cost_accumulation() {
+        // past 65,537 cities of the heaviest possible edges a path no longer fits in u32
+        let heaviest = |_: u32, _: u32| u16::MAX;
+        let path: Vec<u32> = (0..70_000).collect();
+        let cost: u64 = path_cost_in(&heaviest, &path);
+        assert_eq!(cost, 69_999 * u16::MAX as u64);
+        assert!(cost > u32::MAX as u64);
+
+        // scaled up for tie breaking it only fits in a wider type
+        let wide: u128 = path_cost_in(&heaviest, &path);
+        assert!(cost.checked_mul(1 << 40).is_none());
+        assert_eq!((wide << 40) >> 40, cost as u128);
+        assert_eq!(path_cost_in::<i128, _>(&heaviest, &path), cost as i128);
+    }
+}
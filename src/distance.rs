@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::candidates::{grid_candidates, nearest_candidates};
+use crate::common::TSPError;
+use crate::cost::{CostFunction, TourMode};
+use crate::matrix::DistanceMatrix;
+use crate::metric::Metric;
+use crate::weight::Weight;
+
+// where an instance's weights come from, a matrix worked out up front or the coordinates of its
+// cities, their distances worked out whenever a solver asks for one, which keeps the memory of
+// a 100,000 city instance linear rather than the tens of gigabytes its matrix would take
+#[derive(Clone, PartialEq, Debug)]
+pub enum DistanceProvider<W = u16> {
+    Matrix(DistanceMatrix<W>),
+    Coordinates(CoordinateDistances<W>),
+}
+
+impl<W: Weight> DistanceProvider<W> {
+    pub fn num_cities(&self) -> usize {
+        match self {
+            DistanceProvider::Matrix(matrix) => matrix.num_cities(),
+            DistanceProvider::Coordinates(coordinates) => coordinates.num_cities(),
+        }
+    }
+
+    // including the return edge for closed tours
+    pub fn path_cost(&self, path: &[u32], mode: TourMode) -> W::Cost {
+        match self {
+            DistanceProvider::Matrix(matrix) => matrix.path_cost(path, mode),
+            DistanceProvider::Coordinates(coordinates) => mode.cost(coordinates, path),
+        }
+    }
+
+    // each city's k nearest others, see candidates
+    pub fn candidates(&self, k: usize) -> Vec<Vec<u32>> {
+        match self {
+            DistanceProvider::Matrix(matrix) => nearest_candidates(matrix, matrix.num_cities(), k),
+            DistanceProvider::Coordinates(coordinates) => coordinates.candidates(k),
+        }
+    }
+
+    // every weight as nested rows, n^2 of them however they are provided
+    pub fn to_rows(&self) -> Vec<Vec<W>> {
+        match self {
+            DistanceProvider::Matrix(matrix) => matrix.to_rows(),
+            DistanceProvider::Coordinates(coordinates) => {
+                let cities = 0..coordinates.num_cities() as u32;
+                cities
+                    .clone()
+                    .map(|from| {
+                        cities
+                            .clone()
+                            .map(|to| coordinates.cost(from, to))
+                            .collect()
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl<W: Weight> CostFunction<W> for DistanceProvider<W> {
+    fn cost(&self, from: u32, to: u32) -> W {
+        match self {
+            DistanceProvider::Matrix(matrix) => matrix.get(from, to),
+            DistanceProvider::Coordinates(coordinates) => coordinates.cost(from, to),
+        }
+    }
+
+    fn is_symmetric(&self) -> bool {
+        match self {
+            DistanceProvider::Matrix(matrix) => matrix.is_symmetric(),
+            DistanceProvider::Coordinates(_) => true,
+        }
+    }
+
+    fn path_weight(&self, path: &[u32]) -> W::Cost {
+        match self {
+            DistanceProvider::Matrix(matrix) => matrix.path_weight(path),
+            DistanceProvider::Coordinates(coordinates) => coordinates.path_weight(path),
+        }
+    }
+}
+
+impl<W> From<DistanceMatrix<W>> for DistanceProvider<W> {
+    fn from(matrix: DistanceMatrix<W>) -> DistanceProvider<W> {
+        DistanceProvider::Matrix(matrix)
+    }
+}
+
+impl<W> From<CoordinateDistances<W>> for DistanceProvider<W> {
+    fn from(coordinates: CoordinateDistances<W>) -> DistanceProvider<W> {
+        DistanceProvider::Coordinates(coordinates)
+    }
+}
+
+// distances between points under a metric, measured on every lookup, rounded to the nearest
+// integer for whole weights, the metric being symmetric and obeying the triangle inequality as
+// every metric::Metric here does, clones sharing the cache
+#[derive(Clone)]
+pub struct CoordinateDistances<W = u16> {
+    points: Vec<(f64, f64)>,
+    metric: Arc<dyn Metric + Send + Sync>,
+    cache: Option<Arc<Mutex<LruCache<W>>>>,
+}
+
+impl<W: Weight> CoordinateDistances<W> {
+    // every distance fitting in a weight is checked in O(n) rather than O(n^2), none being more
+    // than twice the furthest point from the first by the triangle inequality
+    pub fn new(
+        points: Vec<(f64, f64)>,
+        metric: impl Metric + Send + Sync + 'static,
+    ) -> Result<CoordinateDistances<W>, TSPError> {
+        if points.is_empty() {
+            return Err(TSPError::EmptyMap);
+        }
+        if points.len() > u32::MAX as usize {
+            return Err(TSPError::InvalidCityCount);
+        }
+        if let Some(index) = points.iter().position(|&point| !metric.is_valid(point)) {
+            return Err(TSPError::InvalidCoordinate { index });
+        }
+
+        let furthest = points
+            .iter()
+            .map(|&point| metric.distance(points[0], point))
+            .fold(0., f64::max);
+        if W::from_f64(2. * furthest).is_none() {
+            return Err(TSPError::InvalidWeightRange);
+        }
+
+        Ok(CoordinateDistances {
+            points,
+            metric: Arc::new(metric),
+            cache: None,
+        })
+    }
+
+    // remembers the capacity most recently used distances, for metrics expensive enough to
+    // outweigh the lock every lookup then takes, e.g. haversine's trigonometry
+    pub fn with_cache(self, capacity: usize) -> CoordinateDistances<W> {
+        CoordinateDistances {
+            cache: (capacity > 0).then(|| Arc::new(Mutex::new(LruCache::new(capacity)))),
+            ..self
+        }
+    }
+
+    pub fn num_cities(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    pub fn is_euclidean(&self) -> bool {
+        self.metric.is_euclidean()
+    }
+
+    // each city's k nearest others, found from a grid of the points for the straight line metric
+    // by unrounded distance, and from every weight for the rest
+    pub fn candidates(&self, k: usize) -> Vec<Vec<u32>> {
+        if self.metric.is_euclidean() {
+            grid_candidates(&self.points, k)
+        } else {
+            nearest_candidates(self, self.num_cities(), k)
+        }
+    }
+
+    pub fn num_cached(&self) -> usize {
+        self.cache
+            .as_ref()
+            .map_or(0, |cache| cache.lock().unwrap().len())
+    }
+
+    fn distance(&self, from: u32, to: u32) -> W {
+        let distance = self
+            .metric
+            .distance(self.points[from as usize], self.points[to as usize]);
+        W::from_f64(distance).expect("distances are checked to fit on construction")
+    }
+}
+
+impl<W: Weight> CostFunction<W> for CoordinateDistances<W> {
+    fn cost(&self, from: u32, to: u32) -> W {
+        let Some(cache) = &self.cache else {
+            return self.distance(from, to);
+        };
+        let key = (from.min(to), from.max(to));
+        if let Some(weight) = cache.lock().unwrap().get(key) {
+            return weight;
+        }
+        let weight = self.distance(from, to);
+        cache.lock().unwrap().insert(key, weight);
+        weight
+    }
+
+    fn is_symmetric(&self) -> bool {
+        true
+    }
+}
+
+// the same points under the same metric, the metric being compared by identity
+impl<W> PartialEq for CoordinateDistances<W> {
+    fn eq(&self, other: &Self) -> bool {
+        self.points == other.points && Arc::ptr_eq(&self.metric, &other.metric)
+    }
+}
+
+impl<W> fmt::Debug for CoordinateDistances<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CoordinateDistances")
+            .field("num_points", &self.points.len())
+            .field("cached", &self.cache.is_some())
+            .finish()
+    }
+}
+
+// no entry, at either end of the list
+const NONE: usize = usize::MAX;
+
+// weights keyed by edge, the least recently used one making way for a new one once capacity are
+// held, its entries linked through their indices from the most to the least recently used
+struct LruCache<W> {
+    capacity: usize,
+    index: HashMap<(u32, u32), usize>,
+    entries: Vec<Entry<W>>,
+    newest: usize,
+    oldest: usize,
+}
+
+struct Entry<W> {
+    key: (u32, u32),
+    weight: W,
+    newer: usize,
+    older: usize,
+}
+
+impl<W: Copy> LruCache<W> {
+    fn new(capacity: usize) -> LruCache<W> {
+        LruCache {
+            capacity,
+            index: HashMap::with_capacity(capacity),
+            entries: Vec::with_capacity(capacity),
+            newest: NONE,
+            oldest: NONE,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn get(&mut self, key: (u32, u32)) -> Option<W> {
+        let entry = *self.index.get(&key)?;
+        self.unlink(entry);
+        self.push_newest(entry);
+        Some(self.entries[entry].weight)
+    }
+
+    fn insert(&mut self, key: (u32, u32), weight: W) {
+        if self.index.contains_key(&key) {
+            return;
+        }
+        let entry = if self.entries.len() < self.capacity {
+            self.entries.push(Entry {
+                key,
+                weight,
+                newer: NONE,
+                older: NONE,
+            });
+            self.entries.len() - 1
+        } else {
+            let entry = self.oldest;
+            self.unlink(entry);
+            self.index.remove(&self.entries[entry].key);
+            self.entries[entry].key = key;
+            self.entries[entry].weight = weight;
+            entry
+        };
+        self.index.insert(key, entry);
+        self.push_newest(entry);
+    }
+
+    fn unlink(&mut self, entry: usize) {
+        let Entry { newer, older, .. } = self.entries[entry];
+        match newer {
+            NONE => self.newest = older,
+            newer => self.entries[newer].older = older,
+        }
+        match older {
+            NONE => self.oldest = newer,
+            older => self.entries[older].newer = newer,
+        }
+    }
+
+    fn push_newest(&mut self, entry: usize) {
+        self.entries[entry].newer = NONE;
+        self.entries[entry].older = self.newest;
+        match self.newest {
+            NONE => self.oldest = entry,
+            newest => self.entries[newest].newer = entry,
+        }
+        self.newest = entry;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::TSPError;
+    use crate::cost::{CostFunction, TourMode};
+    use crate::distance::{CoordinateDistances, DistanceProvider, LruCache};
+    use crate::instance::TspInstance;
+    use crate::metric::{metric_map, Euclidean, Haversine};
+    use crate::solver::{solver_by_name, Budget};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_coordinate_distances() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let points: Vec<(f64, f64)> = (0..50)
+            .map(|_| (rng.gen_range(0.0..1000.), rng.gen_range(0.0..1000.)))
+            .collect();
+        let map: Vec<Vec<u16>> = metric_map(&points, &Euclidean).ok().unwrap();
+        let distances: CoordinateDistances = CoordinateDistances::new(points.clone(), Euclidean)
+            .ok()
+            .unwrap()
+            .with_cache(100);
+        let provider = DistanceProvider::from(distances.clone());
+        assert_eq!(provider.to_rows(), map);
+        assert_eq!(distances.num_cached(), 100);
+        assert_eq!(distances.cost(3, 7), distances.cost(7, 3));
+
+        // the same tours as the matrix, solved without one
+        let on_the_fly = TspInstance::from_distances(provider);
+        let matrix = TspInstance::new(map).ok().unwrap();
+        for name in ["nearest_neighbour", "lin_kernighan", "simulated_annealing"] {
+            let solver = solver_by_name::<u16>(name).unwrap();
+            let solution = solver.solve(&on_the_fly, &Budget::default()).ok().unwrap();
+            assert_eq!(solution.cost, matrix.cost(&solution.tour).ok().unwrap());
+        }
+
+        // a hundred thousand cities, whose matrix would take 10 GB
+        let points: Vec<(f64, f64)> = (0..100_000)
+            .map(|_| (rng.gen_range(0.0..1000.), rng.gen_range(0.0..1000.)))
+            .collect();
+        let distances = CoordinateDistances::<f64>::new(points, Euclidean)
+            .ok()
+            .unwrap();
+        let large = TspInstance::from_distances(distances.into()).with_mode(TourMode::Closed);
+        let tour = large.simulated_annealing(10, 2000).ok().unwrap();
+        assert_eq!(tour.cities().len(), 100_000);
+        let candidates = large.candidates(5);
+        assert!(candidates.iter().all(|list| list.len() == 5));
+
+        assert!(matches!(
+            CoordinateDistances::<u16>::new(vec![(0., 0.), (70000., 0.)], Euclidean),
+            Err(TSPError::InvalidWeightRange)
+        ));
+        assert!(matches!(
+            CoordinateDistances::<f64>::new(vec![(0., 0.), (91., 0.)], Haversine),
+            Err(TSPError::InvalidCoordinate { index: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_lru_cache() {
+        let mut cache = LruCache::new(2);
+        cache.insert((0, 1), 5u16);
+        cache.insert((0, 2), 6);
+        assert_eq!(cache.get((0, 1)), Some(5));
+        // (0, 2) is the least recently used now
+        cache.insert((1, 2), 7);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get((0, 2)), None);
+        assert_eq!(cache.get((0, 1)), Some(5));
+        assert_eq!(cache.get((1, 2)), Some(7));
+        cache.insert((0, 2), 6);
+        assert_eq!(cache.get((0, 1)), None);
+    }
+}
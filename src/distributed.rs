@@ -0,0 +1,236 @@
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::api::to_canonical_json;
+use crate::common::{check_city_map, generate_default_path, TSPError};
+use crate::config::SolverConfig;
+use crate::cost::path_cost_with;
+use crate::sim_annealing::{Annealer, Checkpoint};
+
+// the coordinator hands every node the same instance, nodes run independent annealing chains
+// and report their current tour every exchange_interval iterations, the coordinator answers with
+// the best tour seen across all nodes which a node adopts if it beats its own
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Assignment {
+    pub intercity_map: Vec<Vec<u16>>,
+    pub temperature: u64,
+    pub max_iterations: u64,
+    pub exchange_interval: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct TourReport {
+    pub path: Vec<u32>,
+    pub cost: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Assign(Assignment),
+    Report { iteration: u64, tour: TourReport },
+    Best(TourReport),
+    Finished(TourReport),
+}
+
+fn to_protocol_error(err: serde_json::Error) -> TSPError {
+    error!("Malformed message from peer: {}", err);
+    TSPError::InvalidFileFormat
+}
+
+fn send(stream: &mut TcpStream, message: &Message) -> Result<(), TSPError> {
+    let mut line = to_canonical_json(message).map_err(to_protocol_error)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+fn receive(reader: &mut BufReader<TcpStream>) -> Result<Message, TSPError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    serde_json::from_str(&line).map_err(to_protocol_error)
+}
+
+// keeps whichever tour is cheaper and returns the global best
+fn merge_best(best: &Mutex<Option<TourReport>>, tour: TourReport) -> TourReport {
+    let mut best = best.lock().unwrap();
+    match &*best {
+        Some(current) if current.cost <= tour.cost => current.clone(),
+        _ => {
+            *best = Some(tour.clone());
+            tour
+        }
+    }
+}
+
+fn handle_node(
+    stream: TcpStream,
+    assignment: Assignment,
+    best: Arc<Mutex<Option<TourReport>>>,
+) -> Result<(), TSPError> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    send(&mut writer, &Message::Assign(assignment))?;
+
+    loop {
+        match receive(&mut reader)? {
+            Message::Report { tour, .. } => {
+                let global_best = merge_best(&best, tour);
+                send(&mut writer, &Message::Best(global_best))?;
+            }
+            Message::Finished(tour) => {
+                merge_best(&best, tour);
+                return Ok(());
+            }
+            _ => return Err(TSPError::InvalidFileFormat),
+        }
+    }
+}
+
+// waits for num_nodes nodes to connect, runs them to completion and returns the best tour
+pub fn run_coordinator(
+    listener: TcpListener,
+    assignment: Assignment,
+    num_nodes: usize,
+) -> Result<(Vec<u32>, u64), TSPError> {
+    check_city_map(&assignment.intercity_map)?;
+
+    let best = Arc::new(Mutex::new(None));
+    let mut handles = Vec::with_capacity(num_nodes);
+    for _ in 0..num_nodes {
+        let (stream, address) = listener.accept()?;
+        info!("Node {} connected", address);
+        let assignment = assignment.clone();
+        let best = best.clone();
+        handles.push(thread::spawn(move || handle_node(stream, assignment, best)));
+    }
+
+    for handle in handles {
+        // a failed node only loses its own chain, the others still finish
+        if let Err(err) = handle.join().unwrap_or(Err(TSPError::SolverPanicked)) {
+            error!("Node failed: {}", err);
+        }
+    }
+
+    let best = best.lock().unwrap().take();
+    best.map(|tour| (tour.path, tour.cost))
+        .ok_or(TSPError::Cancelled)
+}
+
+// connects to a coordinator and runs its assignment, exchanging tours as it goes
+pub fn run_node(address: impl ToSocketAddrs) -> Result<(Vec<u32>, u64), TSPError> {
+    let stream = TcpStream::connect(address)?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    let Message::Assign(assignment) = receive(&mut reader)? else {
+        return Err(TSPError::InvalidFileFormat);
+    };
+    let intercity_map = &assignment.intercity_map;
+
+    let mut checkpoint = Checkpoint {
+        iteration: 0,
+        path: generate_default_path(intercity_map),
+    };
+    let mut exchange_error = None;
+    let (path, cost) = loop {
+        let mut adopted = None;
+        let config = SolverConfig {
+            temperature: assignment.temperature,
+            max_iterations: assignment.max_iterations,
+            ..SolverConfig::default()
+        };
+        let (path, cost, _) = Annealer::new(config)
+            .resume(checkpoint.clone())
+            .on_progress(assignment.exchange_interval, |progress| {
+                let tour = TourReport {
+                    path: progress.current_path.to_vec(),
+                    cost: progress.current_cost,
+                };
+                let reply = send(
+                    &mut writer,
+                    &Message::Report {
+                        iteration: progress.iteration,
+                        tour,
+                    },
+                )
+                .and_then(|_| receive(&mut reader));
+                match reply {
+                    Ok(Message::Best(best)) if best.cost < progress.current_cost => {
+                        // restart the chain from the better tour at the same point in the schedule
+                        adopted = Some(Checkpoint {
+                            iteration: progress.iteration,
+                            path: best.path,
+                        });
+                        ControlFlow::Break(())
+                    }
+                    Ok(_) => ControlFlow::Continue(()),
+                    Err(err) => {
+                        exchange_error = Some(err);
+                        ControlFlow::Break(())
+                    }
+                }
+            })
+            .solve(intercity_map)?;
+
+        if let Some(err) = exchange_error.take() {
+            return Err(err);
+        }
+        match adopted {
+            Some(next) => checkpoint = next,
+            None => break (path, cost),
+        }
+    };
+
+    debug_assert_eq!(cost, path_cost_with(intercity_map, &path));
+    send(
+        &mut writer,
+        &Message::Finished(TourReport {
+            path: path.clone(),
+            cost,
+        }),
+    )?;
+    Ok((path, cost))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::generate_map;
+    use crate::distributed::{run_coordinator, run_node, Assignment};
+    use crate::testing::is_cost_consistent;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_coordinator_with_nodes() {
+        let map = generate_map(7, (1, 100)).ok().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let assignment = Assignment {
+            intercity_map: map.clone(),
+            temperature: 200,
+            max_iterations: 2000,
+            exchange_interval: 250,
+        };
+
+        let nodes: Vec<_> = (0..3)
+            .map(|_| thread::spawn(move || run_node(address)))
+            .collect();
+        let (best_path, best_cost) = run_coordinator(listener, assignment, 3).ok().unwrap();
+        assert!(is_cost_consistent(&map, &best_path, best_cost));
+
+        for node in nodes {
+            let (path, cost) = node.join().unwrap().ok().unwrap();
+            assert!(is_cost_consistent(&map, &path, cost));
+            assert!(best_cost <= cost);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::candidates::grid_candidates;
+use crate::common::TSPError;
+use crate::cost::CostFunction;
+use crate::instance::TspInstance;
+use crate::io::tsplib::euclidean;
+
+// cities as points on a plane weighed by their straight line distance, rounded to the nearest
+// integer as TSPLIB's EUC_2D does, either computed on the fly as a cost function, which keeps
+// large instances out of a quadratic matrix, or all at once into a TspInstance
+#[derive(Clone, PartialEq, Debug)]
+pub struct EuclideanInstance {
+    points: Vec<(f64, f64)>,
+}
+
+impl EuclideanInstance {
+    pub fn new(points: Vec<(f64, f64)>) -> Result<EuclideanInstance, TSPError> {
+        if points.is_empty() {
+            return Err(TSPError::EmptyMap);
+        }
+        if points.len() > u32::MAX as usize {
+            return Err(TSPError::InvalidCityCount);
+        }
+        if let Some(index) = points
+            .iter()
+            .position(|&(x, y)| !x.is_finite() || !y.is_finite())
+        {
+            return Err(TSPError::InvalidCoordinate { index });
+        }
+
+        // no two points are further apart than the corners of their bounding box
+        let (min, max) = points.iter().fold(
+            ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+            |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+        );
+        let diagonal = euclidean(min.0, min.1, max.0, max.1);
+        if diagonal > u16::MAX as u32 {
+            return Err(TSPError::InvalidWeightRange);
+        }
+
+        Ok(EuclideanInstance { points })
+    }
+
+    pub fn num_cities(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    // each city's k nearest others, see candidates
+    pub fn candidates(&self, k: usize) -> Vec<Vec<u32>> {
+        grid_candidates(&self.points, k)
+    }
+
+    pub fn distance(&self, from: u32, to: u32) -> u16 {
+        let ((x1, y1), (x2, y2)) = (self.points[from as usize], self.points[to as usize]);
+        euclidean(x1, y1, x2, y2) as u16
+    }
+
+    pub fn to_matrix(&self) -> Vec<Vec<u16>> {
+        let num_cities = self.num_cities() as u32;
+        (0..num_cities)
+            .map(|from| (0..num_cities).map(|to| self.distance(from, to)).collect())
+            .collect()
+    }
+
+    pub fn to_instance(&self) -> TspInstance {
+        TspInstance::new(self.to_matrix()).ok().unwrap() // square and non-empty by construction
+    }
+}
+
+impl CostFunction for EuclideanInstance {
+    fn cost(&self, from: u32, to: u32) -> u16 {
+        self.distance(from, to)
+    }
+
+    fn is_symmetric(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::TSPError;
+    use crate::config::SolverConfig;
+    use crate::euclidean::EuclideanInstance;
+    use crate::sim_annealing::Annealer;
+
+    #[test]
+    fn test_euclidean_instance() {
+        // a 3 by 4 rectangle, the diagonal is 5
+        let instance = EuclideanInstance::new(vec![(0., 0.), (3., 0.), (3., 4.), (0., 4.)])
+            .ok()
+            .unwrap();
+        assert_eq!(instance.distance(0, 2), 5);
+        assert_eq!(instance.distance(1, 0), 3);
+        assert_eq!(instance.to_matrix()[3], vec![4, 5, 3, 0]);
+        assert_eq!(instance.to_instance().num_cities(), 4);
+
+        let config = SolverConfig {
+            max_iterations: 2000,
+            ..SolverConfig::default()
+        };
+        let (path, cost, _) = Annealer::new(config).solve_with(&instance, 4).ok().unwrap();
+        assert_eq!(path.len(), 4);
+        assert!(cost >= 10);
+
+        // rounded to the nearest integer
+        let rounded = EuclideanInstance::new(vec![(0., 0.), (1., 1.), (1.2, 2.)])
+            .ok()
+            .unwrap();
+        assert_eq!(rounded.distance(0, 1), 1);
+        assert_eq!(rounded.distance(0, 2), 2);
+
+        assert!(matches!(
+            EuclideanInstance::new(vec![(0., 0.), (f64::NAN, 1.)]),
+            Err(TSPError::InvalidCoordinate { index: 1 })
+        ));
+        assert!(EuclideanInstance::new(vec![(0., 0.), (70000., 0.)]).is_err());
+        assert!(EuclideanInstance::new(Vec::new()).is_err());
+    }
+}
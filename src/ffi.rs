@@ -0,0 +1,193 @@
+use std::ffi::{c_char, CStr};
+use std::ptr;
+use std::slice;
+
+use crate::config::SolverConfig;
+use crate::instance::TspInstance;
+use crate::solver::{solver_by_name, Budget, Solver};
+use crate::tour::normalize_path;
+
+/// The most cities `tsp_solve` solves with `"brute_force"`, larger instances fail.
+pub const TSP_BRUTE_FORCE_MAX_CITIES: usize = 12;
+
+// opaque handles, C code only ever sees pointers to these
+pub struct FfiInstance {
+    instance: TspInstance,
+}
+
+pub struct FfiSolution {
+    path: Vec<u32>,
+    cost: u64,
+}
+
+/// Builds an instance from a row-major `num_cities * num_cities` weight array.
+/// Returns null if the array is null or empty. Free with `tsp_instance_free`.
+///
+/// # Safety
+/// `weights` must point to at least `num_cities * num_cities` readable values.
+#[no_mangle]
+pub unsafe extern "C" fn tsp_instance_new(
+    weights: *const u16,
+    num_cities: usize,
+) -> *mut FfiInstance {
+    if weights.is_null() || num_cities == 0 {
+        return ptr::null_mut();
+    }
+
+    let weights = slice::from_raw_parts(weights, num_cities * num_cities);
+    let intercity_map = weights
+        .chunks_exact(num_cities)
+        .map(<[u16]>::to_vec)
+        .collect();
+    match TspInstance::new(intercity_map) {
+        Ok(instance) => Box::into_raw(Box::new(FfiInstance { instance })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `instance` must be null or come from `tsp_instance_new`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tsp_instance_free(instance: *mut FfiInstance) {
+    if !instance.is_null() {
+        drop(Box::from_raw(instance));
+    }
+}
+
+/// Runs the solver with the given name, e.g. `"simulated_annealing"`, `"brute_force"`,
+/// `"held_karp"` or `"lin_kernighan"`, with its default settings. The temperature is only
+/// used by simulated annealing and `max_iterations` by solvers counting iterations or
+/// generations, 0 keeping the solver's default for either. Returns null if the solver
+/// is unknown or fails. Free with `tsp_solution_free`.
+///
+/// # Safety
+/// `instance` must come from `tsp_instance_new` and `solver` must be a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn tsp_solve(
+    instance: *const FfiInstance,
+    solver: *const c_char,
+    temperature: u64,
+    max_iterations: u64,
+) -> *mut FfiSolution {
+    if instance.is_null() || solver.is_null() {
+        return ptr::null_mut();
+    }
+
+    let instance = &(*instance).instance;
+    let solver: Box<dyn Solver> = match CStr::from_ptr(solver).to_str() {
+        Ok("simulated_annealing") if temperature != 0 => Box::new(SolverConfig {
+            temperature,
+            ..SolverConfig::default()
+        }),
+        Ok(name) => match solver_by_name(name) {
+            Some(solver) => solver,
+            None => return ptr::null_mut(),
+        },
+        Err(_) => return ptr::null_mut(),
+    };
+    let budget = Budget {
+        max_iterations: (max_iterations != 0).then_some(max_iterations),
+        ..Budget::default()
+    };
+
+    match solver.solve(instance, &budget) {
+        Ok(solution) => Box::into_raw(Box::new(FfiSolution {
+            path: normalize_path(&instance.to_rows(), solution.tour.cities()),
+            cost: solution.cost,
+        })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `solution` must come from `tsp_solve`.
+#[no_mangle]
+pub unsafe extern "C" fn tsp_solution_cost(solution: *const FfiSolution) -> u64 {
+    (*solution).cost
+}
+
+/// # Safety
+/// `solution` must come from `tsp_solve`.
+#[no_mangle]
+pub unsafe extern "C" fn tsp_solution_len(solution: *const FfiSolution) -> usize {
+    (*solution).path.len()
+}
+
+/// Copies at most `capacity` cities of the tour into `out`, returns how many were copied.
+///
+/// # Safety
+/// `solution` must come from `tsp_solve` and `out` must have room for `capacity` values.
+#[no_mangle]
+pub unsafe extern "C" fn tsp_solution_tour(
+    solution: *const FfiSolution,
+    out: *mut u32,
+    capacity: usize,
+) -> usize {
+    let path = &(*solution).path;
+    let count = path.len().min(capacity);
+    ptr::copy_nonoverlapping(path.as_ptr(), out, count);
+    count
+}
+
+/// # Safety
+/// `solution` must be null or come from `tsp_solve`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tsp_solution_free(solution: *mut FfiSolution) {
+    if !solution.is_null() {
+        drop(Box::from_raw(solution));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::brute_force::DEFAULT_MAX_CITIES;
+    use crate::ffi::{
+        tsp_instance_free, tsp_instance_new, tsp_solution_cost, tsp_solution_free,
+        tsp_solution_len, tsp_solution_tour, tsp_solve, TSP_BRUTE_FORCE_MAX_CITIES,
+    };
+
+    #[test]
+    fn test_ffi_round_trip() {
+        let weights: Vec<u16> = vec![0, 1, 9, 1, 0, 1, 9, 1, 0];
+
+        unsafe {
+            let instance = tsp_instance_new(weights.as_ptr(), 3);
+            assert!(!instance.is_null());
+
+            let solution = tsp_solve(instance, c"brute_force".as_ptr(), 0, 0);
+            assert!(!solution.is_null());
+            assert_eq!(tsp_solution_cost(solution), 2);
+            assert_eq!(tsp_solution_len(solution), 3);
+
+            let mut tour = [0u32; 3];
+            assert_eq!(
+                tsp_solution_tour(solution, tour.as_mut_ptr(), tour.len()),
+                3
+            );
+            assert_eq!(tour[1], 1);
+
+            assert!(tsp_solve(instance, c"unknown".as_ptr(), 0, 0).is_null());
+            // any solver solver_by_name knows
+            for name in [c"held_karp", c"lin_kernighan", c"simulated_annealing"] {
+                let solution = tsp_solve(instance, name.as_ptr(), 100, 1000);
+                assert_eq!(tsp_solution_cost(solution), 2);
+                tsp_solution_free(solution);
+            }
+
+            tsp_solution_free(solution);
+            tsp_instance_free(instance);
+            // spelt out for cbindgen, which can't follow the constant it mirrors
+            assert_eq!(TSP_BRUTE_FORCE_MAX_CITIES, DEFAULT_MAX_CITIES);
+        }
+    }
+
+    #[test]
+    fn test_header_is_current() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/simulated_annealing_tsp.h"));
+        let checked_in = include_str!("../include/simulated_annealing_tsp.h");
+        assert!(
+            generated == checked_in,
+            "include/simulated_annealing_tsp.h is out of date, build with TSP_WRITE_HEADER=1"
+        );
+    }
+}
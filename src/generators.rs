@@ -0,0 +1,472 @@
+use rand::{thread_rng, Rng};
+
+use crate::common::{
+    check_city_map, generate_asymmetric_map_with_rng, generate_map_with_rng, TSPError,
+};
+use crate::cost::TourMode;
+use crate::metric::{metric_map, Euclidean};
+
+// the widest square whose diagonal still fits in a 16 bit weight
+const MAX_SIDE: f64 = 46340.;
+
+// cities scattered around centres drawn uniformly on a square, each city belonging to a cluster
+// chosen at random and lying a normally distributed distance off its centre along either axis,
+// as in the DIMACS challenge's clustered instances
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Clustered {
+    pub num_clusters: u16,
+    pub side: f64,   // of the square, which the cities are kept to as well
+    pub spread: f64, // standard deviation of a city's offset from its centre
+}
+
+impl Default for Clustered {
+    fn default() -> Self {
+        Clustered {
+            num_clusters: 5,
+            side: 1000.,
+            spread: 50.,
+        }
+    }
+}
+
+impl Clustered {
+    pub fn check(&self) -> Result<(), TSPError> {
+        let valid = self.num_clusters > 0
+            && self.side > 0.
+            && self.side <= MAX_SIDE
+            && self.spread >= 0.
+            && self.spread.is_finite();
+        if !valid {
+            return Err(TSPError::InvalidGeneratorParameters);
+        }
+        Ok(())
+    }
+}
+
+// a pair of independent standard normal samples by the Box-Muller transform
+fn standard_normal_pair<R: Rng>(rng: &mut R) -> (f64, f64) {
+    let radius = (-2. * (1. - rng.gen::<f64>()).ln()).sqrt(); // 1 - u keeps ln away from 0
+    let angle = std::f64::consts::TAU * rng.gen::<f64>();
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+pub fn clustered_points_with_rng<R: Rng>(
+    num_cities: u32,
+    clustered: &Clustered,
+    rng: &mut R,
+) -> Result<Vec<(f64, f64)>, TSPError> {
+    clustered.check()?;
+
+    let side = clustered.side;
+    let centres: Vec<(f64, f64)> = (0..clustered.num_clusters)
+        .map(|_| (rng.gen_range(0.0..side), rng.gen_range(0.0..side)))
+        .collect();
+    Ok((0..num_cities)
+        .map(|_| {
+            let (x, y) = centres[rng.gen_range(0..centres.len())];
+            let (dx, dy) = standard_normal_pair(rng);
+            (
+                (x + clustered.spread * dx).clamp(0., side),
+                (y + clustered.spread * dy).clamp(0., side),
+            )
+        })
+        .collect())
+}
+
+// the clustered cities' Euclidean distances rounded to the nearest integer
+pub fn generate_clustered_map(
+    num_cities: u32,
+    clustered: &Clustered,
+) -> Result<Vec<Vec<u16>>, TSPError> {
+    generate_clustered_map_with_rng(num_cities, clustered, &mut thread_rng())
+}
+
+pub fn generate_clustered_map_with_rng<R: Rng>(
+    num_cities: u32,
+    clustered: &Clustered,
+    rng: &mut R,
+) -> Result<Vec<Vec<u16>>, TSPError> {
+    let points = clustered_points_with_rng(num_cities, clustered, rng)?;
+    metric_map(&points, &Euclidean)
+}
+
+// every weight cut down to the shortest path between its cities through the others, by
+// Floyd-Warshall, which leaves no weight breaking the triangle inequality and the optimal round
+// trip as it was, as any tour taking a shortcut can take the path it stands for instead,
+// asymmetric weights staying so
+#[allow(clippy::needless_range_loop)]
+pub fn metric_closure(intercity_map: &[Vec<u16>]) -> Result<Vec<Vec<u16>>, TSPError> {
+    check_city_map(intercity_map)?;
+
+    let mut closure = intercity_map.to_vec();
+    let num_cities = closure.len();
+    for via in 0..num_cities {
+        for from in 0..num_cities {
+            let to_via = closure[from][via] as u32;
+            for to in 0..num_cities {
+                let through = to_via + closure[via][to] as u32;
+                if through < closure[from][to] as u32 {
+                    closure[from][to] = through as u16;
+                }
+            }
+        }
+    }
+    Ok(closure)
+}
+
+// random weights as generate_map draws them, closed under shortest paths so the instance is
+// metric, as christofides' and the double tree's guarantees need, every weight staying in the
+// range since a path is never lighter than its lightest edge
+pub fn generate_metric_map(
+    num_cities: u32,
+    weight_range: (u16, u16),
+) -> Result<Vec<Vec<u16>>, TSPError> {
+    generate_metric_map_with_rng(num_cities, weight_range, &mut thread_rng())
+}
+
+pub fn generate_metric_map_with_rng<R: Rng>(
+    num_cities: u32,
+    weight_range: (u16, u16),
+    rng: &mut R,
+) -> Result<Vec<Vec<u16>>, TSPError> {
+    metric_closure(&generate_map_with_rng(num_cities, weight_range, rng)?)
+}
+
+// generate_asymmetric_map's weights closed under shortest paths, each direction on its own
+pub fn generate_asymmetric_metric_map_with_rng<R: Rng>(
+    num_cities: u32,
+    weight_range: (u16, u16),
+    rng: &mut R,
+) -> Result<Vec<Vec<u16>>, TSPError> {
+    metric_closure(&generate_asymmetric_map_with_rng(
+        num_cities,
+        weight_range,
+        rng,
+    )?)
+}
+
+// a layout whose optimal open path and round trip are known without searching, for checking
+// that a solver finds them
+#[derive(Clone, PartialEq, Debug)]
+pub struct KnownOptimum {
+    pub points: Vec<(f64, f64)>,
+    pub intercity_map: Vec<Vec<u16>>,
+    pub open_path: Vec<u32>,
+    pub round_trip: Vec<u32>,
+}
+
+impl KnownOptimum {
+    pub fn tour(&self, mode: TourMode) -> &[u32] {
+        match mode {
+            TourMode::Open => &self.open_path,
+            TourMode::Closed => &self.round_trip,
+        }
+    }
+
+    pub fn optimum(&self, mode: TourMode) -> u64 {
+        mode.cost(&self.intercity_map, self.tour(mode))
+    }
+}
+
+// cities at the points of a rows by columns lattice spacing apart, numbered row by row: a path
+// snaking along the rows takes only edges of one spacing, and so does a round trip if either
+// side is even, going along the rows and back up the first column, while with both sides odd
+// one diagonal is the least a round trip can do with, the lattice being bipartite, and a line
+// of cities is walked there and back, so rounded to whole weights the optimal round trip is
+//   rows * columns * spacing                                 either side even
+//   (rows * columns - 1) * spacing + round(spacing * sqrt 2)  both odd
+//   2 * (rows * columns - 1) * spacing                        a single row or column
+pub fn grid_instance(rows: u16, columns: u16, spacing: u16) -> Result<KnownOptimum, TSPError> {
+    let (num_rows, num_columns) = (rows as usize, columns as usize);
+    let points: Vec<(f64, f64)> = (0..num_rows * num_columns)
+        .map(|city| {
+            let (row, column) = (city / num_columns, city % num_columns);
+            (
+                (column * spacing as usize) as f64,
+                (row * spacing as usize) as f64,
+            )
+        })
+        .collect();
+    let intercity_map = metric_map(&points, &Euclidean)?;
+
+    let snake = |num_rows: usize, columns: std::ops::Range<usize>| -> Vec<(usize, usize)> {
+        (0..num_rows)
+            .flat_map(|row| {
+                let columns: Vec<usize> = match row % 2 {
+                    1 => columns.clone().rev().collect(),
+                    _ => columns.clone().collect(),
+                };
+                columns.into_iter().map(move |column| (row, column))
+            })
+            .collect()
+    };
+    let open_path = snake(num_rows, 0..num_columns);
+    let round_trip = if num_rows == 1 || num_columns == 1 {
+        open_path.clone()
+    } else if num_rows % 2 == 1 && num_columns % 2 == 0 {
+        // along the columns instead
+        let mut transposed: Vec<(usize, usize)> = snake(num_columns, 1..num_rows)
+            .into_iter()
+            .map(|(column, row)| (row, column))
+            .collect();
+        transposed.extend((1..num_columns).rev().map(|column| (0, column)));
+        transposed.insert(0, (0, 0));
+        transposed
+    } else {
+        // along the rows, the last two of an odd number zigzagging down and up the columns
+        let snaked_rows = match num_rows % 2 {
+            1 => num_rows - 2,
+            _ => num_rows,
+        };
+        let mut trip = vec![(0, 0)];
+        trip.extend(snake(snaked_rows, 1..num_columns));
+        if snaked_rows < num_rows {
+            for column in (1..num_columns).rev() {
+                let rows = [num_rows - 2, num_rows - 1];
+                match (num_columns - 1 - column) % 2 {
+                    1 => trip.extend(rows.iter().rev().map(|&row| (row, column))),
+                    _ => trip.extend(rows.iter().map(|&row| (row, column))),
+                }
+            }
+        }
+        trip.extend((1..num_rows).rev().map(|row| (row, 0)));
+        trip
+    };
+
+    let to_cities = |cells: Vec<(usize, usize)>| -> Vec<u32> {
+        cells
+            .into_iter()
+            .map(|(row, column)| (row * num_columns + column) as u32)
+            .collect()
+    };
+    Ok(KnownOptimum {
+        points,
+        intercity_map,
+        open_path: to_cities(open_path),
+        round_trip: to_cities(round_trip),
+    })
+}
+
+// cities spaced evenly round a circle, centred on (radius, radius) so no coordinate is negative:
+// no two cities are closer than neighbours on the circle, so going round it is optimal, at
+// num_cities rounded chords of 2 * radius * sin(pi / num_cities) for the round trip and one
+// fewer for the open path
+pub fn ring_instance(num_cities: u32, radius: f64) -> Result<KnownOptimum, TSPError> {
+    if !(0. ..=MAX_SIDE / 2.).contains(&radius) {
+        return Err(TSPError::InvalidGeneratorParameters);
+    }
+
+    let points: Vec<(f64, f64)> = (0..num_cities)
+        .map(|city| {
+            let angle = std::f64::consts::TAU * city as f64 / num_cities as f64;
+            (radius + radius * angle.cos(), radius + radius * angle.sin())
+        })
+        .collect();
+    let intercity_map = metric_map(&points, &Euclidean)?;
+    let tour: Vec<u32> = (0..num_cities).collect();
+    Ok(KnownOptimum {
+        points,
+        intercity_map,
+        open_path: tour.clone(),
+        round_trip: tour,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::triangle_violations;
+    use crate::common::{check_path, check_symmetric, generate_map, TSPError};
+    use crate::config::SolverConfig;
+    use crate::cost::TourMode;
+    use crate::generators::{
+        clustered_points_with_rng, generate_asymmetric_metric_map_with_rng, generate_clustered_map,
+        generate_clustered_map_with_rng, generate_metric_map, generate_metric_map_with_rng,
+        grid_instance, metric_closure, ring_instance, Clustered,
+    };
+    use crate::sim_annealing::Annealer;
+    use crate::solvers::christofides::christofides_tsp_with;
+    use crate::solvers::held_karp::held_karp_tsp_with;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_clustered() {
+        let clustered = Clustered {
+            num_clusters: 4,
+            side: 10000.,
+            spread: 20.,
+        };
+        let points = clustered_points_with_rng(400, &clustered, &mut StdRng::seed_from_u64(2))
+            .ok()
+            .unwrap();
+        assert_eq!(points.len(), 400);
+        assert!(points
+            .iter()
+            .all(|&(x, y)| (0. ..=10000.).contains(&x) && (0. ..=10000.).contains(&y)));
+
+        // tight clusters far apart, so most cities have a neighbour far closer than the average
+        let map = generate_clustered_map_with_rng(400, &clustered, &mut StdRng::seed_from_u64(2))
+            .ok()
+            .unwrap();
+        assert!(check_symmetric(&map).is_ok());
+        let mean = map.iter().flatten().map(|&w| w as f64).sum::<f64>() / (400. * 399.);
+        let nearest: Vec<u16> = map
+            .iter()
+            .enumerate()
+            .map(|(i, row)| (0..400).filter(|&j| j != i).map(|j| row[j]).min().unwrap())
+            .collect();
+        assert!(nearest.iter().all(|&weight| (weight as f64) < mean / 20.));
+        let again = generate_clustered_map_with_rng(400, &clustered, &mut StdRng::seed_from_u64(2))
+            .ok()
+            .unwrap();
+        assert_eq!(again, map);
+
+        // a single point spread nowhere
+        let collapsed = Clustered {
+            num_clusters: 1,
+            spread: 0.,
+            ..Clustered::default()
+        };
+        let map = generate_clustered_map(5, &collapsed).ok().unwrap();
+        assert!(map.iter().flatten().all(|&weight| weight == 0));
+
+        for invalid in [
+            Clustered {
+                num_clusters: 0,
+                ..Clustered::default()
+            },
+            Clustered {
+                side: 50000.,
+                ..Clustered::default()
+            },
+            Clustered {
+                spread: f64::NAN,
+                ..Clustered::default()
+            },
+        ] {
+            assert!(matches!(
+                generate_clustered_map(5, &invalid),
+                Err(TSPError::InvalidGeneratorParameters)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_metric() {
+        let map = generate_map(40, (1, 100)).ok().unwrap();
+        assert!(triangle_violations(&map).ok().unwrap() > 0.);
+        let closure = metric_closure(&map).ok().unwrap();
+        assert_eq!(triangle_violations(&closure).ok().unwrap(), 0.);
+        assert!(check_symmetric(&closure).is_ok());
+        assert!(map
+            .iter()
+            .flatten()
+            .zip(closure.iter().flatten())
+            .all(|(weight, closed)| closed <= weight));
+        assert_eq!(metric_closure(&closure).ok().unwrap(), closure);
+
+        // 0 to 2 is cut down to going through 1
+        let map = vec![vec![0, 1, 9], vec![1, 0, 1], vec![9, 1, 0]];
+        let closure = metric_closure(&map).ok().unwrap();
+        assert_eq!(closure[0][2], 2);
+        assert_eq!(closure[2][0], 2);
+
+        let mut rng = StdRng::seed_from_u64(6);
+        let asymmetric = generate_asymmetric_metric_map_with_rng(30, (1, 100), &mut rng)
+            .ok()
+            .unwrap();
+        assert_eq!(triangle_violations(&asymmetric).ok().unwrap(), 0.);
+        let map = generate_metric_map_with_rng(30, (20, 100), &mut rng)
+            .ok()
+            .unwrap();
+        assert!(map
+            .iter()
+            .flatten()
+            .all(|&weight| weight == 0 || weight >= 20));
+
+        // christofides keeps within half again of the optimum on metric instances
+        for _ in 0..5 {
+            let map = generate_metric_map(12, (1, 100)).ok().unwrap();
+            let (_, approximate) = christofides_tsp_with(&map, map.len(), TourMode::Closed)
+                .ok()
+                .unwrap();
+            let (_, optimal) = held_karp_tsp_with(&map, map.len(), TourMode::Closed)
+                .ok()
+                .unwrap();
+            assert!(2 * approximate <= 3 * optimal);
+        }
+
+        assert!(matches!(
+            generate_metric_map(5, (10, 10)),
+            Err(TSPError::InvalidWeightRange)
+        ));
+    }
+
+    #[test]
+    fn test_known_optima() {
+        for (rows, columns, round_trip) in [
+            (1, 1, 0),
+            (1, 2, 20),
+            (5, 1, 80),
+            (2, 2, 40),
+            (3, 4, 120),
+            (4, 3, 120),
+            (3, 3, 94),
+            (3, 5, 154),
+            (5, 3, 154),
+        ] {
+            let grid = grid_instance(rows, columns, 10).ok().unwrap();
+            let num_cities = rows as usize * columns as usize;
+            assert_eq!(grid.optimum(TourMode::Closed), round_trip);
+            assert_eq!(grid.optimum(TourMode::Open), 10 * (num_cities as u64 - 1));
+            for mode in [TourMode::Open, TourMode::Closed] {
+                assert!(check_path(num_cities, grid.tour(mode)).is_ok());
+                let (_, optimal) =
+                    held_karp_tsp_with(&grid.intercity_map, grid.intercity_map.len(), mode)
+                        .ok()
+                        .unwrap();
+                assert_eq!(grid.optimum(mode), optimal);
+            }
+        }
+
+        for num_cities in [1, 2, 3, 7, 12] {
+            let ring = ring_instance(num_cities, 100.).ok().unwrap();
+            for mode in [TourMode::Open, TourMode::Closed] {
+                let (_, optimal) =
+                    held_karp_tsp_with(&ring.intercity_map, ring.intercity_map.len(), mode)
+                        .ok()
+                        .unwrap();
+                assert_eq!(ring.optimum(mode), optimal);
+            }
+        }
+        // twelve chords of 2 * 100 * sin(15 degrees), 51.76 each
+        let ring = ring_instance(12, 100.).ok().unwrap();
+        assert_eq!(ring.optimum(TourMode::Closed), 12 * 52);
+        assert!(ring
+            .points
+            .iter()
+            .all(|&(x, y)| (0. ..=200.).contains(&x) && (0. ..=200.).contains(&y)));
+
+        // the annealer goes round a ring too big to solve exactly
+        let ring = ring_instance(40, 1000.).ok().unwrap();
+        let config = SolverConfig {
+            temperature: 1000,
+            max_iterations: 1000000,
+            seed: Some(1),
+            ..SolverConfig::default()
+        };
+        let (_, cost, _) = Annealer::new(config)
+            .solve(&ring.intercity_map)
+            .ok()
+            .unwrap();
+        assert_eq!(cost, ring.optimum(TourMode::Open));
+
+        assert!(matches!(
+            grid_instance(60, 60, 1000),
+            Err(TSPError::InvalidWeightRange)
+        ));
+        assert!(matches!(
+            ring_instance(5, -1.),
+            Err(TSPError::InvalidGeneratorParameters)
+        ));
+    }
+}
@@ -0,0 +1,260 @@
+use log::warn;
+
+use super::common::TSPError;
+
+// distances between (latitude, longitude) points in degrees, rounded to whole kilometres
+// for the matrix, which fits any distance on earth in a u16
+
+pub const EARTH_RADIUS_KM: f64 = 6371.0;
+
+// the WGS84 ellipsoid, in metres
+const WGS84_A: f64 = 6378137.0;
+const WGS84_F: f64 = 1. / 298.257223563;
+const VINCENTY_MAX_ITERATIONS: usize = 200;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GeoDistance {
+    Haversine, // on a sphere, within about 0.5% of the ellipsoid
+    Vincenty,  // on the WGS84 ellipsoid, to within a millimetre
+    Projected, // straight lines after project_local, close to haversine for a city or a region
+}
+
+pub fn haversine_km((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.).sin().powi(2);
+    2. * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+// vincenty's inverse formula, None for nearly antipodal points where it does not converge
+pub fn vincenty_km((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> Option<f64> {
+    let b = (1. - WGS84_F) * WGS84_A;
+    let l = (lon2 - lon1).to_radians();
+    let u1 = ((1. - WGS84_F) * lat1.to_radians().tan()).atan();
+    let u2 = ((1. - WGS84_F) * lat2.to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..VINCENTY_MAX_ITERATIONS {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0. {
+            return Some(0.); // the same point
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos2_alpha = 1. - sin_alpha.powi(2);
+        // both points on the equator
+        let cos_2sigma_m = if cos2_alpha == 0. {
+            0.
+        } else {
+            cos_sigma - 2. * sin_u1 * sin_u2 / cos2_alpha
+        };
+        let c = WGS84_F / 16. * cos2_alpha * (4. + WGS84_F * (4. - 3. * cos2_alpha));
+
+        let previous = lambda;
+        lambda = l
+            + (1. - c)
+                * WGS84_F
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2sigma_m + c * cos_sigma * (-1. + 2. * cos_2sigma_m.powi(2))));
+        if (lambda - previous).abs() < 1e-12 {
+            let u_squared = cos2_alpha * (WGS84_A.powi(2) - b.powi(2)) / b.powi(2);
+            let big_a = 1.
+                + u_squared / 16384.
+                    * (4096. + u_squared * (-768. + u_squared * (320. - 175. * u_squared)));
+            let big_b = u_squared / 1024.
+                * (256. + u_squared * (-128. + u_squared * (74. - 47. * u_squared)));
+            let delta_sigma = big_b
+                * sin_sigma
+                * (cos_2sigma_m
+                    + big_b / 4.
+                        * (cos_sigma * (-1. + 2. * cos_2sigma_m.powi(2))
+                            - big_b / 6.
+                                * cos_2sigma_m
+                                * (-3. + 4. * sin_sigma.powi(2))
+                                * (-3. + 4. * cos_2sigma_m.powi(2))));
+            return Some(b * big_a * (sigma - delta_sigma) / 1000.);
+        }
+    }
+
+    None
+}
+
+// the point on the sphere closest to all of them, averaged as unit vectors so points either
+// side of the antimeridian don't average out to the other side of the world
+fn centre(coordinates: &[(f64, f64)]) -> (f64, f64) {
+    let (x, y, z) = coordinates
+        .iter()
+        .fold((0., 0., 0.), |(x, y, z), &(lat, lon)| {
+            let (lat, lon) = (lat.to_radians(), lon.to_radians());
+            (
+                x + lat.cos() * lon.cos(),
+                y + lat.cos() * lon.sin(),
+                z + lat.sin(),
+            )
+        });
+    (z.atan2(x.hypot(y)).to_degrees(), y.atan2(x).to_degrees())
+}
+
+// azimuthal equidistant projection to kilometres east and north of the points' centre, distances
+// and directions from the centre are exact and others distort little within a few hundred km,
+// for planar geometry on geographic points
+pub fn project_local(coordinates: &[(f64, f64)]) -> Result<Vec<(f64, f64)>, TSPError> {
+    check_coordinates(coordinates)?;
+
+    let (lat0, lon0) = centre(coordinates);
+    let (sin_lat0, cos_lat0) = lat0.to_radians().sin_cos();
+    Ok(coordinates
+        .iter()
+        .map(|&(lat, lon)| {
+            let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+            let (sin_dlon, cos_dlon) = (lon - lon0).to_radians().sin_cos();
+            let cos_c = (sin_lat0 * sin_lat + cos_lat0 * cos_lat * cos_dlon).clamp(-1., 1.);
+            let c = cos_c.acos();
+            // c / sin c tends to 1 at the centre
+            let k = if c < 1e-12 { 1. } else { c / c.sin() };
+            (
+                EARTH_RADIUS_KM * k * cos_lat * sin_dlon,
+                EARTH_RADIUS_KM * k * (cos_lat0 * sin_lat - sin_lat0 * cos_lat * cos_dlon),
+            )
+        })
+        .collect())
+}
+
+fn check_coordinates(coordinates: &[(f64, f64)]) -> Result<(), TSPError> {
+    if coordinates.is_empty() {
+        return Err(TSPError::EmptyMap);
+    }
+    if let Some(index) = coordinates
+        .iter()
+        .position(|&(lat, lon)| !(-90. ..=90.).contains(&lat) || !(-180. ..=180.).contains(&lon))
+    {
+        return Err(TSPError::InvalidCoordinate { index });
+    }
+    Ok(())
+}
+
+pub fn geographic_map(
+    coordinates: &[(f64, f64)],
+    distance: GeoDistance,
+) -> Result<Vec<Vec<u16>>, TSPError> {
+    check_coordinates(coordinates)?;
+
+    let projected = match distance {
+        GeoDistance::Projected => project_local(coordinates)?,
+        _ => Vec::new(),
+    };
+    let km = |i: usize, j: usize| {
+        let (from, to) = (coordinates[i], coordinates[j]);
+        match distance {
+            GeoDistance::Haversine => haversine_km(from, to),
+            GeoDistance::Vincenty => vincenty_km(from, to).unwrap_or_else(|| {
+                warn!(
+                    "Vincenty's formula did not converge for {:?} to {:?}, using haversine",
+                    from, to
+                );
+                haversine_km(from, to)
+            }),
+            GeoDistance::Projected => {
+                let ((x1, y1), (x2, y2)) = (projected[i], projected[j]);
+                (x1 - x2).hypot(y1 - y2)
+            }
+        }
+    };
+
+    let num_points = coordinates.len();
+    Ok((0..num_points)
+        .map(|i| (0..num_points).map(|j| km(i, j).round() as u16).collect())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::TSPError;
+    use crate::geo::{geographic_map, haversine_km, project_local, vincenty_km, GeoDistance};
+
+    #[test]
+    fn test_geodesic_distances() {
+        // the reference example from vincenty's paper, 54972.271m
+        let flinders_peak = (-37.95103341666667, 144.42486788888888);
+        let buninyong = (-37.65282113888889, 143.92649552777777);
+        let distance = vincenty_km(flinders_peak, buninyong).unwrap();
+        assert!((distance - 54.972271).abs() < 1e-6);
+        assert!((haversine_km(flinders_peak, buninyong) - distance).abs() < 0.5);
+
+        assert_eq!(vincenty_km((10., 20.), (10., 20.)), Some(0.));
+        // a quarter of the equator
+        let quarter = vincenty_km((0., 0.), (0., 90.)).unwrap();
+        assert!((quarter - 10018.754).abs() < 1e-3);
+        assert_eq!(vincenty_km((0., 0.), (0.5, 179.7)), None);
+    }
+
+    #[test]
+    fn test_geographic_map() {
+        let london_paris = [(51.5074, -0.1278), (48.8566, 2.3522)];
+        for distance in [GeoDistance::Haversine, GeoDistance::Vincenty] {
+            let map = geographic_map(&london_paris, distance).ok().unwrap();
+            assert_eq!(map[0][0], 0);
+            assert_eq!(map[0][1], map[1][0]);
+            assert!((340..=350).contains(&map[0][1]));
+        }
+
+        // antipodal points fall back to haversine
+        let antipodes = [(0., 0.), (0.5, 179.7)];
+        let map = geographic_map(&antipodes, GeoDistance::Vincenty)
+            .ok()
+            .unwrap();
+        assert!(map[0][1] > 19000);
+
+        assert!(matches!(
+            geographic_map(&[(0., 0.), (91., 0.)], GeoDistance::Haversine),
+            Err(TSPError::InvalidCoordinate { index: 1 })
+        ));
+        assert!(geographic_map(&[], GeoDistance::Haversine).is_err());
+    }
+
+    #[test]
+    fn test_project_local() {
+        // berlin, prague, vienna and budapest
+        let points = [
+            (52.52, 13.405),
+            (50.0755, 14.4378),
+            (48.2082, 16.3738),
+            (47.4979, 19.0402),
+        ];
+        let projected = project_local(&points).ok().unwrap();
+        for i in 0..points.len() {
+            for j in 0..points.len() {
+                let (x1, y1) = projected[i];
+                let (x2, y2) = projected[j];
+                let planar = (x1 - x2).hypot(y1 - y2);
+                assert!((planar - haversine_km(points[i], points[j])).abs() < 2.);
+            }
+        }
+        // berlin is north west of budapest
+        assert!(projected[0].0 < projected[3].0 && projected[0].1 > projected[3].1);
+
+        // either side of the antimeridian stays together
+        let fiji = project_local(&[(-17., 179.5), (-17., -179.5)])
+            .ok()
+            .unwrap();
+        assert!((fiji[0].0 - fiji[1].0).abs() < 110.);
+
+        let map = geographic_map(&points, GeoDistance::Projected)
+            .ok()
+            .unwrap();
+        let haversine = geographic_map(&points, GeoDistance::Haversine)
+            .ok()
+            .unwrap();
+        assert!((map[0][3] as i32 - haversine[0][3] as i32).abs() <= 2);
+        assert!(project_local(&[(0., 200.)]).is_err());
+    }
+}
@@ -0,0 +1,512 @@
+use std::borrow::Cow;
+
+use log::error;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use wgpu::util::DeviceExt;
+
+use crate::common::{check_city_map, check_symmetric_with, TSPError};
+use crate::cost::{CostFunction, TourMode};
+use crate::weight::{Cost, Delta, Weight};
+
+// simulated annealing for instances too large for one 2-opt move at a time: each step the GPU
+// costs a batch of thousands of random 2-opt moves at once in a wgpu compute shader, in f32 from
+// a copy of the weights or, for Euclidean instances, from the points, and the CPU goes through
+// them best first, costing each again exactly and accepting it by the Metropolis rule, skipping
+// moves touching a stretch of the tour already changed that step, until one is rejected, needing
+// symmetric weights as a reversal's inside is left uncosted, built with the `gpu` feature
+
+pub struct GpuAnnealing {
+    pub temperature: f64, // at the first step
+    pub cooling: f64,     // what the temperature is multiplied by each step
+    pub steps: u64,
+    pub batch_size: u32, // moves costed on the GPU each step
+    pub seed: Option<u64>,
+}
+
+impl Default for GpuAnnealing {
+    fn default() -> Self {
+        GpuAnnealing {
+            temperature: 100.,
+            cooling: 0.999,
+            steps: 10000,
+            batch_size: 4096,
+            seed: None,
+        }
+    }
+}
+
+impl GpuAnnealing {
+    pub fn check(&self) -> Result<(), TSPError> {
+        if !self.temperature.is_finite()
+            || self.temperature <= 0.
+            || !(self.cooling > 0. && self.cooling <= 1.)
+        {
+            return Err(TSPError::InvalidCoolingSchedule);
+        }
+        if self.batch_size == 0 {
+            return Err(TSPError::InvalidBatchSize);
+        }
+        Ok(())
+    }
+}
+
+// the best tour found from the identity one
+pub fn gpu_annealing_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+    mode: TourMode,
+    annealing: &GpuAnnealing,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    gpu_annealing_tsp_with(&intercity_map, intercity_map.len(), mode, None, annealing)
+}
+
+// same as gpu_annealing_tsp but for any cost function over num_cities cities, the GPU costing
+// moves by straight line distances between points if given rather than from a copy of the
+// weights, which would need n^2 floats of GPU memory
+pub(crate) fn gpu_annealing_tsp_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    mode: TourMode,
+    points: Option<&[(f64, f64)]>,
+    annealing: &GpuAnnealing,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    annealing.check()?;
+    check_symmetric_with(cost_function, num_cities)?;
+
+    let mut path: Vec<u32> = (0..num_cities as u32).collect();
+    let mut cost = mode.cost(cost_function, &path).to_delta();
+    // fewer cities have no move changing the tour but reversing all of it
+    let min_cities = if mode == TourMode::Closed { 4 } else { 3 };
+    if num_cities < min_cities {
+        return Ok((path, W::Cost::from_delta(cost)));
+    }
+
+    let weights = match points {
+        Some(points) => Weights::Points(
+            points
+                .iter()
+                .flat_map(|&(x, y)| [x as f32, y as f32])
+                .collect(),
+        ),
+        None => Weights::Matrix(
+            (0..num_cities as u32)
+                .flat_map(|from| {
+                    (0..num_cities as u32).map(move |to| cost_function.cost(from, to).to_f64())
+                })
+                .map(|weight| weight as f32)
+                .collect(),
+        ),
+    };
+    let evaluator = Evaluator::new(&path, weights, mode, annealing.batch_size)?;
+
+    let mut rng = StdRng::seed_from_u64(annealing.seed.unwrap_or_else(rand::random));
+    let mut best_path = path.clone();
+    let mut best_cost = cost;
+    let mut temperature = annealing.temperature;
+    let mut pairs = Vec::with_capacity(annealing.batch_size as usize);
+    let mut applied: Vec<(usize, usize)> = Vec::new();
+    for _ in 0..annealing.steps {
+        pairs.clear();
+        while pairs.len() < annealing.batch_size as usize {
+            let i = rng.gen_range(0..num_cities - 1);
+            let j = rng.gen_range(i + 1..num_cities);
+            // reversing a whole round trip leaves the same one
+            if mode == TourMode::Open || i > 0 || j < num_cities - 1 {
+                pairs.push((i as u32, j as u32));
+            }
+        }
+        let deltas = evaluator.deltas(&pairs)?;
+        let mut order: Vec<usize> = (0..pairs.len()).collect();
+        order.sort_by(|&a, &b| deltas[a].total_cmp(&deltas[b]));
+
+        applied.clear();
+        for index in order {
+            let (i, j) = (pairs[index].0 as usize, pairs[index].1 as usize);
+            if applied
+                .iter()
+                .any(|&(start, end)| overlaps(num_cities, mode, (i, j), (start, end)))
+            {
+                continue;
+            }
+            let diff = two_opt_delta(cost_function, &path, mode, i, j);
+            if diff >= W::Delta::ZERO
+                && rng.gen_range(0.0..1.0) >= (-diff.to_f64() / temperature).exp()
+            {
+                break;
+            }
+            path[i..=j].reverse();
+            cost += diff;
+            applied.push((i, j));
+        }
+        for &(start, end) in &applied {
+            evaluator.update(&path, start, end);
+        }
+        if cost < best_cost {
+            best_cost = cost;
+            best_path.clone_from(&path);
+        }
+        temperature *= annealing.cooling;
+    }
+
+    Ok((best_path, W::Cost::from_delta(best_cost)))
+}
+
+// what reversing positions i to j of path changes its cost by, symmetric weights leaving the
+// inside unchanged
+fn two_opt_delta<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    path: &[u32],
+    mode: TourMode,
+    i: usize,
+    j: usize,
+) -> W::Delta {
+    let num_cities = path.len();
+    let (first, last) = (path[i], path[j]);
+    let mut delta = W::Delta::ZERO;
+    if i > 0 || mode == TourMode::Closed {
+        let before = path[(i + num_cities - 1) % num_cities];
+        delta += cost_function.cost(before, last).to_delta();
+        delta -= cost_function.cost(before, first).to_delta();
+    }
+    if j + 1 < num_cities || mode == TourMode::Closed {
+        let after = path[(j + 1) % num_cities];
+        delta += cost_function.cost(first, after).to_delta();
+        delta -= cost_function.cost(last, after).to_delta();
+    }
+    delta
+}
+
+// whether reversing positions i to j reads an edge or city that reversing start to end changed,
+// making the GPU's cost of it stale, the edge from the last position to the first being read by
+// both when each starts at the first or ends at the last of a round trip
+fn overlaps(
+    num_cities: usize,
+    mode: TourMode,
+    (i, j): (usize, usize),
+    (start, end): (usize, usize),
+) -> bool {
+    let wraps = |first: usize, last: usize| first == 0 || last == num_cities - 1;
+    (i <= end + 1 && start <= j + 1)
+        || (mode == TourMode::Closed && wraps(i, j) && wraps(start, end))
+}
+
+#[derive(Clone)]
+enum Weights {
+    Matrix(Vec<f32>), // row after row
+    Points(Vec<f32>), // x then y of each city
+}
+
+// 2-opt deltas of each (i, j) pair of positions of the tour, one invocation a pair
+const SHADER: &str = r#"
+struct Params {
+    num_cities: u32,
+    num_pairs: u32,
+    closed: u32,
+    points: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> tour: array<u32>;
+@group(0) @binding(2) var<storage, read> weights: array<f32>;
+@group(0) @binding(3) var<storage, read> pairs: array<vec2<u32>>;
+@group(0) @binding(4) var<storage, read_write> deltas: array<f32>;
+
+fn weight(a: u32, b: u32) -> f32 {
+    if params.points == 1u {
+        let offset = vec2<f32>(weights[2u * a] - weights[2u * b],
+                               weights[2u * a + 1u] - weights[2u * b + 1u]);
+        return length(offset);
+    }
+    return weights[a * params.num_cities + b];
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= params.num_pairs {
+        return;
+    }
+    let n = params.num_cities;
+    let i = pairs[index].x;
+    let j = pairs[index].y;
+    let first = tour[i];
+    let last = tour[j];
+    var delta = 0.0;
+    if i > 0u || params.closed == 1u {
+        let before = tour[(i + n - 1u) % n];
+        delta += weight(before, last) - weight(before, first);
+    }
+    if j + 1u < n || params.closed == 1u {
+        let after = tour[(j + 1u) % n];
+        delta += weight(first, after) - weight(last, after);
+    }
+    deltas[index] = delta;
+}
+"#;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+// the device with the tour, weights and a batch's buffers on it
+struct Evaluator {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    tour: wgpu::Buffer,
+    pairs: wgpu::Buffer,
+    deltas: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    batch_size: u32,
+}
+
+impl Evaluator {
+    fn new(
+        path: &[u32],
+        weights: Weights,
+        mode: TourMode,
+        batch_size: u32,
+    ) -> Result<Evaluator, TSPError> {
+        let instance =
+            wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .map_err(|err| {
+                error!("No GPU adapter found: {}", err);
+                TSPError::GpuUnavailable
+            })?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("tsp"),
+            required_limits: adapter.limits(),
+            ..Default::default()
+        }))
+        .map_err(|err| {
+            error!("No GPU device from {:?}: {}", adapter.get_info().name, err);
+            TSPError::GpuUnavailable
+        })?;
+
+        let (weights, points) = match weights {
+            Weights::Matrix(weights) => (weights, 0),
+            Weights::Points(points) => (points, 1),
+        };
+        let bytes = (weights.len() * 4) as u64;
+        let max_bytes = device.limits().max_storage_buffer_binding_size;
+        if bytes > max_bytes {
+            return Err(TSPError::GpuBufferTooLarge { bytes, max_bytes });
+        }
+
+        let params = [
+            path.len() as u32,
+            batch_size,
+            (mode == TourMode::Closed) as u32,
+            points,
+        ];
+        let storage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST;
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("params"),
+            contents: &to_bytes(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let tour = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("tour"),
+            contents: &to_bytes(path),
+            usage: storage,
+        });
+        let weights = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("weights"),
+            contents: &to_bytes(
+                &weights
+                    .iter()
+                    .map(|weight| weight.to_bits())
+                    .collect::<Vec<_>>(),
+            ),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let pairs = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pairs"),
+            size: batch_size as u64 * 8,
+            usage: storage,
+            mapped_at_creation: false,
+        });
+        let deltas = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("deltas"),
+            size: batch_size as u64 * 4,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: batch_size as u64 * 4,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("two_opt"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("two_opt"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("two_opt"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[&params, &tour, &weights, &pairs, &deltas]
+                .iter()
+                .enumerate()
+                .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: buffer.as_entire_binding(),
+                })
+                .collect::<Vec<_>>(),
+        });
+
+        Ok(Evaluator {
+            device,
+            queue,
+            pipeline,
+            bind_group,
+            tour,
+            pairs,
+            deltas,
+            readback,
+            batch_size,
+        })
+    }
+
+    // copies positions start to end of path to the GPU's tour, sent with the next batch
+    fn update(&self, path: &[u32], start: usize, end: usize) {
+        self.queue
+            .write_buffer(&self.tour, start as u64 * 4, &to_bytes(&path[start..=end]));
+    }
+
+    // batch_size pairs of positions
+    fn deltas(&self, pairs: &[(u32, u32)]) -> Result<Vec<f32>, TSPError> {
+        let pairs: Vec<u32> = pairs.iter().flat_map(|&(i, j)| [i, j]).collect();
+        self.queue.write_buffer(&self.pairs, 0, &to_bytes(&pairs));
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.dispatch_workgroups(self.batch_size.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.deltas, 0, &self.readback, 0, None);
+        self.queue.submit([encoder.finish()]);
+
+        self.readback.map_async(wgpu::MapMode::Read, .., |_| ());
+        let failed = |err: &dyn std::fmt::Display| {
+            error!("Reading the GPU's deltas back failed: {}", err);
+            TSPError::GpuUnavailable
+        };
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|err| failed(&err))?;
+        let deltas = self
+            .readback
+            .get_mapped_range(..)
+            .map_err(|err| failed(&err))?
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        self.readback.unmap();
+        Ok(deltas)
+    }
+}
+
+// little endian, as wgpu's buffers are, floats going as their bits
+fn to_bytes(values: &[u32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{check_path, generate_map, TSPError};
+    use crate::cost::TourMode;
+    use crate::distance::CoordinateDistances;
+    use crate::gpu::{gpu_annealing_tsp, two_opt_delta, Evaluator, GpuAnnealing, Weights};
+    use crate::instance::TspInstance;
+    use crate::metric::Euclidean;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_gpu_annealing() {
+        let map = generate_map(60, (1, 1000)).ok().unwrap();
+        let annealing = GpuAnnealing {
+            temperature: 50.,
+            cooling: 0.99,
+            steps: 300,
+            batch_size: 256,
+            seed: Some(4),
+        };
+        for mode in [TourMode::Open, TourMode::Closed] {
+            let (path, cost) = match gpu_annealing_tsp(&map, mode, &annealing) {
+                Ok(solution) => solution,
+                // no adapter to run on, e.g. a CI machine without drivers
+                Err(TSPError::GpuUnavailable) => return,
+                Err(err) => panic!("{}", err),
+            };
+            assert!(check_path(60, &path).is_ok());
+            assert_eq!(cost, mode.cost(&map, &path));
+            let identity: Vec<u32> = (0..60).collect();
+            assert!(cost < mode.cost(&map, &identity));
+        }
+
+        // the GPU's deltas are the exact ones up to rounding
+        let mut rng = StdRng::seed_from_u64(4);
+        let path: Vec<u32> = (0..60).rev().collect();
+        let weights = Weights::Matrix(map.iter().flatten().map(|&weight| weight as f32).collect());
+        for mode in [TourMode::Open, TourMode::Closed] {
+            let evaluator = Evaluator::new(&path, weights.clone(), mode, 100)
+                .ok()
+                .unwrap();
+            let pairs: Vec<(u32, u32)> = (0..100)
+                .map(|_| {
+                    let i = rng.gen_range(0..59);
+                    (i, rng.gen_range(i + 1..60))
+                })
+                .collect();
+            let deltas = evaluator.deltas(&pairs).ok().unwrap();
+            for (&(i, j), delta) in pairs.iter().zip(deltas) {
+                let exact = two_opt_delta(&map, &path, mode, i as usize, j as usize);
+                assert_eq!(delta, exact as f32);
+            }
+        }
+
+        // costed from the points on the GPU
+        let mut rng = StdRng::seed_from_u64(4);
+        let points: Vec<(f64, f64)> = (0..200)
+            .map(|_| (rng.gen_range(0.0..1000.), rng.gen_range(0.0..1000.)))
+            .collect();
+        let distances = CoordinateDistances::new(points, Euclidean).ok().unwrap();
+        let instance: TspInstance<u32> =
+            TspInstance::from_distances(distances.into()).with_mode(TourMode::Closed);
+        let tour = instance.gpu_annealing(&annealing).ok().unwrap();
+        let identity = instance.tour((0..200).collect()).ok().unwrap();
+        assert!(instance.cost(&tour).ok().unwrap() < instance.cost(&identity).ok().unwrap());
+
+        let frozen = GpuAnnealing {
+            temperature: 0.,
+            ..GpuAnnealing::default()
+        };
+        assert!(gpu_annealing_tsp(&map, TourMode::Open, &frozen).is_err());
+        let empty_batch = GpuAnnealing {
+            batch_size: 0,
+            ..GpuAnnealing::default()
+        };
+        assert!(matches!(
+            gpu_annealing_tsp(&map, TourMode::Open, &empty_batch),
+            Err(TSPError::InvalidBatchSize)
+        ));
+    }
+}
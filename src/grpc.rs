@@ -0,0 +1,178 @@
+use std::ops::ControlFlow;
+use std::pin::Pin;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+
+use crate::brute_force::brute_force_tsp;
+use crate::config::SolverConfig;
+use crate::sim_annealing::Annealer;
+use crate::tour::normalize_path;
+
+pub mod proto {
+    tonic::include_proto!("tsp");
+}
+
+use proto::solve_update::Update;
+use proto::solver_server::{Solver, SolverServer};
+use proto::{Progress, Solution, SolveRequest, SolveUpdate};
+
+const DEFAULT_TEMPERATURE: u64 = 200;
+const DEFAULT_MAX_ITERATIONS: u64 = 200000;
+const DEFAULT_NUM_UPDATES: u64 = 100;
+
+#[derive(Default)]
+pub struct SolverService;
+
+// None if a weight doesn't fit in 16 bits
+fn to_intercity_map(request: &SolveRequest) -> Option<Vec<Vec<u16>>> {
+    request
+        .intercity_map
+        .iter()
+        .map(|row| {
+            row.weights
+                .iter()
+                .map(|&weight| u16::try_from(weight).ok())
+                .collect()
+        })
+        .collect()
+}
+
+fn solution_update(path: Vec<u32>, cost: u64) -> SolveUpdate {
+    SolveUpdate {
+        update: Some(Update::Solution(Solution { path, cost })),
+    }
+}
+
+#[tonic::async_trait]
+impl Solver for SolverService {
+    type SolveStream = Pin<Box<dyn Stream<Item = Result<SolveUpdate, Status>> + Send>>;
+
+    async fn solve(
+        &self,
+        request: Request<SolveRequest>,
+    ) -> Result<Response<Self::SolveStream>, Status> {
+        let request = request.into_inner();
+        let intercity_map = to_intercity_map(&request)
+            .ok_or_else(|| Status::invalid_argument("weights must fit in 16 bits"))?;
+        let temperature = if request.temperature == 0 {
+            DEFAULT_TEMPERATURE
+        } else {
+            request.temperature
+        };
+        let max_iterations = if request.max_iterations == 0 {
+            DEFAULT_MAX_ITERATIONS
+        } else {
+            request.max_iterations
+        };
+        let progress_interval = if request.progress_interval == 0 {
+            (max_iterations / DEFAULT_NUM_UPDATES).max(1)
+        } else {
+            request.progress_interval
+        };
+
+        if !matches!(
+            request.algorithm.as_str(),
+            "" | "simulated_annealing" | "brute_force"
+        ) {
+            return Err(Status::invalid_argument(format!(
+                "unknown algorithm '{}'",
+                request.algorithm
+            )));
+        }
+
+        let (sender, receiver) = mpsc::channel(16);
+        tokio::task::spawn_blocking(move || {
+            let result = if request.algorithm == "brute_force" {
+                brute_force_tsp(&intercity_map)
+            } else {
+                let config = SolverConfig {
+                    temperature,
+                    max_iterations,
+                    ..SolverConfig::default()
+                };
+                Annealer::new(config)
+                    .on_progress(progress_interval, |progress| {
+                        let update = SolveUpdate {
+                            update: Some(Update::Progress(Progress {
+                                iteration: progress.iteration,
+                                current_cost: progress.current_cost,
+                            })),
+                        };
+                        // stop solving once the client has gone away
+                        match sender.blocking_send(Ok(update)) {
+                            Ok(()) => ControlFlow::Continue(()),
+                            Err(_) => ControlFlow::Break(()),
+                        }
+                    })
+                    .solve(&intercity_map)
+                    .map(|(path, cost, _)| (path, cost))
+            };
+
+            let last_update = match result {
+                Ok((path, cost)) => {
+                    Ok(solution_update(normalize_path(&intercity_map, &path), cost))
+                }
+                Err(err) => Err(Status::invalid_argument(err.to_string())),
+            };
+            let _ = sender.blocking_send(last_update);
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+}
+
+pub async fn serve(address: &str) -> Result<(), Box<dyn std::error::Error>> {
+    tonic::transport::Server::builder()
+        .add_service(SolverServer::new(SolverService))
+        .serve(address.parse()?)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grpc::proto::solve_update::Update;
+    use crate::grpc::proto::solver_client::SolverClient;
+    use crate::grpc::proto::solver_server::SolverServer;
+    use crate::grpc::proto::{Row, SolveRequest};
+    use crate::grpc::SolverService;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+
+    #[tokio::test]
+    async fn test_streaming_solve() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(SolverServer::new(SolverService))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut client = SolverClient::connect(format!("http://{}", address))
+            .await
+            .unwrap();
+        let weights = [vec![0, 1, 9], vec![1, 0, 1], vec![9, 1, 0]];
+        let request = SolveRequest {
+            intercity_map: weights.into_iter().map(|weights| Row { weights }).collect(),
+            max_iterations: 1000,
+            progress_interval: 100,
+            ..Default::default()
+        };
+
+        let mut stream = client.solve(request).await.unwrap().into_inner();
+        let mut num_progress_updates = 0;
+        let mut solution = None;
+        while let Some(update) = stream.message().await.unwrap() {
+            match update.update.unwrap() {
+                Update::Progress(_) => num_progress_updates += 1,
+                Update::Solution(found) => solution = Some(found),
+            }
+        }
+
+        assert_eq!(num_progress_updates, 10);
+        assert_eq!(solution.unwrap().path.len(), 3);
+    }
+}
@@ -0,0 +1,388 @@
+use crate::analysis::{summarize_costs, InstanceSummary};
+use crate::brute_force::brute_force_tsp_with;
+use crate::candidates::nearest_cities;
+use crate::common::{check_path, TSPError};
+use crate::config::SolverConfig;
+use crate::cost::{CostFunction, TourMode};
+use crate::distance::DistanceProvider;
+#[cfg(feature = "gpu")]
+use crate::gpu::{gpu_annealing_tsp_with, GpuAnnealing};
+use crate::matrix::DistanceMatrix;
+use crate::metric::{metric_map, Metric};
+use crate::sim_annealing::Annealer;
+use crate::solvers::genetic::{evolve, GeneticAlgorithm};
+use crate::solvers::refine::refine_with;
+use crate::weight::Weight;
+
+// a distance matrix validated once on construction, so costing its tours and solving it need no
+// further checks, the free functions taking bare matrices check them on every call, weights
+// being u16 unless another weight::Weight is given, e.g. f64 for unrounded distances, and kept
+// in one flat matrix::DistanceMatrix, or worked out from coordinates whenever they're needed,
+// that the solvers read through CostFunction
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "InstanceData<W>",
+        into = "InstanceData<W>",
+        bound(
+            serialize = "W: Weight + serde::Serialize",
+            deserialize = "W: Weight + serde::Deserialize<'de>"
+        )
+    )
+)]
+#[derive(Clone, PartialEq, Debug)]
+pub struct TspInstance<W = u16> {
+    distances: DistanceProvider<W>,
+    mode: TourMode,
+}
+
+// what an instance is serialized as, deserializing validates it and works out its symmetry again
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct InstanceData<W> {
+    weights: Vec<Vec<W>>,
+    #[serde(default)]
+    mode: TourMode,
+}
+
+#[cfg(feature = "serde")]
+impl<W: Weight> TryFrom<InstanceData<W>> for TspInstance<W> {
+    type Error = TSPError;
+
+    fn try_from(data: InstanceData<W>) -> Result<TspInstance<W>, TSPError> {
+        Ok(TspInstance::new(data.weights)?.with_mode(data.mode))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<W: Weight> From<TspInstance<W>> for InstanceData<W> {
+    fn from(instance: TspInstance<W>) -> InstanceData<W> {
+        InstanceData {
+            weights: instance.distances.to_rows(),
+            mode: instance.mode,
+        }
+    }
+}
+
+// a path through every city of the instance that made it, visiting each exactly once, serialized
+// as the bare list of cities, which `TspInstance::tour` checks against an instance again
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Tour {
+    cities: Vec<u32>,
+}
+
+impl Tour {
+    pub fn cities(&self) -> &[u32] {
+        &self.cities
+    }
+
+    pub fn into_cities(self) -> Vec<u32> {
+        self.cities
+    }
+}
+
+impl<W: Weight> TspInstance<W> {
+    pub fn new(weights: Vec<Vec<W>>) -> Result<TspInstance<W>, TSPError> {
+        Ok(TspInstance {
+            distances: DistanceMatrix::new(weights)?.into(),
+            mode: TourMode::Open,
+        })
+    }
+
+    // e.g. distance::CoordinateDistances for an instance too large for its matrix to fit in
+    // memory
+    pub fn from_distances(distances: DistanceProvider<W>) -> TspInstance<W> {
+        TspInstance {
+            distances,
+            mode: TourMode::Open,
+        }
+    }
+
+    // the points' distances under the metric, e.g. `metric::Haversine` for great circle
+    // kilometres between latitudes and longitudes, rounded to the nearest integer for whole
+    // weights and kept as they are for floats
+    pub fn from_coordinates(
+        coordinates: &[(f64, f64)],
+        metric: &dyn Metric,
+    ) -> Result<TspInstance<W>, TSPError> {
+        TspInstance::new(metric_map(coordinates, metric)?)
+    }
+
+    // whether tours return to their first city, costs and solvers follow it
+    pub fn with_mode(self, mode: TourMode) -> TspInstance<W> {
+        TspInstance { mode, ..self }
+    }
+
+    pub fn mode(&self) -> TourMode {
+        self.mode
+    }
+
+    pub fn num_cities(&self) -> usize {
+        self.distances.num_cities()
+    }
+
+    pub fn distances(&self) -> &DistanceProvider<W> {
+        &self.distances
+    }
+
+    // the weights as nested rows, e.g. for the free functions taking bare matrices
+    pub fn to_rows(&self) -> Vec<Vec<W>> {
+        self.distances.to_rows()
+    }
+
+    pub fn weight(&self, from: u32, to: u32) -> W {
+        self.distances.cost(from, to)
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        self.distances.is_symmetric()
+    }
+
+    // weight statistics in the instance's own weights, read through its distances so that
+    // coordinate instances are summarized without building their matrix
+    pub fn summary(&self) -> InstanceSummary<W> {
+        summarize_costs(&self.distances, self.num_cities())
+    }
+
+    pub fn tour(&self, cities: Vec<u32>) -> Result<Tour, TSPError> {
+        check_path(self.num_cities(), &cities)?;
+        Ok(Tour { cities })
+    }
+
+    // including the return edge for closed tours, checking the tour first as a deserialized one
+    // or one made for another instance needn't visit this instance's cities
+    pub fn cost(&self, tour: &Tour) -> Result<W::Cost, TSPError> {
+        check_path(self.num_cities(), &tour.cities)?;
+        Ok(self.distances.path_cost(&tour.cities, self.mode))
+    }
+
+    // the k cities closest to city, closest first and ties broken by the lower index
+    pub fn nearest_neighbours(&self, city: u32, k: usize) -> Vec<u32> {
+        nearest_cities(&self.distances, self.num_cities(), city, k)
+    }
+
+    // each city's k nearest others, see candidates
+    pub fn candidates(&self, k: usize) -> Vec<Vec<u32>> {
+        self.distances.candidates(k)
+    }
+
+    pub fn simulated_annealing(
+        &self,
+        temperature: u64,
+        max_iterations: u64,
+    ) -> Result<Tour, TSPError> {
+        let config = SolverConfig {
+            temperature,
+            max_iterations,
+            mode: self.mode,
+            ..SolverConfig::default()
+        };
+        let (cities, _, _) =
+            Annealer::new(config).solve_with(&self.distances, self.num_cities())?;
+        Ok(Tour { cities })
+    }
+
+    // 2-opt and or-opt moves until neither improves the tour, see solvers::refine
+    pub fn refine(&self, tour: &Tour) -> Result<Tour, TSPError> {
+        let (cities, _) = refine_with(
+            &self.distances,
+            self.num_cities(),
+            tour.cities.clone(),
+            self.mode,
+            None,
+        )?;
+        Ok(Tour { cities })
+    }
+
+    // the same only trying moves to each city's k nearest others, for large instances
+    pub fn refine_by_candidates(&self, tour: &Tour, k: usize) -> Result<Tour, TSPError> {
+        let candidates = self.candidates(k);
+        let (cities, _) = refine_with(
+            &self.distances,
+            self.num_cities(),
+            tour.cities.clone(),
+            self.mode,
+            Some(&candidates),
+        )?;
+        Ok(Tour { cities })
+    }
+
+    pub fn brute_force(&self) -> Result<Tour, TSPError> {
+        let (cities, _) = brute_force_tsp_with(&self.distances, self.num_cities(), self.mode)?;
+        Ok(Tour { cities })
+    }
+
+    pub fn genetic_algorithm(&self, genetic: &GeneticAlgorithm) -> Result<Tour, TSPError> {
+        let (cities, _) = evolve(&self.distances, self.num_cities(), genetic, self.mode, None)?;
+        Ok(Tour { cities })
+    }
+
+    // annealing with batches of 2-opt moves costed on the GPU, from the points rather than a copy
+    // of every weight for straight line distances, see gpu
+    #[cfg(feature = "gpu")]
+    pub fn gpu_annealing(&self, annealing: &GpuAnnealing) -> Result<Tour, TSPError> {
+        let points = match &self.distances {
+            DistanceProvider::Coordinates(coordinates) if coordinates.is_euclidean() => {
+                Some(coordinates.points())
+            }
+            _ => None,
+        };
+        let (cities, _) = gpu_annealing_tsp_with(
+            &self.distances,
+            self.num_cities(),
+            self.mode,
+            points,
+            annealing,
+        )?;
+        Ok(Tour { cities })
+    }
+}
+
+impl<W: Weight> CostFunction<W> for TspInstance<W> {
+    fn cost(&self, from: u32, to: u32) -> W {
+        self.weight(from, to)
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.distances.is_symmetric()
+    }
+
+    fn path_weight(&self, path: &[u32]) -> W::Cost {
+        self.distances.path_weight(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{generate_map, TSPError};
+    use crate::config::{InitialTour, SolverConfig};
+    use crate::cost::TourMode;
+    use crate::instance::{Tour, TspInstance};
+    use crate::metric::Euclidean;
+    use crate::solver::{Budget, Solver};
+    use crate::solvers::genetic::GeneticAlgorithm;
+
+    #[test]
+    fn test_instance() {
+        let instance = TspInstance::new(vec![vec![0u16, 1, 5], vec![1, 0, 2], vec![5, 2, 0]])
+            .ok()
+            .unwrap();
+        assert_eq!(instance.num_cities(), 3);
+        assert!(instance.is_symmetric());
+        assert_eq!(instance.nearest_neighbours(0, 2), vec![1, 2]);
+        assert_eq!(instance.nearest_neighbours(2, 1), vec![1]);
+
+        let tour = instance.tour(vec![2, 1, 0]).ok().unwrap();
+        assert_eq!(instance.cost(&tour).ok(), Some(3));
+        assert_eq!(tour.into_cities(), vec![2, 1, 0]);
+        assert!(matches!(
+            instance.tour(vec![0, 1]),
+            Err(TSPError::InvalidPathLength { .. })
+        ));
+
+        // a tour of another instance
+        let larger = TspInstance::new(generate_map(4, (1, 9)).ok().unwrap())
+            .ok()
+            .unwrap();
+        let other = larger.tour(vec![3, 2, 1, 0]).ok().unwrap();
+        assert!(matches!(
+            instance.cost(&other),
+            Err(TSPError::InvalidPathLength { len: 4, .. })
+        ));
+
+        let summary = instance.summary();
+        assert_eq!((summary.weight_min, summary.weight_max), (1, 5));
+        assert!((summary.weight_mean - 8. / 3.).abs() < 1e-9);
+        let real = TspInstance::<f64>::from_coordinates(&[(0., 0.), (1.5, 0.)], &Euclidean)
+            .ok()
+            .unwrap();
+        assert_eq!(real.summary().weight_max, 1.5);
+
+        assert!(TspInstance::new(vec![vec![0u16, 1], vec![1]]).is_err());
+        assert!(TspInstance::<u16>::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_solvers() {
+        let instance = TspInstance::new(generate_map(7, (1, 100)).ok().unwrap())
+            .ok()
+            .unwrap();
+        let cost = |tour: &Tour| instance.cost(tour).ok().unwrap();
+        let optimal = instance.brute_force().ok().unwrap();
+        let annealed = instance.simulated_annealing(200, 20000).ok().unwrap();
+        assert!(instance.tour(annealed.cities().to_vec()).is_ok());
+        assert!(cost(&optimal) <= cost(&annealed));
+        // a warm start from the annealed tour never ends worse than it
+        let warm_start = SolverConfig {
+            temperature: 200,
+            max_iterations: 20000,
+            initial: InitialTour::Tour {
+                cities: annealed.cities().to_vec(),
+            },
+            ..SolverConfig::default()
+        };
+        let warm = warm_start
+            .solve(&instance, &Budget::default())
+            .ok()
+            .unwrap();
+        assert!(warm.cost <= cost(&annealed));
+        let polished = instance.refine(&annealed).ok().unwrap();
+        assert!(cost(&polished) <= cost(&annealed));
+        let near = instance.refine_by_candidates(&annealed, 3).ok().unwrap();
+        assert!(cost(&near) <= cost(&annealed));
+        let evolved = instance
+            .genetic_algorithm(&GeneticAlgorithm::default())
+            .ok()
+            .unwrap();
+        assert!(cost(&optimal) <= cost(&evolved));
+
+        let closed = instance.clone().with_mode(TourMode::Closed);
+        let closed_cost = |tour: &Tour| closed.cost(tour).ok().unwrap();
+        let round_trip = closed.brute_force().ok().unwrap();
+        let (first, last) = (round_trip.cities()[0], round_trip.cities()[6]);
+        assert_eq!(
+            closed_cost(&round_trip),
+            cost(&round_trip) + instance.weight(last, first) as u64
+        );
+        assert!(
+            closed_cost(&closed.simulated_annealing(200, 20000).ok().unwrap())
+                >= closed_cost(&round_trip)
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_serde() {
+        let instance = TspInstance::new(vec![vec![0u16, 1, 5], vec![2, 0, 2], vec![5, 2, 0]])
+            .ok()
+            .unwrap()
+            .with_mode(TourMode::Closed);
+        let json = serde_json::to_string(&instance).unwrap();
+        assert_eq!(
+            json,
+            r#"{"weights":[[0,1,5],[2,0,2],[5,2,0]],"mode":"closed"}"#
+        );
+        let read: TspInstance = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, instance);
+        assert!(!read.is_symmetric());
+
+        let open: TspInstance = serde_json::from_str(r#"{"weights":[[0,1],[1,0]]}"#).unwrap();
+        assert_eq!(open.mode(), TourMode::Open);
+        assert!(serde_json::from_str::<TspInstance>(r#"{"weights":[[0,1],[1]]}"#).is_err());
+
+        let tour = instance.tour(vec![2, 0, 1]).ok().unwrap();
+        assert_eq!(serde_json::to_string(&tour).unwrap(), "[2,0,1]");
+        let read: Tour = serde_json::from_str("[2,0,1]").unwrap();
+        assert_eq!(read, tour);
+        // checked when costed, as a bare list can't be checked without its instance
+        let read: Tour = serde_json::from_str("[2,0,7]").unwrap();
+        assert!(matches!(
+            instance.cost(&read),
+            Err(TSPError::CityOutOfRange { city: 7, .. })
+        ));
+    }
+}
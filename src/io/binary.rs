@@ -0,0 +1,170 @@
+use log::error;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::common::{check_city_map, TSPError};
+
+// layout: magic, version byte, number of cities as a little endian u32,
+// then every row of the matrix as little endian u16 weights
+const MAGIC: &[u8; 4] = b"TSPB";
+const VERSION: u8 = 1;
+
+fn write_header<W: Write>(writer: &mut W, num_cities: u32) -> Result<(), TSPError> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    writer.write_all(&num_cities.to_le_bytes())?;
+    Ok(())
+}
+
+fn write_row<W: Write>(
+    writer: &mut W,
+    row: impl Iterator<Item = u16>,
+    buffer: &mut Vec<u8>,
+) -> Result<(), TSPError> {
+    buffer.clear();
+    buffer.extend(row.flat_map(u16::to_le_bytes));
+    writer.write_all(buffer)?;
+    Ok(())
+}
+
+pub fn write_map<W: Write>(writer: &mut W, intercity_map: &[Vec<u16>]) -> Result<(), TSPError> {
+    check_city_map(intercity_map)?;
+
+    let mut buffer = Vec::new();
+    write_header(writer, intercity_map.len() as u32)?;
+    for row in intercity_map {
+        write_row(writer, row.iter().copied(), &mut buffer)?;
+    }
+
+    Ok(())
+}
+
+pub fn read_map<R: Read + Seek>(reader: &mut R) -> Result<Vec<Vec<u16>>, TSPError> {
+    let start = reader.stream_position()?;
+    let length = reader.seek(SeekFrom::End(0))? - start;
+    reader.seek(SeekFrom::Start(start))?;
+
+    let mut header = [0u8; 9];
+    reader.read_exact(&mut header)?;
+
+    if &header[..4] != MAGIC || header[4] != VERSION {
+        error!("Not a version {} binary map file", VERSION);
+        return Err(TSPError::InvalidFileFormat);
+    }
+
+    // a corrupt header could otherwise ask for gigabytes before the first row fails to read
+    let num_cities = u32::from_le_bytes(header[5..].try_into().unwrap()) as u64;
+    let expected = num_cities
+        .checked_mul(num_cities * 2)
+        .and_then(|weights| weights.checked_add(header.len() as u64));
+    if expected != Some(length) {
+        error!(
+            "A binary map of {} cities can't be {} bytes long",
+            num_cities, length
+        );
+        return Err(TSPError::InvalidFileFormat);
+    }
+
+    let num_cities = num_cities as usize;
+    let mut row_bytes = vec![0u8; num_cities * 2];
+    let mut intercity_map = Vec::with_capacity(num_cities);
+    for _ in 0..num_cities {
+        reader.read_exact(&mut row_bytes)?;
+        intercity_map.push(
+            row_bytes
+                .chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .collect(),
+        );
+    }
+
+    Ok(intercity_map)
+}
+
+// every edge weight is derived from the seed and its endpoints alone, so the lower triangle
+// can be regenerated instead of remembered and only a single row is ever held in memory
+fn edge_weight(seed: u64, i: usize, j: usize, weight_range: (u16, u16)) -> u16 {
+    let (a, b) = if i < j { (i, j) } else { (j, i) };
+    let edge_seed = seed ^ ((a as u64) << 32 | b as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    StdRng::seed_from_u64(edge_seed).gen_range(weight_range.0..weight_range.1)
+}
+
+pub fn stream_generated_map<W: Write>(
+    writer: &mut W,
+    num_cities: u32,
+    weight_range: (u16, u16),
+    seed: u64,
+) -> Result<(), TSPError> {
+    let (low, high) = weight_range;
+    if high <= low {
+        error!("Weight range cannot be reversed or empty");
+        return Err(TSPError::InvalidWeightRange);
+    }
+
+    let num_cities = num_cities as usize;
+    let mut buffer = Vec::with_capacity(num_cities * 2);
+    write_header(writer, num_cities as u32)?;
+    for i in 0..num_cities {
+        let row = (0..num_cities).map(|j| {
+            if i == j {
+                0
+            } else {
+                edge_weight(seed, i, j, weight_range)
+            }
+        });
+        write_row(writer, row, &mut buffer)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::generate_map;
+    use crate::io::binary::{read_map, stream_generated_map, write_map};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_binary_round_trip() {
+        let map = generate_map(12, (0, 1000)).ok().unwrap();
+
+        let mut bytes = Vec::new();
+        assert!(write_map(&mut bytes, &map).is_ok());
+        assert_eq!(bytes.len(), 9 + 12 * 12 * 2);
+
+        let loaded = read_map(&mut Cursor::new(&bytes)).ok().unwrap();
+        assert_eq!(loaded, map);
+
+        // a header claiming more cities than the file holds
+        let mut truncated = bytes.clone();
+        truncated[5..9].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read_map(&mut Cursor::new(&truncated)).is_err());
+        assert!(read_map(&mut Cursor::new(&bytes[..bytes.len() - 1])).is_err());
+
+        bytes[0] = b'X';
+        assert!(read_map(&mut Cursor::new(&bytes)).is_err());
+    }
+
+    #[test]
+    fn test_stream_generated_map() {
+        let mut bytes = Vec::new();
+        assert!(stream_generated_map(&mut bytes, 20, (5, 50), 42).is_ok());
+        let map = read_map(&mut Cursor::new(&bytes)).ok().unwrap();
+
+        for (i, row) in map.iter().enumerate() {
+            for (j, &weight) in row.iter().enumerate() {
+                assert_eq!(weight, map[j][i]);
+                assert!(if i == j {
+                    weight == 0
+                } else {
+                    (5..50).contains(&weight)
+                });
+            }
+        }
+
+        // same seed gives the same map
+        let mut again = Vec::new();
+        assert!(stream_generated_map(&mut again, 20, (5, 50), 42).is_ok());
+        assert_eq!(bytes, again);
+    }
+}
@@ -0,0 +1,73 @@
+use log::error;
+use std::io::{BufRead, BufReader, Read, Write};
+
+use crate::common::{check_city_map, TSPError};
+use crate::io::points::read_points;
+
+// one row of the distance matrix per line, weights separated by commas and/or whitespace, blank
+// lines and lines starting with # are skipped
+pub fn read_matrix<R: Read>(reader: &mut R) -> Result<Vec<Vec<u16>>, TSPError> {
+    let mut intercity_map = Vec::new();
+    for (number, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let row: Result<Vec<u16>, _> = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|field| !field.is_empty())
+            .map(str::parse)
+            .collect();
+        let Ok(row) = row else {
+            error!("Line {} is not a row of weights: '{}'", number + 1, line);
+            return Err(TSPError::InvalidFileFormat);
+        };
+        intercity_map.push(row);
+    }
+
+    check_city_map(&intercity_map)?;
+    Ok(intercity_map)
+}
+
+// the matrix as read_matrix reads it, one comma separated row per line
+pub fn write_matrix<W: Write>(writer: &mut W, intercity_map: &[Vec<u16>]) -> Result<(), TSPError> {
+    for row in intercity_map {
+        let row: Vec<String> = row.iter().map(u16::to_string).collect();
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+// one `x,y` point per line, read as io::points reads latitudes and longitudes
+pub fn read_coordinates<R: Read>(reader: &mut R) -> Result<Vec<(f64, f64)>, TSPError> {
+    read_points(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::TSPError;
+    use crate::io::csv::{read_coordinates, read_matrix};
+
+    #[test]
+    fn test_read_matrix() {
+        let file = "# three cities\n0,1,9\n1, 0, 1\n\n9 1 0\n";
+        assert_eq!(
+            read_matrix(&mut file.as_bytes()).ok().unwrap(),
+            vec![vec![0, 1, 9], vec![1, 0, 1], vec![9, 1, 0]]
+        );
+        assert!(matches!(
+            read_matrix(&mut "0,1\n1\n".as_bytes()),
+            Err(TSPError::InvalidMapShape { .. })
+        ));
+        assert!(read_matrix(&mut "0,x\n1,0\n".as_bytes()).is_err());
+        assert!(read_matrix(&mut "0,-1\n1,0\n".as_bytes()).is_err());
+        assert!(read_matrix(&mut "".as_bytes()).is_err());
+
+        assert_eq!(
+            read_coordinates(&mut "0,0\n3,4\n".as_bytes()).ok().unwrap(),
+            vec![(0., 0.), (3., 4.)]
+        );
+    }
+}
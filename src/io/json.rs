@@ -0,0 +1,191 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+use crate::api::to_canonical_json;
+use crate::common::TSPError;
+use crate::instance::TspInstance;
+use crate::metric::{Euclidean, Haversine, Manhattan, Metric};
+use crate::multi_run::{MultiRunResult, Summary};
+
+// instance files hold either a distance matrix, `{"intercity_map": [[0, 4], [4, 0]]}`, or points
+// weighed under a metric, `{"coordinates": [[0, 0], [3, 4]], "metric": "manhattan"}`, the metric
+// being euclidean unless given
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricName {
+    #[default]
+    Euclidean,
+    Manhattan,
+    Haversine,
+}
+
+impl MetricName {
+    pub fn metric(self) -> &'static dyn Metric {
+        match self {
+            MetricName::Euclidean => &Euclidean,
+            MetricName::Manhattan => &Manhattan,
+            MetricName::Haversine => &Haversine,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct InstanceFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intercity_map: Option<Vec<Vec<u16>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coordinates: Option<Vec<(f64, f64)>>,
+    #[serde(default)]
+    pub metric: MetricName,
+}
+
+// a solved tour with what produced it
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct SolutionReport {
+    pub path: Vec<u32>,
+    pub cost: u64,
+    pub solver: String,
+    pub num_cities: usize,
+    pub seconds: f64,
+}
+
+// every run of a multi run, for plotting the distribution of their costs and times
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct RunsReport {
+    pub runs: Vec<RunReport>,
+    pub best_path: Vec<u32>,
+    pub cost: Summary,
+    pub seconds: Summary,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct RunReport {
+    pub seed: u64,
+    pub cost: u64,
+    pub seconds: f64,
+}
+
+impl From<&MultiRunResult> for RunsReport {
+    fn from(result: &MultiRunResult) -> RunsReport {
+        RunsReport {
+            runs: result
+                .costs
+                .iter()
+                .zip(&result.times)
+                .enumerate()
+                .map(|(index, (&cost, time))| RunReport {
+                    seed: result.seed.wrapping_add(index as u64),
+                    cost,
+                    seconds: time.as_secs_f64(),
+                })
+                .collect(),
+            best_path: result.best_path.clone(),
+            cost: result.cost_summary(),
+            seconds: result.time_summary(),
+        }
+    }
+}
+
+fn to_json_error(err: serde_json::Error) -> TSPError {
+    error!("Malformed json: {}", err);
+    TSPError::InvalidFileFormat
+}
+
+pub fn read_instance<R: Read>(reader: &mut R) -> Result<TspInstance, TSPError> {
+    let file: InstanceFile = serde_json::from_reader(reader).map_err(to_json_error)?;
+    match (file.intercity_map, file.coordinates) {
+        (Some(intercity_map), None) => TspInstance::new(intercity_map),
+        (None, Some(coordinates)) => {
+            TspInstance::from_coordinates(&coordinates, file.metric.metric())
+        }
+        _ => {
+            error!("An instance file needs either an intercity_map or coordinates");
+            Err(TSPError::InvalidFileFormat)
+        }
+    }
+}
+
+// canonical like every other json the crate writes, so reports of the same runs diff cleanly
+pub fn write_solution<W: Write>(writer: &mut W, report: &SolutionReport) -> Result<(), TSPError> {
+    writeln!(
+        writer,
+        "{}",
+        to_canonical_json(report).map_err(to_json_error)?
+    )?;
+    Ok(())
+}
+
+pub fn write_runs<W: Write>(writer: &mut W, result: &MultiRunResult) -> Result<(), TSPError> {
+    let report = RunsReport::from(result);
+    writeln!(
+        writer,
+        "{}",
+        to_canonical_json(&report).map_err(to_json_error)?
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::json::{read_instance, write_runs, write_solution, RunsReport, SolutionReport};
+    use crate::multi_run::MultiRunResult;
+    use std::time::Duration;
+
+    #[test]
+    fn test_read_instance() {
+        let matrix = r#"{"intercity_map": [[0, 1, 9], [1, 0, 1], [9, 1, 0]]}"#;
+        let instance = read_instance(&mut matrix.as_bytes()).ok().unwrap();
+        assert_eq!(instance.weight(0, 2), 9);
+
+        let points = r#"{"coordinates": [[0, 0], [3, 4]]}"#;
+        let instance = read_instance(&mut points.as_bytes()).ok().unwrap();
+        assert_eq!(instance.weight(0, 1), 5);
+        let points = r#"{"coordinates": [[0, 0], [3, 4]], "metric": "manhattan"}"#;
+        let instance = read_instance(&mut points.as_bytes()).ok().unwrap();
+        assert_eq!(instance.weight(0, 1), 7);
+
+        assert!(read_instance(&mut "{}".as_bytes()).is_err());
+        let both = r#"{"intercity_map": [[0]], "coordinates": [[0, 0]]}"#;
+        assert!(read_instance(&mut both.as_bytes()).is_err());
+        let unknown = r#"{"coordinates": [[0, 0]], "metric": "chebyshev"}"#;
+        assert!(read_instance(&mut unknown.as_bytes()).is_err());
+        assert!(read_instance(&mut r#"{"intercity_map": [[0, 1]]}"#.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_write_solution() {
+        let report = SolutionReport {
+            path: vec![0, 1, 2],
+            cost: 2,
+            solver: "brute_force".to_string(),
+            num_cities: 3,
+            seconds: 0.5,
+        };
+        let mut json = Vec::new();
+        write_solution(&mut json, &report).ok().unwrap();
+        let read: SolutionReport = serde_json::from_slice(&json).unwrap();
+        assert_eq!(read, report);
+        // keys sorted, on one line
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "{\"cost\":2,\"num_cities\":3,\"path\":[0,1,2],\"seconds\":0.5,\"solver\":\"brute_force\"}\n"
+        );
+
+        let result = MultiRunResult {
+            best_path: vec![1, 0, 2],
+            costs: vec![5, 3],
+            times: vec![Duration::from_secs(1), Duration::from_secs(2)],
+            seed: 9,
+        };
+        let mut json = Vec::new();
+        write_runs(&mut json, &result).ok().unwrap();
+        let read: RunsReport = serde_json::from_slice(&json).unwrap();
+        assert_eq!(read.runs[1].seed, 10);
+        assert_eq!(read.runs[1].cost, 3);
+        assert_eq!(read.seconds.median, 1.5);
+        assert_eq!(read.cost.min, 3.);
+    }
+}
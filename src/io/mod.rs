@@ -0,0 +1,101 @@
+use log::error;
+use std::fs::File;
+use std::path::Path;
+
+use crate::common::TSPError;
+use crate::weight::Weight;
+
+pub mod binary;
+pub mod csv;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod png;
+pub mod points;
+pub mod tsplib;
+
+// an instance and its name by the file's extension, .tsp as TSPLIB, .json (with the json feature)
+// as an instance file, .bin as a binary map and anything else as a csv matrix, named after the file unless it names
+// itself, weighed in W so TSPLIB instances with long distances can be read as u32
+pub fn read_instance_file<W: Weight>(path: &Path) -> Result<(String, Vec<Vec<W>>), TSPError> {
+    let mut file = File::open(path)?;
+    let stem = path
+        .file_stem()
+        .map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("tsp") => {
+            let instance = tsplib::read_tsplib::<W>(&mut file)?;
+            let name = if instance.name.is_empty() {
+                stem
+            } else {
+                instance.name
+            };
+            Ok((name, instance.intercity_map))
+        }
+        #[cfg(feature = "json")]
+        Some("json") => Ok((stem, widen(json::read_instance(&mut file)?.to_rows())?)),
+        Some("bin") => Ok((stem, widen(binary::read_map(&mut file)?)?)),
+        _ => Ok((stem, widen(csv::read_matrix(&mut file)?)?)),
+    }
+}
+
+// csv and json instances are read as u16, which the wider weights hold exactly
+fn widen<W: Weight>(intercity_map: Vec<Vec<u16>>) -> Result<Vec<Vec<W>>, TSPError> {
+    intercity_map
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|weight| W::from_f64(weight as f64).ok_or(TSPError::InvalidWeightRange))
+                .collect()
+        })
+        .collect()
+}
+
+// the cities' (x, y) coordinates, which only .tsp files with a NODE_COORD_SECTION have
+pub fn read_instance_coordinates(path: &Path) -> Result<Vec<(f64, f64)>, TSPError> {
+    let coordinates = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("tsp") => tsplib::read_tsplib::<u32>(&mut File::open(path)?)?.coordinates,
+        _ => Vec::new(),
+    };
+    if coordinates.is_empty() {
+        error!("{} has no city coordinates", path.display());
+        return Err(TSPError::InvalidFileFormat);
+    }
+    Ok(coordinates)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::csv::write_matrix;
+    use crate::io::{read_instance_coordinates, read_instance_file};
+
+    #[test]
+    fn test_read_instance_file() {
+        let directory = std::env::temp_dir();
+        let matrix = directory.join(format!("tsp_matrix_{}.csv", std::process::id()));
+        let map = vec![vec![0, 1, 9], vec![1, 0, 1], vec![9, 1, 0]];
+        write_matrix(&mut std::fs::File::create(&matrix).unwrap(), &map)
+            .ok()
+            .unwrap();
+        let (name, read) = read_instance_file::<u16>(&matrix).ok().unwrap();
+        assert!(read_instance_coordinates(&matrix).is_err());
+        std::fs::remove_file(&matrix).unwrap();
+        assert_eq!(name, format!("tsp_matrix_{}", std::process::id()));
+        assert_eq!(read, map);
+
+        let tsplib = directory.join(format!("tsp_square_{}.tsp", std::process::id()));
+        std::fs::write(
+            &tsplib,
+            "NAME: square\nDIMENSION: 4\nEDGE_WEIGHT_TYPE: EUC_2D\n\
+             NODE_COORD_SECTION\n1 0 0\n2 3 0\n3 3 4\n4 0 4\nEOF\n",
+        )
+        .unwrap();
+        let (name, read) = read_instance_file::<u32>(&tsplib).ok().unwrap();
+        let coordinates = read_instance_coordinates(&tsplib).ok().unwrap();
+        std::fs::remove_file(&tsplib).unwrap();
+        assert_eq!(coordinates, vec![(0., 0.), (3., 0.), (3., 4.), (0., 4.)]);
+        assert_eq!(name, "square");
+        assert_eq!(read[0], vec![0, 3, 5, 4]);
+
+        assert!(read_instance_file::<u16>(&directory.join("no_such_instance.csv")).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::Write;
+
+use crate::analysis::HistogramBucket;
+use crate::common::TSPError;
+
+// just enough of PNG to write 8 bit grayscale images, the image data is stored uncompressed
+// which keeps the encoder tiny and is fine for the small charts written here
+const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+const MAX_STORED_BLOCK: usize = 65535;
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<(), TSPError> {
+    let mut checked = kind.to_vec();
+    checked.extend_from_slice(data);
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(&checked)?;
+    writer.write_all(&crc32(&checked).to_be_bytes())?;
+    Ok(())
+}
+
+// pixels are row by row, 0 black to 255 white
+pub fn write_grayscale_png<W: Write>(
+    writer: &mut W,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Result<(), TSPError> {
+    assert_eq!(pixels.len(), (width * height) as usize);
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 0, 0, 0, 0]); // bit depth, grayscale, no interlacing
+
+    // every row starts with its filter type, 0 for none
+    let raw: Vec<u8> = pixels
+        .chunks(width.max(1) as usize)
+        .flat_map(|row| std::iter::once(0).chain(row.iter().copied()))
+        .collect();
+
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none() as u8;
+        let len = block.len() as u16;
+        zlib.push(is_final);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    writer.write_all(SIGNATURE)?;
+    write_chunk(writer, b"IHDR", &header)?;
+    write_chunk(writer, b"IDAT", &zlib)?;
+    write_chunk(writer, b"IEND", &[])?;
+    Ok(())
+}
+
+// one black bar per bucket on white, bar heights relative to the fullest bucket
+pub fn write_histogram_png<W: Write>(
+    writer: &mut W,
+    buckets: &[HistogramBucket],
+    width: u32,
+    height: u32,
+) -> Result<(), TSPError> {
+    let max_count = buckets
+        .iter()
+        .map(|bucket| bucket.count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let num_buckets = buckets.len().max(1) as u32;
+
+    let mut pixels = vec![255u8; (width * height) as usize];
+    for x in 0..width {
+        let Some(bucket) = buckets.get((x * num_buckets / width) as usize) else {
+            continue;
+        };
+        let bar_height = (bucket.count * height as usize).div_ceil(max_count) as u32;
+        for y in height - bar_height..height {
+            pixels[(y * width + x) as usize] = 0;
+        }
+    }
+
+    write_grayscale_png(writer, width, height, &pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::HistogramBucket;
+    use crate::io::png::{adler32, crc32, write_grayscale_png, write_histogram_png};
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn test_write_png() {
+        let mut png = Vec::new();
+        write_grayscale_png(&mut png, 2, 2, &[0, 255, 255, 0])
+            .ok()
+            .unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[png.len() - 8..], b"IEND\xae\x42\x60\x82");
+
+        let buckets = [
+            HistogramBucket {
+                low: 1,
+                high: 5,
+                count: 4,
+            },
+            HistogramBucket {
+                low: 6,
+                high: 10,
+                count: 1,
+            },
+        ];
+        let mut chart = Vec::new();
+        write_histogram_png(&mut chart, &buckets, 40, 20)
+            .ok()
+            .unwrap();
+        assert!(chart.len() > 40 * 20);
+    }
+}
@@ -0,0 +1,74 @@
+use log::error;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::common::TSPError;
+
+// splits off the next field, fields being separated by commas and/or whitespace
+fn next_field(line: &str) -> (&str, &str) {
+    let is_separator = |c: char| c == ',' || c.is_whitespace();
+    let line = line.trim_start_matches(is_separator);
+    line.split_at(line.find(is_separator).unwrap_or(line.len()))
+}
+
+pub struct NamedPoints {
+    pub points: Vec<(f64, f64)>, // (latitude, longitude) in degrees
+    pub names: Vec<String>,
+}
+
+// one `latitude,longitude[,name]` point per line in degrees, commas or whitespace between the
+// fields, blank lines and lines starting with # are skipped, the name is the rest of the line
+// and empty for points without one
+pub fn read_named_points<R: Read>(reader: &mut R) -> Result<NamedPoints, TSPError> {
+    let mut points = Vec::new();
+    let mut names = Vec::new();
+    for (number, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (latitude, rest) = next_field(line);
+        let (longitude, rest) = next_field(rest);
+        let Some(point) = latitude.parse().ok().zip(longitude.parse().ok()) else {
+            error!(
+                "Line {} is not a latitude and longitude: '{}'",
+                number + 1,
+                line
+            );
+            return Err(TSPError::InvalidFileFormat);
+        };
+        points.push(point);
+        names.push(
+            rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace())
+                .to_string(),
+        );
+    }
+
+    Ok(NamedPoints { points, names })
+}
+
+pub fn read_points<R: Read>(reader: &mut R) -> Result<Vec<(f64, f64)>, TSPError> {
+    read_named_points(reader).map(|named| named.points)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::points::{read_named_points, read_points};
+
+    #[test]
+    fn test_read_points() {
+        let file = "# london, paris\n51.5074,-0.1278\n\n48.8566 2.3522\n";
+        assert_eq!(
+            read_points(&mut file.as_bytes()).ok().unwrap(),
+            vec![(51.5074, -0.1278), (48.8566, 2.3522)]
+        );
+        assert!(read_points(&mut "51.5,".as_bytes()).is_err());
+        assert!(read_points(&mut "north,south".as_bytes()).is_err());
+
+        let named = "51.5074,-0.1278,London\n40.7128, -74.006, New York, NY\n48.8566 2.3522\n";
+        let named = read_named_points(&mut named.as_bytes()).ok().unwrap();
+        assert_eq!(named.points.len(), 3);
+        assert_eq!(named.names, vec!["London", "New York, NY", ""]);
+    }
+}
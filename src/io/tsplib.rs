@@ -0,0 +1,364 @@
+use log::error;
+use std::io::{Read, Write};
+
+use crate::common::{check_symmetric, TSPError};
+use crate::instance::TspInstance;
+use crate::weight::Weight;
+
+// the subset of the TSPLIB format needed for the symmetric benchmark instances,
+// EUC_2D, CEIL_2D, GEO and ATT coordinates and the explicit matrix layouts, weighed in W, u32
+// for instances such as pla33810 whose distances don't fit in 16 bits
+pub struct TsplibInstance<W: Weight = u16> {
+    pub name: String,
+    pub intercity_map: Vec<Vec<W>>,
+    pub coordinates: Vec<(f64, f64)>, // the NODE_COORD_SECTION's, empty without one
+}
+
+impl<W: Weight> TsplibInstance<W> {
+    // a TspInstance of open tours, `with_mode(TourMode::Closed)` matches the published optima
+    pub fn into_instance(self) -> Result<TspInstance<W>, TSPError> {
+        TspInstance::new(self.intercity_map)
+    }
+}
+
+// TSPLIB's nearest integer rounding
+fn nint(x: f64) -> u32 {
+    (x + 0.5) as u32
+}
+
+fn to_weight<W: Weight>(value: u32) -> Result<W, TSPError> {
+    W::from_f64(value as f64).ok_or_else(|| {
+        error!("Edge weight {} does not fit in the weight type", value);
+        TSPError::InvalidWeightRange
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(token: &str) -> Result<T, TSPError> {
+    token.parse().map_err(|_| {
+        error!("Expected a number but found '{}'", token);
+        TSPError::InvalidFileFormat
+    })
+}
+
+// the distance functions follow the TSPLIB reference code exactly, down to its value of pi,
+// as the published optima were computed with them
+
+pub(crate) fn euclidean(x1: f64, y1: f64, x2: f64, y2: f64) -> u32 {
+    nint((x1 - x2).hypot(y1 - y2))
+}
+
+fn ceil_euclidean(x1: f64, y1: f64, x2: f64, y2: f64) -> u32 {
+    (x1 - x2).hypot(y1 - y2).ceil() as u32
+}
+
+// coordinates are DDD.MM, degrees then minutes, in the reference code the degrees are
+// truncated rather than rounded as the format description says
+#[allow(clippy::approx_constant)]
+fn geo_radians(coordinate: f64) -> f64 {
+    const PI: f64 = 3.141592;
+    let degrees = coordinate.trunc();
+    let minutes = coordinate - degrees;
+    PI * (degrees + 5. * minutes / 3.) / 180.
+}
+
+// great circle distance in km on an idealised sphere, latitude first
+fn geographical(x1: f64, y1: f64, x2: f64, y2: f64) -> u32 {
+    const EARTH_RADIUS: f64 = 6378.388;
+    let (latitude1, longitude1) = (geo_radians(x1), geo_radians(y1));
+    let (latitude2, longitude2) = (geo_radians(x2), geo_radians(y2));
+    let q1 = (longitude1 - longitude2).cos();
+    let q2 = (latitude1 - latitude2).cos();
+    let q3 = (latitude1 + latitude2).cos();
+    (EARTH_RADIUS * (0.5 * ((1. + q1) * q2 - (1. - q1) * q3)).acos() + 1.) as u32
+}
+
+// the att48 and att532 instances' pseudo euclidean distance, always rounded up
+fn pseudo_euclidean(x1: f64, y1: f64, x2: f64, y2: f64) -> u32 {
+    let distance = (((x1 - x2).powi(2) + (y1 - y2).powi(2)) / 10.).sqrt();
+    let rounded = nint(distance);
+    if (rounded as f64) < distance {
+        rounded + 1
+    } else {
+        rounded
+    }
+}
+
+fn coordinate_map<W: Weight>(
+    coordinates: &[(f64, f64)],
+    distance: fn(f64, f64, f64, f64) -> u32,
+) -> Result<Vec<Vec<W>>, TSPError> {
+    coordinates
+        .iter()
+        .enumerate()
+        .map(|(i, &(x1, y1))| {
+            coordinates
+                .iter()
+                .enumerate()
+                .map(|(j, &(x2, y2))| {
+                    // GEO's + 1 would otherwise put a weight on the diagonal
+                    if i == j {
+                        Ok(W::default())
+                    } else {
+                        to_weight(distance(x1, y1, x2, y2))
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn explicit_map<W: Weight>(
+    format: &str,
+    weights: &[u32],
+    dimension: usize,
+) -> Result<Vec<Vec<W>>, TSPError> {
+    // (row, column) pairs in the order the weights are listed, mirrored afterwards
+    let positions: Vec<(usize, usize)> = match format {
+        "FULL_MATRIX" => (0..dimension)
+            .flat_map(|i| (0..dimension).map(move |j| (i, j)))
+            .collect(),
+        "UPPER_ROW" => (0..dimension)
+            .flat_map(|i| (i + 1..dimension).map(move |j| (i, j)))
+            .collect(),
+        "UPPER_DIAG_ROW" => (0..dimension)
+            .flat_map(|i| (i..dimension).map(move |j| (i, j)))
+            .collect(),
+        "LOWER_ROW" => (0..dimension)
+            .flat_map(|i| (0..i).map(move |j| (i, j)))
+            .collect(),
+        "LOWER_DIAG_ROW" => (0..dimension)
+            .flat_map(|i| (0..=i).map(move |j| (i, j)))
+            .collect(),
+        _ => {
+            error!("Unsupported EDGE_WEIGHT_FORMAT {}", format);
+            return Err(TSPError::InvalidFileFormat);
+        }
+    };
+
+    if weights.len() != positions.len() {
+        error!(
+            "Expected {} edge weights but found {}",
+            positions.len(),
+            weights.len()
+        );
+        return Err(TSPError::InvalidFileFormat);
+    }
+
+    let mut intercity_map = vec![vec![W::default(); dimension]; dimension];
+    for (&(i, j), &weight) in positions.iter().zip(weights) {
+        let weight = to_weight(weight)?;
+        intercity_map[i][j] = weight;
+        if format != "FULL_MATRIX" {
+            intercity_map[j][i] = weight;
+        }
+    }
+
+    Ok(intercity_map)
+}
+
+pub fn read_tsplib<W: Weight>(reader: &mut impl Read) -> Result<TsplibInstance<W>, TSPError> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+
+    let mut name = String::new();
+    let mut dimension = None;
+    let mut problem_type = String::new();
+    let mut weight_type = String::new();
+    let mut weight_format = String::from("FULL_MATRIX");
+    let mut coordinates = Vec::new();
+    let mut weights = Vec::new();
+
+    let mut lines = contents.lines().map(str::trim).peekable();
+    while let Some(line) = lines.next() {
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim().to_string();
+            match key.trim() {
+                "NAME" => name = value,
+                "TYPE" => problem_type = value,
+                "DIMENSION" => dimension = Some(parse_number::<usize>(&value)?),
+                "EDGE_WEIGHT_TYPE" => weight_type = value,
+                "EDGE_WEIGHT_FORMAT" => weight_format = value,
+                _ => {}
+            }
+            continue;
+        }
+
+        // a section's data runs until the next line that doesn't start with a number
+        let mut section = Vec::new();
+        while let Some(data) = lines
+            .next_if(|data| data.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.'))
+        {
+            section.push(data);
+        }
+
+        match line {
+            "NODE_COORD_SECTION" => {
+                for data in section {
+                    let fields: Vec<&str> = data.split_whitespace().collect();
+                    if fields.len() != 3 {
+                        error!("Malformed node coordinate line '{}'", data);
+                        return Err(TSPError::InvalidFileFormat);
+                    }
+                    coordinates.push((parse_number(fields[1])?, parse_number(fields[2])?));
+                }
+            }
+            "EDGE_WEIGHT_SECTION" => {
+                for token in section.iter().flat_map(|data| data.split_whitespace()) {
+                    weights.push(parse_number(token)?);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some(dimension) = dimension else {
+        error!("TSPLIB file has no DIMENSION");
+        return Err(TSPError::InvalidFileFormat);
+    };
+
+    let intercity_map = match weight_type.as_str() {
+        "EUC_2D" if coordinates.len() == dimension => coordinate_map(&coordinates, euclidean)?,
+        "CEIL_2D" if coordinates.len() == dimension => {
+            coordinate_map(&coordinates, ceil_euclidean)?
+        }
+        "GEO" if coordinates.len() == dimension => coordinate_map(&coordinates, geographical)?,
+        "ATT" if coordinates.len() == dimension => coordinate_map(&coordinates, pseudo_euclidean)?,
+        "EXPLICIT" => explicit_map(&weight_format, &weights, dimension)?,
+        _ => {
+            error!(
+                "Unsupported EDGE_WEIGHT_TYPE '{}' or wrong number of nodes",
+                weight_type
+            );
+            return Err(TSPError::InvalidFileFormat);
+        }
+    };
+
+    // a FULL_MATRIX can still be asymmetric even when the file claims to be symmetric
+    if problem_type == "TSP" {
+        check_symmetric(&intercity_map)?;
+    }
+
+    Ok(TsplibInstance {
+        name,
+        intercity_map,
+        coordinates,
+    })
+}
+
+// a solved tour in TSPLIB's .tour format, cities numbered from 1 as in the instance files and the
+// cost, however the tour was costed, kept in the comment as published optimal tours do
+pub fn write_tour<W: Write>(
+    writer: &mut W,
+    name: &str,
+    path: &[u32],
+    cost: u64,
+) -> Result<(), TSPError> {
+    writeln!(writer, "NAME : {}.tour", name)?;
+    writeln!(writer, "COMMENT : Length {}", cost)?;
+    writeln!(writer, "TYPE : TOUR")?;
+    writeln!(writer, "DIMENSION : {}", path.len())?;
+    writeln!(writer, "TOUR_SECTION")?;
+    for city in path {
+        writeln!(writer, "{}", *city as usize + 1)?;
+    }
+    writeln!(writer, "-1\nEOF")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::known_optimum;
+    use crate::common::TSPError;
+    use crate::io::tsplib::{read_tsplib, write_tour, TsplibInstance};
+
+    #[test]
+    fn test_read_tsplib() {
+        let euclidean = "NAME : square\nTYPE : TSP\nDIMENSION : 4\nEDGE_WEIGHT_TYPE : EUC_2D\n\
+            NODE_COORD_SECTION\n1 0 0\n2 3 0\n3 3 4\n4 0 4\nEOF\n";
+        let instance: TsplibInstance = read_tsplib(&mut euclidean.as_bytes()).ok().unwrap();
+        assert_eq!(instance.name, "square");
+        assert_eq!(instance.intercity_map[0], vec![0, 3, 5, 4]);
+        assert_eq!(instance.coordinates[2], (3., 4.));
+        let instance = instance.into_instance().ok().unwrap();
+        assert_eq!(instance.num_cities(), 4);
+        assert!(instance.is_symmetric());
+
+        let explicit = "NAME: tri\nDIMENSION: 3\nEDGE_WEIGHT_TYPE: EXPLICIT\n\
+            EDGE_WEIGHT_FORMAT: UPPER_ROW\nEDGE_WEIGHT_SECTION\n 7 9\n 2\nEOF\n";
+        let instance: TsplibInstance = read_tsplib(&mut explicit.as_bytes()).ok().unwrap();
+        assert_eq!(
+            instance.intercity_map,
+            vec![vec![0, 7, 9], vec![7, 0, 2], vec![9, 2, 0]]
+        );
+
+        let truncated = "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EXPLICIT\nEDGE_WEIGHT_SECTION\n1 2\n";
+        assert!(read_tsplib::<u16>(&mut truncated.as_bytes()).is_err());
+
+        // wider weights than 16 bits only fit when read as u32
+        let wide = "DIMENSION: 2\nEDGE_WEIGHT_TYPE: EXPLICIT\nEDGE_WEIGHT_FORMAT: UPPER_ROW\n\
+            EDGE_WEIGHT_SECTION\n70000\nEOF\n";
+        assert!(matches!(
+            read_tsplib::<u16>(&mut wide.as_bytes()),
+            Err(TSPError::InvalidWeightRange)
+        ));
+        let instance = read_tsplib::<u32>(&mut wide.as_bytes()).ok().unwrap();
+        assert_eq!(instance.intercity_map, vec![vec![0, 70000], vec![70000, 0]]);
+        let instance = instance.into_instance().ok().unwrap();
+        assert_eq!(instance.weight(1, 0), 70000);
+
+        let asymmetric = "TYPE: TSP\nDIMENSION: 2\nEDGE_WEIGHT_TYPE: EXPLICIT\n\
+            EDGE_WEIGHT_FORMAT: FULL_MATRIX\nEDGE_WEIGHT_SECTION\n0 1\n2 0\n";
+        assert!(matches!(
+            read_tsplib::<u16>(&mut asymmetric.as_bytes()),
+            Err(TSPError::AsymmetricWeight {
+                row: 0,
+                column: 1,
+                weight: 1.,
+                mirrored: 2.
+            })
+        ));
+    }
+
+    #[test]
+    fn test_tsplib_distances() {
+        let burma14 = "NAME: burma14\nTYPE: TSP\nDIMENSION: 14\nEDGE_WEIGHT_TYPE: GEO\n\
+            NODE_COORD_SECTION\n1 16.47 96.10\n2 16.47 94.44\n3 20.09 92.54\n4 22.39 93.37\n\
+            5 25.23 97.24\n6 22.00 96.05\n7 20.47 97.02\n8 17.20 96.29\n9 16.30 97.38\n\
+            10 14.05 98.12\n11 16.53 97.38\n12 21.52 95.59\n13 19.41 97.13\n14 20.09 94.55\nEOF\n";
+        let instance: TsplibInstance = read_tsplib(&mut burma14.as_bytes()).ok().unwrap();
+        let map = &instance.intercity_map;
+        assert_eq!(map[0][..4], [0, 153, 510, 706]);
+
+        // the published optimal round trip
+        let tour = [0, 1, 13, 2, 3, 4, 5, 11, 6, 12, 7, 10, 8, 9, 0];
+        let cost: u64 = tour
+            .windows(2)
+            .map(|pair| map[pair[0]][pair[1]] as u64)
+            .sum();
+        assert_eq!(Some(cost), known_optimum("burma14"));
+
+        // the first two cities of att48
+        let att = "DIMENSION: 2\nEDGE_WEIGHT_TYPE: ATT\nNODE_COORD_SECTION\n\
+            1 6734 1453\n2 2233 10\nEOF\n";
+        let instance: TsplibInstance = read_tsplib(&mut att.as_bytes()).ok().unwrap();
+        assert_eq!(instance.intercity_map[0][1], 1495);
+
+        let ceil = "DIMENSION: 2\nEDGE_WEIGHT_TYPE: CEIL_2D\nNODE_COORD_SECTION\n\
+            1 0 0\n2 1 1\nEOF\n";
+        let instance: TsplibInstance = read_tsplib(&mut ceil.as_bytes()).ok().unwrap();
+        assert_eq!(instance.intercity_map[0][1], 2);
+    }
+
+    #[test]
+    fn test_write_tour() {
+        let mut tour = Vec::new();
+        write_tour(&mut tour, "square", &[0, 3, 2, 1], 14)
+            .ok()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(tour).unwrap(),
+            "NAME : square.tour\nCOMMENT : Length 14\nTYPE : TOUR\nDIMENSION : 4\n\
+             TOUR_SECTION\n1\n4\n3\n2\n-1\nEOF\n"
+        );
+    }
+}
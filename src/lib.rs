@@ -0,0 +1,69 @@
+pub mod analysis;
+#[cfg(feature = "json")]
+pub mod api;
+#[cfg(feature = "async")]
+pub mod async_solve;
+pub mod auto;
+pub mod bench;
+pub mod bounds;
+pub mod brute_force;
+pub mod candidates;
+pub mod capitals;
+pub mod common;
+pub mod config;
+pub mod convergence;
+pub mod cooling;
+pub mod cost;
+pub mod distance;
+#[cfg(feature = "distributed")]
+pub mod distributed;
+pub mod euclidean;
+pub mod generators;
+pub mod geo;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod instance;
+pub mod io;
+pub mod matching;
+pub mod matrix;
+pub mod memory;
+pub mod metric;
+pub mod moves;
+pub mod multi_run;
+pub mod normalization;
+pub mod perturbation;
+pub mod scoreboard;
+pub mod significance;
+pub mod sim_annealing;
+pub mod simd;
+pub mod solver;
+pub mod solvers;
+pub mod stats;
+pub mod stopping;
+pub mod testing;
+pub mod tour;
+pub mod tour_repr;
+pub mod trace;
+pub mod tune;
+pub mod viz;
+pub mod weight;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "worker")]
+pub mod worker;
+
+// the core of the api at the crate root, everything else is reached through its module
+pub use brute_force::brute_force_tsp;
+pub use common::{generate_map, path_cost, verify_tour, TSPError};
+pub use euclidean::EuclideanInstance;
+pub use instance::{Tour, TspInstance};
+pub use sim_annealing::simulated_annealing_tsp;
+pub use weight::Weight;
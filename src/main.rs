@@ -1,15 +1,386 @@
-mod brute_force;
-mod common;
-mod sim_annealing;
-
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand};
 use log::{error, LevelFilter};
+use std::error::Error;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use rand::{rngs::StdRng, SeedableRng};
+use simulated_annealing_tsp::bench::{parse_solver, BenchSolver};
+use simulated_annealing_tsp::bounds::{gap_percent, spanning_tree_bound};
+use simulated_annealing_tsp::brute_force_tsp;
+use simulated_annealing_tsp::common::{generate_asymmetric_map_with_rng, generate_map_with_rng};
+use simulated_annealing_tsp::config::SolverConfig;
+use simulated_annealing_tsp::convergence::ConvergenceTrace;
+use simulated_annealing_tsp::cost::TourMode;
+use simulated_annealing_tsp::io::csv::read_matrix;
+use simulated_annealing_tsp::sim_annealing::Annealer;
+
+const TEMPERATURE: u64 = 200;
+const MAX_ITERATIONS: u64 = 200000;
+
+/// Simulated annealing and exact solvers for the travelling salesman problem.
+///
+/// Without a command, the demo solves a random map both ways.
+#[derive(Parser)]
+#[command(
+    name = "simulated-annealing-tsp",
+    version,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    demo: DemoArgs,
+}
+
+// every command, the ones marked with a feature need the binary built with it
+#[derive(Subcommand)]
+enum Command {
+    /// Solve a .tsp, .json or csv instance, picking the solver unless given one
+    Solve(SolveArgs),
+    /// Solve an instance exactly, short for `solve <FILE> --solver brute_force`
+    BruteForce(BruteForceArgs),
+    /// Write a random instance as a csv matrix
+    Generate(GenerateArgs),
+    /// Score solvers against known TSPLIB optima or each other
+    Bench(BenchArgs),
+    /// Rank the solvers in a bench store
+    Leaderboard {
+        #[arg(long, default_value = "bench.csv")]
+        store: PathBuf,
+    },
+    /// Search for annealing parameters
+    Tune(TuneArgs),
+    /// Describe an instance
+    Stats(StatsArgs),
+    /// Tour latitude/longitude points
+    Geo(GeoArgs),
+    /// Run the REST service
+    #[cfg(feature = "server")]
+    Serve {
+        #[arg(default_value = "127.0.0.1:3000")]
+        address: String,
+    },
+    /// Run the gRPC service
+    #[cfg(feature = "grpc")]
+    Grpc {
+        #[arg(default_value = "127.0.0.1:50051")]
+        address: String,
+    },
+    /// Process queued jobs
+    #[cfg(feature = "worker")]
+    Worker(WorkerArgs),
+    /// Hand an instance to nodes on other machines and keep the best tour they find
+    #[cfg(feature = "distributed")]
+    Coordinator {
+        #[arg(default_value = "0.0.0.0:4000")]
+        address: String,
+        #[arg(default_value_t = 1)]
+        num_nodes: usize,
+        #[arg(default_value_t = 100)]
+        num_cities: u32,
+    },
+    /// Anneal for a coordinator
+    #[cfg(feature = "distributed")]
+    Node {
+        #[arg(default_value = "127.0.0.1:4000")]
+        address: String,
+    },
+}
+
+#[derive(Args)]
+struct DemoArgs {
+    /// Load a csv distance matrix, or a .json instance with the json feature, instead of
+    /// generating a map
+    #[arg(long)]
+    instance: Option<PathBuf>,
+    /// Weigh each direction of the generated map separately
+    #[arg(long)]
+    asymmetric: bool,
+    /// Make the map and the tours annealed on it the same every time
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Keep the best of n seeded runs
+    #[arg(long)]
+    runs: Option<u64>,
+    /// Run them on as many threads
+    #[arg(long, requires = "runs")]
+    parallel: bool,
+    /// Log every accepted move
+    #[arg(long)]
+    trace: Option<PathBuf>,
+    /// Skip the lower bound the cost is compared against
+    #[arg(long)]
+    no_bound: bool,
+    /// Write the tour as json
+    #[cfg(feature = "json")]
+    #[arg(long)]
+    solution_out: Option<PathBuf>,
+    /// Write the temperature and costs of every 100th iteration as csv
+    #[arg(long)]
+    convergence: Option<PathBuf>,
+}
 
-use brute_force::brute_force_tsp;
-use sim_annealing::simulated_annealing_tsp;
-use common::{generate_map, path_cost};
+#[derive(Args, Default)]
+struct SolveArgs {
+    file: PathBuf,
+    /// A solver by name, with a budget as in bench, e.g. simulated_annealing=5s
+    #[arg(long, value_parser = solver_spec)]
+    solver: Option<String>,
+    /// Seconds the chosen or given solver may take, e.g. 10s
+    #[arg(long, value_parser = seconds)]
+    budget: Option<Duration>,
+    /// Anneal until the deadline unless --iterations run out first
+    #[arg(long, value_parser = seconds)]
+    time_limit: Option<Duration>,
+    /// Annealing iterations, e.g. 1e6
+    #[arg(long, value_parser = iterations)]
+    iterations: Option<u64>,
+    /// Print why the solver was chosen
+    #[arg(long)]
+    explain: bool,
+    /// Anneal with every setting from a TOML solver config instead
+    #[cfg(feature = "config")]
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Also write the tour in TSPLIB's .tour format
+    #[arg(long)]
+    tour_out: Option<PathBuf>,
+    /// Don't draw the annealing progress bar
+    #[arg(long)]
+    no_progress: bool,
+    /// Anneal n seeds at once on as many threads, keeping the best
+    #[arg(long)]
+    parallel_restarts: Option<u64>,
+    /// Finish with 2-opt and or-opt moves until neither improves the tour
+    #[arg(long)]
+    polish: bool,
+    /// Solve n times and report the spread of the costs and times
+    #[arg(long)]
+    runs: Option<u64>,
+    /// Seed annealing, run i of --runs with seed + i
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Write every run's seed, cost and time as json
+    #[cfg(feature = "json")]
+    #[arg(long, requires = "runs")]
+    runs_json: Option<PathBuf>,
+    /// Draw the tour through a .tsp file's coordinates as SVG
+    #[arg(long)]
+    plot: Option<PathBuf>,
+    /// Skip the lower bound the cost is compared against
+    #[arg(long)]
+    no_bound: bool,
+}
+
+#[derive(Args)]
+struct BruteForceArgs {
+    file: PathBuf,
+    /// Also write the tour in TSPLIB's .tour format
+    #[arg(long)]
+    tour_out: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    #[arg(default_value_t = 10)]
+    cities: u32,
+    /// The range of the weights, as <MIN>-<MAX>
+    #[arg(long, value_parser = weight_range, default_value = "1-100")]
+    weights: (u16, u16),
+    /// Weigh each direction separately
+    #[arg(long)]
+    asymmetric: bool,
+    /// Cut every weight down to the shortest path between its cities
+    #[arg(long)]
+    metric: bool,
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Write to a file rather than the terminal
+    #[arg(long)]
+    out: Option<PathBuf>,
+    /// Place the cities in k gaussian clusters on a square and weigh them by their distances
+    #[arg(long, conflicts_with_all = ["grid", "ring"])]
+    clusters: Option<u16>,
+    #[arg(long, requires = "clusters")]
+    spread: Option<f64>,
+    #[arg(long, requires = "clusters")]
+    side: Option<f64>,
+    /// Lay the cities on a <ROWS>x<COLUMNS> lattice, printing the optima to stderr
+    #[arg(long, value_parser = grid_size, conflicts_with = "ring")]
+    grid: Option<(u16, u16)>,
+    #[arg(long, default_value_t = 10, requires = "grid")]
+    spacing: u16,
+    /// Lay the cities round a circle, printing the optima to stderr
+    #[arg(long)]
+    ring: bool,
+    #[arg(long, default_value_t = 1000., requires = "ring")]
+    radius: f64,
+    /// Write the binary map format, a plain random map streamed a row at a time
+    #[arg(long)]
+    binary: bool,
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// A directory of TSPLIB .tsp files
+    #[arg(long, default_value = ".")]
+    suite: PathBuf,
+    /// Comma separated solvers, each optionally with a budget, e.g. a,b=5s
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = solver_spec,
+        default_value = "simulated_annealing"
+    )]
+    solvers: Vec<String>,
+    #[arg(long, value_parser = iterations, default_value = "200000")]
+    iterations: u64,
+    /// Append the results to a csv store
+    #[arg(long)]
+    store: Option<PathBuf>,
+    /// Print time to target plot data for reaching this gap percent instead
+    #[arg(long, value_name = "GAP")]
+    ttt: Option<f64>,
+    #[arg(long, default_value_t = 20, requires = "ttt")]
+    runs: usize,
+    /// Warn about runs projected to need more MiB
+    #[arg(long)]
+    memory_limit: Option<usize>,
+    /// Run on random instances of these comma separated sizes instead of a suite
+    #[arg(long, value_delimiter = ',', value_name = "SIZES")]
+    generate: Vec<u32>,
+    #[arg(long, default_value_t = 1, requires = "generate")]
+    count: usize,
+    #[arg(long, default_value_t = 0, requires = "generate")]
+    seed: u64,
+    /// Run every solver n times on each instance
+    #[arg(long, default_value_t = 1)]
+    repeat: usize,
+    /// Compare the solvers head to head instead of listing every run
+    #[arg(long, conflicts_with = "paired")]
+    table: bool,
+    /// Test whether the costs of the first of two solvers are significantly lower
+    #[arg(long)]
+    paired: bool,
+}
+
+#[derive(Args)]
+struct TuneArgs {
+    #[arg(long, default_value = ".")]
+    suite: PathBuf,
+    /// Settings to try every combination of, e.g. temperature=100,200 iters=1e5,1e6
+    /// cooling=0.90,0.95 epoch=100
+    #[arg(long, num_args = 1.., value_name = "NAME=VALUES")]
+    grid: Vec<String>,
+    #[arg(long, default_value_t = 5)]
+    seeds: u64,
+    #[arg(long, default_value = "experiments.csv")]
+    store: PathBuf,
+    /// Sample n settings and narrow them down by successive halving instead
+    #[arg(long, value_name = "CANDIDATES", conflicts_with = "grid")]
+    random: Option<usize>,
+    /// The ranges to sample from, e.g. temperature=10,1000 iters=1e4,1e6 cooling=0.8,0.99
+    #[arg(long, num_args = 1.., value_name = "NAME=LOW,HIGH", requires = "random")]
+    space: Vec<String>,
+    /// The most runs the search may take
+    #[arg(long, default_value_t = 1000, requires = "random")]
+    budget: u64,
+    #[arg(long, requires = "random")]
+    seed: Option<u64>,
+}
 
-fn main() {
+#[derive(Args)]
+struct StatsArgs {
+    file: PathBuf,
+    /// Flag pairs of cities at most this far apart
+    #[arg(long, default_value_t = 0)]
+    duplicate_threshold: u16,
+    #[arg(long, default_value_t = 10)]
+    buckets: usize,
+    /// Also draw the weight histogram as a PNG
+    #[arg(long)]
+    histogram_png: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct GeoArgs {
+    file: PathBuf,
+    /// Distances on the WGS84 ellipsoid rather than a sphere
+    #[arg(long, conflicts_with = "projected")]
+    vincenty: bool,
+    /// Straight line distances after projecting the points
+    #[arg(long)]
+    projected: bool,
+    /// Draw the tour as SVG with longitude across
+    #[arg(long)]
+    plot: Option<PathBuf>,
+}
+
+#[cfg(feature = "worker")]
+#[derive(Args)]
+struct WorkerArgs {
+    #[arg(default_value = "spool")]
+    spool: PathBuf,
+    /// Wait for more jobs once the queue is empty
+    #[arg(long)]
+    poll: bool,
+    /// Requeue the jobs of a worker that stopped part way
+    #[arg(long)]
+    recover: bool,
+    /// Take the jobs from a Redis queue instead of a spool directory
+    #[cfg(feature = "redis")]
+    #[arg(
+        long,
+        num_args = 0..=1,
+        default_missing_value = "redis://127.0.0.1/"
+    )]
+    redis: Option<String>,
+}
+
+// `5s` or `5`, in seconds
+fn seconds(arg: &str) -> Result<Duration, String> {
+    arg.strip_suffix('s')
+        .unwrap_or(arg)
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("expected seconds such as 5s, found '{}'", arg))
+}
+
+// a whole number, which may be written like 1e6
+fn iterations(arg: &str) -> Result<u64, String> {
+    arg.parse::<f64>()
+        .ok()
+        .filter(|iterations| *iterations >= 0. && iterations.fract() == 0.)
+        .map(|iterations| iterations as u64)
+        .ok_or_else(|| format!("expected a number of iterations, found '{}'", arg))
+}
+
+// checked with any max_iterations as that only fills in a missing budget
+fn solver_spec(arg: &str) -> Result<String, String> {
+    parse_solver(arg, TEMPERATURE, MAX_ITERATIONS)
+        .map(|_| arg.to_string())
+        .ok_or_else(|| format!("invalid solver '{}'", arg))
+}
+
+fn weight_range(arg: &str) -> Result<(u16, u16), String> {
+    arg.split_once('-')
+        .and_then(|(low, high)| low.parse().ok().zip(high.parse().ok()))
+        .ok_or_else(|| format!("expected <MIN>-<MAX>, found '{}'", arg))
+}
+
+fn grid_size(arg: &str) -> Result<(u16, u16), String> {
+    arg.split_once('x')
+        .and_then(|(rows, columns)| rows.parse().ok().zip(columns.parse().ok()))
+        .ok_or_else(|| format!("expected <ROWS>x<COLUMNS>, found '{}'", arg))
+}
+
+fn main() -> ExitCode {
     // setup logging
     env_logger::Builder::new()
         .format(|buff, record| {
@@ -22,34 +393,888 @@ fn main() {
                 record.args()
             )
         })
-        .filter(None, LevelFilter::Error)
+        .filter(None, LevelFilter::Warn)
         .init();
 
-    // generate map
-    let map = generate_map(9, (1, 10)).unwrap_or_default();
+    let cli = Cli::parse();
+    let (failure, result) = match cli.command {
+        None => ("Simulated Annealing TSP finding failed", demo(cli.demo)),
+        Some(Command::Solve(args)) => ("TSP finding failed", solve(args)),
+        Some(Command::BruteForce(args)) => (
+            "TSP finding failed",
+            solve(SolveArgs {
+                file: args.file,
+                solver: Some("brute_force".to_string()),
+                tour_out: args.tour_out,
+                ..SolveArgs::default()
+            }),
+        ),
+        Some(Command::Generate(args)) => ("Could not generate the map", generate(args)),
+        Some(Command::Bench(args)) => ("Benchmark failed", bench(args)),
+        Some(Command::Leaderboard { store }) => {
+            ("Could not read the bench store", leaderboard(&store))
+        }
+        Some(Command::Tune(args)) => ("Tuning failed", tune(args)),
+        Some(Command::Stats(args)) => ("Could not read the instance", stats(args)),
+        Some(Command::Geo(args)) => ("Geographic TSP finding failed", geo(args)),
+        #[cfg(feature = "server")]
+        Some(Command::Serve { address }) => ("Server failed", serve(&address)),
+        #[cfg(feature = "grpc")]
+        Some(Command::Grpc { address }) => ("gRPC server failed", grpc(&address)),
+        #[cfg(feature = "worker")]
+        Some(Command::Worker(args)) => ("Worker failed", worker(args)),
+        #[cfg(feature = "distributed")]
+        Some(Command::Coordinator {
+            address,
+            num_nodes,
+            num_cities,
+        }) => (
+            "Coordinator failed",
+            coordinator(&address, num_nodes, num_cities),
+        ),
+        #[cfg(feature = "distributed")]
+        Some(Command::Node { address }) => (
+            "Node failed",
+            simulated_annealing_tsp::distributed::run_node(address)
+                .map(|_| ())
+                .map_err(Into::into),
+        ),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("{}: {}", failure, err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+fn serve(address: &str) -> Result<(), Box<dyn Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(simulated_annealing_tsp::server::serve(address))?;
+    Ok(())
+}
 
-    // get the correct TSP path using brute force
-    match brute_force_tsp(&map) {
-        Err(err) => error!("Brute Force TSP finding failed: {}", err),
-        Ok((_, optimal_cost)) => {
-            println!(
-                "(Using Brute Force) The optimal path cost was {:}",
-                optimal_cost
+#[cfg(feature = "grpc")]
+fn grpc(address: &str) -> Result<(), Box<dyn Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(simulated_annealing_tsp::grpc::serve(address))?;
+    Ok(())
+}
+
+#[cfg(feature = "worker")]
+fn worker(args: WorkerArgs) -> Result<(), Box<dyn Error>> {
+    use simulated_annealing_tsp::worker::{run_worker, DirectoryQueue, WorkerOptions};
+
+    let options = WorkerOptions {
+        poll_interval: args.poll.then_some(Duration::from_secs(1)),
+        ..WorkerOptions::default()
+    };
+
+    #[cfg(feature = "redis")]
+    if let Some(url) = &args.redis {
+        let mut queue = simulated_annealing_tsp::worker::RedisQueue::open(url, "tsp")?;
+        if args.recover {
+            queue.requeue_interrupted()?;
+        }
+        let num_completed = run_worker(&mut queue, &options)?;
+        println!("Completed {} jobs", num_completed);
+        return Ok(());
+    }
+
+    let mut queue = DirectoryQueue::open(&args.spool)?;
+    if args.recover {
+        queue.requeue_interrupted()?;
+    }
+    let num_completed = run_worker(&mut queue, &options)?;
+    println!("Completed {} jobs", num_completed);
+    Ok(())
+}
+
+// every node anneals the same random instance, exchanging their best tours
+#[cfg(feature = "distributed")]
+fn coordinator(address: &str, num_nodes: usize, num_cities: u32) -> Result<(), Box<dyn Error>> {
+    use simulated_annealing_tsp::distributed::{run_coordinator, Assignment};
+    use simulated_annealing_tsp::generate_map;
+
+    let intercity_map = generate_map(num_cities, (1, 100))?;
+    let listener = std::net::TcpListener::bind(address)?;
+    let assignment = Assignment {
+        intercity_map,
+        temperature: TEMPERATURE,
+        max_iterations: MAX_ITERATIONS,
+        exchange_interval: 10000,
+    };
+    let (_, cost) = run_coordinator(listener, assignment, num_nodes)?;
+    println!("(Distributed) The optimal path cost was {:}", cost);
+    Ok(())
+}
+
+fn bench(args: BenchArgs) -> Result<(), Box<dyn Error>> {
+    use simulated_annealing_tsp::bench::{
+        compare, comparison_table, generate_instances, run_instances, run_paired,
+        run_time_to_target_suite, score, suite_instances,
+    };
+    use simulated_annealing_tsp::scoreboard::append_to_store;
+
+    let solvers: Vec<BenchSolver> = args
+        .solvers
+        .iter()
+        .map(|spec| parse_solver(spec, TEMPERATURE, args.iterations).expect("checked when parsed"))
+        .collect();
+    if args.paired && solvers.len() != 2 {
+        Cli::command()
+            .find_subcommand_mut("bench")
+            .expect("bench is a command")
+            .error(
+                ErrorKind::WrongNumberOfValues,
+                "--paired needs exactly two solvers",
             )
+            .exit();
+    }
+
+    if let Some(target_gap) = args.ttt {
+        let results = run_time_to_target_suite(
+            &args.suite,
+            target_gap,
+            TEMPERATURE,
+            args.iterations,
+            args.runs,
+        )?;
+        println!("instance,target,seconds,probability");
+        for result in &results {
+            for (seconds, probability) in result.distribution() {
+                println!(
+                    "{},{},{:.6},{:.4}",
+                    result.instance, result.target, seconds, probability
+                );
+            }
+            let num_missed = result.num_runs - result.times.len();
+            if num_missed > 0 {
+                println!(
+                    "# {}: {} of {} runs missed the target",
+                    result.instance, num_missed, result.num_runs
+                );
+            }
         }
+        return Ok(());
     }
 
-    const MAX_ITERATIONS: u64 = 200000;
-    const TEMPERATURE: u64 = 200;
+    let memory_limit = args.memory_limit.map(|mebibytes| mebibytes * 1024 * 1024);
+    let instances = if args.generate.is_empty() {
+        suite_instances(&args.suite)?
+    } else {
+        generate_instances(&args.generate, args.count, args.seed)?
+    };
 
-    // and get it using simulated annealing
-    match simulated_annealing_tsp(&map, TEMPERATURE, MAX_ITERATIONS) {
-        Err(err) => error!("Simulated Annealing TSP finding failed: {}", err),
-        Ok((_, optimal_cost)) => {
+    // e.g. `--solvers simulated_annealing=1e5,simulated_annealing=1e6 --generate 50 --count 20
+    // --paired`
+    if args.paired {
+        let ([first, second], [first_spec, second_spec]) = (&solvers[..], &args.solvers[..]) else {
+            unreachable!("checked before loading the instances");
+        };
+        let paired = run_paired(&instances, *first, *second, memory_limit, args.repeat)?;
+        let (first_mean, second_mean) = paired.mean_costs();
+        println!(
+            "{} vs {}: mean cost {:.1} vs {:.1}",
+            first_spec, second_spec, first_mean, second_mean
+        );
+        println!("{}", paired.test);
+        return Ok(());
+    }
+
+    let results = run_instances(&instances, &solvers, memory_limit, args.repeat)?;
+    if args.table {
+        print!("{}", comparison_table(&compare(&results)));
+    } else {
+        println!("instance,cities,solver,cost,optimum,gap_percent,seconds,cpu_seconds,estimated_bytes,peak_bytes");
+        for result in &results {
             println!(
-                "(Using Simulated Annealing) The optimal path cost was {:}",
-                optimal_cost
-            )
+                "{},{},{},{},{},{},{:.3},{},{},{}",
+                result.instance,
+                result.num_cities,
+                result.solver,
+                result.tour_cost,
+                result
+                    .optimum
+                    .map_or(String::new(), |optimum| optimum.to_string()),
+                result
+                    .gap()
+                    .map_or(String::new(), |gap| format!("{:.2}", gap)),
+                result.elapsed.as_secs_f64(),
+                result.cpu_time.map_or(String::new(), |cpu_time| format!(
+                    "{:.3}",
+                    cpu_time.as_secs_f64()
+                )),
+                result.estimated_memory,
+                result
+                    .peak_memory
+                    .map_or(String::new(), |peak| peak.to_string())
+            );
+        }
+    }
+    for solver_score in score(&results) {
+        println!(
+            "{}: {} instances, {} optimal, mean gap {:.2}%, max gap {:.2}%",
+            solver_score.solver,
+            solver_score.num_instances,
+            solver_score.num_optimal,
+            solver_score.mean_gap,
+            solver_score.max_gap
+        );
+    }
+    if let Some(store) = &args.store {
+        append_to_store(store, &results)?;
+    }
+    Ok(())
+}
+
+// the solvers in a bench store ranked per instance class
+fn leaderboard(store: &Path) -> Result<(), Box<dyn Error>> {
+    use simulated_annealing_tsp::scoreboard::{leaderboard, read_store};
+
+    let entries = leaderboard(&read_store(store)?);
+    if entries.is_empty() {
+        println!("No runs with a known optimum in {}", store.display());
+    }
+    let mut last_class = "";
+    for entry in &entries {
+        // the first entry of each class is its best solver
+        let best = entry.class != last_class;
+        if best {
+            println!("{} instances:", entry.class);
+            last_class = entry.class;
+        }
+        println!(
+            "  {}{}: mean gap {:.2}%, mean {:.3}s over {} instances ({} runs)",
+            entry.solver,
+            if best { " (default)" } else { "" },
+            entry.mean_gap,
+            entry.mean_seconds,
+            entry.num_instances,
+            entry.num_runs
+        );
+    }
+    Ok(())
+}
+
+// the best annealing settings per instance class, from a grid or a random search
+fn tune(args: TuneArgs) -> Result<(), Box<dyn Error>> {
+    use simulated_annealing_tsp::cooling::CoolingSchedule;
+    use simulated_annealing_tsp::tune::{
+        append_to_store, best_per_class, parse_grid, parse_space, run_grid, sample_configs,
+        successive_halving, SearchSpace, TuneConfig,
+    };
+
+    if let Some(num_candidates) = args.random {
+        let space: Vec<&str> = args.space.iter().map(String::as_str).collect();
+        let space = parse_space(&space, SearchSpace::default())?;
+        let seed = args.seed.unwrap_or_else(rand::random);
+        let candidates = sample_configs(space, num_candidates, seed);
+        let rounds = successive_halving(&args.suite, candidates, args.budget)?;
+        for round in &rounds {
+            append_to_store(&args.store, &round.results)?;
+        }
+        for (index, round) in rounds.iter().enumerate() {
+            println!(
+                "round {}, {} candidates with {} seeds",
+                index + 1,
+                round.ranking.len(),
+                round.num_seeds
+            );
+            for (config, mean_gap) in &round.ranking {
+                println!(
+                    "  temperature {}, iters {}, cooling {}, mean gap {:.2}%",
+                    config.temperature, config.max_iterations, config.cooling, mean_gap
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    let defaults = TuneConfig {
+        temperature: TEMPERATURE,
+        max_iterations: MAX_ITERATIONS,
+        cooling: CoolingSchedule::default(),
+    };
+    let grid: Vec<&str> = args.grid.iter().map(String::as_str).collect();
+    let configs = parse_grid(&grid, defaults)?;
+    let results = run_grid(&args.suite, &configs, args.seeds)?;
+    append_to_store(&args.store, &results)?;
+    println!("instance,class,temperature,max_iterations,cooling,best_cost,gap_percent");
+    for result in &results {
+        println!(
+            "{},{},{},{},{},{},{:.2}",
+            result.instance,
+            result.class,
+            result.config.temperature,
+            result.config.max_iterations,
+            result.config.cooling,
+            result.best_cost,
+            result.gap
+        );
+    }
+    for best in best_per_class(&results) {
+        println!(
+            "{}: temperature {}, iters {}, cooling {}, mean gap {:.2}% over {} instances",
+            best.class,
+            best.config.temperature,
+            best.config.max_iterations,
+            best.config.cooling,
+            best.mean_gap,
+            best.num_instances
+        );
+    }
+    Ok(())
+}
+
+// describes an instance instead of solving it
+fn stats(args: StatsArgs) -> Result<(), Box<dyn Error>> {
+    use simulated_annealing_tsp::analysis::{
+        estimate_hardness, find_near_duplicates, format_histogram, summarize, weight_histogram,
+    };
+    use simulated_annealing_tsp::io::png::write_histogram_png;
+    use simulated_annealing_tsp::io::tsplib::read_tsplib;
+
+    let instance = read_tsplib(&mut std::fs::File::open(&args.file)?)?;
+    let summary = summarize(&instance.intercity_map)?;
+    let hardness = estimate_hardness(&instance.intercity_map)?;
+    let duplicates = find_near_duplicates(&instance.intercity_map, args.duplicate_threshold)?;
+    let histogram = weight_histogram(&instance.intercity_map, args.buckets)?;
+    if let Some(png) = &args.histogram_png {
+        write_histogram_png(&mut std::fs::File::create(png)?, &histogram, 400, 200)?;
+    }
+
+    println!(
+        "{} cities, weights {} to {}, mean {:.2}, coefficient of variation {:.3}, {:.1}% symmetric",
+        summary.num_cities,
+        summary.weight_min,
+        summary.weight_max,
+        summary.weight_mean,
+        summary.coefficient_of_variation,
+        100. * summary.symmetry
+    );
+    println!(
+        "hardness {:.1}, clustering coefficient {:.3}, nearest neighbour gap {:.3}, \
+         suggesting {} annealing iterations",
+        hardness.score,
+        hardness.clustering_coefficient,
+        hardness.nearest_neighbour_gap_mean,
+        hardness.suggested_max_iterations()
+    );
+    for (i, j, weight) in &duplicates.close_pairs {
+        println!("cities {} and {} are only {} apart", i, j, weight);
+    }
+    for (i, j) in &duplicates.identical_rows {
+        println!("cities {} and {} have identical rows", i, j);
+    }
+    println!("{}", format_histogram(&histogram, 40));
+    Ok(())
+}
+
+// anneals a tour through latitude/longitude points, drawing it with longitude across if asked
+fn geo(args: GeoArgs) -> Result<(), Box<dyn Error>> {
+    use simulated_annealing_tsp::geo::{geographic_map, GeoDistance};
+    use simulated_annealing_tsp::io::points::read_named_points;
+    use simulated_annealing_tsp::sim_annealing::simulated_annealing_tsp;
+    use simulated_annealing_tsp::tour::{format_labeled_tour, normalize_path};
+    use simulated_annealing_tsp::viz::write_tour_svg;
+
+    let distance = if args.vincenty {
+        GeoDistance::Vincenty
+    } else if args.projected {
+        GeoDistance::Projected
+    } else {
+        GeoDistance::Haversine
+    };
+    let named = read_named_points(&mut std::fs::File::open(&args.file)?)?;
+    let map = geographic_map(&named.points, distance)?;
+    let (path, cost) = simulated_annealing_tsp(&map, TEMPERATURE, MAX_ITERATIONS)?;
+    let path = normalize_path(&map, &path);
+    if let Some(plot_file) = &args.plot {
+        let points: Vec<(f64, f64)> = named
+            .points
+            .iter()
+            .map(|&(latitude, longitude)| (longitude, latitude))
+            .collect();
+        write_tour_svg(
+            &mut std::fs::File::create(plot_file)?,
+            &points,
+            &named.names,
+            &path,
+            TourMode::Open,
+        )?;
+    }
+
+    println!("The tour is {} km long", cost);
+    if named.names.iter().any(|name| !name.is_empty()) {
+        println!("{}", format_labeled_tour(&map, &path, &named.names));
+    } else {
+        println!("{:?}", path);
+    }
+    Ok(())
+}
+
+// picks the solver itself unless given one
+fn solve(args: SolveArgs) -> Result<(), Box<dyn Error>> {
+    use simulated_annealing_tsp::auto::choose_solver;
+    #[cfg(feature = "json")]
+    use simulated_annealing_tsp::io::json::write_runs;
+    use simulated_annealing_tsp::io::tsplib::write_tour;
+    use simulated_annealing_tsp::io::{read_instance_coordinates, read_instance_file};
+    use simulated_annealing_tsp::multi_run::multi_run;
+    use simulated_annealing_tsp::solvers::refine::refine;
+    use simulated_annealing_tsp::stats::{cpu_time_since, process_cpu_time};
+    use simulated_annealing_tsp::tour::{normalize_path, normalize_round_trip};
+    use simulated_annealing_tsp::viz::write_tour_svg;
+    use std::io::IsTerminal;
+    use std::time::Instant;
+
+    let max_iterations = args.iterations.unwrap_or(MAX_ITERATIONS);
+    let mut solver = args
+        .solver
+        .as_deref()
+        .map(|spec| parse_solver(spec, TEMPERATURE, max_iterations).expect("checked when parsed"));
+    // a given solver stops at the budget unless its own spec sets a time limit
+    if let Some(
+        BenchSolver::SimulatedAnnealing { time_limit, .. }
+        | BenchSolver::BruteForce { time_limit }
+        | BenchSolver::BranchAndBound { time_limit },
+    ) = &mut solver
+    {
+        *time_limit = time_limit.or(args.budget);
+    }
+    if let Some(BenchSolver::Named { budget, .. }) = &mut solver {
+        budget.time_limit = budget.time_limit.or(args.budget);
+    }
+    if let (None, Some(time_limit)) = (&solver, args.time_limit) {
+        solver = Some(BenchSolver::SimulatedAnnealing {
+            temperature: TEMPERATURE,
+            max_iterations: args.iterations.unwrap_or(u64::MAX),
+            time_limit: Some(time_limit),
+        });
+    }
+    #[cfg(feature = "config")]
+    let config = match &args.config {
+        Some(file) => {
+            let config = SolverConfig::read(file)?;
+            Some(SolverConfig {
+                time_limit: args.time_limit.or(config.time_limit),
+                ..config
+            })
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "config"))]
+    let config = None::<SolverConfig>;
+    let config = match (config, args.parallel_restarts) {
+        (Some(config), Some(restarts)) => Some(config.parallel_restarts(restarts)),
+        (config, _) => config,
+    };
+    // annealing draws a bar with the best cost and the time left on a terminal
+    let show_progress = std::io::stderr().is_terminal() && !args.no_progress;
+    let solve = |solver: &BenchSolver, intercity_map: &[Vec<u32>]| {
+        let BenchSolver::SimulatedAnnealing {
+            temperature,
+            max_iterations,
+            time_limit,
+        } = *solver
+        else {
+            return solver.solve_observed(intercity_map, TourMode::Open, 1000, |_| {});
+        };
+        if let Some(restarts) = args.parallel_restarts {
+            let config = SolverConfig {
+                temperature,
+                max_iterations,
+                time_limit,
+                ..SolverConfig::default()
+            };
+            return Annealer::new(config.parallel_restarts(restarts))
+                .solve(intercity_map)
+                .map(|(path, cost, stats)| (path, cost, Some(stats)));
+        }
+        if !show_progress {
+            return solver.solve_observed(intercity_map, TourMode::Open, 1000, |_| {});
+        }
+
+        const WIDTH: usize = 30;
+        let start = Instant::now();
+        let mut last_drawn: Option<Instant> = None;
+        let result = solver.solve_observed(intercity_map, TourMode::Open, 1000, |event| {
+            // redrawing at most ten times a second
+            if last_drawn.is_some_and(|drawn| drawn.elapsed() < Duration::from_millis(100)) {
+                return;
+            }
+            last_drawn = Some(Instant::now());
+
+            // whichever of the iterations and the time limit runs out first
+            let elapsed = start.elapsed();
+            let done = (event.iteration as f64 / max_iterations as f64)
+                .max(time_limit.map_or(0., |limit| elapsed.as_secs_f64() / limit.as_secs_f64()))
+                .clamp(f64::MIN_POSITIVE, 1.);
+            let remaining = elapsed.as_secs_f64() * (1. - done) / done;
+            let filled = (done * WIDTH as f64) as usize;
+            eprint!(
+                "\r[{}{}] {:>3}% {} iterations, best cost {}, ETA {:.0}s ",
+                "#".repeat(filled),
+                "-".repeat(WIDTH - filled),
+                (done * 100.) as u32,
+                event.iteration,
+                event.best_cost,
+                remaining
+            );
+        });
+        eprintln!();
+        result
+    };
+
+    // TSPLIB's larger instances have distances past 16 bits
+    let (name, intercity_map) = read_instance_file::<u32>(&args.file)?;
+    // the solver given or chosen for the instance, none when a config anneals
+    let solver = match (&config, solver) {
+        (Some(_), _) => None,
+        (None, Some(solver)) => Some(solver),
+        (None, None) => {
+            let mut choice = choose_solver(&intercity_map, args.budget)?;
+            if let (BenchSolver::SimulatedAnnealing { max_iterations, .. }, Some(iterations)) =
+                (&mut choice.solver, args.iterations)
+            {
+                *max_iterations = iterations;
+            }
+            if args.explain {
+                println!("{}", choice);
+            }
+            Some(choice.solver)
+        }
+    };
+    // annealing given a seed is seeded with it, other solvers run as they are, annealing
+    // returning the statistics of its run
+    let solve_once = |seed: Option<u64>| match (&config, solver) {
+        (Some(config), _) => Annealer::new(SolverConfig {
+            seed: seed.or(config.seed),
+            ..config.clone()
+        })
+        .solve(&intercity_map)
+        .map(|(path, cost, stats)| (path, cost, Some(stats))),
+        (
+            None,
+            Some(BenchSolver::SimulatedAnnealing {
+                temperature,
+                max_iterations,
+                time_limit,
+            }),
+        ) if seed.is_some() => Annealer::new(SolverConfig {
+            temperature,
+            max_iterations,
+            time_limit,
+            seed,
+            ..SolverConfig::default()
+        })
+        .solve(&intercity_map)
+        .map(|(path, cost, stats)| (path, cost, Some(stats))),
+        (None, Some(solver)) => solve(&solver, &intercity_map),
+        (None, None) => unreachable!("a solver is chosen unless a config is given"),
+    };
+    let start = Instant::now();
+    let cpu_start = process_cpu_time();
+    // run i of `--runs` seeded with seed + i, which reports the runs rather than their statistics
+    let (path, cost, stats) = match args.runs {
+        None => solve_once(args.seed)?,
+        Some(num_runs) => {
+            let seed = args.seed.unwrap_or_else(rand::random);
+            let runs = multi_run(num_runs, seed, |seed| {
+                solve_once(Some(seed)).map(|(path, cost, _)| (path, cost))
+            })?;
+            println!("{}", runs);
+            #[cfg(feature = "json")]
+            if let Some(file) = &args.runs_json {
+                write_runs(&mut std::fs::File::create(file)?, &runs)?;
+            }
+            let cost = runs.best_cost();
+            (runs.best_path, cost, None)
+        }
+    };
+    let (elapsed, cpu_time) = (start.elapsed(), cpu_time_since(cpu_start));
+    let mode = config.as_ref().map_or(TourMode::Open, |config| config.mode);
+    let (path, cost) = if args.polish {
+        refine(&intercity_map, path, mode)?
+    } else {
+        (path, cost)
+    };
+    // round trips have no ends to keep, so they start at city 0
+    let path = match mode {
+        TourMode::Open => normalize_path(&intercity_map, &path),
+        TourMode::Closed => normalize_round_trip(&intercity_map, &path, 0),
+    };
+    if let Some(tour_file) = &args.tour_out {
+        write_tour(&mut std::fs::File::create(tour_file)?, &name, &path, cost)?;
+    }
+    if let Some(plot_file) = &args.plot {
+        let coordinates = read_instance_coordinates(&args.file)?;
+        write_tour_svg(
+            &mut std::fs::File::create(plot_file)?,
+            &coordinates,
+            &[],
+            &path,
+            mode,
+        )?;
+    }
+
+    println!("The path cost was {}\n{:?}", cost, path);
+    if !args.no_bound {
+        let bound = spanning_tree_bound(&intercity_map)?;
+        println!(
+            "cost {}, bound {}, gap {:.2}%",
+            cost,
+            bound,
+            gap_percent(cost, bound)
+        );
+    }
+    // annealing's statistics include its times, every other solve reports them on their own
+    match (stats, cpu_time) {
+        (Some(stats), _) => println!("{}", stats),
+        (None, Some(cpu_time)) => println!(
+            "wall time {:.3}s, cpu time {:.3}s",
+            elapsed.as_secs_f64(),
+            cpu_time.as_secs_f64()
+        ),
+        (None, None) => println!("wall time {:.3}s", elapsed.as_secs_f64()),
+    }
+    Ok(())
+}
+
+// writes a random map as a csv matrix or in the binary format, to the terminal without `--out`
+fn generate(args: GenerateArgs) -> Result<(), Box<dyn Error>> {
+    use simulated_annealing_tsp::generators::{
+        generate_asymmetric_metric_map_with_rng, generate_clustered_map_with_rng,
+        generate_metric_map_with_rng, grid_instance, ring_instance, Clustered,
+    };
+    use simulated_annealing_tsp::io::binary::{stream_generated_map, write_map};
+    use simulated_annealing_tsp::io::csv::write_matrix;
+
+    let mut out: Box<dyn Write> = match &args.out {
+        Some(out) => Box::new(std::io::BufWriter::new(std::fs::File::create(out)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    // a plain random map never has to be held in memory, however many cities it has
+    let plain = !(args.asymmetric || args.metric || args.ring)
+        && args.grid.is_none()
+        && args.clusters.is_none();
+    if args.binary && plain {
+        let seed = args.seed.unwrap_or_else(rand::random);
+        stream_generated_map(&mut out, args.cities, args.weights, seed)?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    let mut rng = args
+        .seed
+        .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+    let known = if let Some((rows, columns)) = args.grid {
+        Some(grid_instance(rows, columns, args.spacing)?)
+    } else if args.ring {
+        Some(ring_instance(args.cities, args.radius)?)
+    } else {
+        None
+    };
+    let map = if let Some(known) = known {
+        eprintln!(
+            "optimal open path {}, round trip {}",
+            known.optimum(TourMode::Open),
+            known.optimum(TourMode::Closed)
+        );
+        known.intercity_map
+    } else if let Some(num_clusters) = args.clusters {
+        let default = Clustered::default();
+        let clustered = Clustered {
+            num_clusters,
+            side: args.side.unwrap_or(default.side),
+            spread: args.spread.unwrap_or(default.spread),
+        };
+        generate_clustered_map_with_rng(args.cities, &clustered, &mut rng)?
+    } else if args.metric && args.asymmetric {
+        generate_asymmetric_metric_map_with_rng(args.cities, args.weights, &mut rng)?
+    } else if args.metric {
+        generate_metric_map_with_rng(args.cities, args.weights, &mut rng)?
+    } else if args.asymmetric {
+        generate_asymmetric_map_with_rng(args.cities, args.weights, &mut rng)?
+    } else {
+        generate_map_with_rng(args.cities, args.weights, &mut rng)?
+    };
+    if args.binary {
+        write_map(&mut out, &map)?;
+    } else {
+        write_matrix(&mut out, &map)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// solves a random map, or the one given, by brute force and by simulated annealing
+fn demo(args: DemoArgs) -> Result<(), Box<dyn Error>> {
+    let mut rng = args
+        .seed
+        .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+    let map = match &args.instance {
+        Some(file_name) => {
+            let mut file = std::fs::File::open(file_name)?;
+            #[cfg(feature = "json")]
+            if file_name
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                simulated_annealing_tsp::io::json::read_instance(&mut file)?.to_rows()
+            } else {
+                read_matrix(&mut file)?
+            }
+            #[cfg(not(feature = "json"))]
+            read_matrix(&mut file)?
+        }
+        None if args.asymmetric => generate_asymmetric_map_with_rng(9, (1, 10), &mut rng)?,
+        None => generate_map_with_rng(9, (1, 10), &mut rng)?,
+    };
+
+    let (_, optimal_cost) = brute_force_tsp(&map)?;
+    println!(
+        "(Using Brute Force) The optimal path cost was {:}",
+        optimal_cost
+    );
+
+    let print_bound = |cost| {
+        if args.no_bound {
+            return;
+        }
+        if let Ok(bound) = spanning_tree_bound(&map) {
+            println!(
+                "cost {}, bound {}, gap {:.2}%",
+                cost,
+                bound,
+                gap_percent(cost, bound)
+            );
+        }
+    };
+
+    let config = SolverConfig {
+        temperature: TEMPERATURE,
+        max_iterations: MAX_ITERATIONS,
+        ..SolverConfig::default()
+    };
+    // the runs seeded in turn from the seed, the best of them kept
+    if let Some(num_runs) = args.runs {
+        let seed = args.seed.unwrap_or_else(rand::random);
+        let config = SolverConfig {
+            seed: Some(seed),
+            ..config
+        };
+        let config = if args.parallel {
+            config.parallel_restarts(num_runs)
+        } else {
+            SolverConfig {
+                runs: num_runs,
+                ..config
+            }
+        };
+        let (_, cost, stats) = Annealer::new(config).solve(&map)?;
+        println!(
+            "(Using Simulated Annealing) The optimal path cost was {:}",
+            cost
+        );
+        print_bound(cost);
+        println!("{}\nbest of {} runs (seed {})", stats, num_runs, seed);
+        return Ok(());
+    }
+    if let Some(convergence_file) = &args.convergence {
+        let mut convergence = ConvergenceTrace::new(100);
+        let (_, cost, _) = Annealer::new(config.clone())
+            .convergence(&mut convergence)
+            .solve(&map)?;
+        println!(
+            "(Using Simulated Annealing) The optimal path cost was {:}",
+            cost
+        );
+        print_bound(cost);
+        convergence.write_csv(&mut std::fs::File::create(convergence_file)?)?;
+        return Ok(());
+    }
+
+    let mut trace = args.trace.as_ref().map(std::fs::File::create).transpose()?;
+    let annealer = Annealer::new(SolverConfig {
+        seed: args.seed,
+        ..config
+    });
+    let (path, optimal_cost, stats) = match trace.as_mut() {
+        Some(trace) => annealer.trace(trace).solve(&map)?,
+        None => annealer.solve(&map)?,
+    };
+    println!(
+        "(Using Simulated Annealing) The optimal path cost was {:}",
+        optimal_cost
+    );
+    print_bound(optimal_cost);
+    println!("{}", stats);
+
+    #[cfg(feature = "json")]
+    if let Some(solution_file) = &args.solution_out {
+        use simulated_annealing_tsp::io::json::{write_solution, SolutionReport};
+        use simulated_annealing_tsp::tour::normalize_path;
+
+        let report = SolutionReport {
+            path: normalize_path(&map, &path),
+            cost: optimal_cost,
+            solver: "simulated_annealing".to_string(),
+            num_cities: map.len(),
+            seconds: stats.elapsed.as_secs_f64(),
+        };
+        write_solution(&mut std::fs::File::create(solution_file)?, &report)?;
+    }
+    #[cfg(not(feature = "json"))]
+    let _ = path;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cli, Command};
+    use clap::{CommandFactory, Parser};
+    use std::time::Duration;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from([
+            "tsp",
+            "solve",
+            "a.tsp",
+            "--solver",
+            "simulated_annealing=5s",
+            "--iterations",
+            "1e6",
+            "--time-limit",
+            "2.5s",
+        ])
+        .ok()
+        .unwrap();
+        let Some(Command::Solve(args)) = cli.command else {
+            panic!("expected solve");
+        };
+        assert_eq!(args.iterations, Some(1000000));
+        assert_eq!(args.time_limit, Some(Duration::from_millis(2500)));
+
+        // unknown and malformed flags are errors rather than ignored
+        for args in [
+            &["tsp", "solve", "a.tsp", "--itrations", "5"][..],
+            &["tsp", "solve", "a.tsp", "--iterations", "lots"],
+            &["tsp", "solve", "a.tsp", "--solver", "simulated_anealing"],
+            &["tsp", "solve", "a.tsp", "--budget", "-1s"],
+            &["tsp", "generate", "10", "--weights", "100"],
+            &["tsp", "generate", "--grid", "3x4", "--ring"],
+            &["tsp", "bench", "--solvers", "simulated_annealing,genetc"],
+            &["tsp", "frobnicate"],
+            &["tsp", "--seed", "1", "solve", "a.tsp"],
+        ] {
+            assert!(Cli::try_parse_from(args).is_err(), "{:?}", args);
         }
     }
 }
@@ -0,0 +1,561 @@
+// minimum weight perfect matching on a complete graph by Edmonds' blossom algorithm, the
+// O(n^3) primal-dual version of Galil's survey, as done by the well known mwmatching.py: it finds
+// a maximum weight matching of the greatest cardinality, so the weights are flipped around the
+// heaviest edge to make the lightest perfect matching the heaviest one
+
+const NONE: usize = usize::MAX;
+
+// the vertex each vertex is matched to, num_vertices must be even
+pub fn min_weight_perfect_matching(
+    num_vertices: usize,
+    weight: impl Fn(usize, usize) -> u32,
+) -> Vec<usize> {
+    assert!(
+        num_vertices.is_multiple_of(2),
+        "an odd number of vertices has no perfect matching"
+    );
+    let mut edges = Vec::with_capacity(num_vertices * num_vertices.saturating_sub(1) / 2);
+    for i in 0..num_vertices {
+        for j in i + 1..num_vertices {
+            edges.push((i, j, weight(i, j) as i64));
+        }
+    }
+    let heaviest = edges.iter().map(|&(_, _, w)| w).max().unwrap_or(0);
+    for edge in &mut edges {
+        edge.2 = heaviest + 1 - edge.2;
+    }
+    Blossom::new(num_vertices, edges).solve()
+}
+
+// the state of the search, named as in mwmatching.py, where an endpoint p is the end
+// edges[p / 2].(p % 2) and the vertices are 0..n while blossoms take n..2n
+struct Blossom {
+    n: usize,
+    edges: Vec<(usize, usize, i64)>,
+    endpoint: Vec<usize>,
+    neighbend: Vec<Vec<usize>>,
+    mate: Vec<usize>,
+    label: Vec<u8>,
+    labelend: Vec<usize>,
+    inblossom: Vec<usize>,
+    blossomparent: Vec<usize>,
+    blossomchilds: Vec<Vec<usize>>,
+    blossombase: Vec<usize>,
+    blossomendps: Vec<Vec<usize>>,
+    bestedge: Vec<usize>,
+    blossombestedges: Vec<Option<Vec<usize>>>,
+    unusedblossoms: Vec<usize>,
+    dualvar: Vec<i64>,
+    allowedge: Vec<bool>,
+    queue: Vec<usize>,
+}
+
+impl Blossom {
+    fn new(n: usize, edges: Vec<(usize, usize, i64)>) -> Self {
+        let maxweight = edges.iter().map(|&(_, _, w)| w).max().unwrap_or(0).max(0);
+        let endpoint = edges.iter().flat_map(|&(i, j, _)| [i, j]).collect();
+        let mut neighbend = vec![Vec::new(); n];
+        for (k, &(i, j, _)) in edges.iter().enumerate() {
+            neighbend[i].push(2 * k + 1);
+            neighbend[j].push(2 * k);
+        }
+        let mut dualvar = vec![maxweight; n];
+        dualvar.resize(2 * n, 0);
+        let num_edges = edges.len();
+        Blossom {
+            n,
+            edges,
+            endpoint,
+            neighbend,
+            mate: vec![NONE; n],
+            label: vec![0; 2 * n],
+            labelend: vec![NONE; 2 * n],
+            inblossom: (0..n).collect(),
+            blossomparent: vec![NONE; 2 * n],
+            blossomchilds: vec![Vec::new(); 2 * n],
+            blossombase: (0..n).chain(std::iter::repeat_n(NONE, n)).collect(),
+            blossomendps: vec![Vec::new(); 2 * n],
+            bestedge: vec![NONE; 2 * n],
+            blossombestedges: vec![None; 2 * n],
+            unusedblossoms: (n..2 * n).collect(),
+            dualvar,
+            allowedge: vec![false; num_edges],
+            queue: Vec::new(),
+        }
+    }
+
+    fn slack(&self, k: usize) -> i64 {
+        let (i, j, wt) = self.edges[k];
+        self.dualvar[i] + self.dualvar[j] - 2 * wt
+    }
+
+    fn leaves(&self, b: usize) -> Vec<usize> {
+        if b < self.n {
+            return vec![b];
+        }
+        self.blossomchilds[b]
+            .iter()
+            .flat_map(|&child| self.leaves(child))
+            .collect()
+    }
+
+    fn assign_label(&mut self, w: usize, t: u8, p: usize) {
+        let b = self.inblossom[w];
+        self.label[w] = t;
+        self.label[b] = t;
+        self.labelend[w] = p;
+        self.labelend[b] = p;
+        self.bestedge[w] = NONE;
+        self.bestedge[b] = NONE;
+        if t == 1 {
+            let leaves = self.leaves(b);
+            self.queue.extend(leaves);
+        } else {
+            let base = self.blossombase[b];
+            let mate = self.mate[base];
+            self.assign_label(self.endpoint[mate], 1, mate ^ 1);
+        }
+    }
+
+    // follows the alternating trees back from v and w, to their common blossom base if they
+    // meet, or to NONE if they are different trees and so an augmenting path
+    fn scan_blossom(&mut self, mut v: usize, mut w: usize) -> usize {
+        let mut path = Vec::new();
+        let mut base = NONE;
+        while v != NONE || w != NONE {
+            let mut b = self.inblossom[v];
+            if self.label[b] & 4 != 0 {
+                base = self.blossombase[b];
+                break;
+            }
+            path.push(b);
+            self.label[b] = 5;
+            if self.labelend[b] == NONE {
+                v = NONE;
+            } else {
+                v = self.endpoint[self.labelend[b]];
+                b = self.inblossom[v];
+                v = self.endpoint[self.labelend[b]];
+            }
+            if w != NONE {
+                std::mem::swap(&mut v, &mut w);
+            }
+        }
+        for b in path {
+            self.label[b] = 1;
+        }
+        base
+    }
+
+    fn add_blossom(&mut self, base: usize, k: usize) {
+        let (mut v, mut w, _) = self.edges[k];
+        let bb = self.inblossom[base];
+        let mut bv = self.inblossom[v];
+        let mut bw = self.inblossom[w];
+        let b = self.unusedblossoms.pop().unwrap();
+        self.blossombase[b] = base;
+        self.blossomparent[b] = NONE;
+        self.blossomparent[bb] = b;
+
+        let mut path = Vec::new();
+        let mut endps = Vec::new();
+        while bv != bb {
+            self.blossomparent[bv] = b;
+            path.push(bv);
+            endps.push(self.labelend[bv]);
+            v = self.endpoint[self.labelend[bv]];
+            bv = self.inblossom[v];
+        }
+        path.push(bb);
+        path.reverse();
+        endps.reverse();
+        endps.push(2 * k);
+        while bw != bb {
+            self.blossomparent[bw] = b;
+            path.push(bw);
+            endps.push(self.labelend[bw] ^ 1);
+            w = self.endpoint[self.labelend[bw]];
+            bw = self.inblossom[w];
+        }
+
+        self.blossomchilds[b] = path.clone();
+        self.blossomendps[b] = endps;
+        self.label[b] = 1;
+        self.labelend[b] = self.labelend[bb];
+        self.dualvar[b] = 0;
+        for v in self.leaves(b) {
+            if self.label[self.inblossom[v]] == 2 {
+                self.queue.push(v);
+            }
+            self.inblossom[v] = b;
+        }
+
+        let mut bestedgeto = vec![NONE; 2 * self.n];
+        for &bv in &path {
+            let nblists: Vec<Vec<usize>> = match self.blossombestedges[bv].take() {
+                Some(nblist) => vec![nblist],
+                None => self
+                    .leaves(bv)
+                    .into_iter()
+                    .map(|v| self.neighbend[v].iter().map(|p| p / 2).collect())
+                    .collect(),
+            };
+            for k in nblists.into_iter().flatten() {
+                let (mut i, mut j, _) = self.edges[k];
+                if self.inblossom[j] == b {
+                    std::mem::swap(&mut i, &mut j);
+                }
+                let bj = self.inblossom[j];
+                if bj != b
+                    && self.label[bj] == 1
+                    && (bestedgeto[bj] == NONE || self.slack(k) < self.slack(bestedgeto[bj]))
+                {
+                    bestedgeto[bj] = k;
+                }
+            }
+            self.bestedge[bv] = NONE;
+        }
+        let bestedges: Vec<usize> = bestedgeto.into_iter().filter(|&k| k != NONE).collect();
+        self.bestedge[b] = bestedges
+            .iter()
+            .copied()
+            .min_by_key(|&k| self.slack(k))
+            .unwrap_or(NONE);
+        self.blossombestedges[b] = Some(bestedges);
+    }
+
+    fn expand_blossom(&mut self, b: usize, endstage: bool) {
+        for s in self.blossomchilds[b].clone() {
+            self.blossomparent[s] = NONE;
+            if s < self.n {
+                self.inblossom[s] = s;
+            } else if endstage && self.dualvar[s] == 0 {
+                self.expand_blossom(s, endstage);
+            } else {
+                for v in self.leaves(s) {
+                    self.inblossom[v] = s;
+                }
+            }
+        }
+
+        if !endstage && self.label[b] == 2 {
+            // relabels the children on the even length path from the entry to the base
+            let childs = self.blossomchilds[b].clone();
+            let endps = self.blossomendps[b].clone();
+            let len = childs.len() as isize;
+            let at = |j: isize| j.rem_euclid(len) as usize;
+            let entrychild = self.inblossom[self.endpoint[self.labelend[b] ^ 1]];
+            let mut j = childs.iter().position(|&c| c == entrychild).unwrap() as isize;
+            let (jstep, endptrick) = if j & 1 != 0 {
+                j -= len;
+                (1, 0)
+            } else {
+                (-1, 1)
+            };
+            let mut p = self.labelend[b];
+            while j != 0 {
+                self.label[self.endpoint[p ^ 1]] = 0;
+                let q = endps[at(j - endptrick)] ^ endptrick as usize ^ 1;
+                self.label[self.endpoint[q]] = 0;
+                self.assign_label(self.endpoint[p ^ 1], 2, p);
+                self.allowedge[endps[at(j - endptrick)] / 2] = true;
+                j += jstep;
+                p = endps[at(j - endptrick)] ^ endptrick as usize;
+                self.allowedge[p / 2] = true;
+                j += jstep;
+            }
+            let bv = childs[at(j)];
+            self.label[self.endpoint[p ^ 1]] = 2;
+            self.label[bv] = 2;
+            self.labelend[self.endpoint[p ^ 1]] = p;
+            self.labelend[bv] = p;
+            self.bestedge[bv] = NONE;
+            j += jstep;
+            while childs[at(j)] != entrychild {
+                let bv = childs[at(j)];
+                j += jstep;
+                if self.label[bv] == 1 {
+                    continue;
+                }
+                if let Some(v) = self.leaves(bv).into_iter().find(|&v| self.label[v] != 0) {
+                    self.label[v] = 0;
+                    let mate = self.mate[self.blossombase[bv]];
+                    self.label[self.endpoint[mate]] = 0;
+                    self.assign_label(v, 2, self.labelend[v]);
+                }
+            }
+        }
+
+        self.label[b] = 0;
+        self.labelend[b] = NONE;
+        self.blossomchilds[b].clear();
+        self.blossomendps[b].clear();
+        self.blossombase[b] = NONE;
+        self.blossombestedges[b] = None;
+        self.bestedge[b] = NONE;
+        self.unusedblossoms.push(b);
+    }
+
+    // swaps matched and unmatched edges on the path through blossom b from v to its base
+    fn augment_blossom(&mut self, b: usize, v: usize) {
+        let mut t = v;
+        while self.blossomparent[t] != b {
+            t = self.blossomparent[t];
+        }
+        if t >= self.n {
+            self.augment_blossom(t, v);
+        }
+
+        let childs = self.blossomchilds[b].clone();
+        let endps = self.blossomendps[b].clone();
+        let len = childs.len() as isize;
+        let at = |j: isize| j.rem_euclid(len) as usize;
+        let i = childs.iter().position(|&c| c == t).unwrap();
+        let mut j = i as isize;
+        let (jstep, endptrick) = if j & 1 != 0 {
+            j -= len;
+            (1, 0)
+        } else {
+            (-1, 1)
+        };
+        while j != 0 {
+            j += jstep;
+            let t = childs[at(j)];
+            let p = endps[at(j - endptrick)] ^ endptrick as usize;
+            if t >= self.n {
+                self.augment_blossom(t, self.endpoint[p]);
+            }
+            j += jstep;
+            let t = childs[at(j)];
+            if t >= self.n {
+                self.augment_blossom(t, self.endpoint[p ^ 1]);
+            }
+            self.mate[self.endpoint[p]] = p ^ 1;
+            self.mate[self.endpoint[p ^ 1]] = p;
+        }
+        self.blossomchilds[b].rotate_left(i);
+        self.blossomendps[b].rotate_left(i);
+        self.blossombase[b] = self.blossombase[self.blossomchilds[b][0]];
+    }
+
+    fn augment_matching(&mut self, k: usize) {
+        let (v, w, _) = self.edges[k];
+        for (mut s, mut p) in [(v, 2 * k + 1), (w, 2 * k)] {
+            loop {
+                let bs = self.inblossom[s];
+                if bs >= self.n {
+                    self.augment_blossom(bs, s);
+                }
+                self.mate[s] = p;
+                if self.labelend[bs] == NONE {
+                    break;
+                }
+                let t = self.endpoint[self.labelend[bs]];
+                let bt = self.inblossom[t];
+                s = self.endpoint[self.labelend[bt]];
+                let j = self.endpoint[self.labelend[bt] ^ 1];
+                if bt >= self.n {
+                    self.augment_blossom(bt, j);
+                }
+                self.mate[j] = self.labelend[bt];
+                p = self.labelend[bt] ^ 1;
+            }
+        }
+    }
+
+    fn solve(mut self) -> Vec<usize> {
+        let n = self.n;
+        for _ in 0..n {
+            // a stage, each looking for one augmenting path
+            self.label.fill(0);
+            self.bestedge.fill(NONE);
+            for bestedges in &mut self.blossombestedges[n..] {
+                *bestedges = None;
+            }
+            self.allowedge.fill(false);
+            self.queue.clear();
+            for v in 0..n {
+                if self.mate[v] == NONE && self.label[self.inblossom[v]] == 0 {
+                    self.assign_label(v, 1, NONE);
+                }
+            }
+
+            let mut augmented = false;
+            loop {
+                while let Some(v) = self.queue.pop() {
+                    for p in self.neighbend[v].clone() {
+                        let k = p / 2;
+                        let w = self.endpoint[p];
+                        if self.inblossom[v] == self.inblossom[w] {
+                            continue;
+                        }
+                        let mut kslack = 0;
+                        if !self.allowedge[k] {
+                            kslack = self.slack(k);
+                            if kslack <= 0 {
+                                self.allowedge[k] = true;
+                            }
+                        }
+                        if self.allowedge[k] {
+                            if self.label[self.inblossom[w]] == 0 {
+                                self.assign_label(w, 2, p ^ 1);
+                            } else if self.label[self.inblossom[w]] == 1 {
+                                let base = self.scan_blossom(v, w);
+                                if base != NONE {
+                                    self.add_blossom(base, k);
+                                } else {
+                                    self.augment_matching(k);
+                                    augmented = true;
+                                    break;
+                                }
+                            } else if self.label[w] == 0 {
+                                self.label[w] = 2;
+                                self.labelend[w] = p ^ 1;
+                            }
+                        } else if self.label[self.inblossom[w]] == 1 {
+                            let b = self.inblossom[v];
+                            if self.bestedge[b] == NONE || kslack < self.slack(self.bestedge[b]) {
+                                self.bestedge[b] = k;
+                            }
+                        } else if self.label[w] == 0
+                            && (self.bestedge[w] == NONE || kslack < self.slack(self.bestedge[w]))
+                        {
+                            self.bestedge[w] = k;
+                        }
+                    }
+                    if augmented {
+                        break;
+                    }
+                }
+                if augmented {
+                    break;
+                }
+
+                // no augmenting path with the tight edges, the duals are moved by the least
+                // delta that makes another edge tight or a blossom's dual zero
+                let mut delta: Option<(i64, u8, usize)> = None;
+                let mut consider = |d: i64, kind: u8, at: usize| {
+                    if delta.is_none_or(|(best, _, _)| d < best) {
+                        delta = Some((d, kind, at));
+                    }
+                };
+                for v in 0..n {
+                    if self.label[self.inblossom[v]] == 0 && self.bestedge[v] != NONE {
+                        consider(self.slack(self.bestedge[v]), 2, self.bestedge[v]);
+                    }
+                }
+                for b in 0..2 * n {
+                    if self.blossomparent[b] == NONE
+                        && self.label[b] == 1
+                        && self.bestedge[b] != NONE
+                    {
+                        consider(self.slack(self.bestedge[b]) / 2, 3, self.bestedge[b]);
+                    }
+                }
+                for b in n..2 * n {
+                    if self.blossombase[b] != NONE
+                        && self.blossomparent[b] == NONE
+                        && self.label[b] == 2
+                    {
+                        consider(self.dualvar[b], 4, b);
+                    }
+                }
+                let (delta, kind, at) = delta.unwrap_or_else(|| {
+                    let least = self.dualvar[..n].iter().copied().min().unwrap_or(0);
+                    (least.max(0), 1, NONE)
+                });
+
+                for v in 0..n {
+                    match self.label[self.inblossom[v]] {
+                        1 => self.dualvar[v] -= delta,
+                        2 => self.dualvar[v] += delta,
+                        _ => {}
+                    }
+                }
+                for b in n..2 * n {
+                    if self.blossombase[b] != NONE && self.blossomparent[b] == NONE {
+                        match self.label[b] {
+                            1 => self.dualvar[b] += delta,
+                            2 => self.dualvar[b] -= delta,
+                            _ => {}
+                        }
+                    }
+                }
+
+                match kind {
+                    1 => break,
+                    2 => {
+                        self.allowedge[at] = true;
+                        let (mut i, j, _) = self.edges[at];
+                        if self.label[self.inblossom[i]] == 0 {
+                            i = j;
+                        }
+                        self.queue.push(i);
+                    }
+                    3 => {
+                        self.allowedge[at] = true;
+                        self.queue.push(self.edges[at].0);
+                    }
+                    _ => self.expand_blossom(at, false),
+                }
+            }
+            if !augmented {
+                break;
+            }
+
+            for b in n..2 * n {
+                if self.blossomparent[b] == NONE
+                    && self.blossombase[b] != NONE
+                    && self.label[b] == 1
+                    && self.dualvar[b] == 0
+                {
+                    self.expand_blossom(b, true);
+                }
+            }
+        }
+
+        self.mate.iter().map(|&p| self.endpoint[p]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matching::min_weight_perfect_matching;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    // the cheapest perfect matching by trying every one, matching the lowest free vertex first
+    fn brute_force_matching(weight: &dyn Fn(usize, usize) -> u32, free: u32) -> u32 {
+        if free == 0 {
+            return 0;
+        }
+        let first = free.trailing_zeros() as usize;
+        let rest = free & !(1 << first);
+        (0..32)
+            .filter(|&other| rest & (1 << other) != 0)
+            .map(|other| weight(first, other) + brute_force_matching(weight, rest & !(1 << other)))
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_min_weight_perfect_matching() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for num_vertices in [0, 2, 4, 6, 8, 10, 12] {
+            for _ in 0..20 {
+                let random: Vec<Vec<u32>> = (0..num_vertices)
+                    .map(|_| (0..num_vertices).map(|_| rng.gen_range(1..100)).collect())
+                    .collect();
+                let weight = |i: usize, j: usize| random[i.min(j)][i.max(j)];
+                let mate = min_weight_perfect_matching(num_vertices, weight);
+                let mut cost = 0;
+                for (v, &m) in mate.iter().enumerate() {
+                    assert_ne!(v, m);
+                    assert_eq!(mate[m], v);
+                    cost += weight(v, m);
+                }
+                let all = (1u32 << num_vertices) - 1;
+                assert_eq!(cost / 2, brute_force_matching(&weight, all));
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::mem::size_of;
+
+use crate::common::{check_city_map, TSPError};
+use crate::cost::{CostFunction, MatrixCost, TourMode};
+use crate::simd::chunked_path_weight;
+use crate::weight::{Cost, Weight};
+
+// a validated square matrix of weights stored row after row in one allocation, weight [i][j]
+// being at i * n + j, so walking a tour reads from one block of memory instead of following a
+// pointer to every row as a Vec<Vec<W>> does, symmetric matrices keeping only the upper triangle
+// and the diagonal, n (n + 1) / 2 weights rather than n^2, which halves the memory of large
+// instances, [i][j] and [j][i] both being read from row min(i, j)
+#[derive(Clone, PartialEq, Debug)]
+pub struct DistanceMatrix<W = u16> {
+    weights: Vec<W>,
+    num_cities: usize,
+    symmetric: bool, // and so triangular
+}
+
+impl<W: Weight> DistanceMatrix<W> {
+    pub fn new(rows: Vec<Vec<W>>) -> Result<DistanceMatrix<W>, TSPError> {
+        check_city_map(&rows)?;
+        let symmetric = MatrixCost::new(&rows).is_symmetric();
+        let weights = if symmetric {
+            rows.iter()
+                .enumerate()
+                .flat_map(|(row, weights)| weights[row..].iter().copied())
+                .collect()
+        } else {
+            rows.iter().flatten().copied().collect()
+        };
+        Ok(DistanceMatrix {
+            num_cities: rows.len(),
+            weights,
+            symmetric,
+        })
+    }
+
+    pub fn num_cities(&self) -> usize {
+        self.num_cities
+    }
+
+    pub fn get(&self, from: u32, to: u32) -> W {
+        let (from, to) = (from as usize, to as usize);
+        if !self.symmetric {
+            return self.weights[from * self.num_cities + to];
+        }
+        // the rows before row take n, n - 1, ... n - row + 1 weights
+        let (row, column) = if from <= to { (from, to) } else { (to, from) };
+        self.weights[row * (2 * self.num_cities - row + 1) / 2 + column - row]
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        self.symmetric
+    }
+
+    // including the return edge for closed tours
+    pub fn path_cost(&self, path: &[u32], mode: TourMode) -> W::Cost {
+        let return_edge = match (mode, path.first(), path.last()) {
+            (TourMode::Closed, Some(&first), Some(&last)) => self.get(last, first).to_cost(),
+            _ => W::Cost::ZERO,
+        };
+        self.path_weight(path) + return_edge
+    }
+
+    // what the matrix takes up in memory
+    pub fn bytes(&self) -> usize {
+        size_of::<DistanceMatrix<W>>() + self.weights.len() * size_of::<W>()
+    }
+
+    // the nested rows it was made from
+    pub fn to_rows(&self) -> Vec<Vec<W>> {
+        let cities = 0..self.num_cities as u32;
+        cities
+            .clone()
+            .map(|from| cities.clone().map(|to| self.get(from, to)).collect())
+            .collect()
+    }
+}
+
+impl<W: Weight> CostFunction<W> for DistanceMatrix<W> {
+    fn cost(&self, from: u32, to: u32) -> W {
+        self.get(from, to)
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.symmetric
+    }
+
+    // summed in lanes with the `simd` feature, see simd
+    fn path_weight(&self, path: &[u32]) -> W::Cost {
+        if cfg!(feature = "simd") && W::INTEGRAL {
+            return chunked_path_weight(|from, to| self.get(from, to), path);
+        }
+        path.windows(2)
+            .map(|edge| self.get(edge[0], edge[1]).to_cost())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{generate_asymmetric_map, generate_default_path, generate_map, TSPError};
+    use crate::cost::TourMode;
+    use crate::matrix::DistanceMatrix;
+
+    #[test]
+    fn test_distance_matrix() {
+        let map = generate_asymmetric_map(9, (1, 100)).ok().unwrap();
+        let matrix = DistanceMatrix::new(map.clone()).ok().unwrap();
+        assert_eq!(matrix.num_cities(), 9);
+        assert!(!matrix.is_symmetric());
+        for from in 0..9 {
+            for to in 0..9 {
+                assert_eq!(matrix.get(from, to), map[from as usize][to as usize]);
+            }
+        }
+        assert_eq!(matrix.to_rows(), map);
+
+        let path = generate_default_path(&map);
+        for mode in [TourMode::Open, TourMode::Closed] {
+            assert_eq!(matrix.path_cost(&path, mode), mode.cost(&map, &path));
+        }
+
+        // symmetric matrices are stored as a triangle, read back either way round
+        let symmetric = generate_map(100, (1, 100)).ok().unwrap();
+        let triangle = DistanceMatrix::new(symmetric.clone()).ok().unwrap();
+        assert!(triangle.is_symmetric());
+        assert_eq!(triangle.to_rows(), symmetric);
+        assert_eq!(triangle.get(99, 0), symmetric[0][99]);
+        let full = DistanceMatrix::new(generate_asymmetric_map(100, (1, 100)).ok().unwrap())
+            .ok()
+            .unwrap();
+        assert!(triangle.bytes() < full.bytes() * 51 / 100);
+        assert_eq!(
+            DistanceMatrix::new(vec![vec![7u16]])
+                .ok()
+                .unwrap()
+                .get(0, 0),
+            7
+        );
+        assert!(matches!(
+            DistanceMatrix::new(vec![vec![0u16, 1], vec![1]]),
+            Err(TSPError::InvalidMapShape { .. })
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+use log::warn;
+use std::mem::size_of;
+
+// rough footprints of the data structures the solvers allocate, good enough to tell a
+// 10 MB run from a 10 GB one before starting it
+
+pub fn path_bytes(num_cities: usize) -> usize {
+    size_of::<Vec<u32>>() + num_cities * size_of::<u32>()
+}
+
+pub fn matrix_bytes(num_cities: usize) -> usize {
+    size_of::<Vec<Vec<u16>>>()
+        + num_cities * (size_of::<Vec<u16>>() + num_cities * size_of::<u16>())
+}
+
+// the matrix plus the current path and the shuffle move's proposed and previous paths
+pub fn estimate_annealing_memory(num_cities: usize) -> usize {
+    matrix_bytes(num_cities) + 3 * path_bytes(num_cities)
+}
+
+// the matrix plus the permutation iterator's index, cycle and output buffers and the best path
+pub fn estimate_brute_force_memory(num_cities: usize) -> usize {
+    matrix_bytes(num_cities)
+        + 2 * num_cities * size_of::<usize>()
+        + num_cities * size_of::<u32>()
+        + 2 * path_bytes(num_cities)
+}
+
+// the matrix plus a u64 cost per subset of cities and city ending a path through it, saturating
+// at usize::MAX for maps far too big to ever run
+pub fn estimate_held_karp_memory(num_cities: usize) -> usize {
+    u32::try_from(num_cities)
+        .ok()
+        .and_then(|num_cities| 1usize.checked_shl(num_cities))
+        .and_then(|num_subsets| num_subsets.checked_mul(num_cities * size_of::<u64>()))
+        .map_or(usize::MAX, |table| {
+            table.saturating_add(matrix_bytes(num_cities) + path_bytes(num_cities))
+        })
+}
+
+// the matrix plus the matching's edge list, every pair of the odd degree cities of the spanning
+// tree, at most all of them
+pub fn estimate_christofides_memory(num_cities: usize) -> usize {
+    let num_pairs = num_cities * num_cities.saturating_sub(1) / 2;
+    matrix_bytes(num_cities) + num_pairs * size_of::<(usize, usize, i64)>() + path_bytes(num_cities)
+}
+
+// the matrix plus the greedy edge start's sorted edge list and the search's neighbour lists
+pub fn estimate_lin_kernighan_memory(num_cities: usize) -> usize {
+    let num_pairs = num_cities * num_cities.saturating_sub(1) / 2;
+    matrix_bytes(num_cities)
+        + num_pairs * size_of::<(u16, usize, usize)>()
+        + num_cities * 10 * size_of::<usize>()
+}
+
+// the largest resident set size the process has had so far
+#[cfg(unix)]
+pub fn process_peak_memory() -> Option<usize> {
+    let max_rss = crate::stats::resource_usage()?.ru_maxrss as usize;
+    // macOS reports bytes, every other unix kilobytes
+    if cfg!(target_os = "macos") {
+        Some(max_rss)
+    } else {
+        Some(max_rss * 1024)
+    }
+}
+
+#[cfg(not(unix))]
+pub fn process_peak_memory() -> Option<usize> {
+    None
+}
+
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024. && unit < UNITS.len() - 1 {
+        value /= 1024.;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+// warns when a solver is projected to need more than the limit, returns whether it fits
+pub fn check_memory_limit(solver: &str, estimated: usize, limit: Option<usize>) -> bool {
+    match limit {
+        Some(limit) if estimated > limit => {
+            warn!(
+                "{} is projected to need {} which is over the {} limit",
+                solver,
+                format_bytes(estimated),
+                format_bytes(limit)
+            );
+            false
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory::{
+        check_memory_limit, estimate_annealing_memory, format_bytes, matrix_bytes,
+    };
+
+    #[test]
+    fn test_memory_estimates() {
+        // the matrix dominates, 2 bytes per weight
+        assert!(matrix_bytes(1000) >= 2 * 1000 * 1000);
+        assert!(estimate_annealing_memory(1000) > matrix_bytes(1000));
+        assert!(estimate_annealing_memory(1000) < 3 * 1000 * 1000);
+
+        assert_eq!(format_bytes(512), "512.0 B");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+
+        assert!(check_memory_limit("test", 10, None));
+        assert!(check_memory_limit("test", 10, Some(10)));
+        assert!(!check_memory_limit("test", 11, Some(10)));
+    }
+}
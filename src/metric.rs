@@ -0,0 +1,132 @@
+use crate::common::TSPError;
+use crate::geo::haversine_km;
+use crate::weight::Weight;
+
+// how far apart two points are, for building matrices from coordinates, planar metrics take
+// (x, y) and geographic ones (latitude, longitude) in degrees
+pub trait Metric {
+    fn distance(&self, from: (f64, f64), to: (f64, f64)) -> f64;
+
+    // whether the metric can measure from the point at all
+    fn is_valid(&self, point: (f64, f64)) -> bool {
+        point.0.is_finite() && point.1.is_finite()
+    }
+
+    // whether it is the straight line distance between (x, y) points, whose nearest neighbours
+    // candidates::grid_candidates finds without measuring every pair
+    fn is_euclidean(&self) -> bool {
+        false
+    }
+}
+
+// straight line distance
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Euclidean;
+
+// distance along the axes, as on a street grid
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Manhattan;
+
+// great circle distance in kilometres
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Haversine;
+
+impl Metric for Euclidean {
+    fn distance(&self, (x1, y1): (f64, f64), (x2, y2): (f64, f64)) -> f64 {
+        (x1 - x2).hypot(y1 - y2)
+    }
+
+    fn is_euclidean(&self) -> bool {
+        true
+    }
+}
+
+impl Metric for Manhattan {
+    fn distance(&self, (x1, y1): (f64, f64), (x2, y2): (f64, f64)) -> f64 {
+        (x1 - x2).abs() + (y1 - y2).abs()
+    }
+}
+
+impl Metric for Haversine {
+    fn distance(&self, from: (f64, f64), to: (f64, f64)) -> f64 {
+        haversine_km(from, to)
+    }
+
+    fn is_valid(&self, (lat, lon): (f64, f64)) -> bool {
+        (-90. ..=90.).contains(&lat) && (-180. ..=180.).contains(&lon)
+    }
+}
+
+// the matrix of distances between the points, rounded to the nearest integer for whole weights
+pub fn metric_map<W: Weight>(
+    coordinates: &[(f64, f64)],
+    metric: &dyn Metric,
+) -> Result<Vec<Vec<W>>, TSPError> {
+    if coordinates.is_empty() {
+        return Err(TSPError::EmptyMap);
+    }
+    if coordinates.len() > u32::MAX as usize {
+        return Err(TSPError::InvalidCityCount);
+    }
+    if let Some(index) = coordinates
+        .iter()
+        .position(|&point| !metric.is_valid(point))
+    {
+        return Err(TSPError::InvalidCoordinate { index });
+    }
+
+    let mut map = vec![vec![W::default(); coordinates.len()]; coordinates.len()];
+    for (i, &from) in coordinates.iter().enumerate() {
+        for (j, &to) in coordinates.iter().enumerate() {
+            let distance = metric.distance(from, to);
+            let Some(weight) = W::from_f64(distance) else {
+                return Err(TSPError::InvalidWeightRange);
+            };
+            map[i][j] = weight;
+        }
+    }
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::TSPError;
+    use crate::instance::TspInstance;
+    use crate::metric::{metric_map, Euclidean, Haversine, Manhattan};
+
+    #[test]
+    fn test_metrics() {
+        let points = [(0., 0.), (3., 4.), (3., 0.)];
+        let euclidean: Vec<Vec<u16>> = metric_map(&points, &Euclidean).ok().unwrap();
+        assert_eq!(euclidean[0], vec![0, 5, 3]);
+        let manhattan: Vec<Vec<u16>> = metric_map(&points, &Manhattan).ok().unwrap();
+        assert_eq!(manhattan[0], vec![0, 7, 3]);
+        assert_eq!(manhattan[1][2], 4);
+
+        let london_paris = [(51.5074, -0.1278), (48.8566, 2.3522)];
+        let instance = TspInstance::<u16>::from_coordinates(&london_paris, &Haversine)
+            .ok()
+            .unwrap();
+        assert!(instance.is_symmetric());
+        assert!((340..=350).contains(&instance.weight(0, 1)));
+
+        // float weights keep the distances unrounded
+        let exact = TspInstance::<f64>::from_coordinates(&london_paris, &Haversine)
+            .ok()
+            .unwrap();
+        assert!((exact.weight(0, 1) - instance.weight(0, 1) as f64).abs() <= 0.5);
+        let diagonal: Vec<Vec<f64>> = metric_map(&[(0., 0.), (1., 1.)], &Euclidean).ok().unwrap();
+        assert_eq!(diagonal[0][1], 2f64.sqrt());
+
+        assert!(matches!(
+            metric_map::<u16>(&[(0., 0.), (91., 0.)], &Haversine),
+            Err(TSPError::InvalidCoordinate { index: 1 })
+        ));
+        assert!(metric_map::<u16>(&[(0., 0.), (91., 0.)], &Euclidean).is_ok());
+        assert!(metric_map::<f64>(&[(0., 0.), (f64::INFINITY, 0.)], &Manhattan).is_err());
+        // too far for u16 but not for wider weights
+        assert!(metric_map::<u16>(&[(0., 0.), (50000., 50000.)], &Manhattan).is_err());
+        assert!(metric_map::<u32>(&[(0., 0.), (50000., 50000.)], &Manhattan).is_ok());
+        assert!(metric_map::<u16>(&[], &Euclidean).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use rand::{seq::SliceRandom, Rng, RngCore};
+
+use crate::cost::{path_cost_with, CostFunction};
+use crate::weight::{Cost, Delta, Weight};
+
+// a neighbourhood move the annealer can make, propose picks a candidate move for the current
+// path which is then either applied or discarded depending on its delta, implementors holding
+// the proposed move as state so that apply and undo know what to do
+pub trait MoveOperator<W: Weight = u16>: Send {
+    // identifies the operator in move traces
+    fn name(&self) -> &str {
+        "custom"
+    }
+
+    fn propose(&mut self, path: &[u32], rng: &mut dyn RngCore);
+
+    fn apply(&mut self, path: &mut [u32]);
+
+    // reverts the last apply
+    fn undo(&mut self, path: &mut [u32]);
+
+    // change in path cost the proposed move would make, by default the move is applied,
+    // the path costed and the move undone, override this with something incremental if possible
+    fn delta(&mut self, cost_function: &dyn CostFunction<W>, path: &mut [u32]) -> W::Delta {
+        let old_cost = path_cost_with(cost_function, path).to_delta();
+        self.apply(path);
+        let new_cost = path_cost_with(cost_function, path).to_delta();
+        self.undo(path);
+        new_cost - old_cost
+    }
+}
+
+// replaces the whole path with a random permutation, the annealer's original neighbourhood
+#[derive(Default)]
+pub struct Shuffle {
+    proposed: Vec<u32>,
+    previous: Vec<u32>,
+}
+
+impl<W: Weight> MoveOperator<W> for Shuffle {
+    fn name(&self) -> &str {
+        "shuffle"
+    }
+
+    fn propose(&mut self, path: &[u32], rng: &mut dyn RngCore) {
+        self.proposed.clear();
+        self.proposed.extend_from_slice(path);
+        self.proposed.shuffle(rng);
+    }
+
+    fn apply(&mut self, path: &mut [u32]) {
+        self.previous.clear();
+        self.previous.extend_from_slice(path);
+        path.copy_from_slice(&self.proposed);
+    }
+
+    fn undo(&mut self, path: &mut [u32]) {
+        path.copy_from_slice(&self.previous);
+    }
+}
+
+// reverses a random segment of the path, so a candidate keeps all but the edges at the
+// segment's ends (and, for asymmetric weights, the direction of the edges within it)
+#[derive(Default)]
+pub struct TwoOpt {
+    segment: (usize, usize), // inclusive
+}
+
+impl<W: Weight> MoveOperator<W> for TwoOpt {
+    fn name(&self) -> &str {
+        "two_opt"
+    }
+
+    fn propose(&mut self, path: &[u32], rng: &mut dyn RngCore) {
+        let (start, end) = distinct_positions(path, rng);
+        self.segment = (start.min(end), start.max(end));
+    }
+
+    fn apply(&mut self, path: &mut [u32]) {
+        path[self.segment.0..=self.segment.1].reverse();
+    }
+
+    fn undo(&mut self, path: &mut [u32]) {
+        MoveOperator::<W>::apply(self, path);
+    }
+
+    fn delta(&mut self, cost_function: &dyn CostFunction<W>, path: &mut [u32]) -> W::Delta {
+        let cost = |from: u32, to: u32| cost_function.cost(from, to).to_delta();
+        let (start, end) = self.segment;
+
+        let mut delta = W::Delta::ZERO;
+        if start > 0 {
+            delta += cost(path[start - 1], path[end]) - cost(path[start - 1], path[start]);
+        }
+        if end + 1 < path.len() {
+            delta += cost(path[start], path[end + 1]) - cost(path[end], path[end + 1]);
+        }
+        if cost_function.is_symmetric() {
+            return delta;
+        }
+        delta
+            + path[start..=end]
+                .windows(2)
+                .map(|edge| cost(edge[1], edge[0]) - cost(edge[0], edge[1]))
+                .sum::<W::Delta>()
+    }
+}
+
+// two distinct positions of a path, in either order, or the same position of a path too short
+// for two
+fn distinct_positions(path: &[u32], rng: &mut dyn RngCore) -> (usize, usize) {
+    if path.len() < 2 {
+        return (0, 0);
+    }
+    let first = rng.gen_range(0..path.len());
+    let mut second = rng.gen_range(0..path.len() - 1);
+    if second >= first {
+        second += 1;
+    }
+    (first, second)
+}
+
+// exchanges two cities, only the up to four edges touching them change
+#[derive(Default)]
+pub struct Swap {
+    positions: (usize, usize),
+}
+
+impl<W: Weight> MoveOperator<W> for Swap {
+    fn name(&self) -> &str {
+        "swap"
+    }
+
+    fn propose(&mut self, path: &[u32], rng: &mut dyn RngCore) {
+        self.positions = distinct_positions(path, rng);
+    }
+
+    fn apply(&mut self, path: &mut [u32]) {
+        path.swap(self.positions.0, self.positions.1);
+    }
+
+    fn undo(&mut self, path: &mut [u32]) {
+        MoveOperator::<W>::apply(self, path);
+    }
+
+    fn delta(&mut self, cost_function: &dyn CostFunction<W>, path: &mut [u32]) -> W::Delta {
+        let (first, second) = self.positions;
+        // the edges leaving the positions and the ones before them, each counted once
+        let mut edges: Vec<usize> = [first, second]
+            .iter()
+            .flat_map(|&position| [position.checked_sub(1), Some(position)])
+            .flatten()
+            .filter(|&edge| edge + 1 < path.len())
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        let edges_cost = |path: &[u32]| {
+            edges
+                .iter()
+                .map(|&edge| cost_function.cost(path[edge], path[edge + 1]).to_delta())
+                .sum::<W::Delta>()
+        };
+
+        let old_cost = edges_cost(path);
+        MoveOperator::<W>::apply(self, path);
+        let new_cost = edges_cost(path);
+        MoveOperator::<W>::undo(self, path);
+        new_cost - old_cost
+    }
+}
+
+// moves one city to another position, shifting the cities in between along by one
+#[derive(Default)]
+pub struct Insertion {
+    from: usize,
+    to: usize, // the city's position once moved
+}
+
+impl<W: Weight> MoveOperator<W> for Insertion {
+    fn name(&self) -> &str {
+        "insertion"
+    }
+
+    fn propose(&mut self, path: &[u32], rng: &mut dyn RngCore) {
+        (self.from, self.to) = distinct_positions(path, rng);
+    }
+
+    fn apply(&mut self, path: &mut [u32]) {
+        if self.from < self.to {
+            path[self.from..=self.to].rotate_left(1);
+        } else {
+            path[self.to..=self.from].rotate_right(1);
+        }
+    }
+
+    fn undo(&mut self, path: &mut [u32]) {
+        if self.from < self.to {
+            path[self.from..=self.to].rotate_right(1);
+        } else {
+            path[self.to..=self.from].rotate_left(1);
+        }
+    }
+
+    fn delta(&mut self, cost_function: &dyn CostFunction<W>, path: &mut [u32]) -> W::Delta {
+        if self.from == self.to {
+            return W::Delta::ZERO;
+        }
+        let cost = |from: u32, to: u32| cost_function.cost(from, to).to_delta();
+        // the cost of going from a through the city to b instead of from a to b directly, at
+        // either end of the path there is no a or b
+        let detour = |a: Option<u32>, city: u32, b: Option<u32>| {
+            a.map_or(W::Delta::ZERO, |a| cost(a, city))
+                + b.map_or(W::Delta::ZERO, |b| cost(city, b))
+                - a.zip(b).map_or(W::Delta::ZERO, |(a, b)| cost(a, b))
+        };
+
+        let city = path[self.from];
+        let removed = detour(
+            self.from.checked_sub(1).map(|position| path[position]),
+            city,
+            path.get(self.from + 1).copied(),
+        );
+        // the city's new neighbours, adjacent to each other once it is taken out
+        let (before, after) = if self.from < self.to {
+            (Some(path[self.to]), path.get(self.to + 1).copied())
+        } else {
+            (
+                self.to.checked_sub(1).map(|position| path[position]),
+                Some(path[self.to]),
+            )
+        };
+        detour(before, city, after) - removed
+    }
+}
+
+pub fn default_moves<W: Weight>() -> Vec<Box<dyn MoveOperator<W>>> {
+    vec![Box::new(TwoOpt::default())]
+}
+
+// the built in operators by the names they trace under
+pub fn named_move<W: Weight>(name: &str) -> Option<Box<dyn MoveOperator<W>>> {
+    match name {
+        "two_opt" => Some(Box::new(TwoOpt::default())),
+        "swap" => Some(Box::new(Swap::default())),
+        "insertion" => Some(Box::new(Insertion::default())),
+        "shuffle" => Some(Box::new(Shuffle::default())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{generate_default_path, generate_map};
+    use crate::cost::{path_cost_with, CostFunction, MatrixCost};
+    use crate::moves::{Insertion, MoveOperator, Shuffle, Swap, TwoOpt};
+    use rand::{rngs::StdRng, thread_rng, SeedableRng};
+    use std::cell::Cell;
+
+    #[test]
+    fn test_shuffle_apply_undo() {
+        let map = generate_map(8, (1, 100)).ok().unwrap();
+        let mut path = generate_default_path(&map);
+        let original = path.clone();
+        let mut shuffle: Box<dyn MoveOperator> = Box::new(Shuffle::default());
+
+        shuffle.propose(&path, &mut thread_rng());
+        let delta = shuffle.delta(&map, &mut path);
+        assert_eq!(path, original);
+
+        shuffle.apply(&mut path);
+        assert_eq!(
+            path_cost_with(&map, &path) as i64,
+            path_cost_with(&map, &original) as i64 + delta
+        );
+        shuffle.undo(&mut path);
+        assert_eq!(path, original);
+    }
+
+    #[test]
+    fn test_incremental_deltas() {
+        // asymmetric weights, so reversing a segment also changes the cost of its inner edges
+        let map: Vec<Vec<u16>> = (0..7u16)
+            .map(|i| {
+                (0..7u16)
+                    .map(|j| if i == j { 0 } else { i * 7 + j * 3 })
+                    .collect()
+            })
+            .collect();
+        let mut rng = StdRng::seed_from_u64(1);
+        let operators: Vec<Box<dyn MoveOperator>> = vec![
+            Box::new(TwoOpt::default()),
+            Box::new(Swap::default()),
+            Box::new(Insertion::default()),
+        ];
+
+        for mut operator in operators {
+            let mut path = generate_default_path(&map);
+            for _ in 0..50 {
+                let original = path.clone();
+                operator.propose(&path, &mut rng);
+                let delta = operator.delta(&map, &mut path);
+                assert_eq!(path, original);
+
+                operator.apply(&mut path);
+                assert_ne!(path, original, "{}", operator.name());
+                assert_eq!(
+                    path_cost_with(&map, &path) as i64,
+                    path_cost_with(&map, &original) as i64 + delta,
+                    "{}",
+                    operator.name()
+                );
+                operator.undo(&mut path);
+                assert_eq!(path, original);
+                operator.apply(&mut path);
+            }
+
+            let mut single = vec![0];
+            operator.propose(&single, &mut rng);
+            assert_eq!(operator.delta(&map, &mut single), 0);
+        }
+    }
+
+    // a symmetric line of cities counting how often it is asked for an edge
+    struct CountingLine {
+        num_calls: Cell<usize>,
+    }
+
+    impl CostFunction for CountingLine {
+        fn cost(&self, from: u32, to: u32) -> u16 {
+            self.num_calls.set(self.num_calls.get() + 1);
+            from.abs_diff(to) as u16
+        }
+
+        fn is_symmetric(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_constant_time_deltas() {
+        let line = CountingLine {
+            num_calls: Cell::new(0),
+        };
+        let mut path: Vec<u32> = (0..1000).collect();
+        let mut rng = StdRng::seed_from_u64(2);
+        let operators: Vec<Box<dyn MoveOperator>> = vec![
+            Box::new(TwoOpt::default()),
+            Box::new(Swap::default()),
+            Box::new(Insertion::default()),
+        ];
+
+        // however long the path, only the edges around the move are costed
+        for mut operator in operators {
+            for _ in 0..20 {
+                line.num_calls.set(0);
+                operator.propose(&path, &mut rng);
+                operator.delta(&line, &mut path);
+                assert!(line.num_calls.get() <= 8, "{}", operator.name());
+            }
+        }
+
+        let map = generate_map(30, (1, 100)).ok().unwrap();
+        let matrix = MatrixCost::new(&map);
+        let mut path = generate_default_path(&map);
+        let mut two_opt: Box<dyn MoveOperator> = Box::new(TwoOpt::default());
+        for _ in 0..20 {
+            let original = path.clone();
+            two_opt.propose(&path, &mut rng);
+            let delta = two_opt.delta(&matrix, &mut path);
+            two_opt.apply(&mut path);
+            assert_eq!(
+                path_cost_with(&map, &path) as i64,
+                path_cost_with(&map, &original) as i64 + delta
+            );
+        }
+    }
+}
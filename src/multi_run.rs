@@ -0,0 +1,193 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::analysis::mean_and_variance;
+use crate::common::TSPError;
+
+// independent runs of a solver on one instance, run i is seeded with seed + i so a whole set of
+// runs can be repeated, the spread of their costs shows how much a single run can be trusted
+pub struct MultiRunResult {
+    pub best_path: Vec<u32>,
+    pub costs: Vec<u64>, // one per run, in seed order
+    pub times: Vec<Duration>,
+    pub seed: u64, // of the first run
+}
+
+// the spread of a set of values
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Summary {
+    pub min: f64,
+    pub mean: f64,
+    pub median: f64, // the mean of the middle two for an even count
+    pub std: f64,
+}
+
+impl Summary {
+    pub fn of(values: &[f64]) -> Summary {
+        if values.is_empty() {
+            return Summary::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let middle = sorted.len() / 2;
+        let median = if sorted.len() % 2 == 1 {
+            sorted[middle]
+        } else {
+            (sorted[middle - 1] + sorted[middle]) / 2.
+        };
+        let (mean, variance) = mean_and_variance(values);
+        Summary {
+            min: sorted[0],
+            mean,
+            median,
+            std: variance.sqrt(),
+        }
+    }
+}
+
+impl MultiRunResult {
+    pub fn best_cost(&self) -> u64 {
+        self.costs.iter().copied().min().unwrap_or(0)
+    }
+
+    pub fn worst_cost(&self) -> u64 {
+        self.costs.iter().copied().max().unwrap_or(0)
+    }
+
+    // (mean, standard deviation) of the run costs
+    pub fn cost_spread(&self) -> (f64, f64) {
+        let costs: Vec<f64> = self.costs.iter().map(|&cost| cost as f64).collect();
+        let (mean, variance) = mean_and_variance(&costs);
+        (mean, variance.sqrt())
+    }
+
+    pub fn cost_summary(&self) -> Summary {
+        let costs: Vec<f64> = self.costs.iter().map(|&cost| cost as f64).collect();
+        Summary::of(&costs)
+    }
+
+    // in seconds
+    pub fn time_summary(&self) -> Summary {
+        let seconds: Vec<f64> = self.times.iter().map(Duration::as_secs_f64).collect();
+        Summary::of(&seconds)
+    }
+}
+
+impl fmt::Display for MultiRunResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let costs = self.cost_summary();
+        let times = self.time_summary();
+        write!(
+            f,
+            "{} runs, best {}, worst {}, mean {:.2}, median {:.1}, std {:.2}, \
+             seconds min {:.3}, mean {:.3}, median {:.3}, std {:.3}",
+            self.costs.len(),
+            self.best_cost(),
+            self.worst_cost(),
+            costs.mean,
+            costs.median,
+            costs.std,
+            times.min,
+            times.mean,
+            times.median,
+            times.std
+        )
+    }
+}
+
+type RunResult = Result<((Vec<u32>, u64), Duration), TSPError>;
+
+// num_runs runs of any solver in turn, solve being given the seed for each, seed + i for run i,
+// which solvers that take one can use to make the set of runs repeatable
+pub fn multi_run(
+    num_runs: u64,
+    seed: u64,
+    mut solve: impl FnMut(u64) -> Result<(Vec<u32>, u64), TSPError>,
+) -> Result<MultiRunResult, TSPError> {
+    if num_runs == 0 {
+        return Err(TSPError::InvalidRunCount);
+    }
+
+    let results: Vec<RunResult> = (0..num_runs)
+        .map(|index| {
+            let start = Instant::now();
+            solve(seed.wrapping_add(index)).map(|solution| (solution, start.elapsed()))
+        })
+        .collect();
+    collect_runs(results, seed)
+}
+
+fn collect_runs(results: Vec<RunResult>, seed: u64) -> Result<MultiRunResult, TSPError> {
+    let mut best_path = Vec::new();
+    let mut costs = Vec::with_capacity(results.len());
+    let mut times = Vec::with_capacity(results.len());
+    for result in results {
+        let ((path, cost), elapsed) = result?;
+        if costs.iter().all(|&other| cost < other) {
+            best_path = path;
+        }
+        costs.push(cost);
+        times.push(elapsed);
+    }
+
+    Ok(MultiRunResult {
+        best_path,
+        costs,
+        times,
+        seed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::generate_map;
+    use crate::config::SolverConfig;
+    use crate::multi_run::{multi_run, Summary};
+    use crate::sim_annealing::Annealer;
+    use crate::testing::is_cost_consistent;
+
+    #[test]
+    fn test_multi_run() {
+        let map = generate_map(8, (1, 100)).ok().unwrap();
+
+        // any solver, the seed telling the runs apart
+        let mut seeds = Vec::new();
+        let runs = multi_run(4, 11, |seed| {
+            seeds.push(seed);
+            let config = SolverConfig {
+                temperature: 200,
+                max_iterations: 2000,
+                seed: Some(seed),
+                ..SolverConfig::default()
+            };
+            Annealer::new(config)
+                .solve(&map)
+                .map(|(path, cost, _)| (path, cost))
+        })
+        .ok()
+        .unwrap();
+        assert_eq!(seeds, vec![11, 12, 13, 14]);
+        assert_eq!(runs.costs.len(), 4);
+        assert!(is_cost_consistent(&map, &runs.best_path, runs.best_cost()));
+        assert!(runs.best_cost() <= runs.worst_cost());
+        let (mean, std) = runs.cost_spread();
+        assert!(mean >= runs.best_cost() as f64 && std >= 0.);
+        assert!(runs.to_string().starts_with("4 runs"));
+        assert_eq!(runs.times.len(), 4);
+        assert!(runs.time_summary().min > 0.);
+
+        assert!(multi_run(0, 11, |_| Ok((vec![0], 0))).is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = Summary::of(&[4., 1., 3., 2.]);
+        assert_eq!(summary.min, 1.);
+        assert_eq!(summary.mean, 2.5);
+        assert_eq!(summary.median, 2.5);
+        assert!((summary.std - 1.25f64.sqrt()).abs() < 1e-12);
+        assert_eq!(Summary::of(&[5., 1., 3.]).median, 3.);
+        assert_eq!(Summary::of(&[]), Summary::default());
+    }
+}
@@ -0,0 +1,199 @@
+use super::common::{check_city_map, TSPError};
+use super::cost::path_cost_with;
+
+// a map whose off-diagonal weights were linearly rescaled into a target range,
+// the original weights are kept so costs can always be reported in the original units exactly
+pub struct RescaledMap {
+    pub intercity_map: Vec<Vec<u16>>,
+    original_map: Vec<Vec<u16>>,
+    source_min: u16,
+    target_min: u16,
+    scale: f64, // target units per original unit
+}
+
+impl RescaledMap {
+    pub fn original_map(&self) -> &[Vec<u16>] {
+        &self.original_map
+    }
+
+    // exact cost of the path in the original units
+    pub fn original_cost(&self, path: &[u32]) -> u64 {
+        path_cost_with(&self.original_map, path)
+    }
+
+    // inverts the linear transform on a rescaled cost, only approximate since every
+    // rescaled weight was rounded, use original_cost when the path is available
+    pub fn approximate_original_cost(&self, rescaled_cost: u64, num_edges: usize) -> f64 {
+        let offset = self.target_min as f64 - self.source_min as f64 * self.scale;
+        (rescaled_cost as f64 - offset * num_edges as f64) / self.scale
+    }
+
+    pub fn rescaled_weight(&self, weight: u16) -> u16 {
+        (self.target_min as f64 + (weight as f64 - self.source_min as f64) * self.scale).round()
+            as u16
+    }
+}
+
+pub fn rescale_weights(
+    intercity_map: &[Vec<u16>],
+    target_range: (u16, u16),
+) -> Result<RescaledMap, TSPError> {
+    check_city_map(intercity_map)?;
+
+    let (low, high) = target_range;
+    if high <= low {
+        return Err(TSPError::InvalidWeightRange);
+    }
+
+    // the diagonal is always 0 so it is left out of the range
+    let off_diagonal = || {
+        intercity_map.iter().enumerate().flat_map(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .filter(move |&(j, _)| i != j)
+                .map(|(_, &w)| w)
+        })
+    };
+    let source_min = off_diagonal().min().unwrap_or(0);
+    let source_max = off_diagonal().max().unwrap_or(0);
+
+    let scale = if source_max == source_min {
+        1. // every edge is equal, just shift them to the bottom of the range
+    } else {
+        (high - low) as f64 / (source_max - source_min) as f64
+    };
+
+    let mut rescaled = RescaledMap {
+        intercity_map: Vec::new(),
+        original_map: intercity_map.to_vec(),
+        source_min,
+        target_min: low,
+        scale,
+    };
+
+    rescaled.intercity_map = intercity_map
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(j, &w)| {
+                    if i == j {
+                        0
+                    } else {
+                        rescaled.rescaled_weight(w)
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(rescaled)
+}
+
+// translation and uniform scale mapping coordinates into the unit square,
+// the scale is the same on both axes so distances are preserved up to a constant factor
+pub struct CoordinateNormalization {
+    pub offset: (f64, f64),
+    pub scale: f64,
+}
+
+impl CoordinateNormalization {
+    pub fn to_original_point(&self, point: (f64, f64)) -> (f64, f64) {
+        (
+            point.0 / self.scale + self.offset.0,
+            point.1 / self.scale + self.offset.1,
+        )
+    }
+
+    pub fn to_original_distance(&self, distance: f64) -> f64 {
+        distance / self.scale
+    }
+}
+
+pub fn normalize_coordinates(
+    coordinates: &[(f64, f64)],
+) -> (Vec<(f64, f64)>, CoordinateNormalization) {
+    let min_x = coordinates
+        .iter()
+        .map(|p| p.0)
+        .fold(f64::INFINITY, f64::min);
+    let min_y = coordinates
+        .iter()
+        .map(|p| p.1)
+        .fold(f64::INFINITY, f64::min);
+    let max_x = coordinates
+        .iter()
+        .map(|p| p.0)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let max_y = coordinates
+        .iter()
+        .map(|p| p.1)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    let extent = (max_x - min_x).max(max_y - min_y);
+    let normalization = CoordinateNormalization {
+        offset: if coordinates.is_empty() {
+            (0., 0.)
+        } else {
+            (min_x, min_y)
+        },
+        scale: if extent > 0. { 1. / extent } else { 1. },
+    };
+
+    let normalized = coordinates
+        .iter()
+        .map(|&(x, y)| {
+            (
+                (x - normalization.offset.0) * normalization.scale,
+                (y - normalization.offset.1) * normalization.scale,
+            )
+        })
+        .collect();
+
+    (normalized, normalization)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::generate_map;
+    use crate::cost::path_cost_with;
+    use crate::normalization::{normalize_coordinates, rescale_weights};
+
+    #[test]
+    fn test_rescale_weights() {
+        let map = generate_map(7, (200, 5000)).ok().unwrap();
+        let rescaled = rescale_weights(&map, (1, 100)).ok().unwrap();
+
+        for (i, row) in rescaled.intercity_map.iter().enumerate() {
+            for (j, &weight) in row.iter().enumerate() {
+                assert_eq!(weight, rescaled.intercity_map[j][i]);
+                assert!(i == j || (1..=100).contains(&weight));
+            }
+        }
+
+        let path: Vec<u32> = vec![0, 1, 2, 3, 4, 5, 6];
+        assert_eq!(rescaled.original_cost(&path), path_cost_with(&map, &path));
+
+        let rescaled_cost = path_cost_with(&rescaled.intercity_map, &path);
+        let approximate = rescaled.approximate_original_cost(rescaled_cost, path.len() - 1);
+        let max_rounding_error = 0.5 / rescaled.scale * (path.len() - 1) as f64;
+        assert!((approximate - path_cost_with(&map, &path) as f64).abs() <= max_rounding_error);
+
+        assert!(rescale_weights(&map, (10, 10)).is_err());
+    }
+
+    #[test]
+    fn test_normalize_coordinates() {
+        let points = vec![(10., 20.), (30., 20.), (20., 25.)];
+        let (normalized, normalization) = normalize_coordinates(&points);
+
+        assert_eq!(normalized[0], (0., 0.));
+        assert_eq!(normalized[1], (1., 0.));
+        for (&original, &point) in points.iter().zip(&normalized) {
+            let (x, y) = normalization.to_original_point(point);
+            assert!((x - original.0).abs() < 1e-9 && (y - original.1).abs() < 1e-9);
+        }
+        assert!((normalization.to_original_distance(1.) - 20.).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,157 @@
+use rand::{thread_rng, Rng};
+
+use super::common::{check_city_map, TSPError};
+
+// scales every edge weight by a random factor in [1 - fraction, 1 + fraction],
+// the same factor is used for both directions of an edge so symmetric maps stay symmetric
+pub fn jitter_weights(
+    intercity_map: &[Vec<u16>],
+    fraction: f64,
+) -> Result<Vec<Vec<u16>>, TSPError> {
+    check_city_map(intercity_map)?;
+
+    if !(0.0..=1.0).contains(&fraction) {
+        return Err(TSPError::PerturbationOutOfRange);
+    }
+
+    let mut gen = thread_rng();
+    let num_cities = intercity_map.len();
+    let mut perturbed_map = intercity_map.to_vec();
+    for i in 0..num_cities {
+        for j in (i + 1)..num_cities {
+            let factor = 1. + gen.gen_range(-fraction..=fraction);
+            let scale =
+                |weight: u16| (weight as f64 * factor).round().clamp(0., u16::MAX as f64) as u16;
+            perturbed_map[i][j] = scale(intercity_map[i][j]);
+            perturbed_map[j][i] = scale(intercity_map[j][i]);
+        }
+    }
+
+    Ok(perturbed_map)
+}
+
+// moves every point by a random offset of at most max_offset along each axis
+pub fn jitter_coordinates(
+    coordinates: &[(f64, f64)],
+    max_offset: f64,
+) -> Result<Vec<(f64, f64)>, TSPError> {
+    if !max_offset.is_finite() || max_offset < 0. {
+        return Err(TSPError::PerturbationOutOfRange);
+    }
+
+    let mut gen = thread_rng();
+    let perturbed_coordinates = coordinates
+        .iter()
+        .map(|&(x, y)| {
+            (
+                x + gen.gen_range(-max_offset..=max_offset),
+                y + gen.gen_range(-max_offset..=max_offset),
+            )
+        })
+        .collect();
+
+    Ok(perturbed_coordinates)
+}
+
+// removes the row and column of a city, cities after it shift down by one index
+pub fn drop_city(intercity_map: &[Vec<u16>], city: u32) -> Result<Vec<Vec<u16>>, TSPError> {
+    check_city_map(intercity_map)?;
+
+    let city = city as usize;
+    if city >= intercity_map.len() || intercity_map.len() == 1 {
+        return Err(TSPError::PerturbationOutOfRange);
+    }
+
+    let reduced_map = intercity_map
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != city)
+        .map(|(_, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|&(j, _)| j != city)
+                .map(|(_, &weight)| weight)
+                .collect()
+        })
+        .collect();
+
+    Ok(reduced_map)
+}
+
+// appends a new city with random symmetric weights in the given range, it gets the last index
+pub fn add_city(
+    intercity_map: &[Vec<u16>],
+    weight_range: (u16, u16),
+) -> Result<Vec<Vec<u16>>, TSPError> {
+    check_city_map(intercity_map)?;
+
+    let (low, high) = weight_range;
+    if high <= low {
+        return Err(TSPError::InvalidWeightRange);
+    }
+
+    let mut gen = thread_rng();
+    let new_weights: Vec<u16> = (0..intercity_map.len())
+        .map(|_| gen.gen_range(low..high))
+        .collect();
+
+    let mut extended_map: Vec<Vec<u16>> = intercity_map
+        .iter()
+        .zip(&new_weights)
+        .map(|(row, &weight)| row.iter().copied().chain([weight]).collect())
+        .collect();
+    extended_map.push(new_weights.into_iter().chain([0]).collect()); // distance to same city is 0
+
+    Ok(extended_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::generate_map;
+    use crate::perturbation::{add_city, drop_city, jitter_coordinates, jitter_weights};
+
+    #[test]
+    fn test_jitter_weights() {
+        let map = generate_map(8, (100, 200)).ok().unwrap();
+        let jittered = jitter_weights(&map, 0.1).ok().unwrap();
+
+        for (i, row) in jittered.iter().enumerate() {
+            for (j, &weight) in row.iter().enumerate() {
+                assert_eq!(weight, jittered[j][i]); // still symmetric
+                assert!((weight as f64 - map[i][j] as f64).abs() <= map[i][j] as f64 * 0.1 + 1.);
+            }
+        }
+
+        assert!(jitter_weights(&map, 1.5).is_err());
+    }
+
+    #[test]
+    fn test_jitter_coordinates() {
+        let points = vec![(0., 0.), (10., 5.), (-3., 7.)];
+        let moved = jitter_coordinates(&points, 0.5).ok().unwrap();
+
+        for (&(x1, y1), &(x2, y2)) in points.iter().zip(&moved) {
+            assert!((x1 - x2).abs() <= 0.5 && (y1 - y2).abs() <= 0.5);
+        }
+
+        assert!(jitter_coordinates(&points, -1.).is_err());
+    }
+
+    #[test]
+    fn test_drop_and_add_city() {
+        let map: Vec<Vec<u16>> = vec![vec![0, 1, 2], vec![1, 0, 3], vec![2, 3, 0]];
+
+        let reduced = drop_city(&map, 1).ok().unwrap();
+        assert_eq!(reduced, vec![vec![0, 2], vec![2, 0]]);
+        assert!(drop_city(&map, 3).is_err());
+
+        let extended = add_city(&map, (5, 10)).ok().unwrap();
+        assert_eq!(extended.len(), 4);
+        assert_eq!(extended[3][3], 0);
+        for i in 0..3 {
+            assert_eq!(extended[i][3], extended[3][i]);
+            assert!((5..10).contains(&extended[i][3]));
+            assert_eq!(extended[i][..3], map[i][..]);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::brute_force::brute_force_tsp;
+use crate::capitals::generate_capitals_map;
+use crate::common::{check_city_map, generate_map, path_cost, TSPError};
+use crate::sim_annealing::simulated_annealing_tsp;
+use crate::tour::normalize_path;
+
+fn to_py_error(err: TSPError) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+#[pyclass(name = "Instance")]
+#[derive(Clone)]
+pub struct PyInstance {
+    intercity_map: Vec<Vec<u16>>,
+}
+
+#[pymethods]
+impl PyInstance {
+    #[new]
+    fn new(intercity_map: Vec<Vec<u16>>) -> PyResult<Self> {
+        check_city_map(&intercity_map).map_err(to_py_error)?;
+        Ok(PyInstance { intercity_map })
+    }
+
+    #[staticmethod]
+    fn random(num_cities: u32, low: u16, high: u16) -> PyResult<Self> {
+        let intercity_map = generate_map(num_cities, (low, high)).map_err(to_py_error)?;
+        Ok(PyInstance { intercity_map })
+    }
+
+    #[getter]
+    fn num_cities(&self) -> usize {
+        self.intercity_map.len()
+    }
+
+    #[getter]
+    fn matrix(&self) -> Vec<Vec<u16>> {
+        self.intercity_map.clone()
+    }
+
+    fn cost(&self, path: Vec<u32>) -> PyResult<u64> {
+        path_cost(&self.intercity_map, &path).map_err(to_py_error)
+    }
+
+    fn __len__(&self) -> usize {
+        self.intercity_map.len()
+    }
+}
+
+// releases the GIL while solving so other Python threads keep running
+#[pyfunction]
+#[pyo3(signature = (instance, algorithm = "simulated_annealing", temperature = 200, max_iterations = 200000))]
+fn solve(
+    py: Python<'_>,
+    instance: &PyInstance,
+    algorithm: &str,
+    temperature: u64,
+    max_iterations: u64,
+) -> PyResult<(Vec<u32>, u64)> {
+    let intercity_map = &instance.intercity_map;
+    let result = match algorithm {
+        "brute_force" => py.allow_threads(|| brute_force_tsp(intercity_map)),
+        "simulated_annealing" => {
+            py.allow_threads(|| simulated_annealing_tsp(intercity_map, temperature, max_iterations))
+        }
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "unknown algorithm '{}'",
+                algorithm
+            )))
+        }
+    };
+    result
+        .map(|(path, cost)| (normalize_path(intercity_map, &path), cost))
+        .map_err(to_py_error)
+}
+
+#[pyfunction(name = "generate_map")]
+fn py_generate_map(num_cities: u32, low: u16, high: u16) -> PyResult<Vec<Vec<u16>>> {
+    generate_map(num_cities, (low, high)).map_err(to_py_error)
+}
+
+// names, (latitude, longitude) pairs and the instance
+type Capitals = (Vec<&'static str>, Vec<(f64, f64)>, PyInstance);
+
+#[pyfunction]
+fn generate_capitals(num_cities: u16) -> PyResult<Capitals> {
+    let instance = generate_capitals_map(num_cities).map_err(to_py_error)?;
+    Ok((
+        instance.names,
+        instance.coordinates,
+        PyInstance {
+            intercity_map: instance.intercity_map,
+        },
+    ))
+}
+
+#[pymodule]
+#[pyo3(name = "simulated_annealing_tsp")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyInstance>()?;
+    m.add_function(wrap_pyfunction!(solve, m)?)?;
+    m.add_function(wrap_pyfunction!(py_generate_map, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_capitals, m)?)?;
+    Ok(())
+}
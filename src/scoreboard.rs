@@ -0,0 +1,240 @@
+use log::error;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::bench::BenchResult;
+use crate::common::TSPError;
+use crate::tune::instance_class;
+
+// bench results are appended to a csv store across sessions, the leaderboard ranks the solvers
+// in it per instance class by their mean gap to the known optima, then by their mean time
+
+const STORE_HEADER: &str = "instance,cities,solver,cost,optimum,gap_percent,seconds";
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct StoredResult {
+    pub instance: String,
+    pub num_cities: usize,
+    pub solver: String,
+    pub gap: Option<f64>, // none for instances without a known optimum
+    pub seconds: f64,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct LeaderboardEntry {
+    pub class: &'static str,
+    pub solver: String,
+    pub num_instances: usize,
+    pub num_runs: usize,
+    pub mean_gap: f64, // runs are averaged per instance first, then over the instances
+    pub mean_seconds: f64, // likewise
+}
+
+// appends the results to the store, writing the header when the file is new
+pub fn append_to_store(store: &Path, results: &[BenchResult]) -> Result<(), TSPError> {
+    let mut file = OpenOptions::new().create(true).append(true).open(store)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{}", STORE_HEADER)?;
+    }
+    for result in results {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{:.6}",
+            result.instance,
+            result.num_cities,
+            result.solver,
+            result.tour_cost,
+            result
+                .optimum
+                .map_or(String::new(), |optimum| optimum.to_string()),
+            result
+                .gap()
+                .map_or(String::new(), |gap| format!("{:.4}", gap)),
+            result.elapsed.as_secs_f64()
+        )?;
+    }
+    Ok(())
+}
+
+pub fn read_store(store: &Path) -> Result<Vec<StoredResult>, TSPError> {
+    let mut results = Vec::new();
+    for (number, line) in BufReader::new(File::open(store)?).lines().enumerate() {
+        let line = line?;
+        if line.is_empty() || line == STORE_HEADER {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').collect();
+        let parsed = match fields[..] {
+            [instance, num_cities, solver, _, _, gap, seconds] => num_cities
+                .parse()
+                .ok()
+                .zip(seconds.parse().ok())
+                .filter(|_| gap.is_empty() || gap.parse::<f64>().is_ok())
+                .map(|(num_cities, seconds)| StoredResult {
+                    instance: instance.to_string(),
+                    num_cities,
+                    solver: solver.to_string(),
+                    gap: gap.parse().ok(),
+                    seconds,
+                }),
+            _ => None,
+        };
+        let Some(result) = parsed else {
+            error!("Line {} of the store is malformed: '{}'", number + 1, line);
+            return Err(TSPError::InvalidFileFormat);
+        };
+        results.push(result);
+    }
+    Ok(results)
+}
+
+// one entry per solver and instance class, classes in order of first appearance and the best
+// solver of each class first, runs on instances without a known optimum are left out
+pub fn leaderboard(results: &[StoredResult]) -> Vec<LeaderboardEntry> {
+    // (class, solver, instance) -> (runs, gap sum, seconds sum)
+    let mut per_instance: Vec<(&'static str, &str, &str, usize, f64, f64)> = Vec::new();
+    for result in results {
+        let Some(gap) = result.gap else {
+            continue;
+        };
+        let class = instance_class(result.num_cities);
+        match per_instance.iter_mut().find(|(_, solver, instance, ..)| {
+            *solver == result.solver && *instance == result.instance
+        }) {
+            Some((_, _, _, num_runs, gap_sum, seconds_sum)) => {
+                *num_runs += 1;
+                *gap_sum += gap;
+                *seconds_sum += result.seconds;
+            }
+            None => per_instance.push((
+                class,
+                &result.solver,
+                &result.instance,
+                1,
+                gap,
+                result.seconds,
+            )),
+        }
+    }
+
+    let mut entries: Vec<LeaderboardEntry> = Vec::new();
+    for (class, solver, _, num_runs, gap_sum, seconds_sum) in per_instance {
+        let index = match entries
+            .iter()
+            .position(|entry| entry.class == class && entry.solver == solver)
+        {
+            Some(index) => index,
+            None => {
+                entries.push(LeaderboardEntry {
+                    class,
+                    solver: solver.to_string(),
+                    num_instances: 0,
+                    num_runs: 0,
+                    mean_gap: 0.,
+                    mean_seconds: 0.,
+                });
+                entries.len() - 1
+            }
+        };
+
+        let entry = &mut entries[index];
+        entry.num_instances += 1;
+        entry.num_runs += num_runs;
+        entry.mean_gap += (gap_sum / num_runs as f64 - entry.mean_gap) / entry.num_instances as f64;
+        entry.mean_seconds +=
+            (seconds_sum / num_runs as f64 - entry.mean_seconds) / entry.num_instances as f64;
+    }
+
+    let classes: Vec<&'static str> = entries.iter().fold(Vec::new(), |mut classes, entry| {
+        if !classes.contains(&entry.class) {
+            classes.push(entry.class);
+        }
+        classes
+    });
+    entries.sort_by(|a, b| {
+        let class_order = |class| classes.iter().position(|c| *c == class);
+        class_order(a.class)
+            .cmp(&class_order(b.class))
+            .then(a.mean_gap.total_cmp(&b.mean_gap))
+            .then(a.mean_seconds.total_cmp(&b.mean_seconds))
+    });
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::BenchResult;
+    use crate::scoreboard::{append_to_store, leaderboard, read_store};
+    use std::time::Duration;
+
+    fn result(instance: &str, solver: &'static str, tour_cost: u64, seconds: f64) -> BenchResult {
+        BenchResult {
+            instance: instance.to_string(),
+            num_cities: if instance == "berlin52" { 52 } else { 14 },
+            solver,
+            tour_cost,
+            optimum: if instance == "unknown" {
+                None
+            } else {
+                Some(100)
+            },
+            elapsed: Duration::from_secs_f64(seconds),
+            cpu_time: None,
+            estimated_memory: 0,
+            peak_memory: None,
+        }
+    }
+
+    #[test]
+    fn test_leaderboard() {
+        let store = std::env::temp_dir().join(format!("tsp_scoreboard_{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&store);
+        append_to_store(
+            &store,
+            &[
+                result("burma14", "simulated_annealing", 110, 1.),
+                result("burma14", "brute_force", 100, 4.),
+                result("unknown", "brute_force", 100, 4.),
+            ],
+        )
+        .ok()
+        .unwrap();
+        // a second session, the repeated run is averaged with the first
+        append_to_store(
+            &store,
+            &[
+                result("burma14", "simulated_annealing", 100, 1.),
+                result("berlin52", "simulated_annealing", 102, 2.),
+            ],
+        )
+        .ok()
+        .unwrap();
+
+        let results = read_store(&store).ok().unwrap();
+        std::fs::remove_file(&store).unwrap();
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[2].gap, None);
+
+        let entries = leaderboard(&results);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            (entries[0].class, entries[0].solver.as_str()),
+            ("small", "brute_force")
+        );
+        assert_eq!(entries[0].num_instances, 1);
+        assert_eq!(entries[1].solver, "simulated_annealing");
+        assert_eq!(entries[1].num_runs, 2);
+        assert!((entries[1].mean_gap - 5.).abs() < 1e-9);
+        assert_eq!(entries[2].class, "medium");
+    }
+
+    #[test]
+    fn test_malformed_store() {
+        let store = std::env::temp_dir().join(format!("tsp_bad_store_{}.csv", std::process::id()));
+        std::fs::write(&store, "burma14,14,brute_force\n").unwrap();
+        assert!(read_store(&store).is_err());
+        std::fs::remove_file(&store).unwrap();
+    }
+}
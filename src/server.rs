@@ -0,0 +1,179 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::api::{self, JobStatus, SolveRequest};
+
+#[derive(Serialize, Deserialize)]
+pub struct JobCreated {
+    pub id: u64,
+}
+
+// finished jobs kept for polling, the oldest forgotten first, running ones are always kept
+const MAX_FINISHED_JOBS: usize = 1024;
+
+#[derive(Default)]
+struct Jobs {
+    next_id: AtomicU64,
+    statuses: Mutex<Statuses>,
+}
+
+#[derive(Default)]
+struct Statuses {
+    by_id: HashMap<u64, JobStatus>,
+    finished: VecDeque<u64>,
+}
+
+impl Jobs {
+    fn start(&self) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut statuses = self.statuses.lock().unwrap();
+        statuses.by_id.insert(id, JobStatus::Running);
+        id
+    }
+
+    fn finish(&self, id: u64, status: JobStatus) {
+        let mut statuses = self.statuses.lock().unwrap();
+        statuses.by_id.insert(id, status);
+        statuses.finished.push_back(id);
+        while statuses.finished.len() > MAX_FINISHED_JOBS {
+            if let Some(oldest) = statuses.finished.pop_front() {
+                statuses.by_id.remove(&oldest);
+            }
+        }
+    }
+}
+
+// runs solve on the blocking pool so long annealing runs don't stall request handling, a panic
+// failing the job rather than leaving it running forever
+async fn run<F>(jobs: Arc<Jobs>, id: u64, solve: F)
+where
+    F: FnOnce() -> JobStatus + Send + 'static,
+{
+    let status = match tokio::task::spawn_blocking(solve).await {
+        Ok(status) => status,
+        Err(err) => {
+            error!("Job {} failed: {}", id, err);
+            JobStatus::Failed {
+                error: "the solver panicked".to_string(),
+            }
+        }
+    };
+    jobs.finish(id, status);
+}
+
+async fn submit(
+    State(jobs): State<Arc<Jobs>>,
+    Json(request): Json<SolveRequest>,
+) -> (StatusCode, Json<JobCreated>) {
+    let id = jobs.start();
+    tokio::spawn(run(jobs, id, move || {
+        api::solve(&request.instance, &request.config).into()
+    }));
+
+    (StatusCode::ACCEPTED, Json(JobCreated { id }))
+}
+
+async fn job_status(
+    State(jobs): State<Arc<Jobs>>,
+    Path(id): Path<u64>,
+) -> Result<Json<JobStatus>, StatusCode> {
+    let statuses = jobs.statuses.lock().unwrap();
+    statuses
+        .by_id
+        .get(&id)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/solve", post(submit))
+        .route("/jobs/{id}", get(job_status))
+        .with_state(Arc::new(Jobs::default()))
+}
+
+pub async fn serve(address: &str) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    axum::serve(listener, router()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::JobStatus;
+    use crate::server::{router, run, JobCreated, Jobs, MAX_FINISHED_JOBS};
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_solve_job() {
+        let app = router();
+        let body = r#"{"instance": {"intercity_map": [[0, 1, 9], [1, 0, 1], [9, 1, 0]]}, "config": {"algorithm": "brute_force"}}"#;
+
+        let request = Request::post("/solve")
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let created: JobCreated = serde_json::from_slice(&bytes).unwrap();
+
+        loop {
+            let request = Request::get(format!("/jobs/{}", created.id))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            match serde_json::from_slice::<JobStatus>(&bytes).unwrap() {
+                JobStatus::Running => tokio::task::yield_now().await,
+                JobStatus::Done(solution) => {
+                    assert_eq!(solution.cost, 2);
+                    break;
+                }
+                JobStatus::Failed { error } => panic!("job failed: {}", error),
+            }
+        }
+
+        let request = Request::get("/jobs/999").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_job_lifetimes() {
+        let jobs = Arc::new(Jobs::default());
+        let panicked = jobs.start();
+        run(jobs.clone(), panicked, || panic!("solver bug")).await;
+        assert!(matches!(
+            jobs.statuses.lock().unwrap().by_id[&panicked],
+            JobStatus::Failed { .. }
+        ));
+
+        // the oldest finished jobs are forgotten, running ones are kept
+        let running = jobs.start();
+        for _ in 0..MAX_FINISHED_JOBS {
+            let id = jobs.start();
+            jobs.finish(
+                id,
+                JobStatus::Failed {
+                    error: String::new(),
+                },
+            );
+        }
+        let statuses = jobs.statuses.lock().unwrap();
+        assert_eq!(statuses.by_id.len(), MAX_FINISHED_JOBS + 1);
+        assert!(!statuses.by_id.contains_key(&panicked));
+        assert!(matches!(statuses.by_id[&running], JobStatus::Running));
+    }
+}
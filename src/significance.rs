@@ -0,0 +1,176 @@
+use std::fmt;
+
+// the null distribution is counted out exactly up to this many pairs that differ, the normal
+// approximation taking over past it
+const EXACT_MAX_PAIRS: usize = 50;
+
+// a Wilcoxon signed-rank test of paired samples, e.g. the costs two solvers reach on the same
+// instances, two sided, so a small p value says one tends to come out lower than the other
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SignedRankTest {
+    pub num_pairs: usize,
+    pub num_ties: usize,    // pairs left out as they differ by nothing
+    pub first_lower: usize, // pairs where the first sample is the lower
+    pub first_higher: usize,
+    pub rank_sum_lower: f64, // W-, ranks of the differences where the first is lower
+    pub rank_sum_higher: f64, // W+
+    pub p_value: f64,
+}
+
+impl fmt::Display for SignedRankTest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} pairs, first lower in {}, higher in {}, {} ties, W+ {:.1}, W- {:.1}, p = {:.4}",
+            self.num_pairs,
+            self.first_lower,
+            self.first_higher,
+            self.num_ties,
+            self.rank_sum_higher,
+            self.rank_sum_lower,
+            self.p_value
+        )
+    }
+}
+
+// ties are left out and equal differences share the average of their ranks, the p value
+// coming from every way of signing the ranks, or past EXACT_MAX_PAIRS the normal
+// approximation corrected for ties and continuity, pairs taken up to the shorter sample
+pub fn wilcoxon_signed_rank(first: &[f64], second: &[f64]) -> SignedRankTest {
+    let mut differences: Vec<f64> = first
+        .iter()
+        .zip(second)
+        .map(|(a, b)| a - b)
+        .filter(|difference| *difference != 0.)
+        .collect();
+    differences.sort_by(|a, b| a.abs().total_cmp(&b.abs()));
+    let num_pairs = first.len().min(second.len());
+    let n = differences.len();
+
+    // twice the average rank of each run of equal magnitudes, a whole number either way
+    let mut doubled_ranks = vec![0; n];
+    let mut tie_correction = 0.;
+    let mut start = 0;
+    while start < n {
+        let mut end = start;
+        while end + 1 < n && differences[end + 1].abs() == differences[start].abs() {
+            end += 1;
+        }
+        doubled_ranks[start..=end].fill(start + end + 2);
+        let run = (end - start + 1) as f64;
+        tie_correction += run.powi(3) - run;
+        start = end + 1;
+    }
+    let doubled_higher: usize = (0..n)
+        .filter(|&i| differences[i] > 0.)
+        .map(|i| doubled_ranks[i])
+        .sum();
+    let first_higher = differences.iter().filter(|d| **d > 0.).count();
+
+    let p_value = if n == 0 {
+        1.
+    } else if n <= EXACT_MAX_PAIRS {
+        // how many of the 2^n signings give each doubled rank sum
+        let total: usize = doubled_ranks.iter().sum();
+        let mut counts = vec![0f64; total + 1];
+        counts[0] = 1.;
+        for &rank in &doubled_ranks {
+            for sum in (rank..=total).rev() {
+                counts[sum] += counts[sum - rank];
+            }
+        }
+        let signings = 2f64.powi(n as i32);
+        let lower = counts[..=doubled_higher].iter().sum::<f64>() / signings;
+        let upper = counts[doubled_higher..].iter().sum::<f64>() / signings;
+        (2. * lower.min(upper)).min(1.)
+    } else {
+        let n = n as f64;
+        let mean = n * (n + 1.) / 4.;
+        let variance = n * (n + 1.) * (2. * n + 1.) / 24. - tie_correction / 48.;
+        let z = ((doubled_higher as f64 / 2. - mean).abs() - 0.5).max(0.) / variance.sqrt();
+        erfc(z / std::f64::consts::SQRT_2).min(1.)
+    };
+
+    let rank_sum_higher = doubled_higher as f64 / 2.;
+    SignedRankTest {
+        num_pairs,
+        num_ties: num_pairs - n,
+        first_lower: n - first_higher,
+        first_higher,
+        rank_sum_lower: (n * (n + 1)) as f64 / 2. - rank_sum_higher,
+        rank_sum_higher,
+        p_value,
+    }
+}
+
+// the complementary error function, to within 1.2e-7 (Numerical Recipes' erfcc)
+fn erfc(x: f64) -> f64 {
+    let t = 1. / (1. + 0.5 * x.abs());
+    let coefficients = [
+        -1.26551223,
+        1.00002368,
+        0.37409196,
+        0.09678418,
+        -0.18628806,
+        0.27886807,
+        -1.13520398,
+        1.48851587,
+        -0.82215223,
+        0.17087277,
+    ];
+    let polynomial = coefficients
+        .iter()
+        .rev()
+        .fold(0., |sum, coefficient| coefficient + t * sum);
+    let value = t * (-x * x + polynomial).exp();
+    if x >= 0. {
+        value
+    } else {
+        2. - value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::significance::{erfc, wilcoxon_signed_rank};
+
+    #[test]
+    fn test_wilcoxon_signed_rank() {
+        // every one of ten pairs the same way round, only 2 of the 1024 signings are as extreme
+        let first: Vec<f64> = (1..=10).map(|i| 100. + i as f64).collect();
+        let second = vec![100.; 10];
+        let test = wilcoxon_signed_rank(&first, &second);
+        assert_eq!((test.first_higher, test.first_lower), (10, 0));
+        assert_eq!(test.rank_sum_higher, 55.);
+        assert_eq!(test.p_value, 2. / 1024.);
+        assert_eq!(wilcoxon_signed_rank(&second, &first).p_value, 2. / 1024.);
+
+        // W- is 3, which 5 of the 32 signings of ranks 1 to 5 reach or go under
+        let test = wilcoxon_signed_rank(&[1., 2., 0., 4., 5., 7.], &[0., 0., 3., 0., 0., 7.]);
+        assert_eq!((test.num_pairs, test.num_ties), (6, 1));
+        assert_eq!(test.rank_sum_lower, 3.);
+        assert!((test.p_value - 10. / 32.).abs() < 1e-12);
+
+        // tied magnitudes share their ranks
+        let test = wilcoxon_signed_rank(&[1., 1., 0.], &[0., 0., 1.]);
+        assert_eq!(test.rank_sum_higher, 4.);
+        assert_eq!(test.p_value, 1.);
+
+        assert_eq!(wilcoxon_signed_rank(&[3., 4.], &[3., 4.]).p_value, 1.);
+        assert_eq!(wilcoxon_signed_rank(&[], &[]).num_pairs, 0);
+
+        // the normal approximation for many pairs
+        let first: Vec<f64> = (0..80).map(|i| (i % 7) as f64 + 1.).collect();
+        let test = wilcoxon_signed_rank(&first, &vec![0.; 80]);
+        assert!(test.p_value < 1e-10);
+        let alternating: Vec<f64> = (0..80)
+            .map(|i| if i % 2 == 1 { 1. } else { -1. } * (i / 2) as f64)
+            .collect();
+        let test = wilcoxon_signed_rank(&alternating, &vec![0.; 80]);
+        assert!(test.p_value > 0.5);
+
+        assert!((erfc(0.) - 1.).abs() < 1e-7);
+        assert!((erfc(1.) - 0.157299207).abs() < 1e-7);
+        assert!((erfc(-1.) - 1.842700793).abs() < 1e-7);
+    }
+}
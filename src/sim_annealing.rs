@@ -1,99 +1,726 @@
-use log::error;
-use rand::{seq::SliceRandom, thread_rng, Rng};
+use log::debug;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
-use super::common::{generate_default_path, path_cost, valid_city_map, TSPError};
+use super::common::{check_city_map, check_path, TSPError};
+use super::config::SolverConfig;
+use super::convergence::{ConvergencePoint, ConvergenceTrace};
+use super::cooling::CoolingSchedule;
+use super::cost::{CostFunction, MatrixCost, TourMode};
+use super::memory::{estimate_annealing_memory, process_peak_memory};
+use super::moves::MoveOperator;
+use super::stats::{cpu_time_since, process_cpu_time, SolveStats};
+use super::stopping::StoppingCriteria;
+use super::trace::MoveTrace;
+use super::weight::{Cost, Delta, Weight};
 
-struct SimulatedAnnealing {
-    intercity_map: Vec<Vec<u16>>,
-    curr_path: Vec<u16>,
-    curr_path_cost: i64, // the energy of the current solution
+struct SimulatedAnnealing<'a, 't, W: Weight = u16> {
+    cost_function: &'a dyn CostFunction<W>,
+    curr_path: Vec<u32>,
+    curr_path_cost: W::Delta, // the energy of the current solution
+    mode: TourMode,
     init_temperature: u64,
+    schedule: CoolingSchedule,
+    cooling_start: u64, // iteration the schedule last started from, moved on by reheating
+    reheat_patience: Option<u64>, // iterations without a new best tour before reheating
+    best_path: Vec<u32>, // what the run returns, the current tour can end up anywhere worse
     curr_iteration: u64,
     max_iterations: u64,
+    stale_limit: Option<u64>, // iterations without a new best tour before stopping
+    min_temperature: Option<f64>,
+    target_cost: Option<u64>,
+    moves: Vec<Box<dyn MoveOperator<W>>>,
+    trace: Option<MoveTrace<'t>>,
+    convergence: Option<&'t mut ConvergenceTrace<W::Delta>>,
+    stats: SolveStats<W::Delta>,
+    rng: StdRng, // every random choice of the run comes from here so seeding it repeats the run
 }
 
-impl SimulatedAnnealing {
-    fn accept_candidate(&self, new_path: &Vec<u16>) -> bool {
-        let gen = &mut thread_rng();
-        let new_cost = path_cost(&self.intercity_map, &new_path);
-        let temp = self.init_temperature as f64 / (1. + self.curr_iteration as f64);
-        let diff = (new_cost as i64 - self.curr_path_cost) as f64;
-        let metropolis_criterion = (-diff / temp).exp();
+impl<'a, W: Weight> SimulatedAnnealing<'a, '_, W> {
+    // metropolis acceptance, a worse candidate is still taken with probability exp(-diff / T) so
+    // the run can climb out of local optima, T cooling from the initial temperature by the schedule
+    fn accept_candidate(&mut self, diff: W::Delta) -> bool {
+        let temp = self.temperature();
+        let metropolis_criterion = (-diff.to_f64() / temp).exp();
 
-        diff < 0. || gen.gen_range(0.0..1.0) < metropolis_criterion
+        diff < W::Delta::ZERO || self.rng.gen_range(0.0..1.0) < metropolis_criterion
     }
 
-    fn calculate_optimal(&mut self) -> (Vec<u16>, u32) {
-        while self.next().is_some() {}
+    fn temperature(&self) -> f64 {
+        self.schedule.temperature(
+            self.init_temperature as f64,
+            self.curr_iteration - self.cooling_start,
+            self.max_iterations - self.cooling_start,
+        )
+    }
+
+    // whether one of the optional stopping criteria ends the run before max_iterations
+    fn should_stop(&self) -> bool {
+        let best_cost = self.stats.best_cost_at(u64::MAX).unwrap_or(W::Delta::MAX);
+        let last_improved = self.stats.improvements.last().map_or(0, |&(at, _)| at);
+        self.target_cost
+            .is_some_and(|target_cost| best_cost.to_f64() <= target_cost as f64)
+            || self
+                .stale_limit
+                .is_some_and(|limit| self.curr_iteration - last_improved >= limit)
+            || self
+                .min_temperature
+                .is_some_and(|min_temperature| self.temperature() < min_temperature)
+    }
+
+    // stops the run at the criteria rather than only at max_iterations, keeping the best tour
+    fn with_stopping(mut self, criteria: &StoppingCriteria) -> Self {
+        self.max_iterations = criteria.max_iterations;
+        self.stale_limit = criteria.stale_limit();
+        self.min_temperature = criteria.min_temperature;
+        self.target_cost = criteria.target_cost;
+        self
+    }
+
+    // the best tour seen, costed under the run's mode
+    fn final_solution(&self) -> (Vec<u32>, W::Cost) {
         (
-            self.curr_path.clone(),
-            path_cost(&self.intercity_map, &self.curr_path),
+            self.best_path.clone(),
+            self.mode.cost(self.cost_function, &self.best_path),
         )
     }
 
+    // costs the run's tours as round trips or not, before it starts
+    fn with_mode(mut self, mode: TourMode) -> Self {
+        self.mode = mode;
+        self.curr_path_cost = mode.cost(self.cost_function, &self.curr_path).to_delta();
+        self.stats.improvements = vec![(self.curr_iteration, self.curr_path_cost)];
+        self
+    }
+
+    // a closed tour also changes cost when the move changes its ends, which are joined by the
+    // return edge, the moves only know open paths so the ends are found by applying the move
+    fn return_edge_delta(&mut self, move_index: usize) -> W::Delta {
+        let path = &mut self.curr_path;
+        let return_edge = |path: &[u32]| {
+            self.cost_function
+                .cost(path[path.len() - 1], path[0])
+                .to_delta()
+        };
+        let before = return_edge(path);
+        self.moves[move_index].apply(path);
+        let after = return_edge(path);
+        self.moves[move_index].undo(path);
+        after - before
+    }
+
     fn new(
-        intercity_map: Vec<Vec<u16>>,
+        cost_function: &'a dyn CostFunction<W>,
+        checkpoint: Checkpoint,
         temperature: u64,
         max_iterations: u64,
-    ) -> SimulatedAnnealing {
-        let default_path = generate_default_path(&intercity_map);
-        let curr_cost = path_cost(&intercity_map, &default_path);
-
-        SimulatedAnnealing {
-            intercity_map,
-            curr_path: default_path,
-            curr_path_cost: curr_cost as i64,
+        moves: Vec<Box<dyn MoveOperator<W>>>,
+    ) -> Result<Self, TSPError> {
+        if moves.is_empty() {
+            return Err(TSPError::NoMoveOperators);
+        }
+
+        // the checkpoint's path also fixes the number of cities for cost functions
+        check_path(checkpoint.path.len(), &checkpoint.path)?;
+        if checkpoint.iteration > max_iterations {
+            return Err(TSPError::CheckpointOutOfRange {
+                iteration: checkpoint.iteration,
+                max_iterations,
+            });
+        }
+
+        let curr_cost = TourMode::Open.cost(cost_function, &checkpoint.path);
+
+        Ok(SimulatedAnnealing {
+            cost_function,
+            best_path: checkpoint.path.clone(),
+            curr_path: checkpoint.path,
+            curr_path_cost: curr_cost.to_delta(),
+            mode: TourMode::Open,
             init_temperature: temperature,
-            curr_iteration: 0,
+            schedule: CoolingSchedule::default(),
+            cooling_start: 0,
+            reheat_patience: None,
+            curr_iteration: checkpoint.iteration,
             max_iterations,
-        }
+            stale_limit: None,
+            min_temperature: None,
+            target_cost: None,
+            moves,
+            trace: None,
+            convergence: None,
+            stats: SolveStats {
+                improvements: vec![(checkpoint.iteration, curr_cost.to_delta())],
+                ..SolveStats::default()
+            },
+            rng: StdRng::from_entropy(),
+        })
     }
 }
 
-impl Iterator for SimulatedAnnealing {
-    type Item = Vec<u16>;
+impl<W: Weight> Iterator for SimulatedAnnealing<'_, '_, W> {
+    type Item = u64;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.max_iterations == self.curr_iteration {
+        if self.max_iterations == self.curr_iteration || self.should_stop() {
             return None;
         }
 
-        let mut new_path = self.curr_path.clone();
-        new_path.shuffle(&mut thread_rng());
+        // each iteration uses one of the registered moves, chosen uniformly
+        let move_index = self.rng.gen_range(0..self.moves.len());
+        let operator = &mut self.moves[move_index];
+        operator.propose(&self.curr_path, &mut self.rng);
+        let mut diff = operator.delta(self.cost_function, &mut self.curr_path);
+        if self.mode == TourMode::Closed {
+            diff += self.return_edge_delta(move_index);
+        }
+        self.stats.cost_evaluations += 1;
 
-        if self.accept_candidate(&new_path) {
-            self.curr_path = new_path.clone();
-            self.curr_path_cost = path_cost(&self.intercity_map, &new_path) as i64;
-            println!("cost {}, iter {}", self.curr_path_cost, self.curr_iteration)
+        let accepted = self.accept_candidate(diff);
+        if accepted {
+            let operator = &mut self.moves[move_index];
+            operator.apply(&mut self.curr_path);
+            self.curr_path_cost += diff;
+            debug!("cost {}, iter {}", self.curr_path_cost, self.curr_iteration);
+            if let Some(trace) = &mut self.trace {
+                trace.record(
+                    self.curr_iteration,
+                    operator.name(),
+                    diff,
+                    self.curr_path_cost,
+                );
+            }
+
+            self.stats.accepted_moves += 1;
+            if diff < W::Delta::ZERO
+                && self.stats.best_cost_at(u64::MAX) > Some(self.curr_path_cost)
+            {
+                self.stats
+                    .improvements
+                    .push((self.curr_iteration + 1, self.curr_path_cost));
+                self.best_path.clone_from(&self.curr_path);
+            }
+        }
+
+        if self
+            .convergence
+            .as_ref()
+            .is_some_and(|convergence| convergence.is_sampled(self.curr_iteration))
+        {
+            let point = ConvergencePoint {
+                iteration: self.curr_iteration,
+                temperature: self.temperature(),
+                current_cost: self.curr_path_cost,
+                best_cost: self.stats.best_cost_at(u64::MAX).unwrap_or(W::Delta::MAX),
+                accepted,
+            };
+            if let Some(convergence) = &mut self.convergence {
+                convergence.record(point);
+            }
         }
 
         self.curr_iteration += 1;
+        self.stats.iterations += 1;
+
+        if let Some(patience) = self.reheat_patience {
+            let last_improved = self.stats.improvements.last().map_or(0, |&(at, _)| at);
+            if self.curr_iteration - last_improved.max(self.cooling_start) >= patience {
+                self.cooling_start = self.curr_iteration;
+                self.stats.reheats += 1;
+            }
+        }
 
-        Some(new_path)
+        Some(self.curr_iteration)
     }
 }
 
-pub fn simulated_annealing_tsp(
-    intercity_map: &Vec<Vec<u16>>,
+// a run's tour, its cost and how the run went
+pub type StatsResult<W = u16> = Result<
+    (
+        Vec<u32>,
+        <W as Weight>::Cost,
+        SolveStats<<W as Weight>::Delta>,
+    ),
+    TSPError,
+>;
+
+pub struct Progress<'a, C = u64> {
+    pub iteration: u64,
+    pub current_cost: C,
+    pub current_path: &'a [u32],
+    pub best_cost: C,
+    pub best_path: &'a [u32],
+}
+
+// what an observer sees at the end of each epoch, the acceptance rate being over that epoch
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SolverEvent<C = u64> {
+    pub iteration: u64,
+    pub temperature: f64,
+    pub best_cost: C,
+    pub current_cost: C,
+    pub acceptance_rate: f64,
+}
+
+// enough state to carry on an interrupted run, the annealer is otherwise memoryless
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Checkpoint {
+    pub iteration: u64,
+    pub path: Vec<u32>,
+}
+
+type ProgressHook<'a, C> = Box<dyn FnMut(&Progress<C>) -> ControlFlow<()> + 'a>;
+type EventHook<'a, C> = Box<dyn FnMut(&SolverEvent<C>) + 'a>;
+
+// a run set up piece by piece, the knobs that can be kept in a file coming from its SolverConfig
+// and everything else from the methods here, any of which combine, e.g.
+//
+//     let config = SolverConfig { seed: Some(7), ..SolverConfig::default() };
+//     let (path, cost, stats) = Annealer::new(config)
+//         .stopping(&criteria)
+//         .observer(1000, |event| println!("{:?}", event))
+//         .solve(&intercity_map)?;
+//
+// what watches or steers a single run (progress, observer, trace, convergence, custom moves and
+// resuming) needs the config's runs to be 1
+pub struct Annealer<'a, W: Weight = u16> {
+    config: SolverConfig,
+    stopping: Option<StoppingCriteria>,
+    moves: Option<Vec<Box<dyn MoveOperator<W>>>>,
+    checkpoint: Option<Checkpoint>,
+    trace: Option<&'a mut dyn Write>,
+    convergence: Option<&'a mut ConvergenceTrace<W::Delta>>,
+    hooks: Hooks<'a, W::Cost>,
+}
+
+struct Hooks<'a, C> {
+    report_interval: u64,
+    on_progress: Option<ProgressHook<'a, C>>,
+    epoch_length: u64,
+    on_event: Option<EventHook<'a, C>>,
+}
+
+impl<'a, W: Weight> Annealer<'a, W> {
+    pub fn new(config: SolverConfig) -> Self {
+        Annealer {
+            config,
+            stopping: None,
+            moves: None,
+            checkpoint: None,
+            trace: None,
+            convergence: None,
+            hooks: Hooks {
+                report_interval: 0,
+                on_progress: None,
+                epoch_length: 0,
+                on_event: None,
+            },
+        }
+    }
+
+    // stops at the criteria's max_iterations, which replaces the config's, or as soon as one of
+    // the other criteria is met, with the best tour found
+    pub fn stopping(mut self, criteria: &StoppingCriteria) -> Self {
+        self.config.max_iterations = criteria.max_iterations;
+        self.stopping = Some(*criteria);
+        self
+    }
+
+    // custom moves instead of the config's named ones, e.g. ones that respect constraints of the
+    // caller's domain
+    pub fn moves(mut self, moves: Vec<Box<dyn MoveOperator<W>>>) -> Self {
+        self.moves = Some(moves);
+        self
+    }
+
+    // carries on a run from a checkpoint taken with the same map and config, instead of starting
+    // from the config's initial tour
+    pub fn resume(mut self, checkpoint: Checkpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    // on_progress is called every report_interval iterations, 0 disables reporting,
+    // returning ControlFlow::Break stops the run early with the best path so far
+    pub fn on_progress(
+        mut self,
+        report_interval: u64,
+        on_progress: impl FnMut(&Progress<W::Cost>) -> ControlFlow<()> + 'a,
+    ) -> Self {
+        self.hooks.report_interval = report_interval;
+        self.hooks.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    // on_event is called at the end of every epoch of epoch_length iterations, 0 disabling it, for
+    // watching the run converge without changing how it goes
+    pub fn observer(
+        mut self,
+        epoch_length: u64,
+        on_event: impl FnMut(&SolverEvent<W::Cost>) + 'a,
+    ) -> Self {
+        self.hooks.epoch_length = epoch_length;
+        self.hooks.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    // writes every accepted move to trace, see trace::read_trace for reading it back
+    pub fn trace(mut self, trace: &'a mut dyn Write) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    // records the run's temperature and costs into convergence, see ConvergenceTrace::write_csv
+    // for plotting them
+    pub fn convergence(mut self, convergence: &'a mut ConvergenceTrace<W::Delta>) -> Self {
+        self.convergence = Some(convergence);
+        self
+    }
+
+    pub fn solve(self, intercity_map: &[Vec<W>]) -> StatsResult<W> {
+        check_city_map(intercity_map)?;
+
+        let matrix = MatrixCost::new(intercity_map);
+        self.solve_with(&matrix, intercity_map.len())
+    }
+
+    // same as solve but for any cost function over num_cities cities, see cost::CachedCost for
+    // cost functions that are expensive to call
+    //
+    // the config's runs are seeded in turn from its seed and the best tour of them returned, a
+    // time limit being shared equally between sequential runs while parallel runs, each on its
+    // own thread, all get the whole of it
+    pub fn solve_with(
+        mut self,
+        cost_function: &(impl CostFunction<W> + Sync),
+        num_cities: usize,
+    ) -> StatsResult<W> {
+        let start = Instant::now();
+        if num_cities == 0 {
+            return Err(TSPError::InvalidCityCount);
+        }
+        self.config.check()?;
+        if let Some(criteria) = &self.stopping {
+            criteria.check()?;
+        }
+        if let Some(checkpoint) = &self.checkpoint {
+            check_path(num_cities, &checkpoint.path)?;
+        }
+
+        let config = &self.config;
+        let stopping = self.stopping.as_ref();
+        let deadline = |index: u64| {
+            let share = if config.parallel {
+                1.
+            } else {
+                (index + 1) as f64 / config.runs as f64
+            };
+            config
+                .time_limit
+                .map(|time_limit| start + time_limit.mul_f64(share))
+        };
+
+        let watched = self.moves.is_some()
+            || self.checkpoint.is_some()
+            || self.trace.is_some()
+            || self.convergence.is_some()
+            || self.hooks.on_progress.is_some()
+            || self.hooks.on_event.is_some();
+        if watched {
+            if config.runs != 1 {
+                return Err(TSPError::InvalidRunCount);
+            }
+            let mut state = start_run(
+                config,
+                stopping,
+                cost_function,
+                num_cities,
+                0,
+                self.checkpoint.take(),
+                self.moves.take(),
+            )?;
+            state.trace = self.trace.take().map(MoveTrace::new);
+            state.convergence = self.convergence.take();
+            return watch(state, deadline(0), &mut self.hooks);
+        }
+
+        let run = |index: u64| {
+            let state = start_run(
+                config,
+                stopping,
+                cost_function,
+                num_cities,
+                index,
+                None,
+                None,
+            )?;
+            watch(state, deadline(index), &mut Hooks::none())
+        };
+        let results: Vec<_> = if config.parallel {
+            thread::scope(|scope| {
+                let handles: Vec<_> = (0..config.runs)
+                    .map(|index| scope.spawn(move || run(index)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap_or(Err(TSPError::SolverPanicked)))
+                    .collect()
+            })
+        } else {
+            (0..config.runs).map(run).collect()
+        };
+
+        let solutions = results.into_iter().collect::<Result<Vec<_>, _>>()?;
+        let best = solutions
+            .into_iter()
+            .reduce(|best, solution| if solution.1 < best.1 { solution } else { best });
+        Ok(best.unwrap()) // check makes sure there is at least one run
+    }
+}
+
+impl<C> Hooks<'_, C> {
+    fn none() -> Self {
+        Hooks {
+            report_interval: 0,
+            on_progress: None,
+            epoch_length: 0,
+            on_event: None,
+        }
+    }
+}
+
+// run index of the config, from checkpoint rather than the initial tour when there is one
+fn start_run<'c, 't, W: Weight, C: CostFunction<W>>(
+    config: &SolverConfig,
+    stopping: Option<&StoppingCriteria>,
+    cost_function: &'c C,
+    num_cities: usize,
+    index: u64,
+    checkpoint: Option<Checkpoint>,
+    moves: Option<Vec<Box<dyn MoveOperator<W>>>>,
+) -> Result<SimulatedAnnealing<'c, 't, W>, TSPError> {
+    let checkpoint = match checkpoint {
+        Some(checkpoint) => checkpoint,
+        None => Checkpoint {
+            iteration: 0,
+            path: config.initial.tour_with(cost_function, num_cities)?,
+        },
+    };
+    let moves = match moves {
+        Some(moves) => moves,
+        None => config.move_operators()?,
+    };
+    let mut state = SimulatedAnnealing::new(
+        cost_function,
+        checkpoint,
+        config.temperature,
+        config.max_iterations,
+        moves,
+    )?;
+    state.schedule = config.cooling;
+    state.reheat_patience = config.reheat_patience;
+    if let Some(seed) = config.seed {
+        state.rng = StdRng::seed_from_u64(seed.wrapping_add(index));
+    }
+    state.stats.estimated_memory = estimate_annealing_memory(num_cities);
+
+    let state = state.with_mode(config.mode);
+    Ok(match stopping {
+        Some(criteria) => state.with_stopping(criteria),
+        None => state,
+    })
+}
+
+// how many iterations pass between looks at the clock when running against a deadline
+const DEADLINE_CHECK_INTERVAL: u64 = 100;
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// the run with its hooks called and its deadline looked at when they are due, the loop stopping
+// at none of them in between
+fn watch<W: Weight>(
+    state: SimulatedAnnealing<W>,
+    deadline: Option<Instant>,
+    hooks: &mut Hooks<W::Cost>,
+) -> StatsResult<W> {
+    let intervals = [
+        hooks.on_progress.as_ref().map(|_| hooks.report_interval),
+        hooks.on_event.as_ref().map(|_| hooks.epoch_length),
+        deadline.map(|_| DEADLINE_CHECK_INTERVAL),
+    ];
+    let report_interval = intervals.into_iter().flatten().fold(0, gcd);
+
+    let mut epoch_start_accepted = 0;
+    run_reporting(state, report_interval, |state| {
+        let due = |interval: u64| interval != 0 && state.curr_iteration.is_multiple_of(interval);
+        if let Some(on_event) = &mut hooks.on_event {
+            if due(hooks.epoch_length) {
+                let accepted = state.stats.accepted_moves - epoch_start_accepted;
+                epoch_start_accepted = state.stats.accepted_moves;
+                on_event(&SolverEvent {
+                    iteration: state.curr_iteration,
+                    temperature: state.temperature(),
+                    best_cost: W::Cost::from_delta(
+                        state.stats.best_cost_at(u64::MAX).unwrap_or_default(),
+                    ),
+                    current_cost: W::Cost::from_delta(state.curr_path_cost),
+                    acceptance_rate: accepted as f64 / hooks.epoch_length as f64,
+                });
+            }
+        }
+        if let Some(on_progress) = &mut hooks.on_progress {
+            if due(hooks.report_interval) {
+                let progress = Progress {
+                    iteration: state.curr_iteration,
+                    current_cost: W::Cost::from_delta(state.curr_path_cost),
+                    current_path: &state.curr_path,
+                    best_cost: W::Cost::from_delta(
+                        state.stats.best_cost_at(u64::MAX).unwrap_or_default(),
+                    ),
+                    best_path: &state.best_path,
+                };
+                if on_progress(&progress).is_break() {
+                    return ControlFlow::Break(());
+                }
+            }
+        }
+        match deadline {
+            Some(deadline) if due(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline => {
+                ControlFlow::Break(())
+            }
+            _ => ControlFlow::Continue(()),
+        }
+    })
+}
+
+pub fn simulated_annealing_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
     temperature: u64,
     max_iterations: u64,
-) -> Result<(Vec<u16>, u32), TSPError> {
-    if !valid_city_map(&intercity_map) {
-        error!("The provided map must be square");
-        return Err(TSPError::InvalidMapShape);
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    let config = SolverConfig {
+        temperature,
+        max_iterations,
+        ..SolverConfig::default()
+    };
+    let (path, cost, _) = Annealer::new(config).solve(intercity_map)?;
+    Ok((path, cost))
+}
+
+// the run loop, on_report sees the whole state every report_interval iterations
+fn run_reporting<W: Weight>(
+    mut state: SimulatedAnnealing<W>,
+    report_interval: u64,
+    mut on_report: impl FnMut(&SimulatedAnnealing<W>) -> ControlFlow<()>,
+) -> StatsResult<W> {
+    let start = Instant::now();
+    let cpu_start = process_cpu_time();
+    while state.next().is_some() {
+        if report_interval != 0
+            && state.curr_iteration.is_multiple_of(report_interval)
+            && on_report(&state).is_break()
+        {
+            break;
+        }
     }
+    state.stats.elapsed = start.elapsed();
+    state.stats.cpu_time = cpu_time_since(cpu_start);
+    state.stats.peak_memory = process_peak_memory();
 
-    let mut state = SimulatedAnnealing::new(intercity_map.clone(), temperature, max_iterations);
-    Ok(state.calculate_optimal())
+    if let Some(trace) = state.trace.take() {
+        trace.finish()?;
+    }
+    let (path, cost) = state.final_solution();
+    Ok((path, cost, state.stats))
 }
 
+// sent whenever a report finds the run's best tour cheaper than any reported before
+#[derive(Clone, PartialEq, Debug)]
+pub struct ProgressEvent<C = u64> {
+    pub iteration: u64,
+    pub best_cost: C,
+    pub best_path: Vec<u32>,
+}
+
+pub type SolveHandle<C = u64> = JoinHandle<Result<(Vec<u32>, C), TSPError>>;
+
+// runs simulated annealing on its own thread, intermediate bests arrive on the receiver and the
+// channel closes once the run finishes, dropping the receiver does not stop the run
+pub fn spawn_simulated_annealing_tsp<W: Weight>(
+    intercity_map: Vec<Vec<W>>,
+    temperature: u64,
+    max_iterations: u64,
+    report_interval: u64,
+) -> (Receiver<ProgressEvent<W::Cost>>, SolveHandle<W::Cost>) {
+    let (sender, receiver) = channel();
+    let handle = thread::spawn(move || {
+        let mut best_cost = W::Cost::MAX;
+        let config = SolverConfig {
+            temperature,
+            max_iterations,
+            ..SolverConfig::default()
+        };
+        let (path, cost, _) = Annealer::new(config)
+            .on_progress(report_interval, |progress| {
+                if progress.best_cost < best_cost {
+                    best_cost = progress.best_cost;
+                    let _ = sender.send(ProgressEvent {
+                        iteration: progress.iteration,
+                        best_cost,
+                        best_path: progress.best_path.to_vec(),
+                    });
+                }
+                ControlFlow::Continue(())
+            })
+            .solve(&intercity_map)?;
+        Ok((path, cost))
+    });
+
+    (receiver, handle)
+}
+
+#[cfg(test)]
 mod tests {
-    use crate::common::generate_map;
-    use crate::simulated_annealing_tsp;
+    use crate::brute_force::brute_force_tsp;
+    use crate::common::{generate_map, TSPError};
+    use crate::config::{InitialTour, SolverConfig};
+    use crate::convergence::ConvergenceTrace;
+    use crate::cooling::CoolingSchedule;
+    use crate::cost::{path_cost_with, CachedCost, TourMode};
+    use crate::moves::{default_moves, MoveOperator};
+    use crate::sim_annealing::{
+        simulated_annealing_tsp, spawn_simulated_annealing_tsp, Annealer, Checkpoint,
+        SimulatedAnnealing, SolverEvent,
+    };
+    use crate::stopping::StoppingCriteria;
+    use crate::testing::is_cost_consistent;
+    use crate::trace::read_trace;
+    use rand::{Rng, RngCore};
+    use std::ops::ControlFlow;
+    use std::time::{Duration, Instant};
 
     const TEST_TEMPERATURE: u64 = 200;
     const TEST_MAX_ITERATIONS: u64 = 3000;
 
+    fn test_config(temperature: u64, max_iterations: u64) -> SolverConfig {
+        SolverConfig {
+            temperature,
+            max_iterations,
+            ..SolverConfig::default()
+        }
+    }
+
     #[test]
     fn test_simulated_annealing() {
         let num_checks = 30;
@@ -105,4 +732,422 @@ mod tests {
             assert!(res.ok().is_some());
         }
     }
+
+    #[test]
+    fn test_best_tour_returned() {
+        // hot enough to wander off the best tour before the run ends
+        let map = generate_map(30, (1, 300)).ok().unwrap();
+        for seed in 0..20 {
+            let config = SolverConfig {
+                seed: Some(seed),
+                ..test_config(5000, 2000)
+            };
+            let (path, cost, stats) = Annealer::new(config).solve(&map).ok().unwrap();
+            assert!(is_cost_consistent(&map, &path, cost));
+            assert_eq!(Some(cost as i64), stats.best_cost_at(u64::MAX));
+        }
+    }
+
+    #[test]
+    fn test_metropolis_acceptance() {
+        let map = generate_map(10, (1, 300)).ok().unwrap();
+        let uphill_moves = |temperature| {
+            let mut trace = Vec::new();
+            Annealer::new(test_config(temperature, TEST_MAX_ITERATIONS))
+                .trace(&mut trace)
+                .solve(&map)
+                .ok()
+                .unwrap();
+            let entries = read_trace(trace.as_slice()).ok().unwrap();
+            entries.iter().filter(|entry| entry.delta > 0).count()
+        };
+
+        // a hot run takes worse candidates, a run with no temperature is a plain descent
+        assert!(uphill_moves(1_000_000) > 0);
+        assert_eq!(uphill_moves(0), 0);
+    }
+
+    #[test]
+    fn test_cooling_schedules() {
+        let map = generate_map(8, (1, 300)).ok().unwrap();
+        for cooling in [
+            CoolingSchedule::Geometric {
+                alpha: 0.99,
+                epoch_length: 10,
+            },
+            CoolingSchedule::Linear { epoch_length: 1 },
+            CoolingSchedule::Logarithmic { epoch_length: 100 },
+        ] {
+            let config = SolverConfig {
+                cooling,
+                ..test_config(TEST_TEMPERATURE, TEST_MAX_ITERATIONS)
+            };
+            let (path, cost, _) = Annealer::new(config).solve(&map).ok().unwrap();
+            assert!(is_cost_consistent(&map, &path, cost));
+        }
+
+        let invalid = SolverConfig {
+            cooling: CoolingSchedule::Geometric {
+                alpha: 1.,
+                epoch_length: 10,
+            },
+            ..test_config(10, 10)
+        };
+        assert!(Annealer::new(invalid).solve(&map).is_err());
+    }
+
+    #[test]
+    fn test_reheating() {
+        let map = generate_map(10, (1, 300)).ok().unwrap();
+        let reheating = |max_iterations, patience| SolverConfig {
+            reheat_patience: Some(patience),
+            ..test_config(TEST_TEMPERATURE, max_iterations)
+        };
+        let (path, cost, stats) = Annealer::new(reheating(5000, 100))
+            .solve(&map)
+            .ok()
+            .unwrap();
+
+        // a 10 city run settles well within 5000 iterations, so it must have been reheated, the
+        // tour handed back being the best one seen even if the run left it
+        assert!(stats.reheats > 0);
+        assert_eq!(Some(cost as i64), stats.best_cost_at(u64::MAX));
+        assert!(is_cost_consistent(&map, &path, cost));
+
+        assert!(Annealer::new(reheating(1000, 0)).solve(&map).is_err());
+    }
+
+    #[test]
+    fn test_closed_tours() {
+        let map = generate_map(9, (1, 300)).ok().unwrap();
+        let checkpoint = Checkpoint {
+            iteration: 0,
+            path: (0..9).collect(),
+        };
+        let mut state =
+            SimulatedAnnealing::new(&map, checkpoint, TEST_TEMPERATURE, 2000, default_moves())
+                .ok()
+                .unwrap()
+                .with_mode(TourMode::Closed);
+
+        // the running cost has to keep up with the return edge as the moves change the ends
+        while state.next().is_some() {
+            assert_eq!(
+                state.curr_path_cost,
+                TourMode::Closed.cost(&map, &state.curr_path) as i64
+            );
+        }
+
+        let config = SolverConfig {
+            mode: TourMode::Closed,
+            ..test_config(TEST_TEMPERATURE, 1000)
+        };
+        let (path, cost, _) = Annealer::new(config).solve(&map).ok().unwrap();
+        assert_eq!(cost, TourMode::Closed.cost(&map, &path));
+    }
+
+    #[test]
+    fn test_seeded_runs_repeat() {
+        let map = generate_map(8, (0, 300)).ok().unwrap();
+        let run = |seed| {
+            let config = SolverConfig {
+                seed: Some(seed),
+                ..test_config(TEST_TEMPERATURE, TEST_MAX_ITERATIONS)
+            };
+            let (path, cost, _) = Annealer::new(config).solve(&map).ok().unwrap();
+            (path, cost)
+        };
+        assert_eq!(run(7), run(7));
+        assert!(is_cost_consistent(&map, &run(8).0, run(8).1));
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let map = generate_map(30, (1, 300)).ok().unwrap();
+        let timed = |max_iterations, time_limit| SolverConfig {
+            time_limit: Some(time_limit),
+            ..test_config(TEST_TEMPERATURE, max_iterations)
+        };
+        let start = Instant::now();
+        let (path, cost, _) = Annealer::new(timed(u64::MAX, Duration::from_millis(100)))
+            .solve(&map)
+            .ok()
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(is_cost_consistent(&map, &path, cost));
+
+        // or the iterations run out first
+        let start = Instant::now();
+        assert!(
+            Annealer::new(timed(TEST_MAX_ITERATIONS, Duration::from_secs(60)))
+                .solve(&map)
+                .is_ok()
+        );
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_progress_reporting() {
+        let map = generate_map(6, (0, 300)).ok().unwrap();
+        let mut reported = Vec::new();
+        let res = Annealer::new(test_config(TEST_TEMPERATURE, 1000))
+            .on_progress(100, |progress| {
+                reported.push(progress.iteration);
+                ControlFlow::Continue(())
+            })
+            .solve(&map);
+
+        assert!(res.is_ok());
+        assert_eq!(reported, (1..=10).map(|i| i * 100).collect::<Vec<u64>>());
+
+        let mut num_reports = 0;
+        let res = Annealer::new(test_config(TEST_TEMPERATURE, 1000))
+            .on_progress(100, |_| {
+                num_reports += 1;
+                if num_reports == 3 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .solve(&map);
+        assert!(res.is_ok());
+        assert_eq!(num_reports, 3);
+    }
+
+    #[test]
+    fn test_observer() {
+        let map = generate_map(6, (0, 300)).ok().unwrap();
+        let mut events: Vec<SolverEvent> = Vec::new();
+        let (_, cost, _) = Annealer::new(test_config(TEST_TEMPERATURE, 1000))
+            .observer(250, |event| events.push(*event))
+            .solve(&map)
+            .ok()
+            .unwrap();
+
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| event.iteration)
+                .collect::<Vec<_>>(),
+            vec![250, 500, 750, 1000]
+        );
+        assert!(events.windows(2).all(|pair| {
+            pair[1].best_cost <= pair[0].best_cost && pair[1].temperature < pair[0].temperature
+        }));
+        for event in &events {
+            assert!(event.best_cost <= event.current_cost);
+            assert!((0. ..=1.).contains(&event.acceptance_rate));
+        }
+        assert_eq!(cost, events[3].current_cost);
+
+        // stopped by the clock at the end of an epoch, with the best tour found
+        let mut last_event = None;
+        let timed = SolverConfig {
+            time_limit: Some(Duration::from_millis(20)),
+            ..test_config(TEST_TEMPERATURE, u64::MAX)
+        };
+        let (path, cost, _) = Annealer::new(timed)
+            .observer(100, |event| last_event = Some(*event))
+            .solve(&map)
+            .ok()
+            .unwrap();
+        let last_event = last_event.unwrap();
+        assert_eq!(last_event.iteration % 100, 0);
+        assert_eq!(cost, last_event.best_cost);
+        assert!(is_cost_consistent(&map, &path, cost));
+    }
+
+    #[test]
+    fn test_combined_options() {
+        // seeded, stopped early, traced and observed all in the one run, and the same again
+        let map = generate_map(12, (1, 300)).ok().unwrap();
+        let config = SolverConfig {
+            seed: Some(3),
+            mode: TourMode::Closed,
+            initial: InitialTour::GreedyEdge,
+            ..SolverConfig::default()
+        };
+        let criteria = StoppingCriteria {
+            max_iterations: 4000,
+            max_epochs_without_improvement: Some(2),
+            epoch_length: 500,
+            ..StoppingCriteria::default()
+        };
+        let run = || {
+            let mut trace = Vec::new();
+            let mut convergence = ConvergenceTrace::new(10);
+            let mut num_events = 0;
+            let (path, cost, stats) = Annealer::new(config.clone())
+                .stopping(&criteria)
+                .trace(&mut trace)
+                .convergence(&mut convergence)
+                .observer(500, |_| num_events += 1)
+                .solve(&map)
+                .ok()
+                .unwrap();
+            assert_eq!(num_events, stats.iterations / 500);
+            assert!(!convergence.points().is_empty());
+            assert!(stats.iterations <= 4000);
+            assert_eq!(cost, TourMode::Closed.cost(&map, &path));
+            (path, cost, read_trace(trace.as_slice()).ok().unwrap().len())
+        };
+        assert_eq!(run(), run());
+
+        // hooks watch a single run
+        let restarts = SolverConfig::default().parallel_restarts(2);
+        let res = Annealer::new(restarts).observer(100, |_| {}).solve(&map);
+        assert!(matches!(res, Err(TSPError::InvalidRunCount)));
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let map = generate_map(6, (0, 300)).ok().unwrap();
+        let mut checkpoint = None;
+        let res = Annealer::new(test_config(TEST_TEMPERATURE, 1000))
+            .on_progress(400, |progress| {
+                checkpoint = Some(Checkpoint {
+                    iteration: progress.iteration,
+                    path: progress.current_path.to_vec(),
+                });
+                ControlFlow::Break(())
+            })
+            .solve(&map);
+        assert!(res.is_ok());
+
+        let checkpoint = checkpoint.unwrap();
+        assert_eq!(checkpoint.iteration, 400);
+
+        let mut remaining = Vec::new();
+        let res = Annealer::new(test_config(TEST_TEMPERATURE, 1000))
+            .resume(checkpoint)
+            .on_progress(200, |progress| {
+                remaining.push(progress.iteration);
+                ControlFlow::Continue(())
+            })
+            .solve(&map);
+        assert!(res.is_ok());
+        assert_eq!(remaining, vec![600, 800, 1000]);
+
+        let bad_checkpoint = Checkpoint {
+            iteration: 0,
+            path: vec![0, 0, 1, 2, 3, 4],
+        };
+        let res = Annealer::new(test_config(TEST_TEMPERATURE, 1000))
+            .resume(bad_checkpoint)
+            .solve(&map);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_warm_start() {
+        let map = generate_map(8, (1, 100)).ok().unwrap();
+        let (optimal_path, optimal) = brute_force_tsp(&map).ok().unwrap();
+        let warm = |cities: &[u32], temperature, max_iterations| {
+            let config = SolverConfig {
+                initial: InitialTour::Tour {
+                    cities: cities.to_vec(),
+                },
+                ..test_config(temperature, max_iterations)
+            };
+            Annealer::new(config)
+                .solve(&map)
+                .map(|(path, cost, _)| (path, cost))
+        };
+        // however hot, the run never ends worse than where it started
+        let (path, cost) = warm(&optimal_path, 10000, 1000).ok().unwrap();
+        assert_eq!(cost, optimal);
+        assert_eq!(cost, path_cost_with(&map, &path));
+
+        let reversed: Vec<u32> = (0..8).rev().collect();
+        assert_eq!(
+            warm(&reversed, TEST_TEMPERATURE, 0).ok(),
+            Some((reversed.clone(), path_cost_with(&map, &reversed)))
+        );
+        let (_, cost) = warm(&reversed, TEST_TEMPERATURE, 20000).ok().unwrap();
+        assert!(cost <= path_cost_with(&map, &reversed));
+
+        assert!(warm(&[0, 1, 2], TEST_TEMPERATURE, 100).is_err());
+    }
+
+    #[test]
+    fn test_progress_channel() {
+        let map = generate_map(6, (0, 300)).ok().unwrap();
+        let (receiver, handle) =
+            spawn_simulated_annealing_tsp(map.clone(), TEST_TEMPERATURE, 1000, 50);
+
+        let events: Vec<_> = receiver.iter().collect();
+        assert!(!events.is_empty());
+        assert!(events
+            .windows(2)
+            .all(|pair| pair[1].best_cost < pair[0].best_cost));
+        for event in &events {
+            assert!(is_cost_consistent(&map, &event.best_path, event.best_cost));
+        }
+        // the run's best so far rather than wherever the current tour had wandered
+        let (_, cost) = handle.join().unwrap().ok().unwrap();
+        assert!(events.last().unwrap().best_cost >= cost);
+    }
+
+    // swaps two cities but never moves the first one
+    #[derive(Default)]
+    struct FixedStartSwap {
+        swap: (usize, usize),
+    }
+
+    impl MoveOperator for FixedStartSwap {
+        fn propose(&mut self, path: &[u32], rng: &mut dyn RngCore) {
+            self.swap = (rng.gen_range(1..path.len()), rng.gen_range(1..path.len()));
+        }
+
+        fn apply(&mut self, path: &mut [u32]) {
+            path.swap(self.swap.0, self.swap.1);
+        }
+
+        fn undo(&mut self, path: &mut [u32]) {
+            path.swap(self.swap.0, self.swap.1);
+        }
+    }
+
+    #[test]
+    fn test_custom_moves() {
+        let map = generate_map(7, (0, 300)).ok().unwrap();
+        let (path, _, _) = Annealer::new(test_config(TEST_TEMPERATURE, TEST_MAX_ITERATIONS))
+            .moves(vec![Box::new(FixedStartSwap::default())])
+            .solve(&map)
+            .ok()
+            .unwrap();
+        assert_eq!(path[0], 0);
+
+        let res = Annealer::new(test_config(TEST_TEMPERATURE, 10))
+            .moves(Vec::new())
+            .solve(&map);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_custom_cost_function() {
+        // cities on a line, so visiting them in order is optimal
+        let cost_function = CachedCost::new(|from: u32, to: u32| from.abs_diff(to) as u16);
+        let (path, cost, _) = Annealer::new(test_config(TEST_TEMPERATURE, 100))
+            .solve_with(&cost_function, 5)
+            .ok()
+            .unwrap();
+        assert_eq!(path.len(), 5);
+        assert!(cost >= 4);
+        assert!(cost_function.num_cached() <= 20);
+
+        assert!(Annealer::new(test_config(TEST_TEMPERATURE, 100))
+            .solve_with(&cost_function, 0)
+            .is_err());
+
+        // a panic on a restart's thread comes back as an error rather than another panic
+        let panicking = |_: u32, _: u32| -> u16 { panic!("cost function bug") };
+        let restarts = test_config(TEST_TEMPERATURE, 100).parallel_restarts(2);
+        assert!(matches!(
+            Annealer::new(restarts).solve_with(&panicking, 5),
+            Err(TSPError::SolverPanicked)
+        ));
+    }
 }
@@ -0,0 +1,410 @@
+use std::time::{Duration, Instant};
+
+use crate::brute_force::{brute_force_tsp_until_with, brute_force_tsp_with};
+use crate::candidates::DEFAULT_CANDIDATES;
+use crate::common::TSPError;
+use crate::config::SolverConfig;
+use crate::instance::{Tour, TspInstance};
+use crate::sim_annealing::Annealer;
+use crate::solvers::branch_and_bound::branch_and_bound_tsp_with;
+use crate::solvers::christofides::christofides_tsp_with;
+use crate::solvers::double_tree::double_tree_tsp_with;
+use crate::solvers::genetic::{evolve, GeneticAlgorithm};
+use crate::solvers::greedy_edge::greedy_edge_tsp_with;
+use crate::solvers::held_karp::held_karp_tsp_with;
+use crate::solvers::lin_kernighan::lin_kernighan_tsp_with;
+use crate::solvers::nearest_neighbour::nearest_neighbour_tsp_with;
+use crate::solvers::parallel_tempering::{parallel_tempering_tsp_with, ParallelTempering};
+use crate::weight::Weight;
+
+// every algorithm behind one interface, so callers can pick one by name at runtime and swap it
+// for another without changing anything else, each following the instance's tour mode and
+// solving instances of any weight::Weight
+
+// how long a solver may run, those that can stop early returning the best tour they have when
+// either runs out and the rest ignoring it, no limit leaving the solver's own settings
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Budget {
+    pub time_limit: Option<Duration>,
+    pub max_iterations: Option<u64>, // annealing iterations or generations, say
+}
+
+impl Budget {
+    fn deadline(&self) -> Option<Instant> {
+        self.time_limit
+            .map(|time_limit| Instant::now() + time_limit)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Solution<C = u64> {
+    pub tour: Tour,
+    pub cost: C, // under the instance's mode
+}
+
+pub type SolverResult<C = u64> = Result<Solution<C>, TSPError>;
+
+pub trait Solver<W: Weight = u16> {
+    // what solver_by_name knows it as
+    fn name(&self) -> &'static str;
+
+    fn solve(&self, instance: &TspInstance<W>, budget: &Budget) -> SolverResult<W::Cost>;
+}
+
+// the names solver_by_name takes
+pub const SOLVER_NAMES: &[&str] = &[
+    "simulated_annealing",
+    "brute_force",
+    "held_karp",
+    "branch_and_bound",
+    "nearest_neighbour",
+    "greedy_edge",
+    "christofides",
+    "double_tree",
+    "lin_kernighan",
+    "genetic",
+    "parallel_tempering",
+];
+
+// each solver with its default settings
+pub fn solver_by_name<W: Weight>(name: &str) -> Option<Box<dyn Solver<W>>> {
+    let solver: Box<dyn Solver<W>> = match name {
+        "simulated_annealing" => Box::new(SolverConfig::default()),
+        "brute_force" => Box::new(BruteForce),
+        "held_karp" => Box::new(HeldKarp),
+        "branch_and_bound" => Box::new(BranchAndBound),
+        "nearest_neighbour" => Box::new(NearestNeighbour::default()),
+        "greedy_edge" => Box::new(GreedyEdge),
+        "christofides" => Box::new(Christofides),
+        "double_tree" => Box::new(DoubleTree),
+        "lin_kernighan" => Box::new(LinKernighan),
+        "genetic" => Box::new(GeneticAlgorithm::default()),
+        "parallel_tempering" => Box::new(ParallelTempering::default()),
+        _ => return None,
+    };
+    Some(solver)
+}
+
+fn solution<W: Weight>(
+    instance: &TspInstance<W>,
+    (path, cost): (Vec<u32>, W::Cost),
+) -> SolverResult<W::Cost> {
+    Ok(Solution {
+        tour: instance.tour(path)?,
+        cost,
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct BruteForce;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct HeldKarp;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct BranchAndBound;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct NearestNeighbour {
+    pub start: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct GreedyEdge;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Christofides;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct DoubleTree;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct LinKernighan;
+
+impl<W: Weight> Solver<W> for BruteForce {
+    fn name(&self) -> &'static str {
+        "brute_force"
+    }
+
+    fn solve(&self, instance: &TspInstance<W>, budget: &Budget) -> SolverResult<W::Cost> {
+        let (matrix, num_cities) = (instance.distances(), instance.num_cities());
+        let result = match budget.deadline() {
+            Some(deadline) => {
+                brute_force_tsp_until_with(matrix, num_cities, instance.mode(), deadline)
+            }
+            None => brute_force_tsp_with(matrix, num_cities, instance.mode())?,
+        };
+        solution(instance, result)
+    }
+}
+
+impl<W: Weight> Solver<W> for HeldKarp {
+    fn name(&self) -> &'static str {
+        "held_karp"
+    }
+
+    fn solve(&self, instance: &TspInstance<W>, _: &Budget) -> SolverResult<W::Cost> {
+        let result =
+            held_karp_tsp_with(instance.distances(), instance.num_cities(), instance.mode())?;
+        solution(instance, result)
+    }
+}
+
+impl<W: Weight> Solver<W> for BranchAndBound {
+    fn name(&self) -> &'static str {
+        "branch_and_bound"
+    }
+
+    fn solve(&self, instance: &TspInstance<W>, budget: &Budget) -> SolverResult<W::Cost> {
+        let result = branch_and_bound_tsp_with(
+            instance.distances(),
+            instance.num_cities(),
+            instance.mode(),
+            budget.deadline(),
+        )?;
+        solution(instance, result)
+    }
+}
+
+impl<W: Weight> Solver<W> for NearestNeighbour {
+    fn name(&self) -> &'static str {
+        "nearest_neighbour"
+    }
+
+    fn solve(&self, instance: &TspInstance<W>, _: &Budget) -> SolverResult<W::Cost> {
+        let result = nearest_neighbour_tsp_with(
+            instance.distances(),
+            instance.num_cities(),
+            self.start,
+            instance.mode(),
+        )?;
+        solution(instance, result)
+    }
+}
+
+impl<W: Weight> Solver<W> for GreedyEdge {
+    fn name(&self) -> &'static str {
+        "greedy_edge"
+    }
+
+    fn solve(&self, instance: &TspInstance<W>, _: &Budget) -> SolverResult<W::Cost> {
+        let result =
+            greedy_edge_tsp_with(instance.distances(), instance.num_cities(), instance.mode())?;
+        solution(instance, result)
+    }
+}
+
+impl<W: Weight> Solver<W> for Christofides {
+    fn name(&self) -> &'static str {
+        "christofides"
+    }
+
+    fn solve(&self, instance: &TspInstance<W>, _: &Budget) -> SolverResult<W::Cost> {
+        let result =
+            christofides_tsp_with(instance.distances(), instance.num_cities(), instance.mode())?;
+        solution(instance, result)
+    }
+}
+
+impl<W: Weight> Solver<W> for DoubleTree {
+    fn name(&self) -> &'static str {
+        "double_tree"
+    }
+
+    fn solve(&self, instance: &TspInstance<W>, _: &Budget) -> SolverResult<W::Cost> {
+        let result =
+            double_tree_tsp_with(instance.distances(), instance.num_cities(), instance.mode());
+        solution(instance, result)
+    }
+}
+
+impl<W: Weight> Solver<W> for LinKernighan {
+    fn name(&self) -> &'static str {
+        "lin_kernighan"
+    }
+
+    fn solve(&self, instance: &TspInstance<W>, _: &Budget) -> SolverResult<W::Cost> {
+        let candidates = instance.candidates(DEFAULT_CANDIDATES);
+        let result = lin_kernighan_tsp_with(
+            instance.distances(),
+            instance.num_cities(),
+            instance.mode(),
+            &candidates,
+        )?;
+        solution(instance, result)
+    }
+}
+
+// annealing as the config has it, its own mode and limits giving way to the instance's and the
+// budget's
+impl<W: Weight> Solver<W> for SolverConfig {
+    fn name(&self) -> &'static str {
+        "simulated_annealing"
+    }
+
+    fn solve(&self, instance: &TspInstance<W>, budget: &Budget) -> SolverResult<W::Cost> {
+        let config = SolverConfig {
+            max_iterations: budget.max_iterations.unwrap_or(self.max_iterations),
+            time_limit: budget.time_limit.or(self.time_limit),
+            mode: instance.mode(),
+            ..self.clone()
+        };
+        let (path, cost, _) =
+            Annealer::new(config).solve_with(instance.distances(), instance.num_cities())?;
+        solution(instance, (path, cost))
+    }
+}
+
+// the budget's iterations counting generations
+impl<W: Weight> Solver<W> for GeneticAlgorithm {
+    fn name(&self) -> &'static str {
+        "genetic"
+    }
+
+    fn solve(&self, instance: &TspInstance<W>, budget: &Budget) -> SolverResult<W::Cost> {
+        let mut genetic = self.clone();
+        if let Some(max_iterations) = budget.max_iterations {
+            genetic.stopping.max_iterations = max_iterations;
+        }
+        let result = evolve(
+            instance.distances(),
+            instance.num_cities(),
+            &genetic,
+            instance.mode(),
+            budget.deadline(),
+        )?;
+        solution(instance, result)
+    }
+}
+
+// the chains anneal open paths, the best of them is costed under the instance's mode, and a
+// time limit is not kept to
+impl<W: Weight> Solver<W> for ParallelTempering {
+    fn name(&self) -> &'static str {
+        "parallel_tempering"
+    }
+
+    fn solve(&self, instance: &TspInstance<W>, budget: &Budget) -> SolverResult<W::Cost> {
+        let tempering = ParallelTempering {
+            max_iterations: budget.max_iterations.unwrap_or(self.max_iterations),
+            ..self.clone()
+        };
+        let (path, _) =
+            parallel_tempering_tsp_with(instance.distances(), instance.num_cities(), &tempering)?;
+        let tour = instance.tour(path)?;
+        let cost = instance.cost(&tour)?;
+        Ok(Solution { tour, cost })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{generate_map, TSPError};
+    use crate::cost::TourMode;
+    use crate::instance::TspInstance;
+    use crate::metric::Euclidean;
+    use crate::solver::{solver_by_name, Budget, SOLVER_NAMES};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::time::Duration;
+
+    #[test]
+    fn test_solvers_by_name() {
+        let instance = TspInstance::new(generate_map(8, (1, 100)).ok().unwrap())
+            .ok()
+            .unwrap();
+        for instance in [
+            instance.clone(),
+            instance.clone().with_mode(TourMode::Closed),
+        ] {
+            let optimal = instance.brute_force().ok().unwrap();
+            let optimal = instance.cost(&optimal).ok().unwrap();
+            for name in SOLVER_NAMES {
+                let solver = solver_by_name(name).unwrap();
+                assert_eq!(solver.name(), *name);
+                let solution = solver.solve(&instance, &Budget::default()).ok().unwrap();
+                assert_eq!(solution.cost, instance.cost(&solution.tour).ok().unwrap());
+                assert!(solution.cost >= optimal);
+                if ["brute_force", "held_karp", "branch_and_bound"].contains(name) {
+                    assert_eq!(solution.cost, optimal);
+                }
+            }
+        }
+        assert!(solver_by_name::<u16>("bogo_sort").is_none());
+
+        // no iterations leave annealing with the tour it starts from
+        let budget = Budget {
+            max_iterations: Some(0),
+            ..Budget::default()
+        };
+        let annealer = solver_by_name("simulated_annealing").unwrap();
+        let solution = annealer.solve(&instance, &budget).ok().unwrap();
+        assert_eq!(solution.tour.cities(), &[0, 1, 2, 3, 4, 5, 6, 7]);
+
+        // stopping at the deadline with the best so far
+        let budget = Budget {
+            time_limit: Some(Duration::ZERO),
+            max_iterations: Some(u64::MAX),
+        };
+        for name in ["simulated_annealing", "brute_force", "genetic"] {
+            let solver = solver_by_name(name).unwrap();
+            assert!(solver.solve(&instance, &budget).is_ok());
+        }
+        let too_big = TspInstance::new(generate_map(14, (1, 100)).ok().unwrap())
+            .ok()
+            .unwrap();
+        let solution = solver_by_name("brute_force")
+            .unwrap()
+            .solve(&too_big, &budget)
+            .ok()
+            .unwrap();
+        assert_eq!(solution.cost, too_big.cost(&solution.tour).ok().unwrap());
+        assert!(matches!(
+            solver_by_name("brute_force")
+                .unwrap()
+                .solve(&too_big, &Budget::default()),
+            Err(TSPError::TooManyCities { .. })
+        ));
+        // in the instance's mode, round trips keeping city 0 first
+        let round_trip = too_big.with_mode(TourMode::Closed);
+        let solution = solver_by_name("brute_force")
+            .unwrap()
+            .solve(&round_trip, &budget)
+            .ok()
+            .unwrap();
+        assert_eq!(solution.tour.cities()[0], 0);
+        assert_eq!(solution.cost, round_trip.cost(&solution.tour).ok().unwrap());
+    }
+
+    #[test]
+    fn test_float_weights() {
+        // within a unit square, where whole number weights would round every distance to 0 or 1
+        let mut rng = StdRng::seed_from_u64(6);
+        let points: Vec<(f64, f64)> = (0..8)
+            .map(|_| (rng.gen_range(0.0..1.), rng.gen_range(0.0..1.)))
+            .collect();
+        let instance = TspInstance::<f64>::from_coordinates(&points, &Euclidean)
+            .ok()
+            .unwrap();
+        for instance in [instance.clone(), instance.with_mode(TourMode::Closed)] {
+            let optimal = instance
+                .cost(&instance.brute_force().ok().unwrap())
+                .ok()
+                .unwrap();
+            for name in SOLVER_NAMES {
+                let solver = solver_by_name::<f64>(name).unwrap();
+                let solution = solver.solve(&instance, &Budget::default()).ok().unwrap();
+                assert!((solution.cost - instance.cost(&solution.tour).ok().unwrap()).abs() < 1e-9);
+                assert!(solution.cost > optimal - 1e-9, "{}", name);
+                if ["brute_force", "held_karp", "branch_and_bound"].contains(name) {
+                    assert!((solution.cost - optimal).abs() < 1e-9, "{}", name);
+                }
+            }
+        }
+
+        assert!(matches!(
+            TspInstance::new(vec![vec![0., f64::NAN], vec![1., 0.]]),
+            Err(TSPError::InvalidWeight { row: 0, column: 1 })
+        ));
+        assert!(TspInstance::new(vec![vec![0., -1.], vec![1., 0.]]).is_err());
+    }
+}
@@ -0,0 +1,380 @@
+use std::ops::ControlFlow;
+use std::time::Instant;
+
+use crate::bounds::spanning_tree_weight;
+use crate::common::{check_city_map, check_path, TSPError};
+use crate::cost::{CostFunction, TourMode};
+use crate::solvers::nearest_neighbour::nearest_neighbour_tsp_with;
+use crate::weight::{Cost, Weight};
+
+// how many nodes are searched between deadline checks
+const DEADLINE_CHECK_INTERVAL: u64 = 4096;
+
+// exact by depth first search over path prefixes, cheapest next city first, a prefix being cut
+// off once its cost plus the spanning tree bound on the rest reaches the cheapest tour found so
+// far, which a greedy tour starts as, memory stays linear so it reaches maps past Held-Karp's
+// limit when the bound is tight, as on geometric instances
+pub fn branch_and_bound_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    branch_and_bound_tsp_with(&intercity_map, intercity_map.len(), TourMode::Open, None)
+}
+
+// same as branch_and_bound_tsp but for any cost function over num_cities cities and either mode,
+// giving up at the deadline with the incumbent, which is then likely not optimal
+pub(crate) fn branch_and_bound_tsp_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    mode: TourMode,
+    deadline: Option<Instant>,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    search_from(
+        cost_function,
+        num_cities,
+        BranchAndBoundCheckpoint::start(mode),
+        deadline,
+        0,
+        &mut |_| ControlFlow::Continue(()),
+    )
+}
+
+// enough state to carry on an interrupted search, the next cities are always tried in the same
+// order so every prefix before path in depth first order has been searched already, an empty
+// best_path standing for the greedy tour a search starts from
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, PartialEq, Debug)]
+pub struct BranchAndBoundCheckpoint<C = u64> {
+    pub mode: TourMode,
+    pub path: Vec<u32>,      // the next prefix to search, empty before the first
+    pub best_path: Vec<u32>, // the incumbent
+    pub best_cost: C,
+    pub num_nodes: u64,
+}
+
+impl<C: Cost> BranchAndBoundCheckpoint<C> {
+    pub fn start(mode: TourMode) -> BranchAndBoundCheckpoint<C> {
+        BranchAndBoundCheckpoint {
+            mode,
+            path: Vec::new(),
+            best_path: Vec::new(),
+            best_cost: C::MAX,
+            num_nodes: 0,
+        }
+    }
+}
+
+// continues a search from a checkpoint taken with the same map, on_checkpoint gets the state
+// every checkpoint_interval nodes to persist, returning ControlFlow::Break stops the search early
+// with the incumbent
+pub fn resume_branch_and_bound_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+    checkpoint: BranchAndBoundCheckpoint<W::Cost>,
+    checkpoint_interval: u64,
+    mut on_checkpoint: impl FnMut(&BranchAndBoundCheckpoint<W::Cost>) -> ControlFlow<()>,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    let num_cities = intercity_map.len();
+    if !checkpoint.best_path.is_empty() {
+        check_path(num_cities, &checkpoint.best_path)?;
+    }
+    let mut seen = vec![false; num_cities];
+    let valid_prefix = checkpoint.path.len() <= num_cities
+        && checkpoint.path.iter().all(|&city| {
+            (city as usize) < num_cities && !std::mem::replace(&mut seen[city as usize], true)
+        })
+        && (checkpoint.mode == TourMode::Open || checkpoint.path.first().is_none_or(|&c| c == 0));
+    if !valid_prefix {
+        return Err(TSPError::InvalidCheckpoint);
+    }
+
+    search_from(
+        &intercity_map,
+        num_cities,
+        checkpoint,
+        None,
+        checkpoint_interval,
+        &mut on_checkpoint,
+    )
+}
+
+fn search_from<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    checkpoint: BranchAndBoundCheckpoint<W::Cost>,
+    deadline: Option<Instant>,
+    checkpoint_interval: u64,
+    on_checkpoint: &mut dyn FnMut(&BranchAndBoundCheckpoint<W::Cost>) -> ControlFlow<()>,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    let mode = checkpoint.mode;
+    let (best_path, best_cost) = if checkpoint.best_path.is_empty() {
+        greedy_tour(cost_function, num_cities, mode)?
+    } else {
+        (checkpoint.best_path, checkpoint.best_cost)
+    };
+    let mut search = Search {
+        cost_function,
+        mode,
+        path: Vec::with_capacity(num_cities),
+        visited: vec![false; num_cities],
+        cost: W::Cost::ZERO,
+        best_path,
+        best_cost,
+        deadline,
+        num_nodes: checkpoint.num_nodes,
+        stopped: false,
+        resume: checkpoint.path,
+        checkpoint_interval,
+        on_checkpoint,
+    };
+    // round trips are the same from any start, open paths are tried from each
+    let starts = match mode {
+        TourMode::Open => num_cities as u32,
+        TourMode::Closed => 1,
+    };
+    let first = search.resume.first().copied().unwrap_or(0);
+    for start in first..starts {
+        search.visit(start, W::Cost::ZERO);
+        search.branch();
+        search.leave(W::Cost::ZERO);
+    }
+
+    Ok((search.best_path, search.best_cost))
+}
+
+// nearest neighbour from each possible start, the cheapest kept
+fn greedy_tour<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    mode: TourMode,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    let starts = match mode {
+        TourMode::Open => num_cities as u32,
+        TourMode::Closed => 1,
+    };
+    let tours = (0..starts)
+        .map(|start| nearest_neighbour_tsp_with(cost_function, num_cities, start, mode))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tours
+        .into_iter()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap())
+}
+
+struct Search<'a, W: Weight, C> {
+    cost_function: &'a C,
+    mode: TourMode,
+    path: Vec<u32>,
+    visited: Vec<bool>,
+    cost: W::Cost, // of the path so far
+    best_path: Vec<u32>,
+    best_cost: W::Cost,
+    deadline: Option<Instant>,
+    num_nodes: u64,
+    stopped: bool,
+    // the checkpointed prefix while the search walks back down to it, empty once it's reached
+    resume: Vec<u32>,
+    checkpoint_interval: u64,
+    on_checkpoint: &'a mut dyn FnMut(&BranchAndBoundCheckpoint<W::Cost>) -> ControlFlow<()>,
+}
+
+impl<W: Weight, C: CostFunction<W>> Search<'_, W, C> {
+    fn weight(&self, from: u32, to: u32) -> W::Cost {
+        self.cost_function.cost(from, to).to_cost()
+    }
+
+    fn visit(&mut self, city: u32, weight: W::Cost) {
+        self.path.push(city);
+        self.visited[city as usize] = true;
+        self.cost += weight;
+    }
+
+    // back to the path before the last visit and its cost, restored rather than the weight taken
+    // off again so that float costs don't drift
+    fn leave(&mut self, cost: W::Cost) {
+        let city = self.path.pop().unwrap();
+        self.visited[city as usize] = false;
+        self.cost = cost;
+        // a tighter best cost can prune the way down before the checkpointed prefix is reached,
+        // either way it's behind the search once a visit on the way is left
+        self.resume.clear();
+    }
+
+    // the rest of the tour runs from the last city through every unvisited one, and back to the
+    // start for round trips, which is a spanning tree of those cities
+    fn bound(&self) -> W::Cost {
+        let mut rest: Vec<u32> = (0..self.visited.len() as u32)
+            .filter(|&city| !self.visited[city as usize])
+            .collect();
+        rest.push(*self.path.last().unwrap());
+        if self.mode == TourMode::Closed {
+            rest.push(self.path[0]);
+        }
+        self.cost + spanning_tree_weight(self.cost_function, &rest)
+    }
+
+    fn branch(&mut self) {
+        if self.path == self.resume {
+            self.resume.clear();
+        }
+        // prefixes on the way down to the checkpointed one were counted before
+        if self.resume.is_empty() {
+            self.num_nodes += 1;
+            if self.num_nodes.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+                && self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                self.stopped = true;
+            }
+            if self.checkpoint_interval != 0
+                && self.num_nodes.is_multiple_of(self.checkpoint_interval)
+                && !self.stopped
+            {
+                let checkpoint = BranchAndBoundCheckpoint {
+                    mode: self.mode,
+                    path: self.path.clone(),
+                    best_path: self.best_path.clone(),
+                    best_cost: self.best_cost,
+                    num_nodes: self.num_nodes,
+                };
+                if (self.on_checkpoint)(&checkpoint).is_break() {
+                    self.stopped = true;
+                }
+            }
+        }
+        if self.stopped {
+            return;
+        }
+
+        let last = *self.path.last().unwrap();
+        if self.path.len() == self.visited.len() {
+            let cost = match self.mode {
+                TourMode::Open => self.cost,
+                TourMode::Closed => self.cost + self.weight(last, self.path[0]),
+            };
+            if cost < self.best_cost {
+                self.best_cost = cost;
+                self.best_path.clone_from(&self.path);
+            }
+            return;
+        }
+        if self.bound() >= self.best_cost {
+            return;
+        }
+
+        let mut next: Vec<u32> = (0..self.visited.len() as u32)
+            .filter(|&city| !self.visited[city as usize])
+            .collect();
+        next.sort_by(|&a, &b| self.weight(last, a).total_cmp(&self.weight(last, b)));
+        // back down to the checkpointed prefix, skipping the cities before it that were searched
+        if let Some(&resumed) = self.resume.get(self.path.len()) {
+            let position = next.iter().position(|&city| city == resumed).unwrap();
+            next.drain(..position);
+        }
+        let cost = self.cost;
+        for city in next {
+            let weight = self.weight(last, city);
+            if cost + weight >= self.best_cost {
+                break; // the rest are no cheaper
+            }
+            self.visit(city, weight);
+            self.branch();
+            self.leave(cost);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{generate_asymmetric_map, generate_map, TSPError};
+    use crate::cost::TourMode;
+    use crate::metric::{metric_map, Euclidean};
+    use crate::solvers::branch_and_bound::{
+        branch_and_bound_tsp, branch_and_bound_tsp_with, resume_branch_and_bound_tsp,
+        BranchAndBoundCheckpoint,
+    };
+    use crate::solvers::held_karp::held_karp_tsp_with;
+    use crate::testing::is_cost_consistent;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::ops::ControlFlow;
+    use std::time::Instant;
+
+    #[test]
+    fn test_branch_and_bound() {
+        for map in [
+            generate_map(10, (1, 100)).ok().unwrap(),
+            generate_asymmetric_map(10, (1, 100)).ok().unwrap(),
+        ] {
+            for mode in [TourMode::Open, TourMode::Closed] {
+                let (path, cost) = branch_and_bound_tsp_with(&map, map.len(), mode, None)
+                    .ok()
+                    .unwrap();
+                assert_eq!(cost, mode.cost(&map, &path));
+                let exact = held_karp_tsp_with(&map, map.len(), mode).ok().unwrap();
+                assert_eq!(cost, exact.1);
+            }
+        }
+
+        // past Held-Karp's limit on points in the plane, where the bound prunes well
+        let mut rng = StdRng::seed_from_u64(5);
+        let points: Vec<(f64, f64)> = (0..22)
+            .map(|_| (rng.gen_range(0.0..1000.), rng.gen_range(0.0..1000.)))
+            .collect();
+        let map: Vec<Vec<u16>> = metric_map(&points, &Euclidean).ok().unwrap();
+        let (path, cost) = branch_and_bound_tsp_with(&map, map.len(), TourMode::Closed, None)
+            .ok()
+            .unwrap();
+        assert_eq!(cost, TourMode::Closed.cost(&map, &path));
+
+        // a passed deadline still gives a valid path
+        let deadline = Some(Instant::now());
+        let (path, cost) = branch_and_bound_tsp_with(&map, map.len(), TourMode::Open, deadline)
+            .ok()
+            .unwrap();
+        assert!(is_cost_consistent(&map, &path, cost));
+        assert_eq!(branch_and_bound_tsp(&[vec![0u16]]).ok(), Some((vec![0], 0)));
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let map = generate_asymmetric_map(11, (1, 100)).ok().unwrap();
+        for mode in [TourMode::Open, TourMode::Closed] {
+            let (_, optimal_cost) = held_karp_tsp_with(&map, map.len(), mode).ok().unwrap();
+
+            // stopped at every checkpoint and carried on from it until the search ends
+            let mut saved = BranchAndBoundCheckpoint::start(mode);
+            let mut num_stops = 0;
+            let (path, cost) = loop {
+                let mut latest = None;
+                let result = resume_branch_and_bound_tsp(&map, saved.clone(), 50, |checkpoint| {
+                    latest = Some(checkpoint.clone());
+                    ControlFlow::Break(())
+                })
+                .ok()
+                .unwrap();
+                match latest {
+                    Some(checkpoint) => saved = checkpoint,
+                    None => break result,
+                }
+                num_stops += 1;
+            };
+            assert!(num_stops > 1);
+            // no prefix searched twice
+            assert_eq!(saved.num_nodes, 50 * num_stops);
+            assert_eq!(cost, optimal_cost);
+            assert_eq!(cost, mode.cost(&map, &path));
+        }
+
+        let mut repeated = BranchAndBoundCheckpoint::start(TourMode::Open);
+        repeated.path = vec![1, 1];
+        let mut elsewhere = BranchAndBoundCheckpoint::start(TourMode::Closed);
+        elsewhere.path = vec![2];
+        for checkpoint in [repeated, elsewhere] {
+            assert!(matches!(
+                resume_branch_and_bound_tsp(&map, checkpoint, 0, |_| ControlFlow::Continue(())),
+                Err(TSPError::InvalidCheckpoint)
+            ));
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::common::{check_city_map, check_symmetric_with, TSPError};
+use crate::cost::{CostFunction, TourMode};
+use crate::matching::min_weight_perfect_matching;
+use crate::weight::Weight;
+
+// the spanning tree plus a minimum weight perfect matching of its odd degree cities has every
+// degree even, so an euler tour of it exists, and shortcutting past the cities it revisits gives
+// a round trip at most 1.5 times the optimal one when the weights obey the triangle inequality,
+// an open path being that round trip without its heaviest edge
+pub fn christofides_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    christofides_tsp_with(&intercity_map, intercity_map.len(), TourMode::Open)
+}
+
+// same as christofides_tsp but for any cost function over num_cities cities and either mode
+pub(crate) fn christofides_tsp_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    mode: TourMode,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_symmetric_with(cost_function, num_cities)?;
+    let weight = |i: usize, j: usize| cost_function.cost(i as u32, j as u32);
+
+    let mut edges = spanning_tree(cost_function, num_cities);
+    let mut degree = vec![0; num_cities];
+    for &(from, to) in &edges {
+        degree[from as usize] += 1;
+        degree[to as usize] += 1;
+    }
+    let odd: Vec<usize> = (0..num_cities)
+        .filter(|&city| degree[city] % 2 == 1)
+        .collect();
+    // the matching weighs in whole numbers, float weights are scaled to fill a u32 first
+    let heaviest = odd
+        .iter()
+        .flat_map(|&i| odd.iter().map(move |&j| weight(i, j).to_f64()))
+        .fold(0., f64::max);
+    let scale = if W::INTEGRAL || heaviest == 0. {
+        1.
+    } else {
+        u32::MAX as f64 / heaviest
+    };
+    let mate = min_weight_perfect_matching(odd.len(), |i, j| {
+        (weight(odd[i], odd[j]).to_f64() * scale).round() as u32
+    });
+    for (i, &j) in mate.iter().enumerate() {
+        if i < j {
+            edges.push((odd[i] as u32, odd[j] as u32));
+        }
+    }
+
+    let mut path = shortcut_euler_tour(num_cities, &edges);
+    if mode == TourMode::Open {
+        open_at_heaviest_edge(cost_function, &mut path);
+    }
+    let cost = mode.cost(cost_function, &path);
+    Ok((path, cost))
+}
+
+// prim's algorithm as in bounds::spanning_tree_weight, keeping the edges
+pub(crate) fn spanning_tree<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+) -> Vec<(u32, u32)> {
+    let mut in_tree = vec![false; num_cities];
+    let mut closest: Vec<(W, u32)> = (0..num_cities as u32)
+        .map(|city| (cost_function.cost(0, city), 0))
+        .collect();
+    in_tree[0] = true;
+
+    let mut edges = Vec::with_capacity(num_cities.saturating_sub(1));
+    for _ in 1..num_cities {
+        let next = (0..num_cities)
+            .filter(|&city| !in_tree[city])
+            .min_by(|&a, &b| closest[a].0.total_cmp(&closest[b].0))
+            .unwrap();
+        in_tree[next] = true;
+        edges.push((closest[next].1, next as u32));
+        for city in 0..num_cities {
+            let weight = cost_function.cost(next as u32, city as u32);
+            if !in_tree[city] && weight < closest[city].0 {
+                closest[city] = (weight, next as u32);
+            }
+        }
+    }
+    edges
+}
+
+// hierholzer's algorithm from city 0 over the multigraph of edges, every degree being even, each
+// city kept the first time the tour reaches it
+pub(crate) fn shortcut_euler_tour(num_cities: usize, edges: &[(u32, u32)]) -> Vec<u32> {
+    let mut incident = vec![Vec::new(); num_cities];
+    for (index, &(from, to)) in edges.iter().enumerate() {
+        incident[from as usize].push((to, index));
+        incident[to as usize].push((from, index));
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut visited = vec![false; num_cities];
+    let mut path = Vec::with_capacity(num_cities);
+    let mut stack = vec![0u32];
+    while let Some(&city) = stack.last() {
+        let next = loop {
+            match incident[city as usize].pop() {
+                Some((next, index)) if !used[index] => {
+                    used[index] = true;
+                    break Some(next);
+                }
+                Some(_) => continue,
+                None => break None,
+            }
+        };
+        match next {
+            Some(next) => stack.push(next),
+            None => {
+                stack.pop();
+                if !std::mem::replace(&mut visited[city as usize], true) {
+                    path.push(city);
+                }
+            }
+        }
+    }
+    path
+}
+
+// rotates a round trip so that its heaviest edge is the return edge an open path leaves out
+pub(crate) fn open_at_heaviest_edge<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    path: &mut [u32],
+) {
+    let num_cities = path.len();
+    let weight = |i: usize| cost_function.cost(path[i], path[(i + 1) % num_cities]);
+    let heaviest = (0..num_cities).max_by(|&a, &b| weight(a).total_cmp(&weight(b)));
+    if let Some(heaviest) = heaviest {
+        path.rotate_left((heaviest + 1) % num_cities);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{check_path, generate_asymmetric_map, TSPError};
+    use crate::cost::TourMode;
+    use crate::metric::{metric_map, Euclidean};
+    use crate::solvers::christofides::{christofides_tsp, christofides_tsp_with};
+    use crate::solvers::held_karp::held_karp_tsp_with;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_christofides() {
+        let mut rng = StdRng::seed_from_u64(9);
+        for num_cities in [1, 2, 5, 12, 16] {
+            let points: Vec<(f64, f64)> = (0..num_cities)
+                .map(|_| (rng.gen_range(0.0..1000.), rng.gen_range(0.0..1000.)))
+                .collect();
+            let map: Vec<Vec<u16>> = metric_map(&points, &Euclidean).ok().unwrap();
+            let (path, cost) = christofides_tsp_with(&map, map.len(), TourMode::Closed)
+                .ok()
+                .unwrap();
+            assert!(check_path(num_cities, &path).is_ok());
+            assert_eq!(cost, TourMode::Closed.cost(&map, &path));
+            // rounding the distances can break the triangle inequality by a little
+            let (_, optimal) = held_karp_tsp_with(&map, map.len(), TourMode::Closed)
+                .ok()
+                .unwrap();
+            assert!(cost as f64 <= 1.5 * optimal as f64 + num_cities as f64);
+
+            let (path, open_cost) = christofides_tsp(&map).ok().unwrap();
+            assert_eq!(open_cost, TourMode::Open.cost(&map, &path));
+            assert!(open_cost <= cost);
+        }
+
+        let asymmetric = generate_asymmetric_map(5, (1, 100)).ok().unwrap();
+        assert!(matches!(
+            christofides_tsp(&asymmetric),
+            Err(TSPError::AsymmetricWeight { .. })
+        ));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::common::{check_city_map, TSPError};
+use crate::cost::{CostFunction, TourMode};
+use crate::solvers::christofides::{open_at_heaviest_edge, shortcut_euler_tour, spanning_tree};
+use crate::weight::Weight;
+
+pub fn double_tree_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    Ok(double_tree_tsp_with(
+        &intercity_map,
+        intercity_map.len(),
+        TourMode::Open,
+    ))
+}
+
+// same as double_tree_tsp but for any cost function over num_cities cities and either mode
+pub(crate) fn double_tree_tsp_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    mode: TourMode,
+) -> (Vec<u32>, W::Cost) {
+    let mut path = double_tree_tour_with(cost_function, num_cities);
+    if mode == TourMode::Open {
+        open_at_heaviest_edge(cost_function, &mut path);
+    }
+    let cost = mode.cost(cost_function, &path);
+    (path, cost)
+}
+
+// the spanning tree with every edge doubled has an euler tour, shortcutting it visits the cities
+// in depth first order and gives a round trip at most twice the optimal one on symmetric maps
+// obeying the triangle inequality, cheaper than christofides but further from optimal
+pub fn double_tree_tour<W: Weight>(intercity_map: &[Vec<W>]) -> Result<Vec<u32>, TSPError> {
+    check_city_map(intercity_map)?;
+    Ok(double_tree_tour_with(&intercity_map, intercity_map.len()))
+}
+
+pub(crate) fn double_tree_tour_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+) -> Vec<u32> {
+    let tree = spanning_tree(cost_function, num_cities);
+    let doubled: Vec<(u32, u32)> = tree.iter().chain(&tree).copied().collect();
+    shortcut_euler_tour(num_cities, &doubled)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{check_path, generate_asymmetric_map};
+    use crate::cost::TourMode;
+    use crate::metric::{metric_map, Euclidean};
+    use crate::solvers::double_tree::{double_tree_tsp, double_tree_tsp_with};
+    use crate::solvers::held_karp::held_karp_tsp_with;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_double_tree() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for num_cities in [1, 2, 7, 14] {
+            let points: Vec<(f64, f64)> = (0..num_cities)
+                .map(|_| (rng.gen_range(0.0..1000.), rng.gen_range(0.0..1000.)))
+                .collect();
+            let map: Vec<Vec<u16>> = metric_map(&points, &Euclidean).ok().unwrap();
+            let (path, cost) = double_tree_tsp_with(&map, num_cities, TourMode::Closed);
+            assert!(check_path(num_cities, &path).is_ok());
+            assert_eq!(cost, TourMode::Closed.cost(&map, &path));
+            let (_, optimal) = held_karp_tsp_with(&map, map.len(), TourMode::Closed)
+                .ok()
+                .unwrap();
+            assert!(cost <= 2 * optimal + num_cities as u64);
+            assert_eq!(
+                double_tree_tsp_with(&map, num_cities, TourMode::Closed),
+                (path, cost)
+            );
+        }
+
+        // no guarantee without symmetric weights, but still a tour
+        let map = generate_asymmetric_map(20, (1, 100)).ok().unwrap();
+        let (path, cost) = double_tree_tsp(&map).ok().unwrap();
+        assert!(check_path(20, &path).is_ok());
+        assert_eq!(cost, TourMode::Open.cost(&map, &path));
+    }
+}
@@ -0,0 +1,326 @@
+use rand::seq::SliceRandom;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::time::Instant;
+
+use crate::common::{check_city_map, TSPError};
+use crate::cost::{CostFunction, MatrixCost, TourMode};
+use crate::stopping::StoppingCriteria;
+use crate::weight::{Cost, Weight};
+
+// how a child tour is bred from two parents, both keeping a segment of the first parent in place
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Crossover {
+    // OX, the rest of the cities in the order the second parent visits them, which keeps the
+    // relative order and so most of its edges
+    #[default]
+    Order,
+    // PMX, the rest of the cities where the second parent has them, the segment's displaced
+    // cities following the mapping between the parents' segments to a free position
+    PartiallyMapped,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Mutation {
+    // two cities trade places
+    Swap,
+    // a segment is reversed, the 2-opt move
+    #[default]
+    Inversion,
+}
+
+// a population of tours evolved by tournament selection, crossover and mutation, the elite
+// carried over to every next generation unchanged, the stopping criteria counting generations
+// for iterations and the temperature not applying
+#[derive(Clone, PartialEq, Debug)]
+pub struct GeneticAlgorithm {
+    pub population_size: usize,
+    pub crossover: Crossover,
+    pub mutation: Mutation,
+    pub mutation_rate: f64, // chance of each child being mutated once
+    pub tournament_size: usize,
+    pub elitism: usize, // the best this many of a generation survive into the next
+    pub stopping: StoppingCriteria,
+    pub seed: Option<u64>, // unseeded runs differ every time
+}
+
+impl Default for GeneticAlgorithm {
+    fn default() -> Self {
+        GeneticAlgorithm {
+            population_size: 100,
+            crossover: Crossover::default(),
+            mutation: Mutation::default(),
+            mutation_rate: 0.5,
+            tournament_size: 3,
+            elitism: 2,
+            stopping: StoppingCriteria {
+                max_iterations: 2000,
+                epoch_length: 100,
+                ..StoppingCriteria::default()
+            },
+            seed: None,
+        }
+    }
+}
+
+impl GeneticAlgorithm {
+    pub fn check(&self) -> Result<(), TSPError> {
+        self.stopping.check()?;
+        let valid = self.population_size >= 2
+            && (1..=self.population_size).contains(&self.tournament_size)
+            && self.elitism < self.population_size
+            && (0.0..=1.0).contains(&self.mutation_rate);
+        if !valid {
+            return Err(TSPError::InvalidGeneticParameters);
+        }
+        Ok(())
+    }
+}
+
+pub fn genetic_algorithm_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+    genetic: &GeneticAlgorithm,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+
+    let matrix = MatrixCost::new(intercity_map);
+    evolve(&matrix, intercity_map.len(), genetic, TourMode::Open, None)
+}
+
+// the best tour of any generation, no new generation being bred past the deadline
+pub(crate) fn evolve<W: Weight>(
+    cost_function: &dyn CostFunction<W>,
+    num_cities: usize,
+    genetic: &GeneticAlgorithm,
+    mode: TourMode,
+    deadline: Option<Instant>,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    genetic.check()?;
+
+    let mut rng = match genetic.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    // a whole generation at a time, see CostFunction::path_weights
+    let costed = |paths: Vec<Vec<u32>>| {
+        let costs = mode.costs(cost_function, &paths);
+        paths.into_iter().zip(costs)
+    };
+    let paths: Vec<Vec<u32>> = (0..genetic.population_size)
+        .map(|_| {
+            let mut path: Vec<u32> = (0..num_cities as u32).collect();
+            path.shuffle(&mut rng);
+            path
+        })
+        .collect();
+    let mut population: Vec<(Vec<u32>, W::Cost)> = costed(paths).collect();
+    population.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    let stopping = &genetic.stopping;
+    let mut best = population[0].clone();
+    let mut last_improved = 0;
+    for generation in 1..=stopping.max_iterations {
+        if stopping
+            .target_cost
+            .is_some_and(|target| best.1.to_f64() <= target as f64)
+            || stopping
+                .stale_limit()
+                .is_some_and(|limit| generation - 1 - last_improved >= limit)
+            || deadline.is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            break;
+        }
+
+        let mut next: Vec<(Vec<u32>, W::Cost)> = population[..genetic.elitism].to_vec();
+        let mut children: Vec<Vec<u32>> = Vec::new();
+        while next.len() + children.len() < genetic.population_size {
+            let first = tournament(&population, genetic.tournament_size, &mut rng);
+            let second = tournament(&population, genetic.tournament_size, &mut rng);
+            let mut child = match genetic.crossover {
+                Crossover::Order => order_crossover(first, second, &mut rng),
+                Crossover::PartiallyMapped => partially_mapped_crossover(first, second, &mut rng),
+            };
+            if rng.gen_bool(genetic.mutation_rate) {
+                mutate(&mut child, genetic.mutation, &mut rng);
+            }
+            children.push(child);
+        }
+        next.extend(costed(children));
+        next.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        population = next;
+
+        if population[0].1 < best.1 {
+            best = population[0].clone();
+            last_improved = generation;
+        }
+    }
+
+    Ok(best)
+}
+
+// the fittest of tournament_size tours drawn at random, with replacement
+fn tournament<'p, C>(
+    population: &'p [(Vec<u32>, C)],
+    tournament_size: usize,
+    rng: &mut StdRng,
+) -> &'p [u32] {
+    let winner = (0..tournament_size)
+        .map(|_| rng.gen_range(0..population.len()))
+        .min()
+        .unwrap(); // the population is sorted by cost
+    &population[winner].0
+}
+
+// a segment from first to second, inclusive, both within the path
+fn segment(num_cities: usize, rng: &mut StdRng) -> (usize, usize) {
+    let a = rng.gen_range(0..num_cities);
+    let b = rng.gen_range(0..num_cities);
+    (a.min(b), a.max(b))
+}
+
+pub(crate) fn order_crossover(first: &[u32], second: &[u32], rng: &mut StdRng) -> Vec<u32> {
+    let num_cities = first.len();
+    let (start, end) = segment(num_cities, rng);
+    let mut in_segment = vec![false; num_cities];
+    for &city in &first[start..=end] {
+        in_segment[city as usize] = true;
+    }
+
+    // the second parent's other cities fill the child from just past the segment, wrapping round
+    let mut child = first.to_vec();
+    let mut position = (end + 1) % num_cities;
+    for offset in 1..=num_cities {
+        let city = second[(end + offset) % num_cities];
+        if !in_segment[city as usize] {
+            child[position] = city;
+            position = (position + 1) % num_cities;
+        }
+    }
+    child
+}
+
+pub(crate) fn partially_mapped_crossover(
+    first: &[u32],
+    second: &[u32],
+    rng: &mut StdRng,
+) -> Vec<u32> {
+    let num_cities = first.len();
+    let (start, end) = segment(num_cities, rng);
+    // where each city sits in the first parent's segment
+    let mut segment_position = vec![None; num_cities];
+    for position in start..=end {
+        segment_position[first[position] as usize] = Some(position);
+    }
+
+    let mut child = second.to_vec();
+    child[start..=end].copy_from_slice(&first[start..=end]);
+    for position in (0..start).chain(end + 1..num_cities) {
+        let mut city = second[position];
+        while let Some(mapped) = segment_position[city as usize] {
+            city = second[mapped];
+        }
+        child[position] = city;
+    }
+    child
+}
+
+fn mutate(path: &mut [u32], mutation: Mutation, rng: &mut StdRng) {
+    let (start, end) = segment(path.len(), rng);
+    match mutation {
+        Mutation::Swap => path.swap(start, end),
+        Mutation::Inversion => path[start..=end].reverse(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{check_path, generate_map, TSPError};
+    use crate::cost::TourMode;
+    use crate::solvers::genetic::{
+        evolve, genetic_algorithm_tsp, order_crossover, partially_mapped_crossover, Crossover,
+        GeneticAlgorithm, Mutation,
+    };
+    use crate::solvers::held_karp::held_karp_tsp;
+    use crate::stopping::StoppingCriteria;
+    use rand::seq::SliceRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_crossovers() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut first: Vec<u32> = (0..20).collect();
+        let mut second = first.clone();
+        for _ in 0..100 {
+            first.shuffle(&mut rng);
+            second.shuffle(&mut rng);
+            for child in [
+                order_crossover(&first, &second, &mut rng),
+                partially_mapped_crossover(&first, &second, &mut rng),
+            ] {
+                assert!(check_path(20, &child).is_ok());
+            }
+        }
+
+        // identical parents breed the same tour
+        assert_eq!(order_crossover(&first, &first, &mut rng), first);
+        assert_eq!(partially_mapped_crossover(&first, &first, &mut rng), first);
+    }
+
+    #[test]
+    fn test_genetic_algorithm() {
+        let map = generate_map(8, (1, 100)).ok().unwrap();
+        let (_, optimal) = held_karp_tsp(&map).ok().unwrap();
+        for crossover in [Crossover::Order, Crossover::PartiallyMapped] {
+            for mutation in [Mutation::Swap, Mutation::Inversion] {
+                let genetic = GeneticAlgorithm {
+                    population_size: 50,
+                    crossover,
+                    mutation,
+                    seed: Some(5),
+                    stopping: StoppingCriteria {
+                        max_iterations: 300,
+                        ..GeneticAlgorithm::default().stopping
+                    },
+                    ..GeneticAlgorithm::default()
+                };
+                let (path, cost) = genetic_algorithm_tsp(&map, &genetic).ok().unwrap();
+                assert_eq!(cost, TourMode::Open.cost(&map, &path));
+                assert!(cost >= optimal);
+                assert_eq!(
+                    genetic_algorithm_tsp(&map, &genetic).ok(),
+                    Some((path, cost))
+                );
+            }
+        }
+
+        // inversions keep a population diverse enough to find the optimum of a small map
+        let genetic = GeneticAlgorithm {
+            seed: Some(5),
+            ..GeneticAlgorithm::default()
+        };
+        let (_, cost) = genetic_algorithm_tsp(&map, &genetic).ok().unwrap();
+        assert_eq!(cost, optimal);
+
+        // stops as soon as a tour is good enough, any round trip here costing at most 8 * 100
+        let target = GeneticAlgorithm {
+            stopping: StoppingCriteria {
+                max_iterations: u64::MAX,
+                target_cost: Some(800),
+                ..StoppingCriteria::default()
+            },
+            ..GeneticAlgorithm::default()
+        };
+        let (path, cost) = evolve(&map, map.len(), &target, TourMode::Closed, None)
+            .ok()
+            .unwrap();
+        assert_eq!(cost, TourMode::Closed.cost(&map, &path));
+
+        let no_population = GeneticAlgorithm {
+            population_size: 1,
+            ..GeneticAlgorithm::default()
+        };
+        assert!(matches!(
+            genetic_algorithm_tsp(&map, &no_population),
+            Err(TSPError::InvalidGeneticParameters)
+        ));
+    }
+}
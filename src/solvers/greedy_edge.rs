@@ -0,0 +1,161 @@
+use crate::common::{check_city_map, TSPError};
+use crate::cost::{CostFunction, TourMode};
+use crate::weight::{lighter, Weight};
+
+pub fn greedy_edge_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    greedy_edge_tsp_with(&intercity_map, intercity_map.len(), TourMode::Open)
+}
+
+// same as greedy_edge_tsp but for any cost function over num_cities cities and either mode
+pub(crate) fn greedy_edge_tsp_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    mode: TourMode,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    let mut path = greedy_edge_tour_with(cost_function, num_cities);
+    // the edges chosen have no direction, the path is walked whichever way is cheaper
+    let forward = mode.cost(cost_function, &path);
+    path.reverse();
+    let backward = mode.cost(cost_function, &path);
+    let cost = if forward <= backward {
+        path.reverse();
+        forward
+    } else {
+        backward
+    };
+    Ok((path, cost))
+}
+
+// greedy matching of edges, cheapest first, each kept unless it would give a city a third edge
+// or close a cycle, until the n - 1 kept make one path through every city, which usually makes a
+// better start for local search than nearest neighbour, an edge weighing the cheaper of its two
+// directions on asymmetric maps
+pub fn greedy_edge_tour<W: Weight>(intercity_map: &[Vec<W>]) -> Result<Vec<u32>, TSPError> {
+    check_city_map(intercity_map)?;
+    Ok(greedy_edge_tour_with(&intercity_map, intercity_map.len()))
+}
+
+pub(crate) fn greedy_edge_tour_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+) -> Vec<u32> {
+    let mut edges: Vec<(W, usize, usize)> = (0..num_cities)
+        .flat_map(|from| (from + 1..num_cities).map(move |to| (from, to)))
+        .map(|(from, to)| {
+            let (a, b) = (from as u32, to as u32);
+            let weight = lighter(cost_function.cost(a, b), cost_function.cost(b, a));
+            (weight, from, to)
+        })
+        .collect();
+    edges.sort_unstable_by(|a, b| a.0.total_cmp(&b.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+    let mut degree = vec![0u8; num_cities];
+    let mut fragments = Fragments::new(num_cities);
+    let mut neighbours = vec![Vec::with_capacity(2); num_cities];
+    let mut num_edges = 0;
+    for (_, from, to) in edges {
+        if num_edges + 1 == num_cities {
+            break;
+        }
+        if degree[from] == 2 || degree[to] == 2 || !fragments.join(from, to) {
+            continue;
+        }
+        degree[from] += 1;
+        degree[to] += 1;
+        neighbours[from].push(to);
+        neighbours[to].push(from);
+        num_edges += 1;
+    }
+
+    // walks the single path from one of its ends
+    let mut previous = usize::MAX;
+    let mut city = (0..num_cities).find(|&city| degree[city] < 2).unwrap();
+    let mut path = Vec::with_capacity(num_cities);
+    loop {
+        path.push(city as u32);
+        match neighbours[city].iter().find(|&&next| next != previous) {
+            Some(&next) => (previous, city) = (city, next),
+            None => break,
+        }
+    }
+    path
+}
+
+// union-find over the path fragments built so far
+struct Fragments {
+    parent: Vec<usize>,
+}
+
+impl Fragments {
+    fn new(num_cities: usize) -> Self {
+        Fragments {
+            parent: (0..num_cities).collect(),
+        }
+    }
+
+    fn root(&mut self, mut city: usize) -> usize {
+        while self.parent[city] != city {
+            self.parent[city] = self.parent[self.parent[city]];
+            city = self.parent[city];
+        }
+        city
+    }
+
+    // false if they are already in the same fragment, the edge would close a cycle
+    fn join(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.root(a), self.root(b));
+        self.parent[a] = b;
+        a != b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{check_path, generate_asymmetric_map, generate_map};
+    use crate::cost::TourMode;
+    use crate::metric::{metric_map, Euclidean};
+    use crate::solvers::greedy_edge::{greedy_edge_tsp, greedy_edge_tsp_with};
+    use crate::solvers::nearest_neighbour::nearest_neighbour_tsp_with;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_greedy_edge() {
+        // 0 - 1 and 2 - 3 are cheapest, then 1 - 2, as 0 - 3 would close the cycle
+        let map: Vec<Vec<u16>> = vec![
+            vec![0, 1, 9, 4],
+            vec![1, 0, 3, 8],
+            vec![9, 3, 0, 2],
+            vec![4, 8, 2, 0],
+        ];
+        assert_eq!(greedy_edge_tsp(&map).ok(), Some((vec![0, 1, 2, 3], 6)));
+        assert_eq!(greedy_edge_tsp(&[vec![0u16]]).ok(), Some((vec![0], 0)));
+
+        for map in [
+            generate_map(40, (1, 100)).ok().unwrap(),
+            generate_asymmetric_map(40, (1, 100)).ok().unwrap(),
+        ] {
+            for mode in [TourMode::Open, TourMode::Closed] {
+                let (path, cost) = greedy_edge_tsp_with(&map, map.len(), mode).ok().unwrap();
+                assert!(check_path(40, &path).is_ok());
+                assert_eq!(cost, mode.cost(&map, &path));
+            }
+        }
+
+        // on points in the plane it beats nearest neighbour
+        let mut rng = StdRng::seed_from_u64(3);
+        let points: Vec<(f64, f64)> = (0..200)
+            .map(|_| (rng.gen_range(0.0..1000.), rng.gen_range(0.0..1000.)))
+            .collect();
+        let map: Vec<Vec<u16>> = metric_map(&points, &Euclidean).ok().unwrap();
+        let (_, cost) = greedy_edge_tsp_with(&map, map.len(), TourMode::Closed)
+            .ok()
+            .unwrap();
+        let (_, nearest_cost) = nearest_neighbour_tsp_with(&map, map.len(), 0, TourMode::Closed)
+            .ok()
+            .unwrap();
+        assert!(cost < nearest_cost);
+    }
+}
@@ -0,0 +1,266 @@
+use std::ops::ControlFlow;
+
+use crate::common::{check_city_map, TSPError};
+use crate::cost::{CostFunction, TourMode};
+use crate::weight::{Cost, Weight};
+
+// the table holds a cost per subset of cities and city ending a path through it, 2^20 * 20 costs
+// being 160 MiB
+pub const HELD_KARP_MAX_CITIES: usize = 20;
+
+// rough time to relax one (subset, last city, previous city) triple
+const SECONDS_PER_RELAXATION: f64 = 2e-9;
+
+pub fn estimate_held_karp_seconds(num_cities: usize) -> f64 {
+    2f64.powi(num_cities as i32) * (num_cities * num_cities) as f64 * SECONDS_PER_RELAXATION
+}
+
+// exact by dynamic programming over subsets, the cheapest path through each subset ending at each
+// of its cities being built from those of the subset without that city, O(n^2 2^n) rather than
+// the O(n!) of brute force
+pub fn held_karp_tsp<W: Weight>(intercity_map: &[Vec<W>]) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    held_karp_tsp_with(&intercity_map, intercity_map.len(), TourMode::Open)
+}
+
+// same as held_karp_tsp but for any cost function over num_cities cities and either mode, round
+// trips starting and ending at city 0
+pub(crate) fn held_karp_tsp_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    mode: TourMode,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    let checkpoint = HeldKarpCheckpoint::start(num_cities, mode)?;
+    let optimum = held_karp_from(cost_function, num_cities, checkpoint, 0, |_| {
+        ControlFlow::Continue(())
+    });
+    Ok(optimum.unwrap())
+}
+
+fn check_num_cities(num_cities: usize) -> Result<(), TSPError> {
+    if num_cities > HELD_KARP_MAX_CITIES {
+        return Err(TSPError::TooManyCities {
+            num_cities,
+            max_cities: HELD_KARP_MAX_CITIES,
+            estimated_seconds: estimate_held_karp_seconds(num_cities),
+        });
+    }
+    Ok(())
+}
+
+// enough state to carry on an interrupted run, subsets are finished in increasing order and only
+// ever extend to larger ones, so the table's costs are final for every subset before next_subset,
+// the table being most of it at 160 MiB for 20 cities
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, PartialEq, Debug)]
+pub struct HeldKarpCheckpoint<C = u64> {
+    pub mode: TourMode,
+    pub next_subset: usize,
+    // the cheapest path visiting exactly a subset and ending at one of its cities, at
+    // subset * num_cities + city
+    pub cheapest: Vec<C>,
+}
+
+impl<C: Cost> HeldKarpCheckpoint<C> {
+    pub fn start(num_cities: usize, mode: TourMode) -> Result<HeldKarpCheckpoint<C>, TSPError> {
+        check_num_cities(num_cities)?;
+        let mut cheapest = vec![C::MAX; (1 << num_cities) * num_cities];
+        match mode {
+            TourMode::Open => {
+                for city in 0..num_cities {
+                    cheapest[(1 << city) * num_cities + city] = C::ZERO;
+                }
+            }
+            TourMode::Closed if num_cities > 0 => cheapest[num_cities] = C::ZERO,
+            TourMode::Closed => {}
+        }
+        Ok(HeldKarpCheckpoint {
+            mode,
+            next_subset: 1,
+            cheapest,
+        })
+    }
+}
+
+// None when the run was stopped at a checkpoint
+pub type ResumedResult<C = u64> = Result<Option<(Vec<u32>, C)>, TSPError>;
+
+// continues a run from a checkpoint taken with the same map, on_checkpoint gets the state every
+// checkpoint_interval subsets to persist, returning ControlFlow::Break stops the run and gives
+// None as there is no tour until the last subset is done
+pub fn resume_held_karp_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+    checkpoint: HeldKarpCheckpoint<W::Cost>,
+    checkpoint_interval: usize,
+    on_checkpoint: impl FnMut(&HeldKarpCheckpoint<W::Cost>) -> ControlFlow<()>,
+) -> ResumedResult<W::Cost> {
+    check_city_map(intercity_map)?;
+    let num_cities = intercity_map.len();
+    check_num_cities(num_cities)?;
+    if checkpoint.cheapest.len() != (1 << num_cities) * num_cities
+        || !(1..=1 << num_cities).contains(&checkpoint.next_subset)
+    {
+        return Err(TSPError::InvalidCheckpoint);
+    }
+
+    Ok(held_karp_from(
+        &intercity_map,
+        num_cities,
+        checkpoint,
+        checkpoint_interval,
+        on_checkpoint,
+    ))
+}
+
+fn held_karp_from<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    mut checkpoint: HeldKarpCheckpoint<W::Cost>,
+    checkpoint_interval: usize,
+    mut on_checkpoint: impl FnMut(&HeldKarpCheckpoint<W::Cost>) -> ControlFlow<()>,
+) -> Option<(Vec<u32>, W::Cost)> {
+    let weight = |from: usize, to: usize| cost_function.cost(from as u32, to as u32).to_cost();
+    let index = |subset: usize, last: usize| subset * num_cities + last;
+    let full = (1 << num_cities) - 1;
+    let mode = checkpoint.mode;
+
+    let first = checkpoint.next_subset;
+    for subset in first..=full {
+        if checkpoint_interval != 0 && subset != first && subset.is_multiple_of(checkpoint_interval)
+        {
+            checkpoint.next_subset = subset;
+            if on_checkpoint(&checkpoint).is_break() {
+                return None;
+            }
+        }
+        let cheapest = &mut checkpoint.cheapest;
+        for last in (0..num_cities).filter(|last| subset & (1 << last) != 0) {
+            let cost = cheapest[index(subset, last)];
+            if cost == W::Cost::MAX {
+                continue;
+            }
+            for next in (0..num_cities).filter(|next| subset & (1 << next) == 0) {
+                let extended = &mut cheapest[index(subset | (1 << next), next)];
+                let through = cost + weight(last, next);
+                if through < *extended {
+                    *extended = through;
+                }
+            }
+        }
+    }
+
+    let cheapest = checkpoint.cheapest;
+    let total = |last: usize| match (mode, cheapest[index(full, last)]) {
+        (TourMode::Closed, cost) if cost != W::Cost::MAX => cost + weight(last, 0),
+        (_, cost) => cost,
+    };
+    let mut last = (0..num_cities)
+        .min_by(|&a, &b| total(a).total_cmp(&total(b)))
+        .unwrap();
+    let optimal_cost = total(last);
+
+    // walks back from the end, each city preceded by one its cost was built from
+    let mut path = vec![last as u32];
+    let mut subset = full;
+    while subset != 1 << last {
+        let cost = cheapest[index(subset, last)];
+        let rest = subset & !(1 << last);
+        let previous = (0..num_cities)
+            .filter(|previous| rest & (1 << previous) != 0)
+            .find(|&previous| {
+                let before = cheapest[index(rest, previous)];
+                before != W::Cost::MAX && before + weight(previous, last) == cost
+            })
+            .unwrap();
+        path.push(previous as u32);
+        subset = rest;
+        last = previous;
+    }
+    path.reverse();
+
+    Some((path, optimal_cost))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::brute_force::brute_force_tsp_with;
+    use crate::common::{generate_asymmetric_map, generate_map, TSPError};
+    use crate::cost::TourMode;
+    use crate::sim_annealing::simulated_annealing_tsp;
+    use crate::solvers::held_karp::{
+        held_karp_tsp, held_karp_tsp_with, resume_held_karp_tsp, HeldKarpCheckpoint,
+    };
+    use crate::testing::is_cost_consistent;
+    use std::ops::ControlFlow;
+
+    #[test]
+    fn test_held_karp() {
+        for map in [
+            generate_map(8, (1, 100)).ok().unwrap(),
+            generate_asymmetric_map(8, (1, 100)).ok().unwrap(),
+        ] {
+            for mode in [TourMode::Open, TourMode::Closed] {
+                let (path, cost) = held_karp_tsp_with(&map, map.len(), mode).ok().unwrap();
+                assert_eq!(cost, mode.cost(&map, &path));
+                let exact = brute_force_tsp_with(&map, map.len(), mode).ok().unwrap();
+                assert_eq!(cost, exact.1);
+            }
+        }
+
+        // out of brute force's reach, annealing never beats it
+        let map = generate_map(15, (1, 100)).ok().unwrap();
+        let (path, cost) = held_karp_tsp(&map).ok().unwrap();
+        assert!(is_cost_consistent(&map, &path, cost));
+        let (_, annealed_cost) = simulated_annealing_tsp(&map, 200, 20000).ok().unwrap();
+        assert!(annealed_cost >= cost);
+
+        assert_eq!(held_karp_tsp(&[vec![0u16]]).ok(), Some((vec![0], 0)));
+        let too_many = generate_map(21, (1, 100)).ok().unwrap();
+        assert!(matches!(
+            held_karp_tsp(&too_many),
+            Err(TSPError::TooManyCities { max_cities: 20, .. })
+        ));
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let map = generate_asymmetric_map(10, (1, 100)).ok().unwrap();
+        let (_, optimal_cost) = held_karp_tsp_with(&map, map.len(), TourMode::Closed)
+            .ok()
+            .unwrap();
+
+        // stop twice, carrying on from the saved state each time
+        let mut saved = HeldKarpCheckpoint::start(10, TourMode::Closed)
+            .ok()
+            .unwrap();
+        for _ in 0..2 {
+            let mut latest = None;
+            let stopped = resume_held_karp_tsp(&map, saved, 200, |checkpoint| {
+                latest = Some(checkpoint.clone());
+                ControlFlow::Break(())
+            });
+            assert_eq!(stopped.ok(), Some(None));
+            saved = latest.unwrap();
+        }
+        assert_eq!(saved.next_subset, 400);
+
+        let mut num_checkpoints = 0;
+        let (path, cost) = resume_held_karp_tsp(&map, saved, 200, |_| {
+            num_checkpoints += 1;
+            ControlFlow::Continue(())
+        })
+        .ok()
+        .unwrap()
+        .unwrap();
+        assert_eq!(cost, optimal_cost);
+        assert_eq!(cost, TourMode::Closed.cost(&map, &path));
+        assert_eq!(num_checkpoints, 3); // at 600, 800 and 1000 of the 1024 subsets
+
+        let smaller = HeldKarpCheckpoint::start(9, TourMode::Open).ok().unwrap();
+        assert!(matches!(
+            resume_held_karp_tsp(&map, smaller, 0, |_| ControlFlow::Continue(())),
+            Err(TSPError::InvalidCheckpoint)
+        ));
+        assert!(HeldKarpCheckpoint::<u64>::start(21, TourMode::Open).is_err());
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::VecDeque;
+
+use crate::candidates::{nearest_candidates, DEFAULT_CANDIDATES};
+use crate::common::{check_city_map, check_path, check_symmetric_with, TSPError};
+use crate::cost::{CostFunction, TourMode};
+use crate::solvers::greedy_edge::greedy_edge_tour_with;
+use crate::tour_repr::{ArrayTour, TourRepr, TwoLevelList};
+use crate::weight::{Delta, Weight};
+
+// the longest chain of exchanges tried from one city
+const MAX_DEPTH: usize = 50;
+// how many alternatives are backtracked to at the first steps of a chain, one after them
+const BREADTH: [usize; 2] = [5, 3];
+// the fewest cities whose tours are kept in a tour_repr::TwoLevelList, whose reversals only win
+// over an ArrayTour's on long tours
+pub(crate) const TWO_LEVEL_MIN_CITIES: usize = 2000;
+
+// improves the greedy edge tour
+pub fn lin_kernighan_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    let candidates = nearest_candidates(&intercity_map, intercity_map.len(), DEFAULT_CANDIDATES);
+    lin_kernighan_tsp_with(
+        &intercity_map,
+        intercity_map.len(),
+        TourMode::Open,
+        &candidates,
+    )
+}
+
+// same as lin_kernighan_tsp but for any cost function over num_cities cities and either mode,
+// chains joining each city only to its candidates, see candidates
+pub(crate) fn lin_kernighan_tsp_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    mode: TourMode,
+    candidates: &[Vec<u32>],
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    let path = greedy_edge_tour_with(cost_function, num_cities);
+    lin_kernighan_improve_with(cost_function, num_cities, path, mode, candidates)
+}
+
+// local search by sequential edge exchanges in the manner of Lin and Kernighan: an edge (t1, t2)
+// of the tour is removed, and while the gain so far stays positive the path's loose end t2 is
+// joined to a near city t3, whose edge to t4 is removed to keep one path, t4 becoming the loose
+// end, the tour being closed at the chain's most profitable step, a chain of two steps being a
+// sequential 3-opt move, over and over until no city starts an improving chain, needing
+// symmetric weights, an open path being a round trip through an extra city at no distance from
+// any other
+pub fn lin_kernighan_improve<W: Weight>(
+    intercity_map: &[Vec<W>],
+    path: Vec<u32>,
+    mode: TourMode,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    let candidates = nearest_candidates(&intercity_map, intercity_map.len(), DEFAULT_CANDIDATES);
+    lin_kernighan_improve_with(&intercity_map, intercity_map.len(), path, mode, &candidates)
+}
+
+pub(crate) fn lin_kernighan_improve_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    path: Vec<u32>,
+    mode: TourMode,
+    candidates: &[Vec<u32>],
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_symmetric_with(cost_function, num_cities)?;
+    check_path(num_cities, &path)?;
+
+    let mut order: Vec<usize> = path.iter().map(|&city| city as usize).collect();
+    if mode == TourMode::Open {
+        order.push(num_cities);
+    }
+
+    let order = if order.len() < TWO_LEVEL_MIN_CITIES {
+        Search::<W, _, ArrayTour>::new(cost_function, num_cities, order, candidates).optimise()
+    } else {
+        Search::<W, _, TwoLevelList>::new(cost_function, num_cities, order, candidates).optimise()
+    };
+    // the extra city goes back to being the missing return edge
+    let extra = order.iter().position(|&city| city == num_cities);
+    let path: Vec<u32> = match extra {
+        Some(extra) => order[extra + 1..]
+            .iter()
+            .chain(&order[..extra])
+            .map(|&city| city as u32)
+            .collect(),
+        None => order.iter().map(|&city| city as u32).collect(),
+    };
+    let cost = mode.cost(cost_function, &path);
+    Ok((path, cost))
+}
+
+struct Search<'a, W: Weight, C, T> {
+    cost_function: &'a C,
+    num_cities: usize,           // the extra city of an open path aside
+    candidates: Vec<Vec<usize>>, // each city's nearest neighbours, closest first
+    tour: T,                     // the round trip
+    in_chain: Vec<bool>,
+    // the chain being tried
+    steps: Vec<(usize, usize)>, // the reversals made, to undo
+    touched: Vec<usize>,
+    best_gain: W::Delta,
+    best_depth: usize,
+}
+
+impl<'a, W: Weight, C: CostFunction<W>, T: TourRepr> Search<'a, W, C, T> {
+    // the extra city of an open path is at no distance from any other, so it's every city's
+    // nearest and the first cities are its own
+    fn new(
+        cost_function: &'a C,
+        num_cities: usize,
+        order: Vec<usize>,
+        candidates: &[Vec<u32>],
+    ) -> Self {
+        let extra = (order.len() > num_cities).then_some(num_cities);
+        let mut candidates: Vec<Vec<usize>> = candidates
+            .iter()
+            .map(|list| {
+                let k = (list.len() + extra.iter().len()).min(DEFAULT_CANDIDATES);
+                let others = list.iter().map(|&city| city as usize);
+                extra.into_iter().chain(others).take(k).collect()
+            })
+            .collect();
+        if extra.is_some() {
+            candidates.push((0..num_cities.min(DEFAULT_CANDIDATES)).collect());
+        }
+        Search {
+            cost_function,
+            num_cities,
+            candidates,
+            tour: T::new(&order),
+            in_chain: vec![false; order.len()],
+            steps: Vec::new(),
+            touched: Vec::new(),
+            best_gain: W::Delta::ZERO,
+            best_depth: 0,
+        }
+    }
+
+    // the extra city of an open path is at no distance from any other
+    fn weight(&self, from: usize, to: usize) -> W::Delta {
+        if from == self.num_cities || to == self.num_cities {
+            return W::Delta::ZERO;
+        }
+        self.cost_function.cost(from as u32, to as u32).to_delta()
+    }
+
+    // runs chains from every city until none improves the tour, cities whose edges changed being
+    // tried again
+    fn optimise(mut self) -> Vec<usize> {
+        let order = self.tour.order_from(0);
+        if order.len() < 4 {
+            return order; // every round trip through three cities costs the same
+        }
+        let mut queued = vec![true; order.len()];
+        let mut queue: VecDeque<usize> = order.into();
+        while let Some(t1) = queue.pop_front() {
+            queued[t1] = false;
+            if let Some(touched) = self.improve_from(t1) {
+                for city in touched {
+                    if !std::mem::replace(&mut queued[city], true) {
+                        queue.push_back(city);
+                    }
+                }
+            }
+        }
+        self.tour.order_from(0)
+    }
+
+    // the cities whose edges changed if a chain from t1, starting with either of its edges,
+    // improved the tour
+    fn improve_from(&mut self, t1: usize) -> Option<Vec<usize>> {
+        for _ in 0..2 {
+            let t2 = self.tour.next(t1);
+            self.touched = vec![t1, t2];
+            self.best_gain = W::Delta::ZERO;
+            if self.extend(t1, t2, self.weight(t1, t2), 0) {
+                // undoes the steps past the most profitable one
+                while self.steps.len() > self.best_depth {
+                    let (first, last) = self.steps.pop().unwrap();
+                    self.tour.reverse(first, last);
+                }
+                self.steps.clear();
+                for &city in &self.touched {
+                    self.in_chain[city] = false;
+                }
+                return Some(std::mem::take(&mut self.touched));
+            }
+            // the same round trip the other way round, t1's previous city becoming its next
+            self.tour.reverse(self.tour.next(t1), t1);
+        }
+        None
+    }
+
+    // one step of the chain from the loose end t2, gain being what the edges removed so far weigh
+    // more than the ones added, joining t2 to t3 leaves t3's previous city t4 as the loose end,
+    // the most promising t3 keeping the most of the gain, true once a closed tour improved on
+    // the one the chain started from, with the chain's steps left in place
+    fn extend(&mut self, t1: usize, t2: usize, gain: W::Delta, depth: usize) -> bool {
+        if depth == MAX_DEPTH {
+            return false;
+        }
+        let mut options: Vec<(usize, usize, W::Delta)> = Vec::new();
+        for &t3 in &self.candidates[t2] {
+            let joined_gain = gain - self.weight(t2, t3);
+            if joined_gain <= W::Delta::ZERO {
+                break; // the rest are further away
+            }
+            if t3 == t1 || t3 == self.tour.next(t2) || self.in_chain[t3] {
+                continue;
+            }
+            let t4 = self.tour.previous(t3);
+            options.push((t3, t4, joined_gain + self.weight(t4, t3)));
+        }
+        options.sort_by(|(_, _, a), (_, _, b)| b.total_cmp(a));
+        options.truncate(BREADTH.get(depth).copied().unwrap_or(1));
+
+        for (t3, t4, step_gain) in options {
+            self.tour.reverse(t2, t4);
+            self.steps.push((t4, t2));
+            self.in_chain[t3] = true;
+            self.touched.extend([t3, t4]);
+            // closing the tour from the loose end back to t1
+            let closed_gain = step_gain - self.weight(t4, t1);
+            if closed_gain > self.best_gain {
+                self.best_gain = closed_gain;
+                self.best_depth = self.steps.len();
+            }
+            if self.extend(t1, t4, step_gain, depth + 1) || self.best_gain > W::Delta::ZERO {
+                return true;
+            }
+            self.steps.pop();
+            self.tour.reverse(t4, t2);
+            self.in_chain[t3] = false;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::candidates::{nearest_candidates, DEFAULT_CANDIDATES};
+    use crate::common::{check_path, generate_asymmetric_map, generate_default_path, TSPError};
+    use crate::cost::TourMode;
+    use crate::metric::{metric_map, Euclidean};
+    use crate::solvers::greedy_edge::greedy_edge_tsp_with;
+    use crate::solvers::held_karp::held_karp_tsp_with;
+    use crate::solvers::lin_kernighan::{
+        lin_kernighan_improve, lin_kernighan_tsp, lin_kernighan_tsp_with, Search,
+    };
+    use crate::tour_repr::{ArrayTour, TwoLevelList};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn random_points(num_points: usize, seed: u64) -> Vec<Vec<u16>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let points: Vec<(f64, f64)> = (0..num_points)
+            .map(|_| (rng.gen_range(0.0..1000.), rng.gen_range(0.0..1000.)))
+            .collect();
+        metric_map(&points, &Euclidean).ok().unwrap()
+    }
+
+    #[test]
+    fn test_lin_kernighan() {
+        // it finds the optimum of these small maps
+        for seed in 0..5 {
+            let map = random_points(12, seed);
+            for mode in [TourMode::Open, TourMode::Closed] {
+                let candidates = nearest_candidates(&map, 12, DEFAULT_CANDIDATES);
+                let (path, cost) = lin_kernighan_tsp_with(&map, 12, mode, &candidates)
+                    .ok()
+                    .unwrap();
+                assert_eq!(cost, mode.cost(&map, &path));
+                let (_, optimal) = held_karp_tsp_with(&map, map.len(), mode).ok().unwrap();
+                assert_eq!(cost, optimal);
+            }
+        }
+
+        let map = random_points(300, 7);
+        let candidates = nearest_candidates(&map, 300, DEFAULT_CANDIDATES);
+        let (path, cost) = lin_kernighan_tsp_with(&map, 300, TourMode::Closed, &candidates)
+            .ok()
+            .unwrap();
+        assert!(check_path(300, &path).is_ok());
+        assert_eq!(cost, TourMode::Closed.cost(&map, &path));
+        let (_, greedy_cost) = greedy_edge_tsp_with(&map, map.len(), TourMode::Closed)
+            .ok()
+            .unwrap();
+        assert!(cost < greedy_cost);
+
+        // from any tour
+        let identity = generate_default_path(&map);
+        let identity_cost = TourMode::Open.cost(&map, &identity);
+        let (path, cost) = lin_kernighan_improve(&map, identity, TourMode::Open)
+            .ok()
+            .unwrap();
+        assert!(check_path(300, &path).is_ok());
+        assert!(cost < identity_cost / 5);
+
+        // either tour representation makes the same moves
+        let candidates = nearest_candidates(&map, 300, 10);
+        let order: Vec<usize> = (0..301).collect();
+        let array = Search::<u16, _, ArrayTour>::new(&map, 300, order.clone(), &candidates);
+        let list = Search::<u16, _, TwoLevelList>::new(&map, 300, order, &candidates);
+        assert_eq!(array.optimise(), list.optimise());
+
+        assert_eq!(lin_kernighan_tsp(&[vec![0u16]]).ok(), Some((vec![0], 0)));
+        let asymmetric = generate_asymmetric_map(5, (1, 100)).ok().unwrap();
+        assert!(matches!(
+            lin_kernighan_tsp(&asymmetric),
+            Err(TSPError::AsymmetricWeight { .. })
+        ));
+    }
+}
@@ -0,0 +1,11 @@
+// solvers beyond the plain annealer, each a module with its own entry point
+pub mod branch_and_bound;
+pub mod christofides;
+pub mod double_tree;
+pub mod genetic;
+pub mod greedy_edge;
+pub mod held_karp;
+pub mod lin_kernighan;
+pub mod nearest_neighbour;
+pub mod parallel_tempering;
+pub mod refine;
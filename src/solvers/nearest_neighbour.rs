@@ -0,0 +1,102 @@
+use crate::common::{check_city_map, TSPError};
+use crate::cost::{CostFunction, TourMode};
+use crate::weight::Weight;
+
+pub fn nearest_neighbour_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+    start: u32,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    nearest_neighbour_tsp_with(&intercity_map, intercity_map.len(), start, TourMode::Open)
+}
+
+// same as nearest_neighbour_tsp but for any cost function over num_cities cities and either mode
+pub(crate) fn nearest_neighbour_tsp_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    start: u32,
+    mode: TourMode,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    let path = nearest_neighbour_tour_with(cost_function, num_cities, start)?;
+    let cost = mode.cost(cost_function, &path);
+    Ok((path, cost))
+}
+
+// greedy construction from start, always going on to the closest city not yet visited, ties to
+// the lowest index, a quick tour that is usually within a quarter of optimal on geometric maps
+pub fn nearest_neighbour_tour<W: Weight>(
+    intercity_map: &[Vec<W>],
+    start: u32,
+) -> Result<Vec<u32>, TSPError> {
+    check_city_map(intercity_map)?;
+    nearest_neighbour_tour_with(&intercity_map, intercity_map.len(), start)
+}
+
+pub(crate) fn nearest_neighbour_tour_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    start: u32,
+) -> Result<Vec<u32>, TSPError> {
+    if start as usize >= num_cities {
+        return Err(TSPError::CityOutOfRange {
+            position: 0,
+            city: start,
+            num_cities,
+        });
+    }
+
+    let mut path = Vec::with_capacity(num_cities);
+    path.push(start);
+    let mut visited = vec![false; num_cities];
+    visited[start as usize] = true;
+    for _ in 1..num_cities {
+        let last = *path.last().unwrap();
+        let next = (0..num_cities as u32)
+            .filter(|&city| !visited[city as usize])
+            .min_by(|&a, &b| {
+                cost_function
+                    .cost(last, a)
+                    .total_cmp(&cost_function.cost(last, b))
+            })
+            .unwrap();
+        visited[next as usize] = true;
+        path.push(next);
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{check_path, generate_map, TSPError};
+    use crate::cost::TourMode;
+    use crate::solvers::nearest_neighbour::{
+        nearest_neighbour_tour, nearest_neighbour_tsp, nearest_neighbour_tsp_with,
+    };
+
+    #[test]
+    fn test_nearest_neighbour() {
+        let map: Vec<Vec<u16>> = vec![
+            vec![0, 1, 9, 4],
+            vec![1, 0, 2, 8],
+            vec![9, 2, 0, 3],
+            vec![4, 8, 3, 0],
+        ];
+        assert_eq!(
+            nearest_neighbour_tsp(&map, 0).ok(),
+            Some((vec![0, 1, 2, 3], 6))
+        );
+        assert_eq!(
+            nearest_neighbour_tsp_with(&map, map.len(), 3, TourMode::Closed).ok(),
+            Some((vec![3, 2, 1, 0], 10))
+        );
+
+        let map = generate_map(50, (1, 100)).ok().unwrap();
+        let path = nearest_neighbour_tour(&map, 17).ok().unwrap();
+        assert_eq!(path[0], 17);
+        assert!(check_path(50, &path).is_ok());
+        assert!(matches!(
+            nearest_neighbour_tour(&map, 50),
+            Err(TSPError::CityOutOfRange { city: 50, .. })
+        ));
+    }
+}
@@ -0,0 +1,216 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::thread;
+
+use crate::common::{check_city_map, TSPError};
+use crate::cost::{path_cost_with, CostFunction, MatrixCost};
+use crate::moves::{default_moves, MoveOperator};
+use crate::weight::{Cost, Delta, Weight};
+
+// replica exchange, chains held at fixed temperatures from hot to cold that every swap_interval
+// iterations offer to trade tours with their neighbour on the ladder, so a good tour found while
+// exploring hot is refined cold and a cold chain stuck in a local optimum can be lifted out of it
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParallelTempering {
+    pub temperatures: Vec<f64>, // one chain each, from hottest to coldest
+    pub max_iterations: u64,    // per chain
+    pub swap_interval: u64,
+    pub seed: Option<u64>, // chain i is seeded with seed + i, unseeded runs differ every time
+    pub parallel: bool,    // whether the chains each get a thread between swaps
+}
+
+impl Default for ParallelTempering {
+    fn default() -> Self {
+        ParallelTempering {
+            temperatures: geometric_ladder(200., 1., 8),
+            max_iterations: 200000,
+            swap_interval: 1000,
+            seed: None,
+            parallel: false,
+        }
+    }
+}
+
+// num_chains temperatures from hottest to coldest with the same ratio between neighbours, which
+// keeps swaps between them about equally likely
+pub fn geometric_ladder(hottest: f64, coldest: f64, num_chains: usize) -> Vec<f64> {
+    if num_chains < 2 {
+        return vec![coldest; num_chains];
+    }
+    let ratio = (coldest / hottest).powf(1. / (num_chains - 1) as f64);
+    (0..num_chains)
+        .map(|index| hottest * ratio.powi(index as i32))
+        .collect()
+}
+
+struct Chain<W: Weight> {
+    temperature: f64,
+    path: Vec<u32>,
+    cost: W::Delta,
+    best_path: Vec<u32>,
+    best_cost: W::Delta,
+    moves: Vec<Box<dyn MoveOperator<W>>>,
+    rng: StdRng,
+}
+
+impl<W: Weight> Chain<W> {
+    // metropolis steps at the chain's temperature
+    fn anneal(&mut self, cost_function: &impl CostFunction<W>, iterations: u64) {
+        for _ in 0..iterations {
+            let operator = self.rng.gen_range(0..self.moves.len());
+            let operator = &mut self.moves[operator];
+            operator.propose(&self.path, &mut self.rng);
+            let diff = operator.delta(cost_function, &mut self.path);
+            if diff < W::Delta::ZERO
+                || self.rng.gen_range(0.0..1.0) < (-diff.to_f64() / self.temperature).exp()
+            {
+                operator.apply(&mut self.path);
+                self.cost += diff;
+                if self.cost < self.best_cost {
+                    self.best_cost = self.cost;
+                    self.best_path.clone_from(&self.path);
+                }
+            }
+        }
+    }
+}
+
+impl ParallelTempering {
+    pub fn check(&self) -> Result<(), TSPError> {
+        if self.temperatures.is_empty() {
+            return Err(TSPError::InvalidRunCount);
+        }
+        if self.swap_interval == 0
+            || self
+                .temperatures
+                .iter()
+                .any(|temperature| !temperature.is_finite() || *temperature <= 0.)
+        {
+            return Err(TSPError::InvalidCoolingSchedule);
+        }
+        Ok(())
+    }
+}
+
+// the best tour any chain found
+pub fn parallel_tempering_tsp<W: Weight>(
+    intercity_map: &[Vec<W>],
+    tempering: &ParallelTempering,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+
+    let matrix = MatrixCost::new(intercity_map);
+    parallel_tempering_tsp_with(&matrix, intercity_map.len(), tempering)
+}
+
+// same as parallel_tempering_tsp but for any cost function over num_cities cities
+pub(crate) fn parallel_tempering_tsp_with<W: Weight>(
+    cost_function: &(impl CostFunction<W> + Sync),
+    num_cities: usize,
+    tempering: &ParallelTempering,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    tempering.check()?;
+
+    let path: Vec<u32> = (0..num_cities as u32).collect();
+    let cost = path_cost_with(cost_function, &path).to_delta();
+    let seed = tempering.seed.unwrap_or_else(rand::random);
+    let mut chains: Vec<Chain<W>> = (0..tempering.temperatures.len())
+        .map(|index| Chain {
+            temperature: tempering.temperatures[index],
+            path: path.clone(),
+            cost,
+            best_path: path.clone(),
+            best_cost: cost,
+            moves: default_moves(),
+            rng: StdRng::seed_from_u64(seed.wrapping_add(index as u64)),
+        })
+        .collect();
+    // swaps are decided by a generator of their own so threads don't change the outcome
+    let mut rng = StdRng::seed_from_u64(seed.wrapping_sub(1));
+
+    let mut iteration = 0;
+    while iteration < tempering.max_iterations {
+        let iterations = tempering
+            .swap_interval
+            .min(tempering.max_iterations - iteration);
+        if tempering.parallel {
+            thread::scope(|scope| {
+                for chain in &mut chains {
+                    scope.spawn(move || chain.anneal(cost_function, iterations));
+                }
+            });
+        } else {
+            for chain in &mut chains {
+                chain.anneal(cost_function, iterations);
+            }
+        }
+        iteration += iterations;
+
+        // neighbours trade tours with probability min(1, exp((1/T_i - 1/T_j)(E_i - E_j)))
+        for index in 1..chains.len() {
+            let (hotter, colder) = chains.split_at_mut(index);
+            let (hotter, colder) = (&mut hotter[index - 1], &mut colder[0]);
+            let exponent = (1. / hotter.temperature - 1. / colder.temperature)
+                * (hotter.cost - colder.cost).to_f64();
+            if exponent >= 0. || rng.gen_range(0.0..1.0) < exponent.exp() {
+                std::mem::swap(&mut hotter.path, &mut colder.path);
+                std::mem::swap(&mut hotter.cost, &mut colder.cost);
+            }
+        }
+    }
+
+    let best = chains
+        .into_iter()
+        .min_by(|a, b| a.best_cost.total_cmp(&b.best_cost))
+        .unwrap(); // check makes sure there is at least one chain
+    Ok((best.best_path, W::Cost::from_delta(best.best_cost)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{generate_map, TSPError};
+    use crate::solvers::parallel_tempering::{
+        geometric_ladder, parallel_tempering_tsp, ParallelTempering,
+    };
+    use crate::testing::is_cost_consistent;
+
+    #[test]
+    fn test_parallel_tempering() {
+        let ladder = geometric_ladder(100., 1., 3);
+        assert!((ladder[1] - 10.).abs() < 1e-9 && (ladder[2] - 1.).abs() < 1e-9);
+
+        let map = generate_map(10, (1, 100)).ok().unwrap();
+        let tempering = ParallelTempering {
+            temperatures: geometric_ladder(200., 1., 4),
+            max_iterations: 5000,
+            swap_interval: 100,
+            seed: Some(3),
+            ..ParallelTempering::default()
+        };
+        let (path, cost) = parallel_tempering_tsp(&map, &tempering).ok().unwrap();
+        assert!(is_cost_consistent(&map, &path, cost));
+
+        // seeded, so threads don't change the outcome
+        let parallel = ParallelTempering {
+            parallel: true,
+            ..tempering
+        };
+        assert_eq!(
+            parallel_tempering_tsp(&map, &parallel).ok(),
+            Some((path, cost))
+        );
+
+        let no_chains = ParallelTempering {
+            temperatures: Vec::new(),
+            ..ParallelTempering::default()
+        };
+        assert!(matches!(
+            parallel_tempering_tsp(&map, &no_chains),
+            Err(TSPError::InvalidRunCount)
+        ));
+        let frozen = ParallelTempering {
+            temperatures: vec![10., 0.],
+            ..ParallelTempering::default()
+        };
+        assert!(parallel_tempering_tsp(&map, &frozen).is_err());
+    }
+}
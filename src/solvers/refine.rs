@@ -0,0 +1,463 @@
+use itertools::Either;
+use std::collections::VecDeque;
+
+use crate::candidates::check_candidates;
+use crate::common::{check_city_map, check_path, TSPError};
+use crate::cost::{CostFunction, MatrixCost, TourMode};
+use crate::solvers::lin_kernighan::TWO_LEVEL_MIN_CITIES;
+use crate::tour_repr::{ArrayTour, TourRepr, TwoLevelList};
+use crate::weight::{Delta, Weight};
+
+// the longest run of consecutive cities an or-opt move carries elsewhere
+const OR_OPT_MAX_SEGMENT: usize = 3;
+
+// a deterministic finishing pass for any tour, e.g. an annealed, evolved or greedy one, which
+// applies improving 2-opt moves (reversing a stretch of the tour) and or-opt moves (moving a
+// run of up to three cities elsewhere, either way round) until neither finds one, so the tour
+// returned is never worse than the one given and is a local optimum of both, an open path
+// being a round trip through an extra city at no distance from any other as in lin_kernighan
+pub fn refine<W: Weight>(
+    intercity_map: &[Vec<W>],
+    path: Vec<u32>,
+    mode: TourMode,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    let cost_function = MatrixCost::new(intercity_map);
+    refine_with(&cost_function, intercity_map.len(), path, mode, None)
+}
+
+// same as refine but only trying moves that join a city to one of its candidates, see
+// candidates, O(nk) moves a pass rather than O(n^2), which finds almost every improvement on
+// large instances in a fraction of the time, though the tour may not be a local optimum of every
+// move
+pub fn refine_by_candidates<W: Weight>(
+    intercity_map: &[Vec<W>],
+    path: Vec<u32>,
+    mode: TourMode,
+    candidates: &[Vec<u32>],
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_city_map(intercity_map)?;
+    check_candidates(intercity_map.len(), candidates)?;
+    refine_with(
+        &MatrixCost::new(intercity_map),
+        intercity_map.len(),
+        path,
+        mode,
+        Some(candidates),
+    )
+}
+
+// same as refine but for any cost function over num_cities cities, and candidates if given
+pub(crate) fn refine_with<W: Weight>(
+    cost_function: &impl CostFunction<W>,
+    num_cities: usize,
+    path: Vec<u32>,
+    mode: TourMode,
+    candidates: Option<&[Vec<u32>]>,
+) -> Result<(Vec<u32>, W::Cost), TSPError> {
+    check_path(num_cities, &path)?;
+
+    let mut order: Vec<usize> = path.iter().map(|&city| city as usize).collect();
+    if mode == TourMode::Open {
+        order.push(num_cities);
+    }
+    let order = if order.len() < TWO_LEVEL_MIN_CITIES {
+        Polish::<W, _, ArrayTour>::new(cost_function, num_cities, &order, candidates).optimise()
+    } else {
+        Polish::<W, _, TwoLevelList>::new(cost_function, num_cities, &order, candidates).optimise()
+    };
+
+    let extra = order.iter().position(|&city| city == num_cities);
+    let path: Vec<u32> = match extra {
+        Some(extra) => order[extra + 1..]
+            .iter()
+            .chain(&order[..extra])
+            .map(|&city| city as u32)
+            .collect(),
+        None => order.iter().map(|&city| city as u32).collect(),
+    };
+    let cost = mode.cost(cost_function, &path);
+    Ok((path, cost))
+}
+
+struct Polish<'a, W: Weight, C, T> {
+    cost_function: &'a C,
+    num_cities: usize,                  // the extra city of an open path aside
+    candidates: Option<&'a [Vec<u32>]>, // every other city if none
+    tour: T,                            // the round trip
+    start: usize,                       // the city the round trip was given from
+    symmetric: bool,
+    // summed for asymmetric weights once a move needs them, and again only after the tour changed
+    sums: Option<EdgeSums<W::Delta>>,
+}
+
+// what the edges up to each city weigh, walking the tour forwards and backwards from where it was
+// summed, so reversing a long stretch of an asymmetric tour is costed without walking it
+struct EdgeSums<D> {
+    position: Vec<usize>,
+    forward: Vec<D>, // the last closing the round trip
+    backward: Vec<D>,
+}
+
+impl<'a, W: Weight, C: CostFunction<W>, T: TourRepr> Polish<'a, W, C, T> {
+    fn new(
+        cost_function: &'a C,
+        num_cities: usize,
+        order: &[usize],
+        candidates: Option<&'a [Vec<u32>]>,
+    ) -> Self {
+        Polish {
+            cost_function,
+            num_cities,
+            candidates,
+            tour: T::new(order),
+            start: order[0],
+            symmetric: cost_function.is_symmetric(),
+            sums: None,
+        }
+    }
+
+    // the extra city of an open path is at no distance from any other
+    fn weight(&self, from: usize, to: usize) -> W::Delta {
+        if from == self.num_cities || to == self.num_cities {
+            return W::Delta::ZERO;
+        }
+        self.cost_function.cost(from as u32, to as u32).to_delta()
+    }
+
+    // the cities worth joining city to, the extra city of an open path being at no distance from
+    // any, so every city's candidate and every city one of its own
+    fn neighbours<'b>(
+        &'b self,
+        candidates: &'b [Vec<u32>],
+        city: usize,
+    ) -> impl Iterator<Item = usize> + 'b {
+        if city == self.num_cities {
+            return Either::Left(0..self.num_cities);
+        }
+        let extra = (self.tour.num_cities() > self.num_cities).then_some(self.num_cities);
+        let others = candidates[city].iter().map(|&other| other as usize);
+        Either::Right(others.chain(extra))
+    }
+
+    fn sum_edges(&self) -> EdgeSums<W::Delta> {
+        let mut order = self.tour.order_from(self.start);
+        let mut position = vec![0; order.len()];
+        for (index, &city) in order.iter().enumerate() {
+            position[city] = index;
+        }
+        order.push(self.start);
+        let (mut forward, mut backward) = (vec![W::Delta::ZERO], vec![W::Delta::ZERO]);
+        for pair in order.windows(2) {
+            forward.push(*forward.last().unwrap() + self.weight(pair[0], pair[1]));
+            backward.push(*backward.last().unwrap() + self.weight(pair[1], pair[0]));
+        }
+        EdgeSums {
+            position,
+            forward,
+            backward,
+        }
+    }
+
+    // how much more the edges of the stretch from first to last weigh once they are walked the
+    // other way
+    fn reversal_delta(&mut self, first: usize, last: usize) -> W::Delta {
+        if self.symmetric {
+            return W::Delta::ZERO;
+        }
+        if self.sums.is_none() {
+            self.sums = Some(self.sum_edges());
+        }
+        let sums = self.sums.as_ref().unwrap();
+        let (first, last) = (sums.position[first], sums.position[last]);
+        let inside = |sums: &[W::Delta]| {
+            if first <= last {
+                sums[last] - sums[first]
+            } else {
+                sums[sums.len() - 1] - sums[first] + sums[last]
+            }
+        };
+        inside(&sums.backward) - inside(&sums.forward)
+    }
+
+    // moves from every city until none improves the tour, with don't-look bits: a city whose
+    // moves found nothing is only looked from again once an edge at or next to it changes, and
+    // once the cities so looked from run out every city is looked from once more, so the tour
+    // is a local optimum of every move and not only of those tried since
+    fn optimise(mut self) -> Vec<usize> {
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        let mut queued = vec![false; self.tour.num_cities()];
+        loop {
+            queue.extend(self.tour.order_from(self.start));
+            queued.fill(true);
+            let mut improved = false;
+            while let Some(city) = queue.pop_front() {
+                queued[city] = false;
+                let Some(touched) = self.improve_from(city) else {
+                    continue;
+                };
+                improved = true;
+                for city in touched {
+                    if !std::mem::replace(&mut queued[city], true) {
+                        queue.push_back(city);
+                    }
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+        self.tour.order_from(self.start)
+    }
+
+    // the cities whose edges changed if a 2-opt or or-opt move from city improved the tour
+    fn improve_from(&mut self, city: usize) -> Option<Vec<usize>> {
+        self.two_opt(city).or_else(|| self.or_opt(city))
+    }
+
+    // the first reversal of a stretch from first that improves the tour, with candidates only
+    // those joining the city before first or first itself to a candidate
+    fn two_opt(&mut self, first: usize) -> Option<Vec<usize>> {
+        let before = self.tour.previous(first);
+        let Some(candidates) = self.candidates else {
+            // the stretch grows an edge at a time, what reversing it changes summed as it goes
+            let (mut last, mut reversed) = (first, W::Delta::ZERO);
+            loop {
+                let next = self.tour.next(last);
+                if next == before {
+                    return None;
+                }
+                if !self.symmetric {
+                    reversed += self.weight(next, last) - self.weight(last, next);
+                }
+                last = next;
+                if let Some(touched) = self.reverse_if_better(first, last, reversed) {
+                    return Some(touched);
+                }
+            }
+        };
+        // before joined to last, or first to the city after last
+        let lasts: Vec<usize> = self
+            .neighbours(candidates, before)
+            .chain(
+                self.neighbours(candidates, first)
+                    .map(|city| self.tour.previous(city)),
+            )
+            .filter(|&last| last != first && last != before)
+            .collect();
+        lasts.into_iter().find_map(|last| {
+            let reversed = self.reversal_delta(first, last);
+            self.reverse_if_better(first, last, reversed)
+        })
+    }
+
+    // reverses the stretch from first to last if that improves the tour, given what its own
+    // edges weigh more the other way, the cities at the edges it changed if so
+    fn reverse_if_better(
+        &mut self,
+        first: usize,
+        last: usize,
+        reversed: W::Delta,
+    ) -> Option<Vec<usize>> {
+        let before = self.tour.previous(first);
+        let after = self.tour.next(last);
+        if after == first {
+            return None; // the whole round trip the other way
+        }
+        let delta = self.weight(before, last) + self.weight(first, after)
+            - self.weight(before, first)
+            - self.weight(last, after)
+            + reversed;
+        if delta >= W::Delta::ZERO {
+            return None;
+        }
+        self.tour.reverse(first, last);
+        self.sums = None;
+        Some(vec![before, first, last, after])
+    }
+
+    // the runs of up to OR_OPT_MAX_SEGMENT cities from first moved to the cheapest place for
+    // them, either way round, the first that improves the tour moved there
+    fn or_opt(&mut self, first: usize) -> Option<Vec<usize>> {
+        let mut segment = vec![first];
+        for segment_len in 1..=OR_OPT_MAX_SEGMENT.min(self.tour.num_cities().saturating_sub(3)) {
+            if segment_len > 1 {
+                segment.push(self.tour.next(*segment.last().unwrap()));
+            }
+            if let Some(touched) = self.move_segment(&segment) {
+                return Some(touched);
+            }
+        }
+        None
+    }
+
+    // the cheapest place for the segment improving the tour, if any, moving it there, with
+    // candidates only next to a candidate of its start or end, the cities at the edges it
+    // changed if so
+    fn move_segment(&mut self, segment: &[usize]) -> Option<Vec<usize>> {
+        let (start, end) = (segment[0], *segment.last().unwrap());
+        let before = self.tour.previous(start);
+        let after = self.tour.next(end);
+        let removed =
+            self.weight(before, after) - self.weight(before, start) - self.weight(end, after);
+        let mut reversed = W::Delta::ZERO;
+        if !self.symmetric {
+            for pair in segment.windows(2) {
+                reversed += self.weight(pair[1], pair[0]) - self.weight(pair[0], pair[1]);
+            }
+        }
+
+        // the cheapest of putting the segment between from and the city after it
+        let mut best: Option<(W::Delta, usize, bool)> = None;
+        let mut place = |from: usize| {
+            let to = self.tour.next(from);
+            let gap = removed - self.weight(from, to);
+            for (delta, flip) in [
+                (gap + self.weight(from, start) + self.weight(end, to), false),
+                (
+                    gap + self.weight(from, end) + self.weight(start, to) + reversed,
+                    true,
+                ),
+            ] {
+                if delta < best.map_or(W::Delta::ZERO, |(best_delta, _, _)| best_delta) {
+                    best = Some((delta, from, flip));
+                }
+            }
+        };
+        match self.candidates {
+            None => {
+                let mut from = after;
+                while from != before {
+                    place(from);
+                    from = self.tour.next(from);
+                }
+            }
+            Some(candidates) => {
+                // the edges from the city and to it, the segment's own and the one before it aside
+                for city in self
+                    .neighbours(candidates, start)
+                    .chain(self.neighbours(candidates, end))
+                {
+                    for from in [city, self.tour.previous(city)] {
+                        if from != before && !segment.contains(&from) {
+                            place(from);
+                        }
+                    }
+                }
+            }
+        }
+
+        // before, the cities from after to from then the segment, by reversing the whole stretch
+        // and the cities from after to from back again
+        let (_, from, flip) = best?;
+        let to = self.tour.next(from);
+        self.tour.reverse(start, from);
+        self.tour.reverse(from, after);
+        if !flip {
+            self.tour.reverse(end, start);
+        }
+        self.sums = None;
+        Some(vec![before, start, end, after, from, to])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::candidates::nearest_candidates;
+    use crate::common::{check_path, generate_asymmetric_map, generate_default_path, TSPError};
+    use crate::cost::TourMode;
+    use crate::metric::{metric_map, Euclidean};
+    use crate::solvers::nearest_neighbour::nearest_neighbour_tsp;
+    use crate::solvers::refine::{refine, refine_by_candidates};
+    use rand::seq::SliceRandom;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_refine() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let points: Vec<(f64, f64)> = (0..60)
+            .map(|_| (rng.gen_range(0.0..1000.), rng.gen_range(0.0..1000.)))
+            .collect();
+        let map: Vec<Vec<u16>> = metric_map(&points, &Euclidean).ok().unwrap();
+        let mut start = generate_default_path(&map);
+        start.shuffle(&mut rng);
+        for mode in [TourMode::Open, TourMode::Closed] {
+            let (path, cost) = refine(&map, start.clone(), mode).ok().unwrap();
+            assert!(check_path(60, &path).is_ok());
+            assert_eq!(cost, mode.cost(&map, &path));
+            assert!(cost < mode.cost(&map, &start) / 3);
+            // a local optimum, so polishing again changes nothing, and deterministic
+            assert_eq!(refine(&map, path.clone(), mode).ok(), Some((path, cost)));
+            assert_eq!(refine(&map, start.clone(), mode).ok().unwrap().1, cost);
+        }
+
+        // only moves joining cities to their nearest few
+        let candidates = nearest_candidates(&map, 60, 8);
+        for mode in [TourMode::Open, TourMode::Closed] {
+            let (path, cost) = refine_by_candidates(&map, start.clone(), mode, &candidates)
+                .ok()
+                .unwrap();
+            assert!(check_path(60, &path).is_ok());
+            assert_eq!(cost, mode.cost(&map, &path));
+            assert!(cost < mode.cost(&map, &start) / 3);
+        }
+        assert!(matches!(
+            refine_by_candidates(&map, start.clone(), TourMode::Open, &candidates[..59]),
+            Err(TSPError::InvalidCandidates { city: 59 })
+        ));
+
+        // no reversal of a stretch of the open path improves it
+        let (path, cost) = refine(&map, start, TourMode::Open).ok().unwrap();
+        for first in 0..60 {
+            for last in first + 1..60 {
+                let mut reversed = path.clone();
+                reversed[first..=last].reverse();
+                assert!(TourMode::Open.cost(&map, &reversed) >= cost);
+            }
+        }
+
+        // asymmetric weights are costed in the direction the tour takes them
+        let asymmetric = generate_asymmetric_map(30, (1, 100)).ok().unwrap();
+        let identity = generate_default_path(&asymmetric);
+        for mode in [TourMode::Open, TourMode::Closed] {
+            let (path, cost) = refine(&asymmetric, identity.clone(), mode).ok().unwrap();
+            assert_eq!(cost, mode.cost(&asymmetric, &path));
+            assert!(cost <= mode.cost(&asymmetric, &identity));
+            let candidates = nearest_candidates(&asymmetric, 30, 5);
+            let (path, cost) =
+                refine_by_candidates(&asymmetric, identity.clone(), mode, &candidates)
+                    .ok()
+                    .unwrap();
+            assert_eq!(cost, mode.cost(&asymmetric, &path));
+            assert!(cost <= mode.cost(&asymmetric, &identity));
+        }
+
+        for num_cities in 1..5 {
+            let map = generate_asymmetric_map(num_cities, (1, 100)).ok().unwrap();
+            let path = generate_default_path(&map);
+            assert!(refine(&map, path, TourMode::Closed).is_ok());
+        }
+        assert!(matches!(
+            refine(&map, vec![0, 1], TourMode::Open),
+            Err(TSPError::InvalidPathLength { .. })
+        ));
+    }
+
+    #[test]
+    fn test_refine_long_tours() {
+        // enough cities for the tour to be a TwoLevelList
+        let mut rng = StdRng::seed_from_u64(4);
+        let points: Vec<(f64, f64)> = (0..2500)
+            .map(|_| (rng.gen_range(0.0..10000.), rng.gen_range(0.0..10000.)))
+            .collect();
+        let map: Vec<Vec<u16>> = metric_map(&points, &Euclidean).ok().unwrap();
+        let (start, start_cost) = nearest_neighbour_tsp(&map, 0).ok().unwrap();
+        let candidates = nearest_candidates(&map, 2500, 6);
+        let (path, cost) = refine_by_candidates(&map, start, TourMode::Open, &candidates)
+            .ok()
+            .unwrap();
+        assert!(check_path(2500, &path).is_ok());
+        assert_eq!(cost, TourMode::Open.cost(&map, &path));
+        assert!(cost < start_cost);
+    }
+}
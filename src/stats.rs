@@ -0,0 +1,173 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::memory::format_bytes;
+use crate::weight::Delta;
+
+// counters collected while annealing, for spotting slow runs and badly tuned schedules, costs
+// being in the map's weight::Delta
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Default, Debug)]
+pub struct SolveStats<D = i64> {
+    pub iterations: u64,
+    pub cost_evaluations: u64, // one per proposed move
+    pub accepted_moves: u64,
+    pub reheats: u64, // times the cooling schedule was restarted
+    pub elapsed: Duration,
+    pub cpu_time: Option<Duration>, // None where the platform can't measure it
+    pub estimated_memory: usize,
+    pub peak_memory: Option<usize>,  // of the whole process
+    pub improvements: Vec<(u64, D)>, // (iteration, cost) each time the best cost so far fell
+}
+
+#[cfg(unix)]
+pub(crate) fn resource_usage() -> Option<libc::rusage> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+    // safety: getrusage only writes to the struct it is given, and it is only read on success
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    Some(unsafe { usage.assume_init() })
+}
+
+// user plus system time of the whole process, i.e. summed over all of its threads, so
+// concurrent solves in the same process are counted together
+#[cfg(unix)]
+pub fn process_cpu_time() -> Option<Duration> {
+    let usage = resource_usage()?;
+    let to_duration = |time: libc::timeval| {
+        Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+    };
+    Some(to_duration(usage.ru_utime) + to_duration(usage.ru_stime))
+}
+
+#[cfg(not(unix))]
+pub fn process_cpu_time() -> Option<Duration> {
+    None
+}
+
+// cpu time spent between the two readings of process_cpu_time
+pub fn cpu_time_since(start: Option<Duration>) -> Option<Duration> {
+    Some(process_cpu_time()?.saturating_sub(start?))
+}
+
+impl<D: Delta> SolveStats<D> {
+    // about 1 for a single threaded solve, up to the number of threads for a parallel one
+    pub fn cpu_utilisation(&self) -> Option<f64> {
+        self.cpu_time
+            .map(|cpu_time| cpu_time.as_secs_f64() / self.elapsed.as_secs_f64().max(f64::EPSILON))
+    }
+
+    pub fn iterations_per_second(&self) -> f64 {
+        self.iterations as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    // percentage of proposed moves that were taken
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.iterations == 0 {
+            return 0.;
+        }
+        100. * self.accepted_moves as f64 / self.iterations as f64
+    }
+
+    // best cost found by the given iteration
+    pub fn best_cost_at(&self, iteration: u64) -> Option<D> {
+        self.improvements
+            .iter()
+            .take_while(|&&(improved_at, _)| improved_at <= iteration)
+            .last()
+            .map(|&(_, cost)| cost)
+    }
+}
+
+impl<D: Delta> fmt::Display for SolveStats<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} iterations in {:.3}s ({:.0} iterations/s), {} cost evaluations, {:.2}% accepted{}",
+            self.iterations,
+            self.elapsed.as_secs_f64(),
+            self.iterations_per_second(),
+            self.cost_evaluations,
+            self.acceptance_rate(),
+            if self.reheats > 0 {
+                format!(", {} reheats", self.reheats)
+            } else {
+                String::new()
+            }
+        )?;
+        if let (Some(cpu_time), Some(utilisation)) = (self.cpu_time, self.cpu_utilisation()) {
+            writeln!(
+                f,
+                "wall time {:.3}s, cpu time {:.3}s ({:.2} cpu/wall)",
+                self.elapsed.as_secs_f64(),
+                cpu_time.as_secs_f64(),
+                utilisation
+            )?;
+        }
+
+        write!(
+            f,
+            "memory {} estimated",
+            format_bytes(self.estimated_memory)
+        )?;
+        if let Some(peak_memory) = self.peak_memory {
+            write!(f, ", {} process peak", format_bytes(peak_memory))?;
+        }
+        writeln!(f)?;
+
+        let (Some(&(_, first)), Some(&(last_at, last))) =
+            (self.improvements.first(), self.improvements.last())
+        else {
+            return Ok(());
+        };
+        write!(
+            f,
+            "best cost {} -> {} over {} improvements, the last at iteration {}",
+            first,
+            last,
+            self.improvements.len() - 1,
+            last_at
+        )?;
+
+        // where the run was at each quarter, a flat tail means the schedule ran too long
+        let (start, _) = self.improvements[0];
+        let span = self.iterations.max(1);
+        let quarters: Vec<String> = (1..=4)
+            .filter_map(|quarter| self.best_cost_at(start + span * quarter / 4))
+            .map(|cost| cost.to_string())
+            .collect();
+        write!(f, "\nbest cost at 25/50/75/100%: {}", quarters.join(" / "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::generate_map;
+    use crate::config::SolverConfig;
+    use crate::sim_annealing::Annealer;
+
+    #[test]
+    fn test_solve_stats() {
+        let map = generate_map(6, (1, 100)).ok().unwrap();
+        let config = SolverConfig {
+            max_iterations: 1000,
+            ..SolverConfig::default()
+        };
+        let (_, cost, stats) = Annealer::new(config).solve(&map).ok().unwrap();
+
+        assert_eq!(stats.iterations, 1000);
+        assert_eq!(stats.cost_evaluations, 1000);
+        assert!(stats.accepted_moves > 0 && stats.accepted_moves <= 1000);
+        assert!(stats.acceptance_rate() > 0.);
+        assert!(stats
+            .improvements
+            .windows(2)
+            .all(|pair| pair[1].1 < pair[0].1));
+        assert!(stats.best_cost_at(1000).unwrap() <= cost as i64);
+        assert!(stats.to_string().contains("1000 iterations"));
+        #[cfg(unix)]
+        assert!(stats.cpu_time.is_some() && stats.peak_memory.is_some());
+        assert!(stats.estimated_memory > 0);
+    }
+}
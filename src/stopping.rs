@@ -0,0 +1,104 @@
+use crate::common::TSPError;
+
+// when an annealing run ends, at max_iterations or earlier at whichever of the optional criteria
+// is met first
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StoppingCriteria {
+    pub max_iterations: u64,
+    // a run that has not found a better tour for this many epochs has most likely settled
+    pub max_epochs_without_improvement: Option<u64>,
+    pub epoch_length: u64,
+    // below it hardly any uphill move is accepted and the run is a plain descent
+    pub min_temperature: Option<f64>,
+    // good enough, e.g. within a known gap of the optimum
+    pub target_cost: Option<u64>,
+}
+
+impl Default for StoppingCriteria {
+    fn default() -> Self {
+        StoppingCriteria {
+            max_iterations: 200000,
+            max_epochs_without_improvement: None,
+            epoch_length: 1000,
+            min_temperature: None,
+            target_cost: None,
+        }
+    }
+}
+
+impl StoppingCriteria {
+    pub fn check(&self) -> Result<(), TSPError> {
+        let valid = self.epoch_length > 0
+            && self.max_epochs_without_improvement != Some(0)
+            && self
+                .min_temperature
+                .is_none_or(|temperature| temperature >= 0. && temperature.is_finite());
+        if !valid {
+            return Err(TSPError::InvalidStoppingCriteria);
+        }
+        Ok(())
+    }
+
+    // iterations without a better tour after which the run stops
+    pub fn stale_limit(&self) -> Option<u64> {
+        self.max_epochs_without_improvement
+            .map(|epochs| epochs.saturating_mul(self.epoch_length))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::generate_map;
+    use crate::config::SolverConfig;
+    use crate::sim_annealing::Annealer;
+    use crate::stopping::StoppingCriteria;
+    use crate::testing::is_cost_consistent;
+
+    #[test]
+    fn test_stopping_criteria() {
+        let map = generate_map(10, (1, 100)).ok().unwrap();
+        let run = |criteria: &StoppingCriteria| {
+            Annealer::new(SolverConfig::default())
+                .stopping(criteria)
+                .solve(&map)
+                .ok()
+                .unwrap()
+        };
+
+        // an open tour of 10 cities costs at most 9 * 100, so the first one meets the target
+        let (path, cost, stats) = run(&StoppingCriteria {
+            max_iterations: u64::MAX,
+            target_cost: Some(1000),
+            ..StoppingCriteria::default()
+        });
+        assert!(is_cost_consistent(&map, &path, cost));
+        assert_eq!(stats.iterations, 0);
+
+        let (_, _, stats) = run(&StoppingCriteria {
+            max_iterations: u64::MAX,
+            max_epochs_without_improvement: Some(2),
+            epoch_length: 500,
+            ..StoppingCriteria::default()
+        });
+        let last_improved = stats.improvements.last().unwrap().0;
+        assert_eq!(stats.iterations, last_improved + 1000);
+
+        // the default schedule is at T0 / (1 + k)
+        let (_, _, stats) = run(&StoppingCriteria {
+            min_temperature: Some(1.),
+            ..StoppingCriteria::default()
+        });
+        assert_eq!(stats.iterations, 200);
+
+        let invalid = StoppingCriteria {
+            epoch_length: 0,
+            ..StoppingCriteria::default()
+        };
+        assert!(Annealer::new(SolverConfig::default())
+            .stopping(&invalid)
+            .solve(&map)
+            .is_err());
+    }
+}
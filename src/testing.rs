@@ -0,0 +1,78 @@
+// generators and invariant checkers for property testing code built on top of this crate
+
+pub use crate::capitals::generate_capitals_map;
+pub use crate::common::{generate_default_path, generate_map, generate_random_path};
+pub use crate::io::binary::stream_generated_map;
+pub use crate::perturbation::{add_city, drop_city, jitter_coordinates, jitter_weights};
+
+use crate::common::path_cost;
+
+// every city of the map is visited exactly once
+pub fn is_valid_tour(intercity_map: &[Vec<u16>], tour: &[u32]) -> bool {
+    let mut visited = vec![false; intercity_map.len()];
+    tour.len() == intercity_map.len()
+        && tour.iter().all(|&city| {
+            let city = city as usize;
+            city < visited.len() && !std::mem::replace(&mut visited[city], true)
+        })
+}
+
+pub fn is_square(intercity_map: &[Vec<u16>]) -> bool {
+    !intercity_map.is_empty()
+        && intercity_map
+            .iter()
+            .all(|row| row.len() == intercity_map.len())
+}
+
+pub fn is_symmetric(intercity_map: &[Vec<u16>]) -> bool {
+    is_square(intercity_map)
+        && intercity_map.iter().enumerate().all(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .all(|(j, &weight)| weight == intercity_map[j][i])
+        })
+}
+
+pub fn has_zero_diagonal(intercity_map: &[Vec<u16>]) -> bool {
+    is_square(intercity_map) && intercity_map.iter().enumerate().all(|(i, row)| row[i] == 0)
+}
+
+// a solver's reported cost matches the cost recomputed from its tour
+pub fn is_cost_consistent(intercity_map: &[Vec<u16>], tour: &[u32], reported_cost: u64) -> bool {
+    is_valid_tour(intercity_map, tour) && path_cost(intercity_map, tour).ok() == Some(reported_cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim_annealing::simulated_annealing_tsp;
+    use crate::testing::{
+        generate_map, generate_random_path, has_zero_diagonal, is_cost_consistent, is_square,
+        is_symmetric, is_valid_tour,
+    };
+
+    #[test]
+    fn test_map_invariants() {
+        let map = generate_map(8, (1, 50)).ok().unwrap();
+        assert!(is_square(&map) && is_symmetric(&map) && has_zero_diagonal(&map));
+
+        let mut asymmetric = map.clone();
+        asymmetric[0][1] += 1;
+        assert!(!is_symmetric(&asymmetric));
+
+        let ragged = vec![vec![0, 1], vec![1]];
+        assert!(!is_square(&ragged));
+    }
+
+    #[test]
+    fn test_tour_invariants() {
+        let map = generate_map(6, (1, 50)).ok().unwrap();
+        assert!(is_valid_tour(&map, &generate_random_path(&map)));
+        assert!(!is_valid_tour(&map, &[0, 1, 2, 3, 4, 4]));
+        assert!(!is_valid_tour(&map, &[0, 1, 2, 3, 4, 6]));
+        assert!(!is_valid_tour(&map, &[0, 1, 2]));
+
+        let (tour, cost) = simulated_annealing_tsp(&map, 100, 200).ok().unwrap();
+        assert!(is_cost_consistent(&map, &tour, cost));
+        assert!(!is_cost_consistent(&map, &tour, cost + 1));
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::Write;
+
+use crate::cost::path_cost_with;
+use crate::weight::{Cost, Weight};
+
+// presenting tours to people rather than other programs
+
+// `Berlin -> Prague -> Vienna` followed by a line per leg with its cost and the running total,
+// cities without a name are shown by index
+pub fn format_labeled_tour<W: Weight>(
+    intercity_map: &[Vec<W>],
+    path: &[u32],
+    names: &[String],
+) -> String {
+    let label = |city: u32| match names.get(city as usize) {
+        Some(name) if !name.is_empty() => name.clone(),
+        _ => city.to_string(),
+    };
+
+    let mut output = path
+        .iter()
+        .map(|&city| label(city))
+        .collect::<Vec<String>>()
+        .join(" -> ");
+    let mut total = W::Cost::ZERO;
+    for leg in path.windows(2) {
+        let cost = intercity_map[leg[0] as usize][leg[1] as usize];
+        total += cost.to_cost();
+        // writing to a string can't fail
+        let _ = write!(
+            output,
+            "\n  {} -> {}: {} (total {})",
+            label(leg[0]),
+            label(leg[1]),
+            cost,
+            total
+        );
+    }
+    output
+}
+
+// the same route can be written down in several ways, these pick one so tours from different
+// runs and solvers can be compared directly
+
+// open paths can't be rotated without changing which edge is left out, so the only freedom is
+// the direction, the path starts from its lower numbered end unless that changes the cost,
+// which it can only do on asymmetric maps
+pub fn normalize_path<W: Weight>(intercity_map: &[Vec<W>], path: &[u32]) -> Vec<u32> {
+    let mut normalized = path.to_vec();
+    if let (Some(&first), Some(&last)) = (path.first(), path.last()) {
+        if last < first {
+            normalized.reverse();
+            if path_cost_with(&intercity_map, &normalized) != path_cost_with(&intercity_map, path) {
+                normalized.reverse();
+            }
+        }
+    }
+    normalized
+}
+
+// round trips are rotated to start at the depot and then run towards its lower numbered
+// neighbour unless that changes the cost, a depot missing from the tour leaves it unrotated
+pub fn normalize_round_trip<W: Weight>(
+    intercity_map: &[Vec<W>],
+    tour: &[u32],
+    depot: u32,
+) -> Vec<u32> {
+    let mut normalized = tour.to_vec();
+    if let Some(start) = tour.iter().position(|&city| city == depot) {
+        normalized.rotate_left(start);
+    }
+    if normalized.len() > 2 && normalized[normalized.len() - 1] < normalized[1] {
+        let round_trip_cost = |tour: &[u32]| {
+            path_cost_with(&intercity_map, tour)
+                + intercity_map[tour[tour.len() - 1] as usize][tour[0] as usize].to_cost()
+        };
+        let mut reversed = normalized.clone();
+        reversed[1..].reverse();
+        if round_trip_cost(&reversed) == round_trip_cost(&normalized) {
+            normalized = reversed;
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::generate_map;
+    use crate::tour::{format_labeled_tour, normalize_path, normalize_round_trip};
+
+    #[test]
+    fn test_labeled_tour() {
+        let map: Vec<Vec<u16>> = vec![vec![0, 280, 500], vec![280, 0, 250], vec![500, 250, 0]];
+        let names = vec!["Berlin".to_string(), "Prague".to_string(), String::new()];
+        assert_eq!(
+            format_labeled_tour(&map, &[0, 1, 2], &names),
+            "Berlin -> Prague -> 2\n  Berlin -> Prague: 280 (total 280)\n  Prague -> 2: 250 (total 530)"
+        );
+        assert_eq!(format_labeled_tour(&map, &[2], &[]), "2");
+    }
+
+    #[test]
+    fn test_normalize_tours() {
+        let map = generate_map(5, (1, 100)).ok().unwrap();
+        assert_eq!(normalize_path(&map, &[3, 0, 4, 1, 2]), vec![2, 1, 4, 0, 3]);
+        assert_eq!(normalize_path(&map, &[2, 0, 4, 1, 3]), vec![2, 0, 4, 1, 3]);
+
+        // every rotation and direction of a round trip comes out the same
+        let expected = vec![0, 1, 3, 2, 4];
+        for tour in [[3, 2, 4, 0, 1], [1, 0, 4, 2, 3], [0, 1, 3, 2, 4]] {
+            assert_eq!(normalize_round_trip(&map, &tour, 0), expected);
+        }
+        assert_eq!(
+            normalize_round_trip(&map, &[3, 2, 4, 0, 1], 2),
+            vec![2, 3, 1, 0, 4]
+        );
+
+        // reversing would change the cost of an asymmetric path
+        let asymmetric: Vec<Vec<u16>> = vec![vec![0, 1, 9], vec![9, 0, 1], vec![1, 9, 0]];
+        assert_eq!(normalize_path(&asymmetric, &[2, 1, 0]), vec![2, 1, 0]);
+        assert_eq!(
+            normalize_round_trip(&asymmetric, &[1, 2, 0], 0),
+            vec![0, 1, 2]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+// a round trip through the cities 0..n that local search walks and reverses stretches of, which
+// in one Vec costs O(n) for a long stretch, too slow once 2-opt and Lin-Kernighan moves number
+// in the millions on 100,000 city tours, so implementations trade a little speed walking for
+// cheaper reversals
+pub trait TourRepr {
+    // order being every city 0..order.len() once
+    fn new(order: &[usize]) -> Self;
+
+    fn num_cities(&self) -> usize;
+
+    fn next(&self, city: usize) -> usize;
+
+    fn previous(&self, city: usize) -> usize;
+
+    // reverses the stretch of the round trip going forward from first to last, so the city that
+    // was before first is followed by last and first by the city that was after last
+    fn reverse(&mut self, first: usize, last: usize);
+
+    // every city in tour order, from start
+    fn order_from(&self, start: usize) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.num_cities());
+        let mut city = start;
+        loop {
+            order.push(city);
+            city = self.next(city);
+            if city == start {
+                return order;
+            }
+        }
+    }
+}
+
+// the order in one Vec with each city's position in it, reversing whichever of the stretch and
+// the rest of the round trip is shorter, as reversing the rest leaves the same round trip the
+// other way round, which a flag turns back, so reversals are O(n / 2) at worst, fine for tours
+// of a few thousand cities
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ArrayTour {
+    order: Vec<usize>,
+    position: Vec<usize>,
+    backwards: bool, // walking the order from the end
+}
+
+impl ArrayTour {
+    // reverses the len positions from start, wrapping round the end of the order
+    fn reverse_positions(&mut self, start: usize, len: usize) {
+        let num_cities = self.order.len();
+        for offset in 0..len / 2 {
+            let i = (start + offset) % num_cities;
+            let j = (start + len - 1 - offset) % num_cities;
+            self.order.swap(i, j);
+            self.position[self.order[i]] = i;
+            self.position[self.order[j]] = j;
+        }
+    }
+}
+
+impl TourRepr for ArrayTour {
+    fn new(order: &[usize]) -> ArrayTour {
+        let mut position = vec![0; order.len()];
+        for (index, &city) in order.iter().enumerate() {
+            position[city] = index;
+        }
+        ArrayTour {
+            order: order.to_vec(),
+            position,
+            backwards: false,
+        }
+    }
+
+    fn num_cities(&self) -> usize {
+        self.order.len()
+    }
+
+    fn next(&self, city: usize) -> usize {
+        let num_cities = self.order.len();
+        let step = if self.backwards { num_cities - 1 } else { 1 };
+        self.order[(self.position[city] + step) % num_cities]
+    }
+
+    fn previous(&self, city: usize) -> usize {
+        let num_cities = self.order.len();
+        let step = if self.backwards { 1 } else { num_cities - 1 };
+        self.order[(self.position[city] + step) % num_cities]
+    }
+
+    fn reverse(&mut self, first: usize, last: usize) {
+        let num_cities = self.order.len();
+        let (start, end) = if self.backwards {
+            (self.position[last], self.position[first])
+        } else {
+            (self.position[first], self.position[last])
+        };
+        let len = (end + num_cities - start) % num_cities + 1;
+        if 2 * len <= num_cities {
+            self.reverse_positions(start, len);
+        } else {
+            self.reverse_positions(end + 1, num_cities - len);
+            self.backwards = !self.backwards;
+        }
+    }
+}
+
+// the tour cut into segments of about sqrt(n) cities, each walked forwards or backwards by its
+// own flag, so reversing a stretch only splits the segments at its ends and reverses the order
+// of and flips the ones between, O(sqrt(n)) rather than O(n), the segments being cut evenly
+// again once splits have made too many of them
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TwoLevelList {
+    segments: Vec<Segment>,
+    order: Vec<usize>,      // of the segments round the tour
+    segment_of: Vec<usize>, // each city's
+    index_of: Vec<usize>,   // each city's in its segment's cities
+    max_segments: usize,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Segment {
+    cities: Vec<usize>,
+    backwards: bool,
+    rank: usize, // its index in the order
+}
+
+impl TwoLevelList {
+    // how far into its segment the walk reaches city
+    fn offset(&self, city: usize) -> usize {
+        let segment = &self.segments[self.segment_of[city]];
+        if segment.backwards {
+            segment.cities.len() - 1 - self.index_of[city]
+        } else {
+            self.index_of[city]
+        }
+    }
+
+    fn city_at(&self, id: usize, offset: usize) -> usize {
+        let segment = &self.segments[id];
+        if segment.backwards {
+            segment.cities[segment.cities.len() - 1 - offset]
+        } else {
+            segment.cities[offset]
+        }
+    }
+
+    // moves the cities offset onwards in the segment to a new one after it
+    fn split(&mut self, id: usize, offset: usize) {
+        let segment = &mut self.segments[id];
+        let len = segment.cities.len();
+        let tail: Vec<usize> = if segment.backwards {
+            segment.cities.drain(..len - offset).collect()
+        } else {
+            segment.cities.drain(offset..).collect()
+        };
+        let (backwards, rank) = (segment.backwards, segment.rank);
+        let new_id = self.segments.len();
+        self.segments.push(Segment {
+            cities: tail,
+            backwards,
+            rank: rank + 1,
+        });
+        self.order.insert(rank + 1, new_id);
+        for (rank, &id) in self.order.iter().enumerate().skip(rank + 2) {
+            self.segments[id].rank = rank;
+        }
+        self.index(id);
+        self.index(new_id);
+    }
+
+    fn index(&mut self, id: usize) {
+        for (index, &city) in self.segments[id].cities.iter().enumerate() {
+            self.segment_of[city] = id;
+            self.index_of[city] = index;
+        }
+    }
+}
+
+impl TourRepr for TwoLevelList {
+    fn new(order: &[usize]) -> TwoLevelList {
+        let num_cities = order.len();
+        let size = ((num_cities as f64).sqrt().ceil() as usize).max(1);
+        let mut list = TwoLevelList {
+            segments: order
+                .chunks(size)
+                .enumerate()
+                .map(|(rank, cities)| Segment {
+                    cities: cities.to_vec(),
+                    backwards: false,
+                    rank,
+                })
+                .collect(),
+            order: (0..num_cities.div_ceil(size)).collect(),
+            segment_of: vec![0; num_cities],
+            index_of: vec![0; num_cities],
+            max_segments: 3 * num_cities.div_ceil(size),
+        };
+        for id in 0..list.segments.len() {
+            list.index(id);
+        }
+        list
+    }
+
+    fn num_cities(&self) -> usize {
+        self.segment_of.len()
+    }
+
+    fn next(&self, city: usize) -> usize {
+        let id = self.segment_of[city];
+        let offset = self.offset(city) + 1;
+        if offset < self.segments[id].cities.len() {
+            return self.city_at(id, offset);
+        }
+        let rank = (self.segments[id].rank + 1) % self.order.len();
+        self.city_at(self.order[rank], 0)
+    }
+
+    fn previous(&self, city: usize) -> usize {
+        let id = self.segment_of[city];
+        let offset = self.offset(city);
+        if offset > 0 {
+            return self.city_at(id, offset - 1);
+        }
+        let rank = (self.segments[id].rank + self.order.len() - 1) % self.order.len();
+        let id = self.order[rank];
+        self.city_at(id, self.segments[id].cities.len() - 1)
+    }
+
+    fn reverse(&mut self, first: usize, last: usize) {
+        if first == last {
+            return;
+        }
+        // first starting a segment and last ending one
+        let offset = self.offset(first);
+        if offset > 0 {
+            self.split(self.segment_of[first], offset);
+        }
+        let offset = self.offset(last) + 1;
+        if offset < self.segments[self.segment_of[last]].cities.len() {
+            self.split(self.segment_of[last], offset);
+        }
+
+        let num_segments = self.order.len();
+        let start = self.segments[self.segment_of[first]].rank;
+        let end = self.segments[self.segment_of[last]].rank;
+        let len = (end + num_segments - start) % num_segments + 1;
+        for offset in 0..len / 2 {
+            let i = (start + offset) % num_segments;
+            let j = (end + num_segments - offset) % num_segments;
+            self.order.swap(i, j);
+        }
+        for offset in 0..len {
+            let rank = (start + offset) % num_segments;
+            let segment = &mut self.segments[self.order[rank]];
+            segment.backwards = !segment.backwards;
+            segment.rank = rank;
+        }
+
+        if self.segments.len() > self.max_segments {
+            *self = TwoLevelList::new(&self.order_from(first));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tour_repr::{ArrayTour, TourRepr, TwoLevelList};
+    use rand::seq::SliceRandom;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    // reverses the stretch from first to last of a round trip in one Vec, the slow way
+    fn reverse(order: &mut [usize], first: usize, last: usize) {
+        let num_cities = order.len();
+        let start = order.iter().position(|&city| city == first).unwrap();
+        let end = order.iter().position(|&city| city == last).unwrap();
+        let len = (end + num_cities - start) % num_cities + 1;
+        for offset in 0..len / 2 {
+            order.swap(
+                (start + offset) % num_cities,
+                (end + num_cities - offset) % num_cities,
+            );
+        }
+    }
+
+    #[test]
+    fn test_tour_reprs() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for num_cities in [1, 2, 3, 5, 50, 400] {
+            let mut order: Vec<usize> = (0..num_cities).collect();
+            order.shuffle(&mut rng);
+            let mut array = ArrayTour::new(&order);
+            let mut list = TwoLevelList::new(&order);
+            for _ in 0..300 {
+                let first = rng.gen_range(0..num_cities);
+                let last = rng.gen_range(0..num_cities);
+                reverse(&mut order, first, last);
+                array.reverse(first, last);
+                list.reverse(first, last);
+                assert_eq!(array.order_from(order[0]), order);
+                assert_eq!(list.order_from(order[0]), order);
+                let city = rng.gen_range(0..num_cities);
+                assert_eq!(list.next(list.previous(city)), city);
+                assert_eq!(list.previous(city), array.previous(city));
+            }
+            assert_eq!(list.num_cities(), num_cities);
+        }
+    }
+}
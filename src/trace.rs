@@ -0,0 +1,129 @@
+use log::error;
+use std::fmt::Display;
+use std::io::{BufRead, BufWriter, Write};
+
+use crate::common::TSPError;
+
+// one line per accepted move, `iteration operator delta cost`, separated by spaces
+const HEADER: &str = "# iteration operator delta cost";
+
+pub struct MoveTrace<'a> {
+    writer: BufWriter<&'a mut dyn Write>,
+    error: Option<std::io::Error>, // the first failed write, later moves are not written
+}
+
+impl<'a> MoveTrace<'a> {
+    pub(crate) fn new(writer: &'a mut dyn Write) -> MoveTrace<'a> {
+        let mut trace = MoveTrace {
+            writer: BufWriter::new(writer),
+            error: None,
+        };
+        trace.write_line(format_args!("{}", HEADER));
+        trace
+    }
+
+    fn write_line(&mut self, line: std::fmt::Arguments) {
+        if self.error.is_none() {
+            if let Err(err) = writeln!(self.writer, "{}", line) {
+                self.error = Some(err);
+            }
+        }
+    }
+
+    pub(crate) fn record<D: Display>(&mut self, iteration: u64, operator: &str, delta: D, cost: D) {
+        self.write_line(format_args!(
+            "{} {} {} {}",
+            iteration, operator, delta, cost
+        ));
+    }
+
+    pub(crate) fn finish(mut self) -> Result<(), TSPError> {
+        if let Some(err) = self.error.take() {
+            error!("Writing the move trace failed: {}", err);
+            return Err(err.into());
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TraceEntry {
+    pub iteration: u64,
+    pub operator: String,
+    pub delta: i64,
+    pub cost: i64,
+}
+
+fn parse_line(line: &str) -> Option<TraceEntry> {
+    let mut fields = line.split(' ');
+    let entry = TraceEntry {
+        iteration: fields.next()?.parse().ok()?,
+        operator: fields.next()?.to_string(),
+        delta: fields.next()?.parse().ok()?,
+        cost: fields.next()?.parse().ok()?,
+    };
+    fields.next().is_none().then_some(entry)
+}
+
+pub fn read_trace<R: BufRead>(reader: R) -> Result<Vec<TraceEntry>, TSPError> {
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+
+        let Some(entry) = parse_line(&line) else {
+            error!("Malformed trace line '{}'", line);
+            return Err(TSPError::InvalidFileFormat);
+        };
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{generate_default_path, generate_map};
+    use crate::config::SolverConfig;
+    use crate::cost::path_cost_with;
+    use crate::sim_annealing::Annealer;
+    use crate::trace::read_trace;
+
+    #[test]
+    fn test_move_trace() {
+        let map = generate_map(6, (1, 100)).ok().unwrap();
+        let mut trace = Vec::new();
+        let config = SolverConfig {
+            max_iterations: 1000,
+            ..SolverConfig::default()
+        };
+        let (_, cost, _) = Annealer::new(config)
+            .trace(&mut trace)
+            .solve(&map)
+            .ok()
+            .unwrap();
+
+        let entries = read_trace(trace.as_slice()).ok().unwrap();
+        assert!(!entries.is_empty());
+        assert!(entries
+            .windows(2)
+            .all(|pair| pair[0].iteration < pair[1].iteration));
+        assert!(entries.iter().all(|entry| entry.operator == "two_opt"));
+
+        // replaying the deltas from the starting cost passes through the best tour's cost
+        let start_cost = path_cost_with(&map, &generate_default_path(&map)) as i64;
+        let (_, lowest) =
+            entries
+                .iter()
+                .fold((start_cost, start_cost), |(current, lowest), entry| {
+                    assert_eq!(current + entry.delta, entry.cost);
+                    (entry.cost, lowest.min(entry.cost))
+                });
+        assert_eq!(lowest, cost as i64);
+
+        assert!(read_trace("1 shuffle x 4\n".as_bytes()).is_err());
+    }
+}
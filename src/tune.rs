@@ -0,0 +1,603 @@
+use log::{error, info};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use crate::bench::load_suite;
+use crate::bounds::{gap_percent, spanning_tree_bound};
+use crate::common::TSPError;
+use crate::config::SolverConfig;
+use crate::cooling::CoolingSchedule;
+use crate::io::tsplib::TsplibInstance;
+use crate::sim_annealing::Annealer;
+
+// every configuration anneals num_seeds parallel restarts, seeded from 0, on every instance and
+// is scored by the gap between the best of them and the instance's spanning tree bound, lower
+// is better
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TuneConfig {
+    pub temperature: u64,
+    pub max_iterations: u64,
+    pub cooling: CoolingSchedule,
+}
+
+pub struct TuneResult {
+    pub instance: String,
+    pub class: &'static str,
+    pub config: TuneConfig,
+    pub num_seeds: u64,
+    pub best_cost: u64,
+    pub gap: f64,
+}
+
+pub struct ClassBest {
+    pub class: &'static str,
+    pub config: TuneConfig,
+    pub num_instances: usize,
+    pub mean_gap: f64, // averaged over the class's instances
+}
+
+// instances are grouped by size, the best settings rarely carry over between sizes
+pub fn instance_class(num_cities: usize) -> &'static str {
+    match num_cities {
+        0..=20 => "small",
+        21..=100 => "medium",
+        _ => "large",
+    }
+}
+
+// accepts 200, 1e5 and 1.5e6
+fn parse_value(value: &str) -> Result<u64, TSPError> {
+    match value.parse::<f64>() {
+        Ok(number) if number >= 0. && number.fract() == 0. => Ok(number as u64),
+        _ => {
+            error!("'{}' is not a whole number", value);
+            Err(TSPError::InvalidParameterGrid)
+        }
+    }
+}
+
+// a geometric cooling factor, strictly between 0 and 1
+fn parse_alpha(value: &str) -> Result<f64, TSPError> {
+    match value.parse::<f64>() {
+        Ok(alpha) if alpha > 0. && alpha < 1. => Ok(alpha),
+        _ => {
+            error!("'{}' is not a cooling factor between 0 and 1", value);
+            Err(TSPError::InvalidParameterGrid)
+        }
+    }
+}
+
+// the schedule with its epochs epoch_length iterations long, cauchy cooling has no epochs
+fn with_epoch_length(
+    cooling: CoolingSchedule,
+    epoch_length: u64,
+) -> Result<CoolingSchedule, TSPError> {
+    let cooling = match cooling {
+        CoolingSchedule::Cauchy => {
+            error!("An epoch length needs a cooling factor or a schedule with epochs");
+            return Err(TSPError::InvalidParameterGrid);
+        }
+        CoolingSchedule::Geometric { alpha, .. } => CoolingSchedule::Geometric {
+            alpha,
+            epoch_length,
+        },
+        CoolingSchedule::Linear { .. } => CoolingSchedule::Linear { epoch_length },
+        CoolingSchedule::Logarithmic { .. } => CoolingSchedule::Logarithmic { epoch_length },
+    };
+    cooling
+        .check()
+        .map_err(|_| TSPError::InvalidParameterGrid)?;
+    Ok(cooling)
+}
+
+// `name=value,value,...` per parameter, `temperature`, `iters`, `cooling` (geometric cooling
+// factors, e.g. cooling=0.90,0.95,0.99) and `epoch` (the cooling's epoch lengths), parameters
+// left out keep their defaults, the grid is every combination of the listed values
+pub fn parse_grid(parameters: &[&str], defaults: TuneConfig) -> Result<Vec<TuneConfig>, TSPError> {
+    let mut temperatures = vec![defaults.temperature];
+    let mut iterations = vec![defaults.max_iterations];
+    let mut alphas = None;
+    let mut epoch_lengths = None;
+    for parameter in parameters {
+        let Some((name, values)) = parameter.split_once('=') else {
+            error!("Expected name=values but found '{}'", parameter);
+            return Err(TSPError::InvalidParameterGrid);
+        };
+        let values = values.split(',');
+        match name {
+            "temperature" => temperatures = values.map(parse_value).collect::<Result<_, _>>()?,
+            "iters" => iterations = values.map(parse_value).collect::<Result<_, _>>()?,
+            "cooling" => alphas = Some(values.map(parse_alpha).collect::<Result<Vec<_>, _>>()?),
+            "epoch" => {
+                epoch_lengths = Some(values.map(parse_value).collect::<Result<Vec<_>, _>>()?)
+            }
+            _ => {
+                error!("Unknown parameter '{}'", name);
+                return Err(TSPError::InvalidParameterGrid);
+            }
+        }
+    }
+
+    // cooling factors make the schedule geometric, with single iteration epochs unless given
+    let schedules: Vec<CoolingSchedule> = match (alphas, epoch_lengths) {
+        (Some(alphas), epoch_lengths) => {
+            let epoch_lengths = epoch_lengths.unwrap_or(vec![1]);
+            alphas
+                .iter()
+                .flat_map(|&alpha| {
+                    epoch_lengths.iter().map(move |&epoch_length| {
+                        with_epoch_length(
+                            CoolingSchedule::Geometric {
+                                alpha,
+                                epoch_length: 1,
+                            },
+                            epoch_length,
+                        )
+                    })
+                })
+                .collect::<Result<_, _>>()?
+        }
+        (None, Some(epoch_lengths)) => epoch_lengths
+            .iter()
+            .map(|&epoch_length| with_epoch_length(defaults.cooling, epoch_length))
+            .collect::<Result<_, _>>()?,
+        (None, None) => vec![defaults.cooling],
+    };
+
+    Ok(temperatures
+        .iter()
+        .flat_map(|&temperature| {
+            let schedules = &schedules;
+            iterations.iter().flat_map(move |&max_iterations| {
+                schedules.iter().map(move |&cooling| TuneConfig {
+                    temperature,
+                    max_iterations,
+                    cooling,
+                })
+            })
+        })
+        .collect())
+}
+
+pub(crate) fn evaluate(
+    name: &str,
+    intercity_map: &[Vec<u16>],
+    config: TuneConfig,
+    num_seeds: u64,
+) -> Result<TuneResult, TSPError> {
+    let solver_config = SolverConfig {
+        temperature: config.temperature,
+        max_iterations: config.max_iterations,
+        cooling: config.cooling,
+        seed: Some(0),
+        ..SolverConfig::default()
+    };
+    let (_, best_cost, _) =
+        Annealer::new(solver_config.parallel_restarts(num_seeds)).solve(intercity_map)?;
+    let bound = spanning_tree_bound(intercity_map)?;
+
+    Ok(TuneResult {
+        instance: name.to_string(),
+        class: instance_class(intercity_map.len()),
+        config,
+        num_seeds,
+        best_cost,
+        gap: gap_percent(best_cost, bound),
+    })
+}
+
+// every configuration on every .tsp file in the suite
+pub fn run_grid(
+    suite: &Path,
+    configs: &[TuneConfig],
+    num_seeds: u64,
+) -> Result<Vec<TuneResult>, TSPError> {
+    let mut results = Vec::new();
+    for TsplibInstance {
+        name,
+        intercity_map,
+        ..
+    } in load_suite(suite)?
+    {
+        for &config in configs {
+            results.push(evaluate(&name, &intercity_map, config, num_seeds)?);
+        }
+    }
+
+    Ok(results)
+}
+
+// the configuration with the lowest mean gap in each instance class, classes in the order
+// they first appear
+pub fn best_per_class(results: &[TuneResult]) -> Vec<ClassBest> {
+    let mut classes: Vec<&'static str> = Vec::new();
+    for result in results {
+        if !classes.contains(&result.class) {
+            classes.push(result.class);
+        }
+    }
+
+    classes
+        .into_iter()
+        .filter_map(|class| {
+            let in_class: Vec<&TuneResult> = results
+                .iter()
+                .filter(|result| result.class == class)
+                .collect();
+            let mut configs: Vec<TuneConfig> = Vec::new();
+            for result in &in_class {
+                if !configs.contains(&result.config) {
+                    configs.push(result.config);
+                }
+            }
+
+            configs
+                .into_iter()
+                .map(|config| {
+                    let gaps: Vec<f64> = in_class
+                        .iter()
+                        .filter(|result| result.config == config)
+                        .map(|result| result.gap)
+                        .collect();
+                    ClassBest {
+                        class,
+                        config,
+                        num_instances: gaps.len(),
+                        mean_gap: gaps.iter().sum::<f64>() / gaps.len() as f64,
+                    }
+                })
+                .min_by(|a, b| a.mean_gap.total_cmp(&b.mean_gap))
+        })
+        .collect()
+}
+
+// inclusive ranges random search samples from, log uniformly as the temperature, iterations and
+// epoch length matter by order of magnitude, the geometric cooling factor uniformly, cauchy
+// cooling being kept without a range for it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SearchSpace {
+    pub temperature: (u64, u64),
+    pub max_iterations: (u64, u64),
+    pub alpha: Option<(f64, f64)>,
+    pub epoch_length: Option<(u64, u64)>, // single iteration epochs without one
+}
+
+impl Default for SearchSpace {
+    fn default() -> Self {
+        SearchSpace {
+            temperature: (10, 1000),
+            max_iterations: (10000, 1000000),
+            alpha: None,
+            epoch_length: None,
+        }
+    }
+}
+
+// `name=low,high` per parameter, same names as parse_grid
+pub fn parse_space(parameters: &[&str], defaults: SearchSpace) -> Result<SearchSpace, TSPError> {
+    let mut space = defaults;
+    for parameter in parameters {
+        let invalid = || {
+            error!("Expected name=low,high but found '{}'", parameter);
+            TSPError::InvalidParameterGrid
+        };
+        let (name, (low, high)) = parameter
+            .split_once('=')
+            .and_then(|(name, values)| Some((name, values.split_once(',')?)))
+            .ok_or_else(invalid)?;
+        if name == "cooling" {
+            let (low, high) = (parse_alpha(low)?, parse_alpha(high)?);
+            if low > high {
+                return Err(invalid());
+            }
+            space.alpha = Some((low, high));
+            continue;
+        }
+
+        let range = match (parse_value(low), parse_value(high)) {
+            (Ok(low), Ok(high)) if 0 < low && low <= high => (low, high),
+            _ => return Err(invalid()),
+        };
+        match name {
+            "temperature" => space.temperature = range,
+            "iters" => space.max_iterations = range,
+            "epoch" => space.epoch_length = Some(range),
+            _ => {
+                error!("Unknown parameter '{}'", name);
+                return Err(TSPError::InvalidParameterGrid);
+            }
+        }
+    }
+    if space.epoch_length.is_some() && space.alpha.is_none() {
+        error!("An epoch length range needs a cooling factor range");
+        return Err(TSPError::InvalidParameterGrid);
+    }
+    Ok(space)
+}
+
+fn sample_log_uniform(rng: &mut StdRng, (low, high): (u64, u64)) -> u64 {
+    let value = rng.gen_range((low as f64).ln()..=(high as f64).ln()).exp();
+    (value.round() as u64).clamp(low, high)
+}
+
+pub fn sample_configs(space: SearchSpace, num_candidates: usize, seed: u64) -> Vec<TuneConfig> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..num_candidates)
+        .map(|_| TuneConfig {
+            temperature: sample_log_uniform(&mut rng, space.temperature),
+            max_iterations: sample_log_uniform(&mut rng, space.max_iterations),
+            cooling: match space.alpha {
+                Some((low, high)) => CoolingSchedule::Geometric {
+                    alpha: rng.gen_range(low..=high),
+                    epoch_length: space
+                        .epoch_length
+                        .map_or(1, |range| sample_log_uniform(&mut rng, range)),
+                },
+                None => CoolingSchedule::Cauchy,
+            },
+        })
+        .collect()
+}
+
+pub struct HalvingRound {
+    pub num_seeds: u64,
+    pub results: Vec<TuneResult>,
+    pub ranking: Vec<(TuneConfig, f64)>, // mean gap over the suite, best first
+}
+
+// successive halving, every round runs the surviving candidates on the whole suite and keeps
+// the better half for the next round which gets twice the seeds, so weak settings are dropped
+// cheaply and the close calls get the most runs, stops with one candidate left or once the
+// next round would take the annealing runs over max_runs (the first round always runs)
+pub fn successive_halving(
+    suite: &Path,
+    candidates: Vec<TuneConfig>,
+    max_runs: u64,
+) -> Result<Vec<HalvingRound>, TSPError> {
+    if candidates.is_empty() {
+        error!("Random search needs at least one candidate");
+        return Err(TSPError::InvalidParameterGrid);
+    }
+
+    let instances = load_suite(suite)?;
+    let mut survivors = candidates;
+    let mut num_seeds = 1;
+    let mut runs_used = 0;
+    let mut rounds: Vec<HalvingRound> = Vec::new();
+    loop {
+        let round_runs = (survivors.len() * instances.len()) as u64 * num_seeds;
+        if !rounds.is_empty() && runs_used + round_runs > max_runs {
+            info!(
+                "Stopping with {} candidates, out of budget",
+                survivors.len()
+            );
+            break;
+        }
+        runs_used += round_runs;
+
+        let mut results = Vec::new();
+        for &config in &survivors {
+            for TsplibInstance {
+                name,
+                intercity_map,
+                ..
+            } in &instances
+            {
+                results.push(evaluate(name, intercity_map, config, num_seeds)?);
+            }
+        }
+
+        let mut ranking: Vec<(TuneConfig, f64)> = survivors
+            .iter()
+            .map(|&config| {
+                let gaps: Vec<f64> = results
+                    .iter()
+                    .filter(|result| result.config == config)
+                    .map(|result| result.gap)
+                    .collect();
+                (config, gaps.iter().sum::<f64>() / gaps.len() as f64)
+            })
+            .collect();
+        ranking.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let num_survivors = ranking.len().div_ceil(2);
+        survivors = ranking[..num_survivors]
+            .iter()
+            .map(|&(config, _)| config)
+            .collect();
+        rounds.push(HalvingRound {
+            num_seeds,
+            results,
+            ranking,
+        });
+
+        if rounds.last().unwrap().ranking.len() == 1 {
+            break;
+        }
+        num_seeds *= 2;
+    }
+
+    Ok(rounds)
+}
+
+const STORE_HEADER: &str = "instance,class,temperature,max_iterations,cooling,seeds,best_cost,gap";
+
+// appends the results to a csv file kept across tuning sessions, writing the header when the
+// file is new
+pub fn append_to_store(store: &Path, results: &[TuneResult]) -> Result<(), TSPError> {
+    let mut file = OpenOptions::new().create(true).append(true).open(store)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{}", STORE_HEADER)?;
+    }
+    for result in results {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{:.4}",
+            result.instance,
+            result.class,
+            result.config.temperature,
+            result.config.max_iterations,
+            result.config.cooling,
+            result.num_seeds,
+            result.best_cost,
+            result.gap
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cooling::CoolingSchedule;
+    use crate::tune::{
+        append_to_store, best_per_class, instance_class, parse_grid, parse_space, run_grid,
+        sample_configs, successive_halving, SearchSpace, TuneConfig,
+    };
+
+    const DEFAULTS: TuneConfig = TuneConfig {
+        temperature: 200,
+        max_iterations: 200000,
+        cooling: CoolingSchedule::Cauchy,
+    };
+
+    #[test]
+    fn test_parse_grid() {
+        let grid = parse_grid(&["temperature=50,100", "iters=1e3,2.5e3,4000"], DEFAULTS)
+            .ok()
+            .unwrap();
+        assert_eq!(grid.len(), 6);
+        assert_eq!(
+            grid[1],
+            TuneConfig {
+                temperature: 50,
+                max_iterations: 2500,
+                cooling: CoolingSchedule::Cauchy
+            }
+        );
+
+        assert_eq!(parse_grid(&[], DEFAULTS).ok().unwrap(), vec![DEFAULTS]);
+
+        // cooling factors make the schedule geometric, the epochs combining with them
+        let grid = parse_grid(&["cooling=0.90,0.95,0.99", "epoch=1,100"], DEFAULTS)
+            .ok()
+            .unwrap();
+        assert_eq!(grid.len(), 6);
+        assert_eq!(
+            grid[3].cooling,
+            CoolingSchedule::Geometric {
+                alpha: 0.95,
+                epoch_length: 100
+            }
+        );
+        assert!(parse_grid(&["cooling=1.5"], DEFAULTS).is_err());
+        assert!(parse_grid(&["cooling=0.9", "epoch=0"], DEFAULTS).is_err());
+        // cauchy cooling has no epochs to set
+        assert!(parse_grid(&["epoch=10"], DEFAULTS).is_err());
+        let linear = TuneConfig {
+            cooling: CoolingSchedule::Linear { epoch_length: 1 },
+            ..DEFAULTS
+        };
+        assert_eq!(
+            parse_grid(&["epoch=10"], linear).ok().unwrap()[0].cooling,
+            CoolingSchedule::Linear { epoch_length: 10 }
+        );
+
+        assert!(parse_grid(&["iters=1.5"], DEFAULTS).is_err());
+        assert!(parse_grid(&["iters"], DEFAULTS).is_err());
+        assert_eq!(instance_class(52), "medium");
+    }
+
+    #[test]
+    fn test_run_grid() {
+        let suite = std::env::temp_dir().join(format!("tsp_tune_{}", std::process::id()));
+        std::fs::create_dir_all(&suite).unwrap();
+        std::fs::write(
+            suite.join("square.tsp"),
+            "NAME : square\nDIMENSION : 4\nEDGE_WEIGHT_TYPE : EUC_2D\n\
+             NODE_COORD_SECTION\n1 0 0\n2 3 0\n3 3 4\n4 0 4\nEOF\n",
+        )
+        .unwrap();
+
+        let grid = parse_grid(&["iters=1,2000"], DEFAULTS).ok().unwrap();
+        let results = run_grid(&suite, &grid, 3).ok().unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.gap >= 0.));
+
+        let best = best_per_class(&results);
+        assert_eq!(best.len(), 1);
+        assert_eq!(best[0].class, "small");
+        assert_eq!(best[0].num_instances, 1);
+
+        let store = suite.join("experiments.csv");
+        append_to_store(&store, &results).ok().unwrap();
+        append_to_store(&store, &results).ok().unwrap();
+        let stored = std::fs::read_to_string(&store).unwrap();
+        std::fs::remove_dir_all(&suite).unwrap();
+        assert_eq!(stored.lines().count(), 5);
+        assert!(stored.starts_with("instance,class"));
+    }
+
+    #[test]
+    fn test_random_search() {
+        let space = parse_space(
+            &["temperature=5,50", "iters=1e2,1e4"],
+            SearchSpace::default(),
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(space.max_iterations, (100, 10000));
+        assert!(parse_space(&["iters=10"], space).is_err());
+        assert!(parse_space(&["iters=0,10"], space).is_err());
+        assert!(parse_space(&["iters=20,10"], space).is_err());
+
+        let candidates = sample_configs(space, 5, 3);
+        assert_eq!(candidates, sample_configs(space, 5, 3));
+        assert!(candidates
+            .iter()
+            .all(|config| (5..=50).contains(&config.temperature)
+                && (100..=10000).contains(&config.max_iterations)
+                && config.cooling == CoolingSchedule::Cauchy));
+
+        let cooled = parse_space(&["cooling=0.8,0.99", "epoch=1,1e3"], space)
+            .ok()
+            .unwrap();
+        assert!(sample_configs(cooled, 5, 3).iter().all(|config| matches!(
+            config.cooling,
+            CoolingSchedule::Geometric { alpha, epoch_length }
+                if (0.8..=0.99).contains(&alpha) && (1..=1000).contains(&epoch_length)
+        )));
+        assert!(parse_space(&["cooling=0.99,0.8"], space).is_err());
+        assert!(parse_space(&["epoch=1,10"], space).is_err());
+
+        let suite = std::env::temp_dir().join(format!("tsp_search_{}", std::process::id()));
+        std::fs::create_dir_all(&suite).unwrap();
+        std::fs::write(
+            suite.join("square.tsp"),
+            "NAME : square\nDIMENSION : 4\nEDGE_WEIGHT_TYPE : EUC_2D\n\
+             NODE_COORD_SECTION\n1 0 0\n2 3 0\n3 3 4\n4 0 4\nEOF\n",
+        )
+        .unwrap();
+
+        // 5 candidates with 1 seed, 3 with 2, 2 with 4 and the winner with 8
+        let rounds = successive_halving(&suite, candidates.clone(), 1000)
+            .ok()
+            .unwrap();
+        let sizes: Vec<(usize, u64)> = rounds
+            .iter()
+            .map(|round| (round.ranking.len(), round.num_seeds))
+            .collect();
+        assert_eq!(sizes, vec![(5, 1), (3, 2), (2, 4), (1, 8)]);
+        assert!(rounds[0]
+            .ranking
+            .windows(2)
+            .all(|pair| pair[0].1 <= pair[1].1));
+
+        // the second round alone would need 6 runs
+        let rounds = successive_halving(&suite, candidates, 10).ok().unwrap();
+        std::fs::remove_dir_all(&suite).unwrap();
+        assert_eq!(rounds.len(), 1);
+    }
+}
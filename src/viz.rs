@@ -0,0 +1,168 @@
+use std::fmt::Write as _;
+use std::io::Write;
+
+use crate::common::{check_path, TSPError};
+use crate::cost::TourMode;
+
+// coordinate instances and their tours drawn as SVG, each city a dot labelled with its name or
+// number and the tour a line through them, the first city in red, the quickest way to see a
+// neighbourhood operator leaving crossed edges behind, y growing upwards as on a map
+
+// of the longer side in pixels, the other scaled to keep the points' proportions
+const SIZE: f64 = 800.;
+const MARGIN: f64 = 20.;
+
+// the points, labelled by labels or their numbers where there are none, and the tour through them
+// in path unless it is empty, returning to the first city for closed tours
+pub fn tour_svg(
+    points: &[(f64, f64)],
+    labels: &[String],
+    path: &[u32],
+    mode: TourMode,
+) -> Result<String, TSPError> {
+    if let Some(index) = points
+        .iter()
+        .position(|(x, y)| !x.is_finite() || !y.is_finite())
+    {
+        return Err(TSPError::InvalidCoordinate { index });
+    }
+    if !labels.is_empty() && labels.len() != points.len() {
+        return Err(TSPError::InvalidCityCount);
+    }
+    if !path.is_empty() {
+        check_path(points.len(), path)?;
+    }
+
+    let (min, max) = points.iter().fold(
+        ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+        |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+    );
+    let extent = (max.0 - min.0).max(max.1 - min.1);
+    let scale = if extent > 0. {
+        (SIZE - 2. * MARGIN) / extent
+    } else {
+        1.
+    };
+    let (width, height) = if points.is_empty() {
+        (2. * MARGIN, 2. * MARGIN)
+    } else {
+        (
+            (max.0 - min.0) * scale + 2. * MARGIN,
+            (max.1 - min.1) * scale + 2. * MARGIN,
+        )
+    };
+    let position = |city: usize| {
+        let (x, y) = points[city];
+        (MARGIN + (x - min.0) * scale, MARGIN + (max.1 - y) * scale)
+    };
+
+    // writing to a String can't fail
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" \
+         viewBox=\"0 0 {:.0} {:.0}\">",
+        width, height, width, height
+    );
+    let _ = writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>");
+    if !path.is_empty() {
+        let line = if mode == TourMode::Closed {
+            "polygon"
+        } else {
+            "polyline"
+        };
+        let _ = write!(svg, "<{} points=\"", line);
+        for (index, &city) in path.iter().enumerate() {
+            let (x, y) = position(city as usize);
+            let separator = if index == 0 { "" } else { " " };
+            let _ = write!(svg, "{}{:.1},{:.1}", separator, x, y);
+        }
+        let _ = writeln!(
+            svg,
+            "\" fill=\"none\" stroke=\"steelblue\" stroke-width=\"1.5\"/>"
+        );
+    }
+    let _ = writeln!(svg, "<g font-family=\"sans-serif\" font-size=\"10\">");
+    for city in 0..points.len() {
+        let (x, y) = position(city);
+        let colour = if path.first() == Some(&(city as u32)) {
+            "red"
+        } else {
+            "black"
+        };
+        let _ = writeln!(
+            svg,
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\"/>",
+            x, y, colour
+        );
+        let label = match labels.get(city) {
+            Some(label) if !label.is_empty() => escape(label),
+            _ => city.to_string(),
+        };
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+            x + 4.,
+            y - 4.,
+            label
+        );
+    }
+    let _ = writeln!(svg, "</g>\n</svg>");
+    Ok(svg)
+}
+
+pub fn write_tour_svg<W: Write>(
+    writer: &mut W,
+    points: &[(f64, f64)],
+    labels: &[String],
+    path: &[u32],
+    mode: TourMode,
+) -> Result<(), TSPError> {
+    writer.write_all(tour_svg(points, labels, path, mode)?.as_bytes())?;
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::TSPError;
+    use crate::cost::TourMode;
+    use crate::viz::tour_svg;
+
+    #[test]
+    fn test_tour_svg() {
+        let square = [(0., 0.), (10., 0.), (10., 10.), (0., 10.)];
+        let svg = tour_svg(&square, &[], &[1, 0, 3, 2], TourMode::Closed)
+            .ok()
+            .unwrap();
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        // scaled to the size, y upwards
+        assert!(svg.contains("<polygon points=\"780.0,780.0 20.0,780.0 20.0,20.0 780.0,20.0\""));
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert!(svg.contains("<circle cx=\"780.0\" cy=\"780.0\" r=\"3\" fill=\"red\"/>"));
+        assert!(svg.contains(">3</text>"));
+
+        let labels: Vec<String> = ["a", "b & c", "", "e"].map(String::from).to_vec();
+        let svg = tour_svg(&square, &labels, &[0, 1, 2, 3], TourMode::Open)
+            .ok()
+            .unwrap();
+        assert!(svg.contains("<polyline") && svg.contains(">b &amp; c</text>"));
+        assert!(svg.contains(">2</text>"));
+        // the cities alone
+        let svg = tour_svg(&square, &[], &[], TourMode::Open).ok().unwrap();
+        assert!(!svg.contains("<poly") && !svg.contains("red"));
+
+        assert!(tour_svg(&square, &[], &[0, 1], TourMode::Open).is_err());
+        assert!(tour_svg(&square, &labels[..2], &[], TourMode::Open).is_err());
+        assert!(matches!(
+            tour_svg(&[(0., f64::NAN)], &[], &[], TourMode::Open),
+            Err(TSPError::InvalidCoordinate { index: 0 })
+        ));
+    }
+}
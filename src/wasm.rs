@@ -0,0 +1,41 @@
+use wasm_bindgen::prelude::*;
+
+use crate::api::{self, Config, Instance};
+
+fn to_js_error(err: impl std::fmt::Display) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+// instance_json is {"intercity_map": [[...], ...]}, config_json may set any of
+// "algorithm" ("brute_force" or "simulated_annealing"), "temperature" and "max_iterations"
+#[wasm_bindgen]
+pub fn solve(instance_json: &str, config_json: &str) -> Result<String, JsError> {
+    let instance: Instance = serde_json::from_str(instance_json).map_err(to_js_error)?;
+    let config: Config = if config_json.trim().is_empty() {
+        Config::default()
+    } else {
+        serde_json::from_str(config_json).map_err(to_js_error)?
+    };
+
+    let solution = api::solve(&instance, &config).map_err(to_js_error)?;
+
+    api::to_canonical_json(&solution).map_err(to_js_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wasm::solve;
+
+    #[test]
+    fn test_solve_json() {
+        let instance = r#"{"intercity_map": [[0, 1, 9], [1, 0, 1], [9, 1, 0]]}"#;
+        let solution = solve(instance, r#"{"algorithm": "brute_force"}"#)
+            .ok()
+            .unwrap();
+        // either direction is optimal, the output always starts from the lower end
+        assert_eq!(solution, r#"{"cost":2,"path":[0,1,2]}"#);
+
+        let solution = solve(instance, r#"{"max_iterations": 100}"#).ok().unwrap();
+        assert!(solution.contains("\"cost\":"));
+    }
+}
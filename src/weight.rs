@@ -0,0 +1,272 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+// the numbers a map can weigh its edges in, whole numbers by default, or floats so real
+// distances, e.g. kilometres between coordinates, are kept exactly rather than rounded, every
+// solver working with any of them through these traits
+
+// an edge weight, which a valid map never has negative, infinite or NaN
+pub trait Weight: Copy + PartialOrd + Default + Debug + Display + Send + Sync + 'static {
+    // what tours are costed in, wide enough that no tour overflows it
+    type Cost: Cost<Delta = Self::Delta>;
+    // what local searches weigh their moves in, the difference between two costs
+    type Delta: Delta;
+
+    // whole numbers, which sum exactly
+    const INTEGRAL: bool;
+
+    fn to_cost(self) -> Self::Cost;
+
+    fn to_delta(self) -> Self::Delta;
+
+    fn to_f64(self) -> f64;
+
+    // a distance as a weight, rounded to the nearest whole number if integral, None if it is
+    // negative or doesn't fit
+    fn from_f64(distance: f64) -> Option<Self>;
+
+    fn is_valid(self) -> bool;
+
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+// a tour cost, u64 for whole weights and f64 for floats
+pub trait Cost:
+    Copy
+    + PartialOrd
+    + Default
+    + Debug
+    + Display
+    + Send
+    + Sync
+    + 'static
+    + Add<Output = Self>
+    + AddAssign
+    + Sum
+{
+    type Delta: Delta;
+
+    const ZERO: Self;
+    // more than any tour costs, what an incumbent starts as before any tour is found
+    const MAX: Self;
+
+    fn to_delta(self) -> Self::Delta;
+
+    // the delta must not be negative, e.g. a cost worked out by adding up moves from a tour's
+    fn from_delta(delta: Self::Delta) -> Self;
+
+    fn to_f64(self) -> f64;
+
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+// a signed difference between costs, i64 for whole weights and f64 for floats
+pub trait Delta:
+    Copy
+    + PartialOrd
+    + Default
+    + Debug
+    + Display
+    + Send
+    + Sync
+    + 'static
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + Sum
+{
+    const ZERO: Self;
+    const MAX: Self;
+
+    fn to_f64(self) -> f64;
+
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+macro_rules! integral_weight {
+    ($($weight:ty),*) => {$(
+        impl Weight for $weight {
+            type Cost = u64;
+            type Delta = i64;
+
+            const INTEGRAL: bool = true;
+
+            fn to_cost(self) -> u64 {
+                self as u64
+            }
+
+            fn to_delta(self) -> i64 {
+                self as i64
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_f64(distance: f64) -> Option<Self> {
+                let rounded = distance.round();
+                (0. ..=<$weight>::MAX as f64)
+                    .contains(&rounded)
+                    .then_some(rounded as $weight)
+            }
+
+            fn is_valid(self) -> bool {
+                true
+            }
+
+            fn total_cmp(&self, other: &Self) -> Ordering {
+                self.cmp(other)
+            }
+        }
+    )*};
+}
+
+macro_rules! float_weight {
+    ($($weight:ty),*) => {$(
+        impl Weight for $weight {
+            type Cost = f64;
+            type Delta = f64;
+
+            const INTEGRAL: bool = false;
+
+            fn to_cost(self) -> f64 {
+                self as f64
+            }
+
+            fn to_delta(self) -> f64 {
+                self as f64
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_f64(distance: f64) -> Option<Self> {
+                Some(distance as $weight).filter(|weight| weight.is_valid())
+            }
+
+            fn is_valid(self) -> bool {
+                self.is_finite() && self >= 0.
+            }
+
+            fn total_cmp(&self, other: &Self) -> Ordering {
+                <$weight>::total_cmp(self, other)
+            }
+        }
+    )*};
+}
+
+// the lighter of two weights, the first if they weigh the same
+pub fn lighter<W: Weight>(a: W, b: W) -> W {
+    if b < a {
+        b
+    } else {
+        a
+    }
+}
+
+integral_weight!(u16, u32);
+float_weight!(f32, f64);
+
+impl Cost for u64 {
+    type Delta = i64;
+
+    const ZERO: u64 = 0;
+    const MAX: u64 = u64::MAX;
+
+    fn to_delta(self) -> i64 {
+        self as i64
+    }
+
+    fn from_delta(delta: i64) -> u64 {
+        delta as u64
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+}
+
+impl Cost for f64 {
+    type Delta = f64;
+
+    const ZERO: f64 = 0.;
+    const MAX: f64 = f64::INFINITY;
+
+    fn to_delta(self) -> f64 {
+        self
+    }
+
+    fn from_delta(delta: f64) -> f64 {
+        delta
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+}
+
+impl Delta for i64 {
+    const ZERO: i64 = 0;
+    const MAX: i64 = i64::MAX;
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+}
+
+impl Delta for f64 {
+    const ZERO: f64 = 0.;
+    const MAX: f64 = f64::INFINITY;
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::weight::{Cost, Weight};
+    use std::cmp::Ordering;
+
+    #[test]
+    fn test_weights() {
+        assert_eq!(u16::from_f64(2.5), Some(3));
+        assert_eq!(u16::from_f64(65535.4), Some(u16::MAX));
+        assert_eq!(u16::from_f64(65535.5), None);
+        assert_eq!(u16::from_f64(-0.6), None);
+        assert_eq!(u32::from_f64(70000.), Some(70000));
+        assert_eq!(u16::from_f64(f64::NAN), None);
+        assert_eq!(f64::from_f64(2.5), Some(2.5));
+        assert_eq!(f32::from_f64(1e40), None);
+        assert_eq!(f64::from_f64(-1.), None);
+        assert!(!f64::NAN.is_valid() && !f32::INFINITY.is_valid() && 0f64.is_valid());
+
+        assert_eq!(u16::MAX.to_cost() + u16::MAX.to_cost(), 2 * 65535);
+        assert_eq!(3u16.to_delta() - 5u16.to_delta(), -2);
+        assert_eq!(0.5f32.to_cost() + 0.25f64.to_cost(), 0.75);
+        assert_eq!(u64::from_delta(7u64.to_delta()), 7);
+        assert_eq!(1.5f64.total_cmp(&f64::MAX), Ordering::Less);
+        assert_eq!(<f64 as Cost>::MAX, f64::INFINITY);
+    }
+}
@@ -0,0 +1,523 @@
+use log::{error, info};
+use std::fs;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::api::{self, to_canonical_json, Algorithm, JobStatus, Solution, SolveRequest};
+use crate::common::{generate_default_path, TSPError};
+use crate::config::SolverConfig;
+use crate::sim_annealing::{Annealer, Checkpoint};
+
+pub struct Job {
+    pub id: String,
+    pub request: SolveRequest,
+}
+
+// a source of solve jobs that can persist progress, jobs that are claimed but never
+// completed keep their checkpoint so another attempt can pick up where they stopped
+pub trait JobQueue {
+    fn claim(&mut self) -> Result<Option<Job>, TSPError>;
+    fn load_checkpoint(&mut self, id: &str) -> Result<Option<Checkpoint>, TSPError>;
+    fn save_checkpoint(&mut self, id: &str, checkpoint: &Checkpoint) -> Result<(), TSPError>;
+    fn complete(&mut self, id: &str, status: &JobStatus) -> Result<(), TSPError>;
+}
+
+pub struct WorkerOptions {
+    pub checkpoint_interval: u64, // iterations between checkpoints, 0 disables them
+    pub poll_interval: Option<Duration>, // None stops the worker once the queue is empty
+}
+
+impl Default for WorkerOptions {
+    fn default() -> Self {
+        WorkerOptions {
+            checkpoint_interval: 10000,
+            poll_interval: None,
+        }
+    }
+}
+
+fn to_json_error(err: serde_json::Error) -> TSPError {
+    error!("Malformed job file: {}", err);
+    TSPError::InvalidFileFormat
+}
+
+fn process<Q: JobQueue>(
+    queue: &mut Q,
+    job: &Job,
+    checkpoint_interval: u64,
+) -> Result<JobStatus, TSPError> {
+    let Job { id, request } = job;
+    if request.config.algorithm != Algorithm::SimulatedAnnealing {
+        return Ok(api::solve(&request.instance, &request.config).into());
+    }
+
+    let intercity_map = &request.instance.intercity_map;
+    let checkpoint = match queue.load_checkpoint(id)? {
+        Some(checkpoint) => checkpoint,
+        None => Checkpoint {
+            iteration: 0,
+            path: generate_default_path(intercity_map),
+        },
+    };
+
+    let mut save_error = None;
+    let config = SolverConfig {
+        temperature: request.config.temperature,
+        max_iterations: request.config.max_iterations,
+        ..SolverConfig::default()
+    };
+    let result = Annealer::new(config)
+        .resume(checkpoint)
+        .on_progress(checkpoint_interval, |progress| {
+            let checkpoint = Checkpoint {
+                iteration: progress.iteration,
+                path: progress.current_path.to_vec(),
+            };
+            match queue.save_checkpoint(id, &checkpoint) {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(err) => {
+                    save_error = Some(err);
+                    ControlFlow::Break(())
+                }
+            }
+        })
+        .solve(intercity_map);
+
+    // a checkpoint that can't be saved fails this job, the worker carries on with the next
+    if let Some(err) = save_error {
+        return Ok(JobStatus::Failed {
+            error: err.to_string(),
+        });
+    }
+    Ok(result.map(|(path, cost, _)| Solution { path, cost }).into())
+}
+
+// processes jobs until the queue is empty (or forever when polling), returns how many were completed
+pub fn run_worker<Q: JobQueue>(queue: &mut Q, options: &WorkerOptions) -> Result<usize, TSPError> {
+    let mut num_completed = 0;
+    loop {
+        let job = match queue.claim()? {
+            Some(job) => job,
+            None => match options.poll_interval {
+                Some(interval) => {
+                    std::thread::sleep(interval);
+                    continue;
+                }
+                None => return Ok(num_completed),
+            },
+        };
+
+        info!("Processing job {}", job.id);
+        let status = process(queue, &job, options.checkpoint_interval)?;
+        queue.complete(&job.id, &status)?;
+        num_completed += 1;
+    }
+}
+
+// jobs move between pending/, running/ and done/ as <id>.json files, claiming is an atomic rename
+// so several workers can share one spool, checkpoints live next to the running job
+pub struct DirectoryQueue {
+    root: PathBuf,
+}
+
+impl DirectoryQueue {
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, TSPError> {
+        let root = root.as_ref().to_path_buf();
+        for dir in ["pending", "running", "done"] {
+            fs::create_dir_all(root.join(dir))?;
+        }
+        Ok(DirectoryQueue { root })
+    }
+
+    pub fn submit(&self, id: &str, request: &SolveRequest) -> Result<(), TSPError> {
+        // written elsewhere first so a worker never sees a half written job
+        let staged = self.root.join(format!(".{}.json", id));
+        fs::write(&staged, to_canonical_json(request).map_err(to_json_error)?)?;
+        fs::rename(
+            staged,
+            self.root.join("pending").join(format!("{}.json", id)),
+        )?;
+        Ok(())
+    }
+
+    pub fn result(&self, id: &str) -> Result<Option<JobStatus>, TSPError> {
+        match fs::read(self.root.join("done").join(format!("{}.json", id))) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes).map_err(to_json_error)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // puts jobs left in running/ by a crashed worker back in the queue, only safe when no other
+    // worker is using the spool, their checkpoints are kept so the jobs resume
+    pub fn requeue_interrupted(&self) -> Result<usize, TSPError> {
+        let mut num_requeued = 0;
+        for entry in fs::read_dir(self.root.join("running"))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                fs::rename(
+                    &path,
+                    self.root.join("pending").join(path.file_name().unwrap()),
+                )?;
+                num_requeued += 1;
+            }
+        }
+        Ok(num_requeued)
+    }
+
+    fn checkpoint_path(&self, id: &str) -> PathBuf {
+        self.root.join("running").join(format!("{}.checkpoint", id))
+    }
+}
+
+impl JobQueue for DirectoryQueue {
+    fn claim(&mut self) -> Result<Option<Job>, TSPError> {
+        let mut pending: Vec<PathBuf> = fs::read_dir(self.root.join("pending"))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        pending.sort();
+
+        for path in pending {
+            let running = self.root.join("running").join(path.file_name().unwrap());
+            // another worker got there first
+            if fs::rename(&path, &running).is_err() {
+                continue;
+            }
+
+            let id = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let request = serde_json::from_slice(&fs::read(&running)?).map_err(to_json_error);
+            match request {
+                Ok(request) => return Ok(Some(Job { id, request })),
+                Err(err) => self.complete(
+                    &id,
+                    &JobStatus::Failed {
+                        error: err.to_string(),
+                    },
+                )?,
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn load_checkpoint(&mut self, id: &str) -> Result<Option<Checkpoint>, TSPError> {
+        match fs::read(self.checkpoint_path(id)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes).map_err(to_json_error)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save_checkpoint(&mut self, id: &str, checkpoint: &Checkpoint) -> Result<(), TSPError> {
+        // rename over the old checkpoint so a crash mid write never corrupts it
+        let path = self.checkpoint_path(id);
+        let staged = path.with_extension("checkpoint.tmp");
+        fs::write(
+            &staged,
+            to_canonical_json(checkpoint).map_err(to_json_error)?,
+        )?;
+        fs::rename(staged, path)?;
+        Ok(())
+    }
+
+    fn complete(&mut self, id: &str, status: &JobStatus) -> Result<(), TSPError> {
+        let done = self.root.join("done").join(format!("{}.json", id));
+        fs::write(&done, to_canonical_json(status).map_err(to_json_error)?)?;
+        let _ = fs::remove_file(self.checkpoint_path(id));
+        fs::remove_file(self.root.join("running").join(format!("{}.json", id)))?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use redis_queue::RedisQueue;
+
+#[cfg(feature = "redis")]
+mod redis_queue {
+    use redis::Commands;
+    use serde::{Deserialize, Serialize};
+
+    use super::{to_json_error, Job, JobQueue};
+    use crate::api::{to_canonical_json, JobStatus, SolveRequest};
+    use crate::common::TSPError;
+    use crate::sim_annealing::Checkpoint;
+
+    #[derive(Serialize, Deserialize)]
+    struct QueuedJob {
+        id: String,
+        request: SolveRequest,
+    }
+
+    // jobs are pushed onto <prefix>:pending and atomically moved to <prefix>:running when
+    // claimed, results and checkpoints are stored under <prefix>:result:<id> and <prefix>:checkpoint:<id>
+    pub struct RedisQueue {
+        connection: redis::Connection,
+        prefix: String,
+        claimed: Vec<(String, String)>, // job id and the raw entry in the running list
+    }
+
+    fn to_redis_error(err: redis::RedisError) -> TSPError {
+        TSPError::Io(std::io::Error::other(err))
+    }
+
+    impl RedisQueue {
+        pub fn open(url: &str, prefix: &str) -> Result<Self, TSPError> {
+            let client = redis::Client::open(url).map_err(to_redis_error)?;
+            Ok(RedisQueue {
+                connection: client.get_connection().map_err(to_redis_error)?,
+                prefix: prefix.to_string(),
+                claimed: Vec::new(),
+            })
+        }
+
+        pub fn submit(&mut self, id: &str, request: &SolveRequest) -> Result<(), TSPError> {
+            let entry = to_canonical_json(&QueuedJob {
+                id: id.to_string(),
+                request: request.clone(),
+            })
+            .map_err(to_json_error)?;
+            self.connection
+                .lpush::<_, _, ()>(format!("{}:pending", self.prefix), entry)
+                .map_err(to_redis_error)
+        }
+
+        // puts jobs left in <prefix>:running by a crashed worker back in the queue, oldest claimed
+        // first, only safe when no other worker is using the queue, their checkpoints are kept
+        pub fn requeue_interrupted(&mut self) -> Result<usize, TSPError> {
+            let mut num_requeued = 0;
+            loop {
+                let entry: Option<String> = redis::cmd("LMOVE")
+                    .arg(format!("{}:running", self.prefix))
+                    .arg(format!("{}:pending", self.prefix))
+                    .arg("LEFT")
+                    .arg("RIGHT")
+                    .query(&mut self.connection)
+                    .map_err(to_redis_error)?;
+                if entry.is_none() {
+                    return Ok(num_requeued);
+                }
+                num_requeued += 1;
+            }
+        }
+    }
+
+    impl JobQueue for RedisQueue {
+        fn claim(&mut self) -> Result<Option<Job>, TSPError> {
+            let entry: Option<String> = redis::cmd("LMOVE")
+                .arg(format!("{}:pending", self.prefix))
+                .arg(format!("{}:running", self.prefix))
+                .arg("RIGHT")
+                .arg("LEFT")
+                .query(&mut self.connection)
+                .map_err(to_redis_error)?;
+
+            let Some(entry) = entry else {
+                return Ok(None);
+            };
+            let queued: QueuedJob = serde_json::from_str(&entry).map_err(to_json_error)?;
+            self.claimed.push((queued.id.clone(), entry));
+            Ok(Some(Job {
+                id: queued.id,
+                request: queued.request,
+            }))
+        }
+
+        fn load_checkpoint(&mut self, id: &str) -> Result<Option<Checkpoint>, TSPError> {
+            let saved: Option<String> = self
+                .connection
+                .get(format!("{}:checkpoint:{}", self.prefix, id))
+                .map_err(to_redis_error)?;
+            saved
+                .map(|saved| serde_json::from_str(&saved).map_err(to_json_error))
+                .transpose()
+        }
+
+        fn save_checkpoint(&mut self, id: &str, checkpoint: &Checkpoint) -> Result<(), TSPError> {
+            let saved = to_canonical_json(checkpoint).map_err(to_json_error)?;
+            self.connection
+                .set::<_, _, ()>(format!("{}:checkpoint:{}", self.prefix, id), saved)
+                .map_err(to_redis_error)
+        }
+
+        fn complete(&mut self, id: &str, status: &JobStatus) -> Result<(), TSPError> {
+            let result = to_canonical_json(status).map_err(to_json_error)?;
+            self.connection
+                .set::<_, _, ()>(format!("{}:result:{}", self.prefix, id), result)
+                .map_err(to_redis_error)?;
+            self.connection
+                .del::<_, ()>(format!("{}:checkpoint:{}", self.prefix, id))
+                .map_err(to_redis_error)?;
+
+            if let Some(idx) = self
+                .claimed
+                .iter()
+                .position(|(claimed_id, _)| claimed_id == id)
+            {
+                let (_, entry) = self.claimed.swap_remove(idx);
+                self.connection
+                    .lrem::<_, _, ()>(format!("{}:running", self.prefix), 1, entry)
+                    .map_err(to_redis_error)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{Algorithm, Config, Instance, JobStatus, SolveRequest};
+    use crate::common::{generate_map, TSPError};
+    use crate::sim_annealing::Checkpoint;
+    use crate::testing::is_cost_consistent;
+    use crate::worker::{run_worker, DirectoryQueue, Job, JobQueue, WorkerOptions};
+
+    fn spool_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("tsp-worker-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_directory_queue() {
+        let dir = spool_dir("queue");
+        let mut queue = DirectoryQueue::open(&dir).ok().unwrap();
+        let map = generate_map(6, (1, 100)).ok().unwrap();
+        let request = SolveRequest {
+            instance: Instance {
+                intercity_map: map.clone(),
+            },
+            config: Config {
+                max_iterations: 500,
+                ..Config::default()
+            },
+        };
+
+        for id in ["a", "b", "c"] {
+            assert!(queue.submit(id, &request).is_ok());
+        }
+        std::fs::write(dir.join("pending").join("broken.json"), "{").unwrap();
+
+        let options = WorkerOptions {
+            checkpoint_interval: 100,
+            poll_interval: None,
+        };
+        assert_eq!(run_worker(&mut queue, &options).ok(), Some(3));
+
+        for id in ["a", "b", "c"] {
+            match queue.result(id).ok().unwrap() {
+                Some(JobStatus::Done(solution)) => {
+                    assert!(is_cost_consistent(&map, &solution.path, solution.cost))
+                }
+                _ => panic!("job {} did not finish", id),
+            }
+        }
+        assert!(matches!(
+            queue.result("broken").ok().unwrap(),
+            Some(JobStatus::Failed { .. })
+        ));
+        assert!(std::fs::read_dir(dir.join("running"))
+            .unwrap()
+            .next()
+            .is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resume_interrupted_job() {
+        let dir = spool_dir("resume");
+        let mut queue = DirectoryQueue::open(&dir).ok().unwrap();
+        let map = generate_map(5, (1, 100)).ok().unwrap();
+        let request = SolveRequest {
+            instance: Instance { intercity_map: map },
+            config: Config {
+                max_iterations: 300,
+                ..Config::default()
+            },
+        };
+        assert!(queue.submit("job", &request).is_ok());
+
+        // simulate a worker that crashed after checkpointing
+        let job = queue.claim().ok().unwrap().unwrap();
+        let checkpoint = Checkpoint {
+            iteration: 200,
+            path: vec![4, 3, 2, 1, 0],
+        };
+        assert!(queue.save_checkpoint(&job.id, &checkpoint).is_ok());
+        assert_eq!(queue.requeue_interrupted().ok(), Some(1));
+        assert_eq!(queue.load_checkpoint("job").ok().unwrap(), Some(checkpoint));
+
+        assert_eq!(
+            run_worker(&mut queue, &WorkerOptions::default()).ok(),
+            Some(1)
+        );
+        assert!(matches!(
+            queue.result("job").ok().unwrap(),
+            Some(JobStatus::Done(_))
+        ));
+        assert_eq!(queue.load_checkpoint("job").ok().unwrap(), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // a spool whose checkpoints can never be saved
+    struct ReadOnlyCheckpoints(DirectoryQueue);
+
+    impl JobQueue for ReadOnlyCheckpoints {
+        fn claim(&mut self) -> Result<Option<Job>, TSPError> {
+            self.0.claim()
+        }
+        fn load_checkpoint(&mut self, id: &str) -> Result<Option<Checkpoint>, TSPError> {
+            self.0.load_checkpoint(id)
+        }
+        fn save_checkpoint(&mut self, _: &str, _: &Checkpoint) -> Result<(), TSPError> {
+            Err(TSPError::Io(std::io::Error::other("read only")))
+        }
+        fn complete(&mut self, id: &str, status: &JobStatus) -> Result<(), TSPError> {
+            self.0.complete(id, status)
+        }
+    }
+
+    #[test]
+    fn test_failed_checkpoint_fails_job() {
+        let dir = spool_dir("read-only");
+        let queue = DirectoryQueue::open(&dir).ok().unwrap();
+        let map = generate_map(5, (1, 100)).ok().unwrap();
+        let request = |algorithm| SolveRequest {
+            instance: Instance {
+                intercity_map: map.clone(),
+            },
+            config: Config {
+                algorithm,
+                max_iterations: 300,
+                ..Config::default()
+            },
+        };
+        assert!(queue
+            .submit("annealed", &request(Algorithm::SimulatedAnnealing))
+            .is_ok());
+        assert!(queue
+            .submit("exact", &request(Algorithm::BruteForce))
+            .is_ok());
+
+        // the annealing job fails without taking the worker down with it
+        let mut queue = ReadOnlyCheckpoints(queue);
+        let options = WorkerOptions {
+            checkpoint_interval: 100,
+            poll_interval: None,
+        };
+        assert_eq!(run_worker(&mut queue, &options).ok(), Some(2));
+        assert!(matches!(
+            queue.0.result("annealed").ok().unwrap(),
+            Some(JobStatus::Failed { .. })
+        ));
+        assert!(matches!(
+            queue.0.result("exact").ok().unwrap(),
+            Some(JobStatus::Done(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}